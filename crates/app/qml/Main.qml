@@ -14,7 +14,23 @@ ApplicationWindow {
     title: qsTr("Hestia")
 
     property int selectedFolderId: -1
-    property int selectedFileId: -1
+    property string selectedFolderPath: ""
+    property int selectedSmartFolderId: -1
+    property var selectedFileIds: []
+
+    function refreshFiles() {
+        if (selectedSmartFolderId >= 0)
+            files.refreshSmartFolder(selectedSmartFolderId, search.text)
+        else
+            files.refresh(selectedFolderId, search.text)
+    }
+
+    function toggleSelection(fileId) {
+        if (selectedFileIds.includes(fileId))
+            selectedFileIds = selectedFileIds.filter(selected => selected !== fileId)
+        else
+            selectedFileIds = selectedFileIds.concat([fileId])
+    }
 
     HestiaBackend {
         id: backend
@@ -22,14 +38,202 @@ ApplicationWindow {
         onOperationFinished: {
             if (ready) {
                 folders.refresh()
-                files.refresh(window.selectedFolderId, search.text)
+                smartFolders.refresh()
+                window.refreshFiles()
                 tags.refresh()
             }
         }
     }
-    FolderModel { id: folders }
-    FileModel { id: files }
-    TagModel { id: tags }
+    FolderTreeModel {
+        id: folders
+        Component.onCompleted: listenForChanges()
+    }
+    SmartFolderModel {
+        id: smartFolders
+    }
+    FileModel {
+        id: files
+        Component.onCompleted: listenForChanges()
+    }
+    TagModel {
+        id: tags
+        Component.onCompleted: listenForChanges()
+    }
+
+    Shortcut {
+        sequences: [StandardKey.Undo]
+        enabled: backend.ready
+        onActivated: tags.undo()
+    }
+    Shortcut {
+        sequences: [StandardKey.Redo]
+        enabled: backend.ready
+        onActivated: tags.redo()
+    }
+
+    Dialog {
+        id: tagDetailsDialog
+        property int tagId: -1
+        anchors.centerIn: parent
+        modal: true
+        standardButtons: Dialog.Ok | Dialog.Cancel
+
+        function edit(id, name, color, icon, description, aliases) {
+            tagId = id
+            title = qsTr("Edit %1").arg(name)
+            tagColor.text = color
+            tagIcon.text = icon
+            tagDescription.text = description
+            tagAliases.text = aliases
+            open()
+        }
+
+        onAccepted: tags.editDetails(tagId, tagColor.text, tagIcon.text, tagDescription.text,
+                                     tagAliases.text)
+
+        GridLayout {
+            columns: 2
+            Label { text: qsTr("Color") }
+            TextField { id: tagColor; placeholderText: "#e5484d" }
+            Label { text: qsTr("Icon") }
+            TextField { id: tagIcon; placeholderText: qsTr("Emoji or short text") }
+            Label { text: qsTr("Description") }
+            TextField { id: tagDescription }
+            Label { text: qsTr("Aliases") }
+            TextField { id: tagAliases; placeholderText: qsTr("Separated by commas") }
+        }
+    }
+
+    Dialog {
+        id: folderDecorationDialog
+        property int folderId: -1
+        anchors.centerIn: parent
+        modal: true
+        standardButtons: Dialog.Ok | Dialog.Cancel
+
+        function edit(id, name, displayName, color, icon) {
+            folderId = id
+            title = qsTr("Show %1 as").arg(name)
+            folderDisplayName.placeholderText = name
+            folderDisplayName.text = displayName
+            folderColor.text = color
+            folderIcon.text = icon
+            open()
+        }
+
+        onAccepted: folders.decorate(folderId, folderDisplayName.text, folderColor.text,
+                                     folderIcon.text)
+
+        GridLayout {
+            columns: 2
+            Label { text: qsTr("Name") }
+            TextField { id: folderDisplayName }
+            Label { text: qsTr("Color") }
+            TextField { id: folderColor; placeholderText: "#e5484d" }
+            Label { text: qsTr("Icon") }
+            TextField { id: folderIcon; placeholderText: qsTr("Emoji or short text") }
+        }
+    }
+
+    Dialog {
+        id: tagMergeDialog
+        property int tagId: -1
+        anchors.centerIn: parent
+        modal: true
+        standardButtons: Dialog.Ok | Dialog.Cancel
+
+        function merge(id, name) {
+            tagId = id
+            title = qsTr("Merge %1 into").arg(name)
+            open()
+        }
+
+        onAccepted: {
+            if (mergeTarget.currentValue !== undefined && mergeTarget.currentValue !== tagId)
+                tags.merge([tagId], mergeTarget.currentValue)
+        }
+
+        ComboBox {
+            id: mergeTarget
+            model: tags
+            textRole: "name"
+            valueRole: "id"
+        }
+    }
+
+    Dialog {
+        id: tagSplitDialog
+        property int tagId: -1
+        anchors.centerIn: parent
+        modal: true
+        standardButtons: Dialog.Ok | Dialog.Cancel
+
+        function split(id, name) {
+            tagId = id
+            title = qsTr("Move the selected files of %1 to").arg(name)
+            splitName.text = ""
+            open()
+        }
+
+        onAccepted: tags.splitFiles(tagId, window.selectedFileIds, splitName.text)
+
+        TextField {
+            id: splitName
+            placeholderText: qsTr("New tag")
+        }
+    }
+
+    Dialog {
+        id: saveSearchDialog
+        anchors.centerIn: parent
+        modal: true
+        title: qsTr("Save search as smart folder")
+        standardButtons: Dialog.Ok | Dialog.Cancel
+
+        function ask() {
+            searchName.text = search.text
+            searchUntagged.checked = false
+            searchExtensions.text = ""
+            searchAddedDays.value = 0
+            open()
+        }
+
+        onAccepted: smartFolders.save(searchName.text, window.selectedFolderPath, search.text,
+                                      searchUntagged.checked, searchExtensions.text,
+                                      searchAddedDays.value > 0 ? searchAddedDays.value : -1)
+
+        ColumnLayout {
+            anchors.fill: parent
+            TextField {
+                id: searchName
+                Layout.fillWidth: true
+                placeholderText: qsTr("Name")
+            }
+            Label {
+                text: window.selectedFolderPath === ""
+                      ? qsTr("In all folders")
+                      : qsTr("Below %1").arg(window.selectedFolderPath)
+            }
+            CheckBox {
+                id: searchUntagged
+                text: qsTr("Only files without tags")
+            }
+            TextField {
+                id: searchExtensions
+                Layout.fillWidth: true
+                placeholderText: qsTr("File types, e.g. jpg, png")
+            }
+            RowLayout {
+                Label { text: qsTr("Added in the last") }
+                SpinBox {
+                    id: searchAddedDays
+                    from: 0
+                    to: 3650
+                }
+                Label { text: qsTr("days (0 for any time)") }
+            }
+        }
+    }
 
     FolderDialog {
         id: folderDialog
@@ -37,6 +241,12 @@ ApplicationWindow {
         onAccepted: backend.createLibrary(libraryName.text, selectedFolder)
     }
 
+    FolderDialog {
+        id: backupDialog
+        title: qsTr("Choose the backup to restore, it replaces its library")
+        onAccepted: backend.restoreLibrary(selectedFolder)
+    }
+
     ColumnLayout {
         anchors.fill: parent
         anchors.margins: 16
@@ -94,6 +304,11 @@ ApplicationWindow {
                     enabled: libraryName.text.trim().length > 0 && !backend.busy
                     onClicked: folderDialog.open()
                 }
+                Button {
+                    text: qsTr("Restore backup")
+                    enabled: !backend.busy
+                    onClicked: backupDialog.open()
+                }
             }
         }
 
@@ -111,13 +326,96 @@ ApplicationWindow {
                 TextField {
                     id: search
                     placeholderText: qsTr("Filter files or tags")
-                    onAccepted: files.refresh(window.selectedFolderId, text)
+                    onAccepted: window.refreshFiles()
+                }
+                Label {
+                    text: qsTr("%n file(s)", "", files.total)
+                }
+                ComboBox {
+                    id: sortKey
+                    textRole: "text"
+                    valueRole: "value"
+                    model: [
+                        { text: qsTr("Name"), value: FileModel.Name },
+                        { text: qsTr("Path"), value: FileModel.Path },
+                        { text: qsTr("Size"), value: FileModel.Size },
+                        { text: qsTr("Modified"), value: FileModel.Modified },
+                        { text: qsTr("Type"), value: FileModel.Type },
+                        { text: qsTr("Tag count"), value: FileModel.TagCount },
+                        { text: qsTr("Rating"), value: FileModel.Rating },
+                        { text: qsTr("Favorite"), value: FileModel.Favorite },
+                        { text: qsTr("Color label"), value: FileModel.ColorLabel }
+                    ]
+                    Accessible.name: qsTr("Sort files by")
+                    onActivated: files.setSort(currentValue, sortDescending.checked)
+                }
+                Button {
+                    id: sortDescending
+                    checkable: true
+                    text: checked ? "↓" : "↑"
+                    Accessible.name: qsTr("Sort descending")
+                    onToggled: files.setSort(sortKey.currentValue, checked)
+                }
+                Button {
+                    text: qsTr("Save search")
+                    enabled: window.selectedSmartFolderId < 0
+                    onClicked: saveSearchDialog.ask()
                 }
                 Button {
                     text: qsTr("Scan")
                     enabled: !backend.busy
                     onClicked: backend.scan()
                 }
+                Button {
+                    text: qsTr("Verify")
+                    enabled: !backend.busy
+                    onClicked: backend.verifyLibrary(false)
+                }
+                Button {
+                    text: qsTr("Back up")
+                    enabled: !backend.busy
+                    onClicked: backend.backupLibrary()
+                }
+                Button {
+                    text: qsTr("Restore")
+                    enabled: !backend.busy
+                    onClicked: backupDialog.open()
+                }
+            }
+
+            RowLayout {
+                Layout.fillWidth: true
+                Timer {
+                    interval: 2000
+                    running: backend.ready
+                    repeat: true
+                    triggeredOnStart: true
+                    onTriggered: backend.refreshThumbnailStatus()
+                }
+                Label {
+                    Layout.fillWidth: true
+                    color: backend.thumbnailsStalled ? "crimson" : palette.windowText
+                    text: qsTr("Thumbnails: %1 queued, %2 in progress, %3/s · %4")
+                        .arg(backend.thumbnailsPending)
+                        .arg(backend.thumbnailsProcessing)
+                        .arg(backend.thumbnailThroughput.toFixed(1))
+                        .arg(backend.thumbnailStorage)
+                }
+                Button {
+                    visible: backend.thumbnailFailures.length > 0
+                    text: qsTr("Retry %1 failed").arg(backend.thumbnailFailures.length)
+                    ToolTip.visible: hovered
+                    ToolTip.text: backend.thumbnailFailures.join("\n")
+                    onClicked: backend.retryFailedThumbnails()
+                }
+                Button {
+                    visible: backend.integrityProblems.length > 0
+                    enabled: !backend.busy
+                    text: qsTr("Repair %1 problems").arg(backend.integrityProblems.length)
+                    ToolTip.visible: hovered
+                    ToolTip.text: backend.integrityProblems.join("\n")
+                    onClicked: backend.verifyLibrary(true)
+                }
             }
 
             SplitView {
@@ -129,12 +427,21 @@ ApplicationWindow {
                     ColumnLayout {
                         anchors.fill: parent
                         Label { text: qsTr("Folders"); font.bold: true }
+                        Label {
+                            Layout.fillWidth: true
+                            visible: folders.error !== ""
+                            color: "crimson"
+                            wrapMode: Text.Wrap
+                            text: folders.error
+                        }
                         Button {
                             Layout.fillWidth: true
                             text: qsTr("All files")
                             onClicked: {
                                 window.selectedFolderId = -1
-                                files.refresh(-1, search.text)
+                                window.selectedFolderPath = ""
+                                window.selectedSmartFolderId = -1
+                                window.refreshFiles()
                             }
                         }
                         ListView {
@@ -143,16 +450,109 @@ ApplicationWindow {
                             clip: true
                             model: folders
                             delegate: ItemDelegate {
+                                id: folderRow
+                                required property int index
                                 required property int id
                                 required property string name
                                 required property string path
+                                required property int depth
+                                required property bool expanded
+                                required property bool hasChildren
+                                required property double files
+                                required property double size
+                                required property double tagCoverage
+                                required property string displayName
+                                required property string color
+                                required property string icon
+                                readonly property string label: displayName.length > 0 ? displayName : name
                                 width: ListView.view.width
-                                text: name
-                                ToolTip.text: path
+                                leftPadding: 44 + depth * 14
+                                rightPadding: 36
+                                highlighted: window.selectedFolderId === id
+                                text: qsTr("%1 (%2)").arg(icon.length > 0 ? icon + " " + label : label)
+                                    .arg(files)
+                                ToolTip.text: qsTr("%1\n%n file(s), %2, %3% tagged", "", files)
+                                    .arg(path)
+                                    .arg(Qt.locale().formattedDataSize(size))
+                                    .arg(Math.round(tagCoverage * 100))
                                 ToolTip.visible: hovered
+                                ToolButton {
+                                    x: 4 + folderRow.depth * 14
+                                    anchors.verticalCenter: parent.verticalCenter
+                                    visible: folderRow.hasChildren
+                                    text: folderRow.expanded ? "▾" : "▸"
+                                    Accessible.name: folderRow.expanded
+                                                     ? qsTr("Collapse %1").arg(folderRow.name)
+                                                     : qsTr("Expand %1").arg(folderRow.name)
+                                    onClicked: folders.toggle(folderRow.index)
+                                }
+                                Rectangle {
+                                    x: 30 + folderRow.depth * 14
+                                    anchors.verticalCenter: parent.verticalCenter
+                                    width: 10
+                                    height: 10
+                                    radius: 5
+                                    color: folderRow.color.length > 0 ? folderRow.color : "transparent"
+                                    border.color: folderRow.color.length > 0 ? palette.mid : "transparent"
+                                }
+                                ToolButton {
+                                    anchors.right: parent.right
+                                    anchors.verticalCenter: parent.verticalCenter
+                                    text: "✎"
+                                    Accessible.name: qsTr("Decorate %1").arg(folderRow.label)
+                                    onClicked: folderDecorationDialog.edit(folderRow.id, folderRow.name,
+                                                                           folderRow.displayName,
+                                                                           folderRow.color, folderRow.icon)
+                                }
                                 onClicked: {
                                     window.selectedFolderId = id
-                                    files.refresh(id, search.text)
+                                    window.selectedFolderPath = path
+                                    window.selectedSmartFolderId = -1
+                                    window.refreshFiles()
+                                }
+                            }
+                        }
+                        Label { text: qsTr("Smart folders"); font.bold: true }
+                        Label {
+                            Layout.fillWidth: true
+                            visible: smartFolders.error !== ""
+                            color: "crimson"
+                            wrapMode: Text.Wrap
+                            text: smartFolders.error
+                        }
+                        ListView {
+                            Layout.fillWidth: true
+                            Layout.preferredHeight: contentHeight
+                            clip: true
+                            model: smartFolders
+                            delegate: ItemDelegate {
+                                id: smartFolder
+                                required property int id
+                                required property string name
+                                required property string summary
+                                width: ListView.view.width
+                                text: name
+                                highlighted: window.selectedSmartFolderId === id
+                                ToolTip.text: summary
+                                ToolTip.visible: hovered && summary !== ""
+                                onClicked: {
+                                    window.selectedFolderId = -1
+                                    window.selectedFolderPath = ""
+                                    window.selectedSmartFolderId = id
+                                    window.refreshFiles()
+                                }
+                                Button {
+                                    anchors.right: parent.right
+                                    anchors.verticalCenter: parent.verticalCenter
+                                    text: "×"
+                                    Accessible.name: qsTr("Delete %1").arg(smartFolder.name)
+                                    onClicked: {
+                                        if (window.selectedSmartFolderId === smartFolder.id) {
+                                            window.selectedSmartFolderId = -1
+                                            window.refreshFiles()
+                                        }
+                                        smartFolders.remove(smartFolder.id)
+                                    }
                                 }
                             }
                         }
@@ -162,18 +562,62 @@ ApplicationWindow {
                 Pane {
                     SplitView.fillWidth: true
                     GridView {
+                        id: fileGrid
                         anchors.fill: parent
                         clip: true
                         model: files
                         cellWidth: 160
                         cellHeight: 180
+                        onContentYChanged: visibleThumbnails.restart()
+                        onHeightChanged: visibleThumbnails.restart()
+                        onCountChanged: visibleThumbnails.restart()
+
+                        Timer {
+                            id: visibleThumbnails
+                            interval: 150
+                            onTriggered: {
+                                const first = fileGrid.indexAt(1, fileGrid.contentY + 1)
+                                let last = fileGrid.indexAt(fileGrid.width - 2,
+                                                            fileGrid.contentY + fileGrid.height - 2)
+                                if (last < 0)
+                                    last = fileGrid.count - 1
+                                files.requestThumbnails(Math.max(first, 0), last)
+                            }
+                        }
                         delegate: ItemDelegate {
                             required property int id
                             required property string name
                             required property url thumbnailUrl
+                            required property double size
+                            required property int rating
+                            required property bool favorite
+                            required property string colorLabel
+                            id: fileCell
                             width: 150
                             height: 170
-                            onClicked: window.selectedFileId = id
+                            ToolTip.text: qsTr("%1 · %2").arg(name).arg(Qt.locale().formattedDataSize(size))
+                            ToolTip.visible: hovered
+                            ToolTip.delay: 800
+                            highlighted: window.selectedFileIds.includes(id)
+                            onClicked: window.selectedFileIds = [id]
+                            CheckBox {
+                                z: 1
+                                checked: fileCell.highlighted
+                                Accessible.name: qsTr("Select %1").arg(name)
+                                onToggled: window.toggleSelection(id)
+                            }
+                            Rectangle {
+                                z: 1
+                                anchors.right: parent.right
+                                anchors.top: parent.top
+                                anchors.margins: 8
+                                visible: fileCell.colorLabel.length > 0
+                                width: 12
+                                height: 12
+                                radius: 6
+                                color: visible ? fileCell.colorLabel : "transparent"
+                                border.color: palette.mid
+                            }
                             contentItem: Column {
                                 spacing: 6
                                 Image {
@@ -188,6 +632,12 @@ ApplicationWindow {
                                     elide: Text.ElideMiddle
                                     horizontalAlignment: Text.AlignHCenter
                                 }
+                                Label {
+                                    width: 140
+                                    visible: fileCell.rating > 0 || fileCell.favorite
+                                    text: "★".repeat(fileCell.rating) + (fileCell.favorite ? " ♥" : "")
+                                    horizontalAlignment: Text.AlignHCenter
+                                }
                             }
                         }
                     }
@@ -197,7 +647,77 @@ ApplicationWindow {
                     SplitView.preferredWidth: 230
                     ColumnLayout {
                         anchors.fill: parent
-                        Label { text: qsTr("Tags"); font.bold: true }
+                        Label { text: qsTr("Rating and label"); font.bold: true }
+                        RowLayout {
+                            Layout.fillWidth: true
+                            enabled: window.selectedFileIds.length > 0
+                            Repeater {
+                                model: 5
+                                ToolButton {
+                                    required property int index
+                                    text: "★"
+                                    Accessible.name: qsTr("Rate %n star(s)", "", index + 1)
+                                    onClicked: files.setRating(window.selectedFileIds, index + 1)
+                                }
+                            }
+                            ToolButton {
+                                text: "☆"
+                                Accessible.name: qsTr("Remove rating")
+                                onClicked: files.setRating(window.selectedFileIds, 0)
+                            }
+                        }
+                        RowLayout {
+                            Layout.fillWidth: true
+                            enabled: window.selectedFileIds.length > 0
+                            Button {
+                                text: "♥"
+                                Accessible.name: qsTr("Mark as favorite")
+                                onClicked: files.setFavorite(window.selectedFileIds, true)
+                            }
+                            Button {
+                                text: "♡"
+                                Accessible.name: qsTr("Remove from favorites")
+                                onClicked: files.setFavorite(window.selectedFileIds, false)
+                            }
+                            ComboBox {
+                                Layout.fillWidth: true
+                                textRole: "text"
+                                valueRole: "value"
+                                model: [
+                                    { text: qsTr("No label"), value: "" },
+                                    { text: qsTr("Red"), value: "red" },
+                                    { text: qsTr("Orange"), value: "orange" },
+                                    { text: qsTr("Yellow"), value: "yellow" },
+                                    { text: qsTr("Green"), value: "green" },
+                                    { text: qsTr("Blue"), value: "blue" },
+                                    { text: qsTr("Purple"), value: "purple" },
+                                    { text: qsTr("Gray"), value: "gray" }
+                                ]
+                                Accessible.name: qsTr("Color label")
+                                onActivated: files.setColorLabel(window.selectedFileIds, currentValue)
+                            }
+                        }
+                        Label {
+                            Layout.fillWidth: true
+                            visible: files.error.length > 0
+                            color: "crimson"
+                            text: files.error
+                            wrapMode: Text.Wrap
+                        }
+                        RowLayout {
+                            Layout.fillWidth: true
+                            Label { Layout.fillWidth: true; text: qsTr("Tags"); font.bold: true }
+                            Button {
+                                text: "↶"
+                                Accessible.name: qsTr("Undo")
+                                onClicked: tags.undo()
+                            }
+                            Button {
+                                text: "↷"
+                                Accessible.name: qsTr("Redo")
+                                onClicked: tags.redo()
+                            }
+                        }
                         Label {
                             Layout.fillWidth: true
                             visible: tags.error.length > 0
@@ -205,6 +725,12 @@ ApplicationWindow {
                             text: tags.error
                             wrapMode: Text.Wrap
                         }
+                        Label {
+                            Layout.fillWidth: true
+                            visible: tags.status.length > 0
+                            text: tags.status
+                            wrapMode: Text.Wrap
+                        }
                         ListView {
                             Layout.fillWidth: true
                             Layout.fillHeight: true
@@ -213,19 +739,66 @@ ApplicationWindow {
                             delegate: RowLayout {
                                 required property int id
                                 required property string name
+                                required property string color
+                                required property string icon
+                                required property string description
+                                required property string aliases
                                 width: ListView.view.width
-                                Label { Layout.fillWidth: true; text: name }
+                                Rectangle {
+                                    width: 12
+                                    height: 12
+                                    radius: 6
+                                    color: parent.color.length > 0 ? parent.color : "transparent"
+                                    border.color: palette.mid
+                                }
+                                Label {
+                                    Layout.fillWidth: true
+                                    text: icon.length > 0 ? icon + " " + name : name
+                                    ToolTip.visible: tagHover.hovered && ToolTip.text.length > 0
+                                    ToolTip.text: [description, aliases].filter(part => part.length > 0).join("\n")
+                                    HoverHandler { id: tagHover }
+                                }
+                                Button {
+                                    text: "✎"
+                                    Accessible.name: qsTr("Edit %1").arg(name)
+                                    onClicked: tagDetailsDialog.edit(id, name, color, icon, description, aliases)
+                                }
                                 Button {
                                     text: "+"
-                                    enabled: window.selectedFileId >= 0
+                                    enabled: window.selectedFileIds.length > 0
                                     Accessible.name: qsTr("Assign %1").arg(name)
-                                    onClicked: tags.assign(window.selectedFileId, id)
+                                    onClicked: tags.assignFiles(window.selectedFileIds, [id])
                                 }
                                 Button {
                                     text: "−"
-                                    enabled: window.selectedFileId >= 0
+                                    enabled: window.selectedFileIds.length > 0
                                     Accessible.name: qsTr("Remove %1").arg(name)
-                                    onClicked: tags.unassign(window.selectedFileId, id)
+                                    onClicked: tags.unassignFiles(window.selectedFileIds, [id])
+                                }
+                                Button {
+                                    text: qsTr("All")
+                                    Accessible.name: qsTr("Assign %1 to every file shown").arg(name)
+                                    ToolTip.visible: hovered
+                                    ToolTip.text: Accessible.name
+                                    enabled: window.selectedSmartFolderId < 0
+                                    onClicked: tags.assignMatching(window.selectedFolderId, search.text, [id])
+                                }
+                                Button {
+                                    text: "⋯"
+                                    Accessible.name: qsTr("More actions for %1").arg(name)
+                                    onClicked: tagMenu.popup()
+                                    Menu {
+                                        id: tagMenu
+                                        MenuItem {
+                                            text: qsTr("Merge into…")
+                                            onTriggered: tagMergeDialog.merge(id, name)
+                                        }
+                                        MenuItem {
+                                            text: qsTr("Split selected files…")
+                                            enabled: window.selectedFileIds.length > 0
+                                            onTriggered: tagSplitDialog.split(id, name)
+                                        }
+                                    }
                                 }
                                 Button {
                                     text: "×"
@@ -234,6 +807,38 @@ ApplicationWindow {
                                 }
                             }
                         }
+                        RowLayout {
+                            Layout.fillWidth: true
+                            Button {
+                                Layout.fillWidth: true
+                                text: qsTr("Preview rules")
+                                ToolTip.visible: hovered
+                                ToolTip.text: qsTr("Show which files shown the auto-tag rules would tag")
+                                enabled: window.selectedSmartFolderId < 0
+                                onClicked: tags.previewAutoTags(window.selectedFolderId, search.text)
+                            }
+                            Button {
+                                Layout.fillWidth: true
+                                text: qsTr("Apply rules")
+                                enabled: window.selectedSmartFolderId < 0
+                                onClicked: tags.applyAutoTags(window.selectedFolderId, search.text)
+                            }
+                            Button {
+                                Layout.fillWidth: true
+                                text: qsTr("Sync files")
+                                ToolTip.visible: hovered
+                                ToolTip.text: qsTr("Exchange tags with extended attributes and XMP sidecars")
+                                onClicked: tags.syncFiles()
+                            }
+                            Button {
+                                Layout.fillWidth: true
+                                text: qsTr("Suggest")
+                                enabled: window.selectedFileIds.length === 1
+                                ToolTip.visible: hovered
+                                ToolTip.text: qsTr("Suggest tags for the selected file")
+                                onClicked: tags.suggestFor(window.selectedFileIds[0])
+                            }
+                        }
                         RowLayout {
                             Layout.fillWidth: true
                             TextField {
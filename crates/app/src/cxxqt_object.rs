@@ -1,13 +1,21 @@
-use controllers::{AppController, FileInfo, FolderInfo, LibraryInfo, TagInfo};
+use controllers::{
+    AppController, BulkTagReport, Color, ColorLabel, DateFilter, FileInfo, FileSelection, FileSort,
+    FileSortKey, Filter, FolderDecoration, FolderFilter, FolderInfo, Icon, IconType, LibraryChange,
+    LibraryInfo, SmartFolderInfo, TagDetails, TagFilter, TagInfo, WatchedFolderTree,
+};
 use core::pin::Pin;
-use cxx_qt::{CxxQtType, Threading};
+use cxx_qt::{CxxQtThread, CxxQtType, Threading};
 use cxx_qt_lib::{
     QByteArray, QHash, QHashPair_i32_QByteArray, QModelIndex, QString, QStringList, QUrl, QVariant,
+    QVector,
 };
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::future::Future;
 use std::path::PathBuf;
 use std::sync::{Arc, OnceLock};
 use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
 
 #[derive(Clone)]
 struct AppContext {
@@ -17,6 +25,26 @@ struct AppContext {
 
 static CONTEXT: OnceLock<AppContext> = OnceLock::new();
 
+/// How a library is made the active one before it is scanned and watched
+enum LibraryTask {
+    Open(PathBuf),
+    Create {
+        name: String,
+        folder: PathBuf,
+    },
+    /// Put a backup folder back in place of its library
+    Restore(PathBuf),
+}
+
+/// Files listed in the status after previewing the auto-tag rules
+const AUTO_TAG_PREVIEW_LINES: usize = 20;
+
+/// Tags suggested at most for the selected file
+const TAG_SUGGESTION_LIMIT: usize = 5;
+
+/// Files loaded at once, more are loaded as the view is scrolled
+const FILE_PAGE_SIZE: u64 = 200;
+
 pub fn initialize(controller: Arc<AppController>, runtime: Handle) -> Result<(), &'static str> {
     CONTEXT
         .set(AppContext {
@@ -44,6 +72,8 @@ mod ffi {
         type QUrl = cxx_qt_lib::QUrl;
         include!("cxx-qt-lib/qvariant.h");
         type QVariant = cxx_qt_lib::QVariant;
+        include!("cxx-qt-lib/qvector.h");
+        type QVector_i32 = cxx_qt_lib::QVector<i32>;
     }
 
     #[qenum(FolderModel)]
@@ -52,6 +82,33 @@ mod ffi {
         Name,
         Path,
         ParentId,
+        DisplayName,
+        Color,
+        Icon,
+    }
+
+    #[qenum(FolderTreeModel)]
+    enum FolderTreeRole {
+        Id,
+        Name,
+        Path,
+        Depth,
+        Expanded,
+        HasChildren,
+        DirectFiles,
+        Files,
+        Size,
+        TagCoverage,
+        DisplayName,
+        Color,
+        Icon,
+    }
+
+    #[qenum(SmartFolderModel)]
+    enum SmartFolderRole {
+        Id,
+        Name,
+        Summary,
     }
 
     #[qenum(FileModel)]
@@ -61,12 +118,33 @@ mod ffi {
         Path,
         Type,
         ThumbnailUrl,
+        Size,
+        Rating,
+        Favorite,
+        ColorLabel,
+    }
+
+    #[qenum(FileModel)]
+    enum SortKey {
+        Name,
+        Path,
+        Size,
+        Modified,
+        Type,
+        TagCount,
+        Rating,
+        Favorite,
+        ColorLabel,
     }
 
     #[qenum(TagModel)]
     enum TagRole {
         Id,
         Name,
+        Color,
+        Icon,
+        Description,
+        Aliases,
     }
 
     extern "RustQt" {
@@ -77,6 +155,13 @@ mod ffi {
         #[qproperty(bool, ready)]
         #[qproperty(QString, error)]
         #[qproperty(QString, status)]
+        #[qproperty(i32, thumbnails_pending, cxx_name = "thumbnailsPending")]
+        #[qproperty(i32, thumbnails_processing, cxx_name = "thumbnailsProcessing")]
+        #[qproperty(f64, thumbnail_throughput, cxx_name = "thumbnailThroughput")]
+        #[qproperty(bool, thumbnails_stalled, cxx_name = "thumbnailsStalled")]
+        #[qproperty(QString, thumbnail_storage, cxx_name = "thumbnailStorage")]
+        #[qproperty(QStringList, thumbnail_failures, cxx_name = "thumbnailFailures")]
+        #[qproperty(QStringList, integrity_problems, cxx_name = "integrityProblems")]
         type HestiaBackend = super::HestiaBackendRust;
 
         #[qinvokable]
@@ -90,6 +175,21 @@ mod ffi {
         fn create_library(self: Pin<&mut HestiaBackend>, name: &QString, folder: &QUrl);
         #[qinvokable]
         fn scan(self: Pin<&mut HestiaBackend>);
+        #[qinvokable]
+        #[cxx_name = "refreshThumbnailStatus"]
+        fn refresh_thumbnail_status(self: Pin<&mut HestiaBackend>);
+        #[qinvokable]
+        #[cxx_name = "retryFailedThumbnails"]
+        fn retry_failed_thumbnails(self: Pin<&mut HestiaBackend>);
+        #[qinvokable]
+        #[cxx_name = "verifyLibrary"]
+        fn verify_library(self: Pin<&mut HestiaBackend>, repair: bool);
+        #[qinvokable]
+        #[cxx_name = "backupLibrary"]
+        fn backup_library(self: Pin<&mut HestiaBackend>);
+        #[qinvokable]
+        #[cxx_name = "restoreLibrary"]
+        fn restore_library(self: Pin<&mut HestiaBackend>, folder: &QUrl);
 
         #[qsignal]
         #[cxx_name = "operationFinished"]
@@ -110,6 +210,9 @@ mod ffi {
         fn role_names(self: &FolderModel) -> QHash_i32_QByteArray;
         #[qinvokable]
         fn refresh(self: Pin<&mut FolderModel>);
+        #[qinvokable]
+        #[cxx_name = "listenForChanges"]
+        fn listen_for_changes(self: Pin<&mut FolderModel>);
         #[inherit]
         #[cxx_name = "beginResetModel"]
         unsafe fn begin_reset_model(self: Pin<&mut FolderModel>);
@@ -120,6 +223,117 @@ mod ffi {
         #[qobject]
         #[qml_element]
         #[base = QAbstractListModel]
+        #[qproperty(QString, error)]
+        type FolderTreeModel = super::FolderTreeModelRust;
+
+        #[cxx_override]
+        #[cxx_name = "rowCount"]
+        fn row_count(self: &FolderTreeModel, parent: &QModelIndex) -> i32;
+        #[cxx_override]
+        fn data(self: &FolderTreeModel, index: &QModelIndex, role: i32) -> QVariant;
+        #[cxx_override]
+        #[cxx_name = "roleNames"]
+        fn role_names(self: &FolderTreeModel) -> QHash_i32_QByteArray;
+        #[qinvokable]
+        fn refresh(self: Pin<&mut FolderTreeModel>);
+        #[qinvokable]
+        fn toggle(self: Pin<&mut FolderTreeModel>, row: i32);
+        #[qinvokable]
+        fn decorate(
+            self: Pin<&mut FolderTreeModel>,
+            folder_id: i32,
+            display_name: &QString,
+            color: &QString,
+            icon: &QString,
+        );
+        #[qinvokable]
+        #[cxx_name = "listenForChanges"]
+        fn listen_for_changes(self: Pin<&mut FolderTreeModel>);
+        #[inherit]
+        #[cxx_name = "beginResetModel"]
+        unsafe fn begin_reset_model(self: Pin<&mut FolderTreeModel>);
+        #[inherit]
+        #[cxx_name = "endResetModel"]
+        unsafe fn end_reset_model(self: Pin<&mut FolderTreeModel>);
+        #[inherit]
+        #[cxx_name = "beginInsertRows"]
+        unsafe fn begin_insert_rows(
+            self: Pin<&mut FolderTreeModel>,
+            parent: &QModelIndex,
+            first: i32,
+            last: i32,
+        );
+        #[inherit]
+        #[cxx_name = "endInsertRows"]
+        unsafe fn end_insert_rows(self: Pin<&mut FolderTreeModel>);
+        #[inherit]
+        #[cxx_name = "beginRemoveRows"]
+        unsafe fn begin_remove_rows(
+            self: Pin<&mut FolderTreeModel>,
+            parent: &QModelIndex,
+            first: i32,
+            last: i32,
+        );
+        #[inherit]
+        #[cxx_name = "endRemoveRows"]
+        unsafe fn end_remove_rows(self: Pin<&mut FolderTreeModel>);
+        #[inherit]
+        fn index(
+            self: &FolderTreeModel,
+            row: i32,
+            column: i32,
+            parent: &QModelIndex,
+        ) -> QModelIndex;
+        #[inherit]
+        #[qsignal]
+        #[cxx_name = "dataChanged"]
+        fn data_changed(
+            self: Pin<&mut FolderTreeModel>,
+            top_left: &QModelIndex,
+            bottom_right: &QModelIndex,
+            roles: &QVector_i32,
+        );
+
+        #[qobject]
+        #[qml_element]
+        #[base = QAbstractListModel]
+        #[qproperty(QString, error)]
+        type SmartFolderModel = super::SmartFolderModelRust;
+
+        #[cxx_override]
+        #[cxx_name = "rowCount"]
+        fn row_count(self: &SmartFolderModel, parent: &QModelIndex) -> i32;
+        #[cxx_override]
+        fn data(self: &SmartFolderModel, index: &QModelIndex, role: i32) -> QVariant;
+        #[cxx_override]
+        #[cxx_name = "roleNames"]
+        fn role_names(self: &SmartFolderModel) -> QHash_i32_QByteArray;
+        #[qinvokable]
+        fn refresh(self: Pin<&mut SmartFolderModel>);
+        #[qinvokable]
+        fn save(
+            self: Pin<&mut SmartFolderModel>,
+            name: &QString,
+            folder_path: &QString,
+            search: &QString,
+            untagged: bool,
+            extensions: &QString,
+            added_within_days: i32,
+        );
+        #[qinvokable]
+        fn remove(self: Pin<&mut SmartFolderModel>, smart_folder_id: i32);
+        #[inherit]
+        #[cxx_name = "beginResetModel"]
+        unsafe fn begin_reset_model(self: Pin<&mut SmartFolderModel>);
+        #[inherit]
+        #[cxx_name = "endResetModel"]
+        unsafe fn end_reset_model(self: Pin<&mut SmartFolderModel>);
+
+        #[qobject]
+        #[qml_element]
+        #[base = QAbstractListModel]
+        #[qproperty(i32, total)]
+        #[qproperty(QString, error)]
         type FileModel = super::FileModelRust;
 
         #[cxx_override]
@@ -130,19 +344,80 @@ mod ffi {
         #[cxx_override]
         #[cxx_name = "roleNames"]
         fn role_names(self: &FileModel) -> QHash_i32_QByteArray;
+        #[cxx_override]
+        #[cxx_name = "canFetchMore"]
+        fn can_fetch_more(self: &FileModel, parent: &QModelIndex) -> bool;
+        #[cxx_override]
+        #[cxx_name = "fetchMore"]
+        fn fetch_more(self: Pin<&mut FileModel>, parent: &QModelIndex);
+        #[qinvokable]
+        #[cxx_name = "setSort"]
+        fn set_sort(self: Pin<&mut FileModel>, key: SortKey, descending: bool);
         #[qinvokable]
         fn refresh(self: Pin<&mut FileModel>, folder_id: i32, search: &QString);
+        #[qinvokable]
+        #[cxx_name = "refreshSmartFolder"]
+        fn refresh_smart_folder(self: Pin<&mut FileModel>, smart_folder_id: i32, search: &QString);
+        #[qinvokable]
+        #[cxx_name = "requestThumbnails"]
+        fn request_thumbnails(self: &FileModel, first_row: i32, last_row: i32);
+        #[qinvokable]
+        #[cxx_name = "setRating"]
+        fn set_rating(self: Pin<&mut FileModel>, file_ids: &QVector_i32, rating: i32);
+        #[qinvokable]
+        #[cxx_name = "setFavorite"]
+        fn set_favorite(self: Pin<&mut FileModel>, file_ids: &QVector_i32, favorite: bool);
+        #[qinvokable]
+        #[cxx_name = "setColorLabel"]
+        fn set_color_label(self: Pin<&mut FileModel>, file_ids: &QVector_i32, label: &QString);
+        #[qinvokable]
+        #[cxx_name = "listenForChanges"]
+        fn listen_for_changes(self: Pin<&mut FileModel>);
         #[inherit]
         #[cxx_name = "beginResetModel"]
         unsafe fn begin_reset_model(self: Pin<&mut FileModel>);
         #[inherit]
         #[cxx_name = "endResetModel"]
         unsafe fn end_reset_model(self: Pin<&mut FileModel>);
+        #[inherit]
+        #[cxx_name = "beginInsertRows"]
+        unsafe fn begin_insert_rows(
+            self: Pin<&mut FileModel>,
+            parent: &QModelIndex,
+            first: i32,
+            last: i32,
+        );
+        #[inherit]
+        #[cxx_name = "endInsertRows"]
+        unsafe fn end_insert_rows(self: Pin<&mut FileModel>);
+        #[inherit]
+        #[cxx_name = "beginRemoveRows"]
+        unsafe fn begin_remove_rows(
+            self: Pin<&mut FileModel>,
+            parent: &QModelIndex,
+            first: i32,
+            last: i32,
+        );
+        #[inherit]
+        #[cxx_name = "endRemoveRows"]
+        unsafe fn end_remove_rows(self: Pin<&mut FileModel>);
+        #[inherit]
+        fn index(self: &FileModel, row: i32, column: i32, parent: &QModelIndex) -> QModelIndex;
+        #[inherit]
+        #[qsignal]
+        #[cxx_name = "dataChanged"]
+        fn data_changed(
+            self: Pin<&mut FileModel>,
+            top_left: &QModelIndex,
+            bottom_right: &QModelIndex,
+            roles: &QVector_i32,
+        );
 
         #[qobject]
         #[qml_element]
         #[base = QAbstractListModel]
         #[qproperty(QString, error)]
+        #[qproperty(QString, status)]
         type TagModel = super::TagModelRust;
 
         #[cxx_override]
@@ -160,11 +435,64 @@ mod ffi {
         #[qinvokable]
         fn rename(self: Pin<&mut TagModel>, tag_id: i32, name: &QString);
         #[qinvokable]
+        #[cxx_name = "editDetails"]
+        fn edit_details(
+            self: Pin<&mut TagModel>,
+            tag_id: i32,
+            color: &QString,
+            icon: &QString,
+            description: &QString,
+            aliases: &QString,
+        );
+        #[qinvokable]
         fn remove(self: Pin<&mut TagModel>, tag_id: i32);
         #[qinvokable]
+        fn merge(self: Pin<&mut TagModel>, tag_ids: &QVector_i32, into: i32);
+        #[qinvokable]
+        #[cxx_name = "splitFiles"]
+        fn split_files(
+            self: Pin<&mut TagModel>,
+            tag_id: i32,
+            file_ids: &QVector_i32,
+            name: &QString,
+        );
+        #[qinvokable]
         fn assign(self: Pin<&mut TagModel>, file_id: i32, tag_id: i32);
         #[qinvokable]
         fn unassign(self: Pin<&mut TagModel>, file_id: i32, tag_id: i32);
+        #[qinvokable]
+        #[cxx_name = "assignFiles"]
+        fn assign_files(self: Pin<&mut TagModel>, file_ids: &QVector_i32, tag_ids: &QVector_i32);
+        #[qinvokable]
+        #[cxx_name = "unassignFiles"]
+        fn unassign_files(self: Pin<&mut TagModel>, file_ids: &QVector_i32, tag_ids: &QVector_i32);
+        #[qinvokable]
+        #[cxx_name = "assignMatching"]
+        fn assign_matching(
+            self: Pin<&mut TagModel>,
+            folder_id: i32,
+            search: &QString,
+            tag_ids: &QVector_i32,
+        );
+        #[qinvokable]
+        #[cxx_name = "suggestFor"]
+        fn suggest_for(self: Pin<&mut TagModel>, file_id: i32);
+        #[qinvokable]
+        #[cxx_name = "previewAutoTags"]
+        fn preview_auto_tags(self: Pin<&mut TagModel>, folder_id: i32, search: &QString);
+        #[qinvokable]
+        #[cxx_name = "applyAutoTags"]
+        fn apply_auto_tags(self: Pin<&mut TagModel>, folder_id: i32, search: &QString);
+        #[qinvokable]
+        #[cxx_name = "syncFiles"]
+        fn sync_files(self: Pin<&mut TagModel>);
+        #[qinvokable]
+        fn undo(self: Pin<&mut TagModel>);
+        #[qinvokable]
+        fn redo(self: Pin<&mut TagModel>);
+        #[qinvokable]
+        #[cxx_name = "listenForChanges"]
+        fn listen_for_changes(self: Pin<&mut TagModel>);
         #[inherit]
         #[cxx_name = "beginResetModel"]
         unsafe fn begin_reset_model(self: Pin<&mut TagModel>);
@@ -186,6 +514,13 @@ pub struct HestiaBackendRust {
     ready: bool,
     error: QString,
     status: QString,
+    thumbnails_pending: i32,
+    thumbnails_processing: i32,
+    thumbnail_throughput: f64,
+    thumbnails_stalled: bool,
+    thumbnail_storage: QString,
+    thumbnail_failures: QStringList,
+    integrity_problems: QStringList,
     libraries: Vec<LibraryInfo>,
 }
 
@@ -218,7 +553,7 @@ impl ffi::HestiaBackend {
             self.set_error("Select a valid library.".into());
             return;
         };
-        self.start_library_task(None, path);
+        self.start_library_task(LibraryTask::Open(path));
     }
 
     fn create_library(self: Pin<&mut Self>, name: &QString, folder: &QUrl) {
@@ -226,10 +561,21 @@ impl ffi::HestiaBackend {
             self.set_error("Choose a local folder.".into());
             return;
         };
-        self.start_library_task(Some(name.to_string()), PathBuf::from(path.to_string()));
+        self.start_library_task(LibraryTask::Create {
+            name: name.to_string(),
+            folder: PathBuf::from(path.to_string()),
+        });
+    }
+
+    fn restore_library(self: Pin<&mut Self>, folder: &QUrl) {
+        let Some(path) = folder.to_local_file() else {
+            self.set_error("Choose a local backup folder.".into());
+            return;
+        };
+        self.start_library_task(LibraryTask::Restore(PathBuf::from(path.to_string())));
     }
 
-    fn start_library_task(mut self: Pin<&mut Self>, name: Option<String>, path: PathBuf) {
+    fn start_library_task(mut self: Pin<&mut Self>, task: LibraryTask) {
         let Some(context) = CONTEXT.get().cloned() else {
             self.set_error("Backend is not initialized.".into());
             return;
@@ -239,12 +585,19 @@ impl ffi::HestiaBackend {
         }
         self.as_mut().set_busy(true);
         self.as_mut().set_error(QString::default());
-        self.as_mut().set_status("Opening library…".into());
+        let status = match task {
+            LibraryTask::Restore(_) => "Restoring library…",
+            LibraryTask::Open(_) | LibraryTask::Create { .. } => "Opening library…",
+        };
+        self.as_mut().set_status(status.into());
         let qt_thread = self.qt_thread();
         context.runtime.spawn(async move {
-            let result = match name {
-                Some(name) => context.controller.create_library(&name, path).await,
-                None => context.controller.select_library(path).await,
+            let result = match task {
+                LibraryTask::Open(path) => context.controller.select_library(path).await,
+                LibraryTask::Create { name, folder } => {
+                    context.controller.create_library(&name, folder).await
+                }
+                LibraryTask::Restore(path) => context.controller.restore_library(path).await,
             };
             let result = match result {
                 Ok(_) => context.controller.initialize_workspace().await,
@@ -257,27 +610,13 @@ impl ffi::HestiaBackend {
             if result.is_ok() {
                 let _thumbnail_result = context.controller.generate_thumbnails().await;
             }
-            if result.is_ok() {
-                match context.controller.start_watching().await {
-                    Ok(mut changes) => {
-                        let watcher_thread = qt_thread.clone();
-                        let controller = Arc::clone(&context.controller);
-                        tokio::spawn(async move {
-                            while changes.recv().await.is_some() {
-                                let _thumbnail_result = controller.generate_thumbnails().await;
-                                drop(watcher_thread.queue(|mut backend| {
-                                    backend.as_mut().set_status("Library updated".into());
-                                    backend.as_mut().operation_finished();
-                                }));
-                            }
-                        });
-                    }
-                    Err(error) => {
-                        drop(qt_thread.queue(move |mut backend| {
-                            backend.as_mut().set_error(error.to_string().into());
-                        }));
-                    }
-                }
+            // The models pick up what the watcher stores through their change listeners
+            if result.is_ok()
+                && let Err(error) = context.controller.start_watching().await
+            {
+                drop(qt_thread.queue(move |mut backend| {
+                    backend.as_mut().set_error(error.to_string().into());
+                }));
             }
             drop(qt_thread.queue(move |mut backend| {
                 backend.as_mut().set_busy(false);
@@ -331,11 +670,172 @@ impl ffi::HestiaBackend {
             }));
         });
     }
+
+    fn refresh_thumbnail_status(self: Pin<&mut Self>) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let status = context.controller.thumbnail_status().await;
+            let failures = context.controller.failed_thumbnails().await;
+            drop(qt_thread.queue(move |mut backend| {
+                if let Ok(status) = status {
+                    let count = |value: usize| i32::try_from(value).unwrap_or(i32::MAX);
+                    backend
+                        .as_mut()
+                        .set_thumbnails_pending(count(status.pending()));
+                    backend
+                        .as_mut()
+                        .set_thumbnails_processing(count(status.processing()));
+                    backend
+                        .as_mut()
+                        .set_thumbnail_throughput(status.throughput_per_second());
+                    backend.as_mut().set_thumbnails_stalled(status.is_stalled());
+                    backend.as_mut().set_thumbnail_storage(
+                        format!(
+                            "{} thumbnails, {}",
+                            status.stored(),
+                            format_bytes(status.storage_bytes())
+                        )
+                        .into(),
+                    );
+                }
+                if let Ok(failures) = failures {
+                    let failures = failures
+                        .iter()
+                        .map(|failure| {
+                            QString::from(&format!(
+                                "{} ({}): {}",
+                                failure.path().display(),
+                                failure.size().as_str(),
+                                failure.reason()
+                            ))
+                        })
+                        .collect();
+                    backend.as_mut().set_thumbnail_failures(failures);
+                }
+            }));
+        });
+    }
+
+    fn retry_failed_thumbnails(self: Pin<&mut Self>) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context.controller.retry_failed_thumbnails(None).await;
+            drop(qt_thread.queue(move |mut backend| {
+                match result {
+                    Ok(count) => backend
+                        .as_mut()
+                        .set_status(format!("Retrying {count} thumbnails").into()),
+                    Err(error) => backend.as_mut().set_error(error.to_string().into()),
+                }
+                backend.as_mut().refresh_thumbnail_status();
+            }));
+        });
+    }
+
+    /// Check the library against the disk, repairing what can be repaired with `repair`
+    fn verify_library(mut self: Pin<&mut Self>, repair: bool) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            self.set_error("Backend is not initialized.".into());
+            return;
+        };
+        if *self.busy() {
+            return;
+        }
+        self.as_mut().set_busy(true);
+        let status = if repair {
+            "Repairing…"
+        } else {
+            "Verifying…"
+        };
+        self.as_mut().set_status(status.into());
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context.controller.verify_library(repair).await;
+            drop(qt_thread.queue(move |mut backend| {
+                backend.as_mut().set_busy(false);
+                match result {
+                    Ok(report) => {
+                        // Only what a repair fixes is offered for repair
+                        let (repairable, other): (Vec<_>, Vec<_>) = report
+                            .remaining()
+                            .partition(|problem| problem.is_repairable());
+                        backend.as_mut().set_integrity_problems(
+                            repairable
+                                .iter()
+                                .map(|problem| QString::from(&problem.to_string()))
+                                .collect(),
+                        );
+                        let other: Vec<String> = other.iter().map(ToString::to_string).collect();
+                        backend.as_mut().set_error(other.join("\n").into());
+                        backend.as_mut().set_status(
+                            format!(
+                                "Verified {} files and {} folders; {} problems",
+                                report.files_checked,
+                                report.folders_checked,
+                                report.remaining().count()
+                            )
+                            .into(),
+                        );
+                    }
+                    Err(error) => backend.as_mut().set_error(error.to_string().into()),
+                }
+                backend.as_mut().operation_finished();
+            }));
+        });
+    }
+
+    fn backup_library(mut self: Pin<&mut Self>) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            self.set_error("Backend is not initialized.".into());
+            return;
+        };
+        if *self.busy() {
+            return;
+        }
+        self.as_mut().set_busy(true);
+        self.as_mut().set_status("Backing up…".into());
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context.controller.backup_library().await;
+            drop(qt_thread.queue(move |mut backend| {
+                backend.as_mut().set_busy(false);
+                match result {
+                    Ok(backup) => backend
+                        .as_mut()
+                        .set_status(format!("Backed up to {}", backup.path.display()).into()),
+                    Err(error) => backend.as_mut().set_error(error.to_string().into()),
+                }
+                backend.as_mut().operation_finished();
+            }));
+        });
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[unit])
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
 }
 
 #[derive(Default)]
 pub struct FolderModelRust {
     items: Vec<FolderInfo>,
+    listening: bool,
 }
 
 impl ffi::FolderModel {
@@ -357,6 +857,9 @@ impl ffi::FolderModel {
                 (&QString::from(&item.path().to_string_lossy().to_string())).into()
             }
             ffi::FolderRole::ParentId => (&item.parent_id().unwrap_or(-1)).into(),
+            ffi::FolderRole::DisplayName => (&decoration_name(item.decoration())).into(),
+            ffi::FolderRole::Color => (&decoration_color(item.decoration())).into(),
+            ffi::FolderRole::Icon => (&decoration_icon(item.decoration())).into(),
             _ => QVariant::default(),
         }
     }
@@ -368,6 +871,9 @@ impl ffi::FolderModel {
             (ffi::FolderRole::Name.repr, "name"),
             (ffi::FolderRole::Path.repr, "path"),
             (ffi::FolderRole::ParentId.repr, "parentId"),
+            (ffi::FolderRole::DisplayName.repr, "displayName"),
+            (ffi::FolderRole::Color.repr, "color"),
+            (ffi::FolderRole::Icon.repr, "icon"),
         ])
     }
 
@@ -385,41 +891,61 @@ impl ffi::FolderModel {
             }));
         });
     }
+
+    fn listen_for_changes(mut self: Pin<&mut Self>) {
+        if std::mem::replace(&mut self.as_mut().rust_mut().listening, true) {
+            return;
+        }
+        forward_changes(self.qt_thread(), |model, batch| {
+            if batch.folders_changed {
+                model.refresh();
+            }
+        });
+    }
+}
+
+/// A folder shown in the tree, below the expanded folder before it with a smaller depth
+struct FolderRow {
+    folder: WatchedFolderTree,
+    depth: usize,
+    expanded: bool,
 }
 
+/// The folder tree flattened into the rows of a list, children are loaded when expanded
 #[derive(Default)]
-pub struct FileModelRust {
-    items: Vec<FileInfo>,
+pub struct FolderTreeModelRust {
+    error: QString,
+    rows: Vec<FolderRow>,
+    listening: bool,
 }
 
-impl ffi::FileModel {
+impl ffi::FolderTreeModel {
     fn row_count(&self, _parent: &QModelIndex) -> i32 {
-        self.items.len() as i32
+        self.rows.len() as i32
     }
 
     fn data(&self, index: &QModelIndex, role: i32) -> QVariant {
-        let Some(item) = usize::try_from(index.row())
+        let Some(row) = usize::try_from(index.row())
             .ok()
-            .and_then(|row| self.items.get(row))
+            .and_then(|row| self.rows.get(row))
         else {
             return QVariant::default();
         };
-        match (ffi::FileRole { repr: role }) {
-            ffi::FileRole::Id => (&item.id()).into(),
-            ffi::FileRole::Name => (&QString::from(item.name())).into(),
-            ffi::FileRole::Path => {
-                (&QString::from(&item.path().to_string_lossy().to_string())).into()
-            }
-            ffi::FileRole::Type => (&item.file_type_id()).into(),
-            ffi::FileRole::ThumbnailUrl => {
-                item.thumbnail_path()
-                    .map_or_else(QVariant::default, |path| {
-                        (&QUrl::from_local_file(&QString::from(
-                            &path.to_string_lossy().to_string(),
-                        )))
-                            .into()
-                    })
-            }
+        let folder = &row.folder;
+        match (ffi::FolderTreeRole { repr: role }) {
+            ffi::FolderTreeRole::Id => (&folder.id()).into(),
+            ffi::FolderTreeRole::Name => (&QString::from(folder.name())).into(),
+            ffi::FolderTreeRole::Path => (&QString::from(folder.path())).into(),
+            ffi::FolderTreeRole::Depth => (&(row.depth as i32)).into(),
+            ffi::FolderTreeRole::Expanded => (&row.expanded).into(),
+            ffi::FolderTreeRole::HasChildren => (&folder.has_children()).into(),
+            ffi::FolderTreeRole::DirectFiles => (&folder.stats().direct_files).into(),
+            ffi::FolderTreeRole::Files => (&folder.stats().files).into(),
+            ffi::FolderTreeRole::Size => (&folder.stats().size).into(),
+            ffi::FolderTreeRole::TagCoverage => (&folder.stats().tag_coverage()).into(),
+            ffi::FolderTreeRole::DisplayName => (&decoration_name(folder.decoration())).into(),
+            ffi::FolderTreeRole::Color => (&decoration_color(folder.decoration())).into(),
+            ffi::FolderTreeRole::Icon => (&decoration_icon(folder.decoration())).into(),
             _ => QVariant::default(),
         }
     }
@@ -427,62 +953,795 @@ impl ffi::FileModel {
     fn role_names(&self) -> QHash<QHashPair_i32_QByteArray> {
         let _ = self;
         roles(&[
-            (ffi::FileRole::Id.repr, "id"),
-            (ffi::FileRole::Name.repr, "name"),
-            (ffi::FileRole::Path.repr, "path"),
-            (ffi::FileRole::Type.repr, "type"),
-            (ffi::FileRole::ThumbnailUrl.repr, "thumbnailUrl"),
+            (ffi::FolderTreeRole::Id.repr, "id"),
+            (ffi::FolderTreeRole::Name.repr, "name"),
+            (ffi::FolderTreeRole::Path.repr, "path"),
+            (ffi::FolderTreeRole::Depth.repr, "depth"),
+            (ffi::FolderTreeRole::Expanded.repr, "expanded"),
+            (ffi::FolderTreeRole::HasChildren.repr, "hasChildren"),
+            (ffi::FolderTreeRole::DirectFiles.repr, "directFiles"),
+            (ffi::FolderTreeRole::Files.repr, "files"),
+            (ffi::FolderTreeRole::Size.repr, "size"),
+            (ffi::FolderTreeRole::TagCoverage.repr, "tagCoverage"),
+            (ffi::FolderTreeRole::DisplayName.repr, "displayName"),
+            (ffi::FolderTreeRole::Color.repr, "color"),
+            (ffi::FolderTreeRole::Icon.repr, "icon"),
         ])
     }
 
-    fn refresh(self: Pin<&mut Self>, folder_id: i32, search: &QString) {
+    /// Load the tree again, keeping the folders that are expanded now expanded
+    fn refresh(self: Pin<&mut Self>) {
         let Some(context) = CONTEXT.get().cloned() else {
             return;
         };
-        let folder_id = (folder_id >= 0).then_some(folder_id);
-        let search = search.to_string();
+        let expanded: HashSet<i32> = self
+            .rows
+            .iter()
+            .filter(|row| row.expanded)
+            .map(|row| row.folder.id())
+            .collect();
         let qt_thread = self.qt_thread();
         context.runtime.spawn(async move {
-            let result = context.controller.list_files(folder_id, &search).await;
+            let result = visible_folder_rows(&context.controller, &expanded).await;
             drop(qt_thread.queue(move |mut model| {
-                if let Ok(items) = result {
-                    reset_files(model.as_mut(), items);
+                if let Ok(rows) = result {
+                    unsafe { model.as_mut().begin_reset_model() };
+                    model.as_mut().rust_mut().rows = rows;
+                    unsafe { model.as_mut().end_reset_model() };
                 }
             }));
         });
     }
-}
-
-#[derive(Default)]
-pub struct TagModelRust {
-    error: QString,
-    items: Vec<TagInfo>,
-}
-
-impl ffi::TagModel {
-    fn row_count(&self, _parent: &QModelIndex) -> i32 {
-        self.items.len() as i32
-    }
 
-    fn data(&self, index: &QModelIndex, role: i32) -> QVariant {
-        let Some(item) = usize::try_from(index.row())
+    /// Expand or collapse the folder in a row
+    fn toggle(mut self: Pin<&mut Self>, row: i32) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let Some((folder_id, depth, expanded)) = usize::try_from(row)
             .ok()
-            .and_then(|row| self.items.get(row))
+            .and_then(|row| self.rows.get(row))
+            .map(|row| (row.folder.id(), row.depth, row.expanded))
         else {
-            return QVariant::default();
+            return;
         };
-        match (ffi::TagRole { repr: role }) {
-            ffi::TagRole::Id => (&item.id()).into(),
-            ffi::TagRole::Name => (&QString::from(item.name())).into(),
-            _ => QVariant::default(),
+        if expanded {
+            collapse_folder_row(self.as_mut(), folder_id);
+            return;
         }
-    }
-
-    fn role_names(&self) -> QHash<QHashPair_i32_QByteArray> {
-        let _ = self;
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context.controller.folder_children(Some(folder_id)).await;
+            drop(qt_thread.queue(move |model| {
+                if let Ok(children) = result {
+                    expand_folder_row(model, folder_id, depth, children);
+                }
+            }));
+        });
+    }
+
+    /// Show a folder with another name, color or icon, blank values clear them
+    fn decorate(
+        mut self: Pin<&mut Self>,
+        folder_id: i32,
+        display_name: &QString,
+        color: &QString,
+        icon: &QString,
+    ) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let (color, icon) = match color_and_icon(color, icon) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.as_mut().set_error(error.into());
+                return;
+            }
+        };
+        let decoration = FolderDecoration {
+            color,
+            icon,
+            display_name: Some(display_name.to_string()),
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context
+                .controller
+                .decorate_folder(folder_id, decoration)
+                .await;
+            drop(qt_thread.queue(move |mut model| match result {
+                Ok(_) => {
+                    model.as_mut().set_error(QString::default());
+                    model.refresh();
+                }
+                Err(error) => model.as_mut().set_error(error.to_string().into()),
+            }));
+        });
+    }
+
+    fn listen_for_changes(mut self: Pin<&mut Self>) {
+        if std::mem::replace(&mut self.as_mut().rust_mut().listening, true) {
+            return;
+        }
+        // Counts change with every file, not only when folders do
+        forward_changes(self.qt_thread(), |model, batch| {
+            if batch.folders_changed
+                || batch.reload_files
+                || !batch.changed_files.is_empty()
+                || !batch.removed_files.is_empty()
+            {
+                model.refresh();
+            }
+        });
+    }
+}
+
+/// A color given as `#rrggbb` and an icon given as text, blank values are none
+fn color_and_icon(
+    color: &QString,
+    icon: &QString,
+) -> Result<(Option<Color>, Option<Icon>), String> {
+    let color = match color.to_string().trim() {
+        "" => None,
+        hex => Some(Color::new(hex, hex).map_err(|error| error.to_string())?),
+    };
+    let icon = icon.to_string();
+    let icon =
+        (!icon.trim().is_empty()).then(|| Icon::new(icon.trim(), icon.trim(), IconType::Text));
+    Ok((color, icon))
+}
+
+/// The display name, empty if the folder is shown with its own name
+fn decoration_name(decoration: &FolderDecoration) -> QString {
+    QString::from(decoration.display_name.as_deref().unwrap_or_default())
+}
+
+fn decoration_color(decoration: &FolderDecoration) -> QString {
+    QString::from(decoration.color.as_ref().map_or("", Color::hex))
+}
+
+fn decoration_icon(decoration: &FolderDecoration) -> QString {
+    QString::from(decoration.icon.as_ref().map_or("", Icon::content))
+}
+
+/// The rows of the watched folders and of every expanded folder below them, in tree order
+async fn visible_folder_rows(
+    controller: &AppController,
+    expanded: &HashSet<i32>,
+) -> Result<Vec<FolderRow>, controllers::ControllerError> {
+    let mut rows = Vec::new();
+    let mut pending: Vec<(WatchedFolderTree, usize)> = controller
+        .folder_children(None)
+        .await?
+        .into_iter()
+        .rev()
+        .map(|folder| (folder, 0))
+        .collect();
+    while let Some((folder, depth)) = pending.pop() {
+        let expand = folder.has_children() && expanded.contains(&folder.id());
+        if expand {
+            let children = controller.folder_children(Some(folder.id())).await?;
+            pending.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
+        rows.push(FolderRow {
+            folder,
+            depth,
+            expanded: expand,
+        });
+    }
+    Ok(rows)
+}
+
+fn expand_folder_row(
+    mut model: Pin<&mut ffi::FolderTreeModel>,
+    folder_id: i32,
+    depth: usize,
+    children: Vec<WatchedFolderTree>,
+) {
+    // The rows may have changed while the children were loading
+    let Some(row) = model
+        .rows
+        .iter()
+        .position(|row| row.folder.id() == folder_id && !row.expanded)
+    else {
+        return;
+    };
+    if let Some(expanded) = model.as_mut().rust_mut().rows.get_mut(row) {
+        expanded.expanded = true;
+    }
+    let index = model.index(row as i32, 0, &QModelIndex::default());
+    model
+        .as_mut()
+        .data_changed(&index, &index, &QVector::default());
+    if children.is_empty() {
+        return;
+    }
+    let first = row + 1;
+    let last = row + children.len();
+    unsafe {
+        model
+            .as_mut()
+            .begin_insert_rows(&QModelIndex::default(), first as i32, last as i32);
+    }
+    let rows = children.into_iter().map(|folder| FolderRow {
+        folder,
+        depth: depth + 1,
+        expanded: false,
+    });
+    model.as_mut().rust_mut().rows.splice(first..first, rows);
+    unsafe { model.as_mut().end_insert_rows() };
+}
+
+fn collapse_folder_row(mut model: Pin<&mut ffi::FolderTreeModel>, folder_id: i32) {
+    let Some((row, depth)) = model
+        .rows
+        .iter()
+        .enumerate()
+        .find(|(_, row)| row.folder.id() == folder_id)
+        .map(|(row, folder_row)| (row, folder_row.depth))
+    else {
+        return;
+    };
+    let descendants = model
+        .rows
+        .iter()
+        .skip(row + 1)
+        .take_while(|below| below.depth > depth)
+        .count();
+    if let Some(collapsed) = model.as_mut().rust_mut().rows.get_mut(row) {
+        collapsed.expanded = false;
+    }
+    let index = model.index(row as i32, 0, &QModelIndex::default());
+    model
+        .as_mut()
+        .data_changed(&index, &index, &QVector::default());
+    if descendants == 0 {
+        return;
+    }
+    unsafe {
+        model.as_mut().begin_remove_rows(
+            &QModelIndex::default(),
+            (row + 1) as i32,
+            (row + descendants) as i32,
+        );
+    }
+    model
+        .as_mut()
+        .rust_mut()
+        .rows
+        .drain(row + 1..=row + descendants);
+    unsafe { model.as_mut().end_remove_rows() };
+}
+
+#[derive(Default)]
+pub struct SmartFolderModelRust {
+    error: QString,
+    items: Vec<SmartFolderInfo>,
+}
+
+impl ffi::SmartFolderModel {
+    fn row_count(&self, _parent: &QModelIndex) -> i32 {
+        self.items.len() as i32
+    }
+
+    fn data(&self, index: &QModelIndex, role: i32) -> QVariant {
+        let Some(item) = usize::try_from(index.row())
+            .ok()
+            .and_then(|row| self.items.get(row))
+        else {
+            return QVariant::default();
+        };
+        match (ffi::SmartFolderRole { repr: role }) {
+            ffi::SmartFolderRole::Id => (&item.id()).into(),
+            ffi::SmartFolderRole::Name => (&QString::from(item.name())).into(),
+            ffi::SmartFolderRole::Summary => {
+                (&QString::from(&filter_summary(item.filter()))).into()
+            }
+            _ => QVariant::default(),
+        }
+    }
+
+    fn role_names(&self) -> QHash<QHashPair_i32_QByteArray> {
+        let _ = self;
+        roles(&[
+            (ffi::SmartFolderRole::Id.repr, "id"),
+            (ffi::SmartFolderRole::Name.repr, "name"),
+            (ffi::SmartFolderRole::Summary.repr, "summary"),
+        ])
+    }
+
+    fn refresh(self: Pin<&mut Self>) {
+        self.run_smart_folder_action(
+            |controller| async move { controller.list_smart_folders().await },
+        );
+    }
+
+    /// Save the current view as a smart folder
+    ///
+    /// Extensions are separated by commas, a negative number of days means any date.
+    fn save(
+        self: Pin<&mut Self>,
+        name: &QString,
+        folder_path: &QString,
+        search: &QString,
+        untagged: bool,
+        extensions: &QString,
+        added_within_days: i32,
+    ) {
+        let name = name.to_string();
+        let folder_path = folder_path.to_string();
+        let filter = Filter {
+            folder_filter: (!folder_path.is_empty()).then(|| FolderFilter {
+                folders: vec![folder_path.into()],
+            }),
+            tag_filter: untagged.then(|| TagFilter {
+                untagged,
+                ..TagFilter::default()
+            }),
+            text: search.to_string(),
+            extensions: extensions
+                .to_string()
+                .split(',')
+                .map(str::trim)
+                .filter(|extension| !extension.is_empty())
+                .map(str::to_string)
+                .collect(),
+            added: u32::try_from(added_within_days)
+                .ok()
+                .map(|days| DateFilter::LastDays { days }),
+            ..Filter::default()
+        };
+        self.run_smart_folder_action(move |controller| async move {
+            controller.create_smart_folder(&name, filter).await?;
+            controller.list_smart_folders().await
+        });
+    }
+
+    fn remove(self: Pin<&mut Self>, smart_folder_id: i32) {
+        self.run_smart_folder_action(move |controller| async move {
+            controller.delete_smart_folder(smart_folder_id).await?;
+            controller.list_smart_folders().await
+        });
+    }
+
+    fn run_smart_folder_action<F, Fut>(self: Pin<&mut Self>, action: F)
+    where
+        F: FnOnce(Arc<AppController>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Vec<SmartFolderInfo>, controllers::ControllerError>>
+            + Send
+            + 'static,
+    {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = action(context.controller).await;
+            drop(qt_thread.queue(move |mut model| match result {
+                Ok(items) => {
+                    model.as_mut().set_error(QString::default());
+                    reset_smart_folders(model.as_mut(), items);
+                }
+                Err(error) => model.as_mut().set_error(error.to_string().into()),
+            }));
+        });
+    }
+}
+
+/// Short description of what a smart folder shows, e.g. "untagged · pdf · last 7 days"
+fn filter_summary(filter: &Filter) -> String {
+    let mut parts = Vec::new();
+    if let Some(folder_filter) = &filter.folder_filter {
+        parts.extend(
+            folder_filter
+                .folders
+                .iter()
+                .map(|folder| folder.to_string_lossy().to_string()),
+        );
+    }
+    if let Some(tag_filter) = &filter.tag_filter {
+        if tag_filter.untagged {
+            parts.push("untagged".to_string());
+        }
+        parts.extend(tag_filter.tags.iter().map(|tag| format!("#{}", tag.name)));
+    }
+    if !filter.extensions.is_empty() {
+        parts.push(filter.extensions.join(", "));
+    }
+    if let Some(DateFilter::LastDays { days }) = filter.added {
+        parts.push(format!("last {days} days"));
+    }
+    if !filter.text.is_empty() {
+        parts.push(format!("\"{}\"", filter.text));
+    }
+    parts.join(" · ")
+}
+
+#[derive(Default)]
+pub struct FileModelRust {
+    /// The first rows of the listing, more are loaded by `fetchMore`
+    items: Vec<FileInfo>,
+    /// Number of files in the whole listing
+    total: i32,
+    error: QString,
+    folder_id: Option<i32>,
+    /// Set while the model shows a smart folder instead of a real one
+    smart_folder_id: Option<i32>,
+    search: String,
+    sort: FileSort,
+    /// A page is being loaded
+    loading: bool,
+    /// Changed on every reload, pages requested for an older listing are dropped
+    generation: u64,
+    listening: bool,
+}
+
+impl ffi::FileModel {
+    fn row_count(&self, _parent: &QModelIndex) -> i32 {
+        self.items.len() as i32
+    }
+
+    fn data(&self, index: &QModelIndex, role: i32) -> QVariant {
+        let Some(item) = usize::try_from(index.row())
+            .ok()
+            .and_then(|row| self.items.get(row))
+        else {
+            return QVariant::default();
+        };
+        match (ffi::FileRole { repr: role }) {
+            ffi::FileRole::Id => (&item.id()).into(),
+            ffi::FileRole::Name => (&QString::from(item.name())).into(),
+            ffi::FileRole::Path => {
+                (&QString::from(&item.path().to_string_lossy().to_string())).into()
+            }
+            ffi::FileRole::Type => (&item.file_type_id()).into(),
+            ffi::FileRole::Size => (&item.size()).into(),
+            ffi::FileRole::Rating => (&i32::from(item.rating())).into(),
+            ffi::FileRole::Favorite => (&item.favorite()).into(),
+            ffi::FileRole::ColorLabel => {
+                (&QString::from(item.color_label().map_or("", ColorLabel::as_str))).into()
+            }
+            ffi::FileRole::ThumbnailUrl => {
+                item.thumbnail_path()
+                    .map_or_else(QVariant::default, |path| {
+                        (&QUrl::from_local_file(&QString::from(
+                            &path.to_string_lossy().to_string(),
+                        )))
+                            .into()
+                    })
+            }
+            _ => QVariant::default(),
+        }
+    }
+
+    fn role_names(&self) -> QHash<QHashPair_i32_QByteArray> {
+        let _ = self;
+        roles(&[
+            (ffi::FileRole::Id.repr, "id"),
+            (ffi::FileRole::Name.repr, "name"),
+            (ffi::FileRole::Path.repr, "path"),
+            (ffi::FileRole::Type.repr, "type"),
+            (ffi::FileRole::ThumbnailUrl.repr, "thumbnailUrl"),
+            (ffi::FileRole::Size.repr, "size"),
+            (ffi::FileRole::Rating.repr, "rating"),
+            (ffi::FileRole::Favorite.repr, "favorite"),
+            (ffi::FileRole::ColorLabel.repr, "colorLabel"),
+        ])
+    }
+
+    fn can_fetch_more(&self, _parent: &QModelIndex) -> bool {
+        !self.loading && self.items.len() < usize::try_from(self.total).unwrap_or_default()
+    }
+
+    fn fetch_more(mut self: Pin<&mut Self>, _parent: &QModelIndex) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        if self.loading {
+            return;
+        }
+        self.as_mut().rust_mut().loading = true;
+        let selection = self.selection();
+        let sort = self.sort;
+        let generation = self.generation;
+        let offset = self.items.len() as u64;
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context
+                .controller
+                .list_file_page(selection, sort, offset, FILE_PAGE_SIZE)
+                .await;
+            drop(qt_thread.queue(move |mut model| {
+                if model.generation != generation {
+                    return;
+                }
+                model.as_mut().rust_mut().loading = false;
+                if let Ok(page) = result {
+                    model.as_mut().set_total(row_total(page.total()));
+                    append_file_rows(model.as_mut(), page.into_files());
+                }
+            }));
+        });
+    }
+
+    fn set_sort(mut self: Pin<&mut Self>, key: ffi::SortKey, descending: bool) {
+        let key = match key {
+            ffi::SortKey::Path => FileSortKey::Path,
+            ffi::SortKey::Size => FileSortKey::Size,
+            ffi::SortKey::Modified => FileSortKey::Modified,
+            ffi::SortKey::Type => FileSortKey::Type,
+            ffi::SortKey::TagCount => FileSortKey::TagCount,
+            ffi::SortKey::Rating => FileSortKey::Rating,
+            ffi::SortKey::Favorite => FileSortKey::Favorite,
+            ffi::SortKey::ColorLabel => FileSortKey::ColorLabel,
+            _ => FileSortKey::Name,
+        };
+        self.as_mut().rust_mut().sort = FileSort { key, descending };
+        self.load(FILE_PAGE_SIZE);
+    }
+
+    /// The files the model lists, for the controller
+    fn selection(&self) -> FileSelection {
+        match self.smart_folder_id {
+            Some(smart_folder_id) => FileSelection::SmartFolder {
+                smart_folder_id,
+                search: self.search.clone(),
+            },
+            None => FileSelection::Matching {
+                folder_id: self.folder_id,
+                search: self.search.clone(),
+            },
+        }
+    }
+
+    fn refresh(mut self: Pin<&mut Self>, folder_id: i32, search: &QString) {
+        self.as_mut().rust_mut().folder_id = (folder_id >= 0).then_some(folder_id);
+        self.as_mut().rust_mut().smart_folder_id = None;
+        self.as_mut().rust_mut().search = search.to_string();
+        self.load(FILE_PAGE_SIZE);
+    }
+
+    fn refresh_smart_folder(mut self: Pin<&mut Self>, smart_folder_id: i32, search: &QString) {
+        self.as_mut().rust_mut().folder_id = None;
+        self.as_mut().rust_mut().smart_folder_id = Some(smart_folder_id);
+        self.as_mut().rust_mut().search = search.to_string();
+        self.load(FILE_PAGE_SIZE);
+    }
+
+    /// Load the listing again, as many rows as are loaded now but at least one page
+    fn reload(self: Pin<&mut Self>) {
+        let limit = (self.items.len() as u64).max(FILE_PAGE_SIZE);
+        self.load(limit);
+    }
+
+    /// Replace the rows with the first `limit` files of the listing
+    fn load(mut self: Pin<&mut Self>, limit: u64) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        self.as_mut().rust_mut().generation += 1;
+        self.as_mut().rust_mut().loading = true;
+        let selection = self.selection();
+        let sort = self.sort;
+        let generation = self.generation;
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context
+                .controller
+                .list_file_page(selection, sort, 0, limit)
+                .await;
+            drop(qt_thread.queue(move |mut model| {
+                if model.generation != generation {
+                    return;
+                }
+                model.as_mut().rust_mut().loading = false;
+                if let Ok(page) = result {
+                    model.as_mut().set_total(row_total(page.total()));
+                    reset_files(model.as_mut(), page.into_files());
+                }
+            }));
+        });
+    }
+
+    fn request_thumbnails(&self, first_row: i32, last_row: i32) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let first = usize::try_from(first_row).unwrap_or(0);
+        let last = usize::try_from(last_row).unwrap_or(0);
+        let file_ids: Vec<i32> = self
+            .items
+            .iter()
+            .skip(first)
+            .take(last.saturating_sub(first) + 1)
+            .filter(|item| item.thumbnail_path().is_none())
+            .map(FileInfo::id)
+            .collect();
+        context.runtime.spawn(async move {
+            let _thumbnail_result = context.controller.request_thumbnails(file_ids).await;
+        });
+    }
+
+    fn set_rating(self: Pin<&mut Self>, file_ids: &QVector<i32>, rating: i32) {
+        let file_ids: Vec<i32> = file_ids.iter().copied().collect();
+        // Out of range ratings are left to the controller to reject
+        let rating = u8::try_from(rating).unwrap_or(u8::MAX);
+        self.curate(
+            move |controller| async move { controller.set_rating(&file_ids, rating).await },
+        );
+    }
+
+    fn set_favorite(self: Pin<&mut Self>, file_ids: &QVector<i32>, favorite: bool) {
+        let file_ids: Vec<i32> = file_ids.iter().copied().collect();
+        self.curate(
+            move |controller| async move { controller.set_favorite(&file_ids, favorite).await },
+        );
+    }
+
+    /// An empty label removes the label of the files
+    fn set_color_label(mut self: Pin<&mut Self>, file_ids: &QVector<i32>, label: &QString) {
+        let label = label.to_string();
+        let label = if label.trim().is_empty() {
+            None
+        } else {
+            match ColorLabel::parse(&label) {
+                Ok(label) => Some(label),
+                Err(error) => {
+                    self.as_mut().set_error(error.to_string().into());
+                    return;
+                }
+            }
+        };
+        let file_ids: Vec<i32> = file_ids.iter().copied().collect();
+        self.curate(
+            move |controller| async move { controller.set_color_label(&file_ids, label).await },
+        );
+    }
+
+    /// Run a change to the rating, favorite flag or label of files, rows update as the changes
+    /// arrive
+    fn curate<F, Fut>(self: Pin<&mut Self>, action: F)
+    where
+        F: FnOnce(Arc<AppController>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), controllers::ControllerError>> + Send + 'static,
+    {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = action(context.controller).await;
+            drop(qt_thread.queue(move |mut model| {
+                let error = result.err().map(|error| error.to_string());
+                model.as_mut().set_error(error.unwrap_or_default().into());
+            }));
+        });
+    }
+
+    fn listen_for_changes(mut self: Pin<&mut Self>) {
+        if std::mem::replace(&mut self.as_mut().rust_mut().listening, true) {
+            return;
+        }
+        forward_changes(self.qt_thread(), |mut model, mut batch| {
+            if batch.reload_files {
+                model.reload();
+                return;
+            }
+            for file_id in batch.removed_files {
+                remove_file_row(model.as_mut(), file_id);
+            }
+            batch.changed_files.sort_unstable();
+            batch.changed_files.dedup();
+            model.update_rows(batch.changed_files, &batch.added_files);
+        });
+    }
+
+    /// Insert, update or remove the rows of changed files depending on whether they still match
+    fn update_rows(self: Pin<&mut Self>, file_ids: Vec<i32>, added_files: &[i32]) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        if file_ids.is_empty() {
+            return;
+        }
+        let folder_id = self.folder_id;
+        let smart_folder_id = self.smart_folder_id;
+        let search = self.search.clone();
+        let added_files: HashSet<i32> = added_files.iter().copied().collect();
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = match smart_folder_id {
+                Some(smart_folder_id) => {
+                    context
+                        .controller
+                        .list_matching_smart_folder_files(
+                            smart_folder_id,
+                            &search,
+                            file_ids.clone(),
+                        )
+                        .await
+                }
+                None => {
+                    context
+                        .controller
+                        .list_matching_files(folder_id, &search, file_ids.clone())
+                        .await
+                }
+            };
+            drop(qt_thread.queue(move |mut model| {
+                let Ok(items) = result else {
+                    return;
+                };
+                let mut matching: HashMap<i32, FileInfo> =
+                    items.into_iter().map(|item| (item.id(), item)).collect();
+                let mut reload = false;
+                for file_id in file_ids {
+                    let Some(item) = matching.remove(&file_id) else {
+                        remove_file_row(model.as_mut(), file_id);
+                        continue;
+                    };
+                    let loaded = model.items.iter().any(|existing| existing.id() == file_id);
+                    if !loaded {
+                        // A file that is not new may be listed below the loaded rows already
+                        if !added_files.contains(&file_id) {
+                            reload = true;
+                            continue;
+                        }
+                        let total = model.total;
+                        model.as_mut().set_total(total.saturating_add(1));
+                    }
+                    reload |= !update_file_row(model.as_mut(), item);
+                }
+                if reload {
+                    model.reload();
+                }
+            }));
+        });
+    }
+}
+
+#[derive(Default)]
+pub struct TagModelRust {
+    error: QString,
+    status: QString,
+    items: Vec<TagInfo>,
+    listening: bool,
+}
+
+impl ffi::TagModel {
+    fn row_count(&self, _parent: &QModelIndex) -> i32 {
+        self.items.len() as i32
+    }
+
+    fn data(&self, index: &QModelIndex, role: i32) -> QVariant {
+        let Some(item) = usize::try_from(index.row())
+            .ok()
+            .and_then(|row| self.items.get(row))
+        else {
+            return QVariant::default();
+        };
+        match (ffi::TagRole { repr: role }) {
+            ffi::TagRole::Id => (&item.id()).into(),
+            ffi::TagRole::Name => (&QString::from(item.name())).into(),
+            ffi::TagRole::Color => (&QString::from(item.color().map_or("", Color::hex))).into(),
+            ffi::TagRole::Icon => (&QString::from(item.icon().map_or("", Icon::content))).into(),
+            ffi::TagRole::Description => {
+                (&QString::from(item.description().unwrap_or_default())).into()
+            }
+            ffi::TagRole::Aliases => (&QString::from(&item.aliases().join(", "))).into(),
+            _ => QVariant::default(),
+        }
+    }
+
+    fn role_names(&self) -> QHash<QHashPair_i32_QByteArray> {
+        let _ = self;
         roles(&[
             (ffi::TagRole::Id.repr, "id"),
             (ffi::TagRole::Name.repr, "name"),
+            (ffi::TagRole::Color.repr, "color"),
+            (ffi::TagRole::Icon.repr, "icon"),
+            (ffi::TagRole::Description.repr, "description"),
+            (ffi::TagRole::Aliases.repr, "aliases"),
         ])
     }
 
@@ -506,6 +1765,35 @@ impl ffi::TagModel {
         });
     }
 
+    /// Replace the details of a tag, the aliases are separated by commas
+    fn edit_details(
+        self: Pin<&mut Self>,
+        tag_id: i32,
+        color: &QString,
+        icon: &QString,
+        description: &QString,
+        aliases: &QString,
+    ) {
+        let (color, icon) = match color_and_icon(color, icon) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.set_error(error.into());
+                return;
+            }
+        };
+        let description = description.to_string();
+        let details = TagDetails {
+            color,
+            icon,
+            description: (!description.trim().is_empty()).then_some(description),
+            aliases: aliases.to_string().split(',').map(str::to_string).collect(),
+        };
+        self.run_tag_action(move |controller| async move {
+            controller.update_tag_details(tag_id, details).await?;
+            controller.list_tags().await
+        });
+    }
+
     fn remove(self: Pin<&mut Self>, tag_id: i32) {
         self.run_tag_action(move |controller| async move {
             controller.delete_tag(tag_id).await?;
@@ -513,6 +1801,24 @@ impl ffi::TagModel {
         });
     }
 
+    fn merge(self: Pin<&mut Self>, tag_ids: &QVector<i32>, into: i32) {
+        let tag_ids: Vec<i32> = tag_ids.iter().copied().collect();
+        self.run_tag_action(move |controller| async move {
+            controller.merge_tags(&tag_ids, into).await?;
+            controller.list_tags().await
+        });
+    }
+
+    /// Move the given files of a tag to a new tag with the name
+    fn split_files(self: Pin<&mut Self>, tag_id: i32, file_ids: &QVector<i32>, name: &QString) {
+        let selection = FileSelection::Files(file_ids.iter().copied().collect());
+        let name = name.to_string();
+        self.run_tag_action(move |controller| async move {
+            controller.split_tag(tag_id, selection, &name).await?;
+            controller.list_tags().await
+        });
+    }
+
     fn assign(self: Pin<&mut Self>, file_id: i32, tag_id: i32) {
         self.run_tag_action(move |controller| async move {
             controller.assign_tag(file_id, tag_id).await?;
@@ -527,6 +1833,162 @@ impl ffi::TagModel {
         });
     }
 
+    fn assign_files(self: Pin<&mut Self>, file_ids: &QVector<i32>, tag_ids: &QVector<i32>) {
+        let selection = FileSelection::Files(file_ids.iter().copied().collect());
+        let tag_ids: Vec<i32> = tag_ids.iter().copied().collect();
+        self.run_bulk_action(move |controller| async move {
+            controller.assign_tags(selection, &tag_ids).await
+        });
+    }
+
+    fn unassign_files(self: Pin<&mut Self>, file_ids: &QVector<i32>, tag_ids: &QVector<i32>) {
+        let selection = FileSelection::Files(file_ids.iter().copied().collect());
+        let tag_ids: Vec<i32> = tag_ids.iter().copied().collect();
+        self.run_bulk_action(move |controller| async move {
+            controller.remove_tags(selection, &tag_ids).await
+        });
+    }
+
+    fn assign_matching(
+        self: Pin<&mut Self>,
+        folder_id: i32,
+        search: &QString,
+        tag_ids: &QVector<i32>,
+    ) {
+        let selection = FileSelection::Matching {
+            folder_id: (folder_id >= 0).then_some(folder_id),
+            search: search.to_string(),
+        };
+        let tag_ids: Vec<i32> = tag_ids.iter().copied().collect();
+        self.run_bulk_action(move |controller| async move {
+            controller.assign_tags(selection, &tag_ids).await
+        });
+    }
+
+    /// Show in the status which tags the file probably should have
+    fn suggest_for(self: Pin<&mut Self>, file_id: i32) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context
+                .controller
+                .suggest_tags(file_id, TAG_SUGGESTION_LIMIT)
+                .await;
+            drop(qt_thread.queue(move |mut model| match result {
+                Ok(suggestions) if suggestions.is_empty() => {
+                    model.as_mut().set_error(QString::default());
+                    model.as_mut().set_status("No tags to suggest".into());
+                }
+                Ok(suggestions) => {
+                    let names: Vec<String> = suggestions
+                        .iter()
+                        .map(|suggestion| {
+                            format!(
+                                "{} ({:.0}%)",
+                                suggestion.name(),
+                                suggestion.confidence() * 100.0
+                            )
+                        })
+                        .collect();
+                    model.as_mut().set_error(QString::default());
+                    model
+                        .as_mut()
+                        .set_status(QString::from(&format!("Suggested: {}", names.join(", "))));
+                }
+                Err(error) => model.as_mut().set_error(error.to_string().into()),
+            }));
+        });
+    }
+
+    /// Show in the status which files the auto-tag rules would tag, without tagging them
+    fn preview_auto_tags(self: Pin<&mut Self>, folder_id: i32, search: &QString) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let selection = FileSelection::Matching {
+            folder_id: (folder_id >= 0).then_some(folder_id),
+            search: search.to_string(),
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context.controller.preview_auto_tags(selection).await;
+            drop(qt_thread.queue(move |mut model| match result {
+                Ok(preview) => {
+                    let mut lines = vec![format!("Rules would tag {} files", preview.len())];
+                    lines.extend(preview.iter().take(AUTO_TAG_PREVIEW_LINES).map(|found| {
+                        let name = found
+                            .path()
+                            .file_name()
+                            .map(|name| name.to_string_lossy())
+                            .unwrap_or_default();
+                        format!("{name}: {}", found.tags().join(", "))
+                    }));
+                    model.as_mut().set_error(QString::default());
+                    model.as_mut().set_status(QString::from(&lines.join("\n")));
+                }
+                Err(error) => model.as_mut().set_error(error.to_string().into()),
+            }));
+        });
+    }
+
+    fn apply_auto_tags(self: Pin<&mut Self>, folder_id: i32, search: &QString) {
+        let selection = FileSelection::Matching {
+            folder_id: (folder_id >= 0).then_some(folder_id),
+            search: search.to_string(),
+        };
+        self.run_bulk_action(move |controller| async move {
+            controller.apply_auto_tags(selection).await
+        });
+    }
+
+    /// Exchange tags with the extended attributes and XMP sidecars of every file
+    fn sync_files(self: Pin<&mut Self>) {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = context.controller.sync_tags().await;
+            drop(qt_thread.queue(move |mut model| match result {
+                Ok(report) => {
+                    model.as_mut().set_error(report.problems.join("\n").into());
+                    model.as_mut().set_status(QString::from(&format!(
+                        "Synced tags of {} files, wrote {}",
+                        report.files_checked, report.files_written
+                    )));
+                }
+                Err(error) => model.as_mut().set_error(error.to_string().into()),
+            }));
+        });
+    }
+
+    fn undo(self: Pin<&mut Self>) {
+        self.run_tag_action(|controller| async move {
+            controller.undo().await?;
+            controller.list_tags().await
+        });
+    }
+
+    fn redo(self: Pin<&mut Self>) {
+        self.run_tag_action(|controller| async move {
+            controller.redo().await?;
+            controller.list_tags().await
+        });
+    }
+
+    fn listen_for_changes(mut self: Pin<&mut Self>) {
+        if std::mem::replace(&mut self.as_mut().rust_mut().listening, true) {
+            return;
+        }
+        forward_changes(self.qt_thread(), |model, batch| {
+            if batch.tags_changed {
+                model.refresh();
+            }
+        });
+    }
+
     fn run_tag_action<F, Fut>(self: Pin<&mut Self>, action: F)
     where
         F: FnOnce(Arc<AppController>) -> Fut + Send + 'static,
@@ -547,6 +2009,114 @@ impl ffi::TagModel {
             }));
         });
     }
+
+    fn run_bulk_action<F, Fut>(self: Pin<&mut Self>, action: F)
+    where
+        F: FnOnce(Arc<AppController>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<BulkTagReport, controllers::ControllerError>> + Send + 'static,
+    {
+        let Some(context) = CONTEXT.get().cloned() else {
+            return;
+        };
+        let qt_thread = self.qt_thread();
+        context.runtime.spawn(async move {
+            let result = action(context.controller).await;
+            drop(qt_thread.queue(move |mut model| match result {
+                Ok(report) => {
+                    model.as_mut().set_error(QString::default());
+                    model.as_mut().set_status(QString::from(&format!(
+                        "Changed {} tags on {} files",
+                        report.changed(),
+                        report.files_selected()
+                    )));
+                }
+                Err(error) => model.as_mut().set_error(error.to_string().into()),
+            }));
+        });
+    }
+}
+
+/// Library changes received together, reduced to what the models need to update
+#[derive(Default)]
+struct ChangeBatch {
+    removed_files: Vec<i32>,
+    changed_files: Vec<i32>,
+    /// Files new to the library, which are part of `changed_files` as well
+    added_files: Vec<i32>,
+    folders_changed: bool,
+    tags_changed: bool,
+    /// Too much changed, or changes were missed, to update the files row by row
+    reload_files: bool,
+}
+
+impl ChangeBatch {
+    fn everything() -> Self {
+        Self {
+            folders_changed: true,
+            tags_changed: true,
+            reload_files: true,
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, change: LibraryChange) {
+        match change {
+            LibraryChange::FileRemoved { file_id } => self.removed_files.push(file_id),
+            LibraryChange::FileAdded { file_id } => {
+                self.added_files.push(file_id);
+                self.changed_files.push(file_id);
+            }
+            LibraryChange::FolderAdded { .. } | LibraryChange::FolderDecorated { .. } => {
+                self.folders_changed = true;
+            }
+            // Every file below the folder may have a new path or be gone
+            LibraryChange::FolderMoved { .. }
+            | LibraryChange::FolderRemoved { .. }
+            | LibraryChange::FolderResynced { .. } => {
+                self.folders_changed = true;
+                self.reload_files = true;
+            }
+            LibraryChange::TagsChanged { .. } => self.tags_changed = true,
+            // Searches on the fields may match other files now
+            LibraryChange::FieldsChanged => self.reload_files = true,
+            change => self.changed_files.extend(change.file_id()),
+        }
+    }
+}
+
+/// Apply library changes to a model on the Qt thread for as long as the model exists
+fn forward_changes<T>(qt_thread: CxxQtThread<T>, apply: fn(Pin<&mut T>, ChangeBatch))
+where
+    T: Threading + 'static,
+{
+    let Some(context) = CONTEXT.get().cloned() else {
+        return;
+    };
+    let mut changes = context.controller.subscribe_changes();
+    context.runtime.spawn(async move {
+        loop {
+            let mut batch = match changes.recv().await {
+                Ok(change) => {
+                    let mut batch = ChangeBatch::default();
+                    batch.add(change);
+                    batch
+                }
+                Err(RecvError::Lagged(_)) => ChangeBatch::everything(),
+                Err(RecvError::Closed) => break,
+            };
+            // A watcher batch arrives as a burst; apply it in one go
+            loop {
+                match changes.try_recv() {
+                    Ok(change) => batch.add(change),
+                    Err(TryRecvError::Lagged(_)) => batch = ChangeBatch::everything(),
+                    Err(_) => break,
+                }
+            }
+            if qt_thread.queue(move |model| apply(model, batch)).is_err() {
+                break;
+            }
+        }
+    });
 }
 
 fn roles(values: &[(i32, &str)]) -> QHash<QHashPair_i32_QByteArray> {
@@ -557,13 +2127,19 @@ fn roles(values: &[(i32, &str)]) -> QHash<QHashPair_i32_QByteArray> {
     roles
 }
 
-// ponytail: reset small models; add row-level diffs only when profiling shows a need.
+// Folders and tags are small enough to reset; files are updated row by row as changes arrive.
 fn reset_folders(mut model: Pin<&mut ffi::FolderModel>, items: Vec<FolderInfo>) {
     unsafe { model.as_mut().begin_reset_model() };
     model.as_mut().rust_mut().items = items;
     unsafe { model.as_mut().end_reset_model() };
 }
 
+fn reset_smart_folders(mut model: Pin<&mut ffi::SmartFolderModel>, items: Vec<SmartFolderInfo>) {
+    unsafe { model.as_mut().begin_reset_model() };
+    model.as_mut().rust_mut().items = items;
+    unsafe { model.as_mut().end_reset_model() };
+}
+
 fn reset_files(mut model: Pin<&mut ffi::FileModel>, items: Vec<FileInfo>) {
     unsafe { model.as_mut().begin_reset_model() };
     model.as_mut().rust_mut().items = items;
@@ -575,3 +2151,117 @@ fn reset_tags(mut model: Pin<&mut ffi::TagModel>, items: Vec<TagInfo>) {
     model.as_mut().rust_mut().items = items;
     unsafe { model.as_mut().end_reset_model() };
 }
+
+fn remove_file_row(mut model: Pin<&mut ffi::FileModel>, file_id: i32) {
+    let Some(row) = model.items.iter().position(|item| item.id() == file_id) else {
+        return;
+    };
+    unsafe {
+        model
+            .as_mut()
+            .begin_remove_rows(&QModelIndex::default(), row as i32, row as i32);
+    }
+    model.as_mut().rust_mut().items.remove(row);
+    unsafe { model.as_mut().end_remove_rows() };
+    let total = model.total;
+    model.as_mut().set_total((total - 1).max(0));
+}
+
+/// Update the row of a file in place, or move or insert it to where it sorts now
+///
+/// The total must already count the file. Returns false if where the file goes cannot be told from
+/// the loaded rows, the caller then reloads.
+fn update_file_row(mut model: Pin<&mut ffi::FileModel>, item: FileInfo) -> bool {
+    let Some(row) = model
+        .items
+        .iter()
+        .position(|existing| existing.id() == item.id())
+    else {
+        return insert_file_row(model, item);
+    };
+    let sort = model.sort;
+    let moved = model.items.get(row).is_none_or(|existing| {
+        existing.name() != item.name()
+            || match sort.key {
+                FileSortKey::Name => false,
+                FileSortKey::Path => existing.path() != item.path(),
+                FileSortKey::Size => existing.size() != item.size(),
+                FileSortKey::Modified => existing.modified_at() != item.modified_at(),
+                FileSortKey::Type => existing.file_type_id() != item.file_type_id(),
+                // Tag counts are not part of the rows
+                FileSortKey::TagCount => true,
+                FileSortKey::Rating => existing.rating() != item.rating(),
+                FileSortKey::Favorite => existing.favorite() != item.favorite(),
+                FileSortKey::ColorLabel => existing.color_label() != item.color_label(),
+            }
+    });
+    if moved {
+        unsafe {
+            model
+                .as_mut()
+                .begin_remove_rows(&QModelIndex::default(), row as i32, row as i32);
+        }
+        model.as_mut().rust_mut().items.remove(row);
+        unsafe { model.as_mut().end_remove_rows() };
+        return insert_file_row(model, item);
+    }
+    if let Some(existing) = model.as_mut().rust_mut().items.get_mut(row) {
+        *existing = item;
+    }
+    let index = model.index(row as i32, 0, &QModelIndex::default());
+    model
+        .as_mut()
+        .data_changed(&index, &index, &QVector::default());
+    true
+}
+
+/// Insert the row of a file that is not loaded at its sort position
+///
+/// A file sorting after the loaded rows is left to `fetchMore`, unless it is the only file that is
+/// not loaded. Returns false if the sort order depends on what the rows do not hold.
+fn insert_file_row(mut model: Pin<&mut ffi::FileModel>, item: FileInfo) -> bool {
+    let sort = model.sort;
+    let mut comparable = true;
+    let row = model.items.partition_point(|existing| {
+        let ordering = sort.compare(existing, &item);
+        comparable &= ordering.is_some();
+        ordering == Some(Ordering::Less)
+    });
+    if !comparable {
+        return false;
+    }
+    let not_loaded = usize::try_from(model.total)
+        .unwrap_or_default()
+        .saturating_sub(model.items.len());
+    if row == model.items.len() && not_loaded > 1 {
+        return true;
+    }
+    unsafe {
+        model
+            .as_mut()
+            .begin_insert_rows(&QModelIndex::default(), row as i32, row as i32);
+    }
+    model.as_mut().rust_mut().items.insert(row, item);
+    unsafe { model.as_mut().end_insert_rows() };
+    true
+}
+
+fn append_file_rows(mut model: Pin<&mut ffi::FileModel>, items: Vec<FileInfo>) {
+    if items.is_empty() {
+        return;
+    }
+    let first = model.items.len();
+    let last = first + items.len() - 1;
+    unsafe {
+        model
+            .as_mut()
+            .begin_insert_rows(&QModelIndex::default(), first as i32, last as i32);
+    }
+    model.as_mut().rust_mut().items.extend(items);
+    unsafe { model.as_mut().end_insert_rows() };
+}
+
+/// Row counts are `i32` in Qt
+fn row_total(total: u64) -> i32 {
+    i32::try_from(total).unwrap_or(i32::MAX)
+}
@@ -6,8 +6,10 @@
 )]
 mod cxxqt_object;
 
-use controllers::AppController;
+use controllers::{AppController, ControllerError};
 use cxx_qt_lib::{QGuiApplication, QQmlApplicationEngine, QUrl};
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
 use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -27,22 +29,160 @@ fn init_tracing() {
         .init();
 }
 
-fn main() {
+/// The folder of the library with the given name, or `name` itself when no library has it
+fn library_path(controller: &AppController, name: &str) -> PathBuf {
+    controller
+        .list_libraries()
+        .ok()
+        .and_then(|libraries| {
+            libraries
+                .into_iter()
+                .find(|info| info.name().as_str() == name)
+        })
+        .map_or_else(|| PathBuf::from(name), |info| info.path().to_path_buf())
+}
+
+/// Open a library for maintenance, run `operation` on it and close it again
+async fn with_library<T>(
+    controller: &AppController,
+    library: &str,
+    operation: impl AsyncFnOnce(&AppController) -> Result<T, ControllerError>,
+) -> Result<T, ControllerError> {
+    controller
+        .open_library_for_maintenance(library_path(controller, library))
+        .await?;
+    let result = operation(controller).await;
+    controller.close_library().await;
+    result
+}
+
+/// Check a library without starting the interface: `hestia verify [--repair] <library>`
+///
+/// Fails when the library still has problems afterwards.
+async fn verify(controller: &AppController, arguments: &[String]) -> ExitCode {
+    let repair = arguments.iter().any(|argument| argument == "--repair");
+    let Some(library) = arguments
+        .iter()
+        .find(|argument| !argument.starts_with("--"))
+    else {
+        eprintln!("Usage: hestia verify [--repair] <library name or folder>");
+        return ExitCode::FAILURE;
+    };
+    let result = with_library(controller, library, async |controller| {
+        controller.verify_library(repair).await
+    })
+    .await;
+    let report = match result {
+        Ok(report) => report,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for problem in &report.problems {
+        let repaired = report.repaired && problem.is_repairable();
+        println!("{}{problem}", if repaired { "repaired: " } else { "" });
+    }
+    println!(
+        "Verified {} files and {} folders; {} problems remain",
+        report.files_checked,
+        report.folders_checked,
+        report.remaining().count()
+    );
+    if report.is_healthy() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Report or maintain the stored thumbnails of a library:
+/// `hestia thumbnails [migrate | gc] <library>`
+///
+/// `migrate` moves every thumbnail into the storage set in the library config, `gc` deletes
+/// cached images that no thumbnail refers to any more. Queue and failure statistics belong to a
+/// running interface and are shown there instead.
+async fn thumbnails(controller: &AppController, arguments: &[String]) -> ExitCode {
+    let (action, library) = match arguments {
+        [library] => ("status", library),
+        [action, library] if matches!(action.as_str(), "migrate" | "gc") => {
+            (action.as_str(), library)
+        }
+        _ => {
+            eprintln!("Usage: hestia thumbnails [migrate | gc] <library name or folder>");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = with_library(controller, library, async |controller| match action {
+        "migrate" => controller.migrate_thumbnail_storage().await.map(|report| {
+            format!(
+                "Moved {} thumbnails; removed {} cached images, freeing {} bytes",
+                report.migrated(),
+                report.files_removed(),
+                report.bytes_freed()
+            )
+        }),
+        "gc" => controller.collect_thumbnail_garbage().await.map(|report| {
+            format!(
+                "Removed {} cached images, freeing {} bytes",
+                report.files_removed(),
+                report.bytes_freed()
+            )
+        }),
+        _ => controller.thumbnail_status().await.map(|status| {
+            format!(
+                "{} thumbnails stored in {} bytes; {} files have no thumbnail yet",
+                status.stored(),
+                status.storage_bytes(),
+                status.missing()
+            )
+        }),
+    })
+    .await;
+
+    match result {
+        Ok(summary) => {
+            println!("{summary}");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
     init_tracing();
 
     let Ok(backend_runtime) = tokio::runtime::Runtime::new() else {
         tracing::error!("Could not start the backend task runtime.");
-        return;
+        return ExitCode::FAILURE;
     };
     let Ok(controller) = AppController::new() else {
         tracing::error!("Could not initialize Hestia's application data directory.");
-        return;
+        return ExitCode::FAILURE;
     };
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    match arguments
+        .split_first()
+        .map(|(command, arguments)| (command.as_str(), arguments))
+    {
+        Some(("verify", arguments)) => {
+            return backend_runtime.block_on(verify(&controller, arguments));
+        }
+        Some(("thumbnails", arguments)) => {
+            return backend_runtime.block_on(thumbnails(&controller, arguments));
+        }
+        _ => {}
+    }
+    let controller = Arc::new(controller);
     if let Err(error) =
-        cxxqt_object::initialize(Arc::new(controller), backend_runtime.handle().clone())
+        cxxqt_object::initialize(Arc::clone(&controller), backend_runtime.handle().clone())
     {
         tracing::error!(error = %error, "Could not initialize the Qt backend");
-        return;
+        return ExitCode::FAILURE;
     }
 
     let mut app = QGuiApplication::new();
@@ -55,4 +195,7 @@ fn main() {
     if let Some(app) = app.as_mut() {
         app.exec();
     }
+    // Let running backups and thumbnail jobs finish before the runtime is dropped
+    backend_runtime.block_on(controller.close_library());
+    ExitCode::SUCCESS
 }
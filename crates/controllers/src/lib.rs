@@ -1,24 +1,74 @@
 use anyhow::{Context, Result};
-use entity::{file_has_tags, files, folders, tags};
+use chrono::{NaiveDateTime, Utc};
+use entity::{
+    custom_fields, file_field_values, file_has_tags, file_types, files, folders, media_metadata,
+    tags,
+};
+use events::changes::ChangeBus;
 use library::library::{Library, LibraryConfig, LibraryPathConfig};
 use migration::{Migrator, MigratorTrait};
+use model::commands::filter::SavedSearch;
+use model::commands::history::{HistoryEntry, TagCommand};
+use model::commands::query::{Comparison, FieldFilter, SearchFilter, SearchQuery};
 use model::services::CanonPath;
+use model::services::journal::{JournalEntry, JournalEventKind, JournalSource};
+use model::services::media::MediaMetadata;
+use model::services::tag_suggestion::TagSuggestion;
+use model::services::thumbnail::{ThumbnailSize, ThumbnailStorageKind};
 use repositories::config::DatabaseSettings;
+use repositories::curation::operations::CurationOperations;
+use repositories::fields::operations::FieldOperations;
+use repositories::folders::operations::FolderOperations;
 use repositories::fs::operations::FileRepository;
+use repositories::history::operations::HistoryOperations;
+use repositories::integrity::operations::IntegrityOperations;
+use repositories::journal::operations::JournalOperations;
 use repositories::manager::DatabaseManager;
+use repositories::media::operations::MediaMetadataOperations;
+use repositories::searches::operations::SavedSearchOperations;
+use repositories::tags::operations::TagOperations;
+use repositories::thumbnail::cache::ThumbnailCache;
 use repositories::thumbnail::operations::ThumbnailOperations;
+use sea_orm::sea_query::{CaseStatement, Expr, Func, Query, SimpleExpr};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, EntityTrait, IntoActiveModel, QueryFilter,
-    QueryOrder, Set, TransactionTrait,
+    ColumnTrait, Condition, EntityTrait, JoinType, Order, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait, RelationTrait, Select,
 };
+use services::backup::archive::LibraryBackups;
 use services::fs::scanner::DirectoryScanner;
-use services::fs::watcher::{DatabaseFileWatcherEventHandler, FileWatcher, FileWatcherMessage};
+use services::fs::watcher::{DatabaseFileWatcherEventHandler, FileWatcher, FileWatcherHandler};
+use services::tagging::auto_tag::{AutoTagMatch, AutoTagger};
+use services::tagging::tag_sync::TagSync;
 use services::thumbnails::generator::ThumbnailGenerator;
-use services::thumbnails::thumbnails::{ThumbnailProcessor, ThumbnailProcessorHandler};
+use services::thumbnails::thumbnails::{
+    FailedJob, ProcessingStats, ThumbnailProcessor, ThumbnailProcessorHandler,
+};
+use std::cmp::Ordering;
 use std::fmt::{self, Display, Formatter};
 use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
+use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{Mutex, broadcast};
+use tokio::task::JoinHandle;
+
+pub use events::changes::LibraryChange;
+pub use model::commands::filter::{DateFilter, Filter, FolderFilter, TagFilter};
+pub use model::commands::tag::Tag;
+pub use model::commands::watched_folders::{FolderStats, WatchedFolderTree};
+pub use model::services::backup::{BackupConfig, BackupManifest};
+pub use model::services::curation::{ColorLabel, MAX_RATING};
+pub use model::services::custom_field::{
+    FieldDefinition, FieldExport, FieldImportReport, FieldType, FieldValue,
+};
+pub use model::services::decorations::{Color, Icon, IconType};
+pub use model::services::folder::FolderDecoration;
+pub use model::services::integrity::{IntegrityProblem, IntegrityReport};
+pub use model::services::tag::TagDetails;
+pub use model::services::tag_suggestion::SuggestionSource;
+pub use model::services::tag_sync::{TagSyncConfig, TagSyncConflict};
+pub use services::backup::archive::Backup;
+pub use services::tagging::tag_sync::TagSyncReport;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct LibraryName(String);
@@ -77,12 +127,48 @@ impl LibraryInfo {
     }
 }
 
+/// A saved search, shown next to the folders
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SmartFolderInfo {
+    id: i32,
+    name: String,
+    filter: Filter,
+}
+
+impl SmartFolderInfo {
+    #[must_use]
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+}
+
+impl From<SavedSearch> for SmartFolderInfo {
+    fn from(search: SavedSearch) -> Self {
+        Self {
+            id: search.id,
+            name: search.name,
+            filter: search.filter,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct FolderInfo {
     id: i32,
     name: String,
     path: PathBuf,
     parent_id: Option<i32>,
+    decoration: FolderDecoration,
 }
 
 impl FolderInfo {
@@ -105,15 +191,30 @@ impl FolderInfo {
     pub fn parent_id(&self) -> Option<i32> {
         self.parent_id
     }
+
+    #[must_use]
+    pub fn decoration(&self) -> &FolderDecoration {
+        &self.decoration
+    }
+
+    /// The display name if the folder has one, its name otherwise
+    #[must_use]
+    pub fn label(&self) -> &str {
+        self.decoration
+            .display_name
+            .as_deref()
+            .unwrap_or(&self.name)
+    }
 }
 
-impl From<folders::Model> for FolderInfo {
-    fn from(folder: folders::Model) -> Self {
+impl From<(folders::Model, FolderDecoration)> for FolderInfo {
+    fn from((folder, decoration): (folders::Model, FolderDecoration)) -> Self {
         Self {
             id: folder.id,
             name: folder.name,
             path: folder.path.into(),
             parent_id: folder.parent_folder_id,
+            decoration,
         }
     }
 }
@@ -124,6 +225,12 @@ pub struct FileInfo {
     name: String,
     path: PathBuf,
     file_type_id: i32,
+    size: u64,
+    modified_at: NaiveDateTime,
+    thumbnail_path: Option<PathBuf>,
+    rating: u8,
+    favorite: bool,
+    color_label: Option<ColorLabel>,
 }
 
 impl FileInfo {
@@ -147,10 +254,38 @@ impl FileInfo {
         self.file_type_id
     }
 
+    /// Size in bytes, 0 for files that have not been scanned since sizes are recorded
+    #[must_use]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    #[must_use]
+    pub fn modified_at(&self) -> NaiveDateTime {
+        self.modified_at
+    }
+
     #[must_use]
     pub fn thumbnail_path(&self) -> Option<&Path> {
-        // ponytail: thumbnails are DB blobs; add a QML image provider before exposing URLs.
-        None
+        // Only thumbnails kept in the cache directory have a path; database blobs still need
+        // a QML image provider before they can be exposed as URLs.
+        self.thumbnail_path.as_deref()
+    }
+
+    /// Stars from 1 to [`MAX_RATING`], 0 for files that are not rated
+    #[must_use]
+    pub fn rating(&self) -> u8 {
+        self.rating
+    }
+
+    #[must_use]
+    pub fn favorite(&self) -> bool {
+        self.favorite
+    }
+
+    #[must_use]
+    pub fn color_label(&self) -> Option<ColorLabel> {
+        self.color_label
     }
 }
 
@@ -161,6 +296,146 @@ impl From<files::Model> for FileInfo {
             name: file.name,
             path: file.path.into(),
             file_type_id: file.file_type_id,
+            size: u64::try_from(file.size).unwrap_or_default(),
+            modified_at: file.modified_at,
+            thumbnail_path: None,
+            rating: u8::try_from(file.rating).unwrap_or_default(),
+            favorite: file.favorite,
+            color_label: file
+                .color_label
+                .and_then(|label| ColorLabel::parse(&label).ok()),
+        }
+    }
+}
+
+/// What file listings are ordered by, files that compare equal are ordered by name
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum FileSortKey {
+    #[default]
+    Name,
+    Path,
+    Size,
+    Modified,
+    Type,
+    TagCount,
+    Rating,
+    Favorite,
+    /// Labeled files in the order of [`ColorLabel::ALL`], unlabeled files last
+    ColorLabel,
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileSort {
+    pub key: FileSortKey,
+    pub descending: bool,
+}
+
+impl FileSort {
+    /// Order of two files in a listing sorted this way
+    ///
+    /// Returns `None` when the order depends on what the rows do not hold, the type name or the
+    /// number of tags.
+    #[must_use]
+    pub fn compare(self, a: &FileInfo, b: &FileInfo) -> Option<Ordering> {
+        let label = |file: &FileInfo| {
+            file.color_label
+                .and_then(|label| ColorLabel::ALL.iter().position(|other| *other == label))
+                .unwrap_or(usize::MAX)
+        };
+        let by_key = match self.key {
+            FileSortKey::Name => Ordering::Equal,
+            // Paths are compared as stored, not component by component
+            FileSortKey::Path => a.path.as_os_str().cmp(b.path.as_os_str()),
+            FileSortKey::Size => a.size.cmp(&b.size),
+            FileSortKey::Modified => a.modified_at.cmp(&b.modified_at),
+            FileSortKey::Type | FileSortKey::TagCount => return None,
+            FileSortKey::Rating => a.rating.cmp(&b.rating),
+            FileSortKey::Favorite => a.favorite.cmp(&b.favorite),
+            FileSortKey::ColorLabel => label(a).cmp(&label(b)),
+        };
+        let ordering = by_key
+            .then_with(|| a.name.cmp(&b.name))
+            .then(a.id.cmp(&b.id));
+        Some(if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        })
+    }
+}
+
+/// A slice of a file listing together with the number of files in the whole listing
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FilePage {
+    files: Vec<FileInfo>,
+    total: u64,
+}
+
+impl FilePage {
+    #[must_use]
+    pub fn files(&self) -> &[FileInfo] {
+        &self.files
+    }
+
+    #[must_use]
+    pub fn into_files(self) -> Vec<FileInfo> {
+        self.files
+    }
+
+    #[must_use]
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaInfo {
+    taken_at: Option<NaiveDateTime>,
+    camera_make: Option<String>,
+    camera_model: Option<String>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+impl MediaInfo {
+    #[must_use]
+    pub fn taken_at(&self) -> Option<NaiveDateTime> {
+        self.taken_at
+    }
+
+    #[must_use]
+    pub fn camera_make(&self) -> Option<&str> {
+        self.camera_make.as_deref()
+    }
+
+    #[must_use]
+    pub fn camera_model(&self) -> Option<&str> {
+        self.camera_model.as_deref()
+    }
+
+    #[must_use]
+    pub fn location(&self) -> Option<(f64, f64)> {
+        self.latitude.zip(self.longitude)
+    }
+
+    #[must_use]
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.width.zip(self.height)
+    }
+}
+
+impl From<MediaMetadata> for MediaInfo {
+    fn from(metadata: MediaMetadata) -> Self {
+        Self {
+            taken_at: metadata.taken_at,
+            camera_make: metadata.camera_make,
+            camera_model: metadata.camera_model,
+            latitude: metadata.latitude,
+            longitude: metadata.longitude,
+            width: metadata.width,
+            height: metadata.height,
         }
     }
 }
@@ -169,6 +444,7 @@ impl From<files::Model> for FileInfo {
 pub struct TagInfo {
     id: i32,
     name: String,
+    details: TagDetails,
 }
 
 impl TagInfo {
@@ -181,728 +457,4331 @@ impl TagInfo {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    #[must_use]
+    pub fn color(&self) -> Option<&Color> {
+        self.details.color.as_ref()
+    }
+
+    #[must_use]
+    pub fn icon(&self) -> Option<&Icon> {
+        self.details.icon.as_ref()
+    }
+
+    #[must_use]
+    pub fn description(&self) -> Option<&str> {
+        self.details.description.as_deref()
+    }
+
+    #[must_use]
+    pub fn aliases(&self) -> &[String] {
+        &self.details.aliases
+    }
+
+    #[must_use]
+    pub fn details(&self) -> &TagDetails {
+        &self.details
+    }
 }
 
-impl From<tags::Model> for TagInfo {
-    fn from(tag: tags::Model) -> Self {
+impl From<(tags::Model, TagDetails)> for TagInfo {
+    fn from((tag, details): (tags::Model, TagDetails)) -> Self {
         Self {
             id: tag.id,
             name: tag.name,
+            details,
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub struct ScanReport {
-    files_scanned: usize,
+/// The files a bulk operation applies to
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileSelection {
+    Files(Vec<i32>),
+    /// Every file that [`AppController::list_files`] returns for the same arguments
+    Matching {
+        folder_id: Option<i32>,
+        search: String,
+    },
+    /// Every file that [`AppController::list_smart_folder_files`] returns for the same arguments
+    SmartFolder {
+        smart_folder_id: i32,
+        search: String,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct BulkTagReport {
+    files_selected: usize,
     changed: usize,
 }
 
-impl ScanReport {
+impl BulkTagReport {
     #[must_use]
-    pub fn files_scanned(self) -> usize {
-        self.files_scanned
+    pub fn files_selected(self) -> usize {
+        self.files_selected
     }
 
+    /// Number of tag assignments that were added or removed
     #[must_use]
     pub fn changed(self) -> usize {
         self.changed
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub enum ControllerOperation {
-    OpenDataDirectory,
-    ListLibraries,
-    OpenLibrary,
-    CreateLibrary,
-    InitializeLibrary,
-    QueryLibrary,
-    ScanLibrary,
-    StartWatcher,
-    GenerateThumbnails,
-    ManageTags,
+/// Tags the auto-tag rules would assign to a file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutoTagPreview {
+    file_id: i32,
+    path: PathBuf,
+    tags: Vec<String>,
 }
 
-impl ControllerOperation {
-    fn action(self) -> &'static str {
-        match self {
-            Self::OpenDataDirectory => "Could not open the application data directory",
-            Self::ListLibraries => "Could not list libraries",
-            Self::OpenLibrary => "Could not open the library",
-            Self::CreateLibrary => "Could not create the library",
-            Self::InitializeLibrary => "Could not initialize the library",
-            Self::QueryLibrary => "Could not query the library",
-            Self::ScanLibrary => "Could not scan the library",
-            Self::StartWatcher => "Could not watch the library folders",
-            Self::GenerateThumbnails => "Could not generate thumbnails",
-            Self::ManageTags => "Could not update tags",
-        }
+impl AutoTagPreview {
+    #[must_use]
+    pub fn file_id(&self) -> i32 {
+        self.file_id
     }
-}
 
-#[derive(Debug)]
-pub enum ControllerError {
-    InvalidLibraryName,
-    InvalidLibraryFolderName,
-    NotHestiaLibrary,
-    InvalidContentFolder,
-    LibraryAlreadyExists,
-    NoLibrarySelected,
-    MissingStorageFolder,
-    NoContentFolders,
-    InvalidTagName,
-    FileNotFound,
-    TagNotFound,
-    OperationFailed {
-        operation: ControllerOperation,
-        source: anyhow::Error,
-    },
-}
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-impl ControllerError {
-    fn operation(operation: ControllerOperation, source: impl Into<anyhow::Error>) -> Self {
-        Self::OperationFailed {
-            operation,
-            source: source.into(),
-        }
+    #[must_use]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
     }
 }
 
-impl Display for ControllerError {
-    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::InvalidLibraryName => {
-                formatter.write_str("Library name must be a single folder name.")
-            }
-            Self::InvalidLibraryFolderName => {
-                formatter.write_str("A library has an invalid folder name.")
-            }
-            Self::NotHestiaLibrary => {
-                formatter.write_str("The selected folder is not a Hestia library.")
-            }
-            Self::InvalidContentFolder => {
-                formatter.write_str("The library content path must be a folder.")
-            }
-            Self::LibraryAlreadyExists => {
-                formatter.write_str("A library with that name already exists.")
-            }
-            Self::NoLibrarySelected => {
-                formatter.write_str("Select a library before initializing it.")
-            }
-            Self::MissingStorageFolder => formatter.write_str("The library has no storage folder."),
-            Self::NoContentFolders => formatter.write_str("The library has no content folders."),
-            Self::InvalidTagName => formatter.write_str("Tag names cannot be empty."),
-            Self::FileNotFound => formatter.write_str("The selected file no longer exists."),
-            Self::TagNotFound => formatter.write_str("The selected tag no longer exists."),
-            Self::OperationFailed { operation, source } => {
-                write!(formatter, "{}: {source:#}", operation.action())
-            }
+impl From<AutoTagMatch> for AutoTagPreview {
+    fn from(found: AutoTagMatch) -> Self {
+        Self {
+            file_id: found.file_id,
+            path: found.path,
+            tags: found.tags,
         }
     }
 }
 
-impl std::error::Error for ControllerError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::OperationFailed { source, .. } => Some(source.as_ref()),
-            _ => None,
-        }
-    }
+/// A tag that may fit a file
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagSuggestionInfo {
+    tag_id: i32,
+    name: String,
+    confidence: f64,
+    sources: Vec<SuggestionSource>,
 }
 
-type ControllerResult<T> = std::result::Result<T, ControllerError>;
+impl TagSuggestionInfo {
+    #[must_use]
+    pub fn tag_id(&self) -> i32 {
+        self.tag_id
+    }
 
-#[derive(Debug)]
-struct Workspace {
-    database_manager: Arc<DatabaseManager>,
-    file_operations: Arc<FileRepository>,
-    thumbnail_processor: ThumbnailProcessorHandler,
-    watcher_started: bool,
-}
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-#[derive(Debug)]
-enum AppState {
-    AwaitingLibrary,
-    Ready {
-        library: Library,
-        workspace: Workspace,
-    },
-}
+    /// Between 0 and 1, higher is more likely
+    #[must_use]
+    pub fn confidence(&self) -> f64 {
+        self.confidence
+    }
 
-#[derive(Debug)]
-pub struct AppController {
-    data_home: PathBuf,
-    state: Mutex<AppState>,
+    #[must_use]
+    pub fn sources(&self) -> &[SuggestionSource] {
+        &self.sources
+    }
 }
 
-impl AppController {
-    pub fn new() -> ControllerResult<Self> {
-        let data_home = library::io::create_or_validate_data_directory().map_err(|error| {
-            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
-        })?;
-        Self::new_in(data_home)
+impl From<TagSuggestion> for TagSuggestionInfo {
+    fn from(suggestion: TagSuggestion) -> Self {
+        Self {
+            tag_id: suggestion.tag_id,
+            name: suggestion.name,
+            confidence: suggestion.confidence,
+            sources: suggestion.sources,
+        }
     }
+}
 
-    pub fn new_in(data_home: impl AsRef<Path>) -> ControllerResult<Self> {
-        std::fs::create_dir_all(data_home.as_ref()).map_err(|error| {
-            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
-        })?;
-        let data_home = data_home.as_ref().canonicalize().map_err(|error| {
-            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
-        })?;
-        std::fs::create_dir_all(data_home.join("hestia")).map_err(|error| {
-            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
-        })?;
+/// One undoable tag change
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HistoryInfo {
+    id: i32,
+    created_at: NaiveDateTime,
+    label: String,
+    undone: bool,
+}
 
-        Ok(Self {
-            data_home,
-            state: Mutex::new(AppState::AwaitingLibrary),
-        })
+impl HistoryInfo {
+    #[must_use]
+    pub fn id(&self) -> i32 {
+        self.id
     }
 
-    pub fn list_libraries(&self) -> ControllerResult<Vec<LibraryInfo>> {
-        let mut libraries = Library::list_libraries_in(&self.data_home)
-            .map_err(|error| ControllerError::operation(ControllerOperation::ListLibraries, error))?
-            .into_iter()
-            .map(PathBuf::from)
-            .map(LibraryInfo::from_path)
-            .collect::<ControllerResult<Vec<_>>>()?;
-        libraries.sort_by(|left, right| left.name.as_str().cmp(right.name.as_str()));
-        Ok(libraries)
+    #[must_use]
+    pub fn created_at(&self) -> NaiveDateTime {
+        self.created_at
     }
 
-    pub async fn select_library(&self, path: impl AsRef<Path>) -> ControllerResult<LibraryInfo> {
-        let path = path
-            .as_ref()
-            .canonicalize()
-            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))?;
-        let libraries_root = self.data_home.join("hestia");
-        if !path.starts_with(&libraries_root)
-            || !path.is_dir()
-            || !path.join("config.toml").is_file()
-        {
-            return Err(ControllerError::NotHestiaLibrary);
-        }
-
-        let library = Library::new_in(&self.data_home)
-            .switch_or_create_lib_in(&path, &self.data_home)
-            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))?;
-        self.activate(library).await
+    #[must_use]
+    pub fn label(&self) -> &str {
+        &self.label
     }
 
-    pub async fn create_library(
-        &self,
-        name: &str,
-        content_path: impl AsRef<Path>,
-    ) -> ControllerResult<LibraryInfo> {
-        let name = LibraryName::parse(name)?;
-        let content_path = content_path
-            .as_ref()
-            .canonicalize()
-            .map_err(|_| ControllerError::InvalidContentFolder)?;
-        if !content_path.is_dir() {
-            return Err(ControllerError::InvalidContentFolder);
-        }
+    /// Whether the change was undone and can be redone
+    #[must_use]
+    pub fn undone(&self) -> bool {
+        self.undone
+    }
+}
 
-        let share_path = self.data_home.join("hestia").join(name.as_str());
-        if share_path.try_exists().map_err(|error| {
-            ControllerError::operation(ControllerOperation::CreateLibrary, error)
-        })? {
-            return Err(ControllerError::LibraryAlreadyExists);
+impl From<HistoryEntry> for HistoryInfo {
+    fn from(entry: HistoryEntry) -> Self {
+        Self {
+            id: entry.id,
+            created_at: entry.created_at,
+            label: entry.label,
+            undone: entry.undone,
         }
+    }
+}
 
-        let content_name = content_path
-            .file_name()
-            .and_then(|value| value.to_str())
-            .unwrap_or("Folder")
-            .to_string();
-        let mut config = LibraryConfig::default();
-        config.name = name.to_string();
-        config.library_paths = vec![LibraryPathConfig {
-            name: Some(content_name),
-            path: Some(content_path),
-        }];
+/// Which part of the library to list the activity of
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ActivityScope {
+    Library,
+    File(i32),
+    /// Every file that is or was below the folder
+    Folder(i32),
+}
 
-        let mut library = Library::new_in(&self.data_home);
-        library.share_path = Some(share_path);
-        library.library_config = Some(config);
-        library.save_config().map_err(|error| {
-            ControllerError::operation(ControllerOperation::CreateLibrary, error)
-        })?;
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActivityInfo {
+    occurred_at: NaiveDateTime,
+    kind: JournalEventKind,
+    source: JournalSource,
+    file_id: Option<i32>,
+    old_path: Option<PathBuf>,
+    new_path: Option<PathBuf>,
+    old_hash: Option<String>,
+    new_hash: Option<String>,
+    detail: Option<String>,
+}
 
-        self.activate(library).await
+impl ActivityInfo {
+    #[must_use]
+    pub fn occurred_at(&self) -> NaiveDateTime {
+        self.occurred_at
     }
 
-    pub async fn initialize_workspace(&self) -> ControllerResult<()> {
-        let (database_manager, file_operations, library_paths) = {
-            let state = self.state.lock().await;
-            let AppState::Ready { library, workspace } = &*state else {
-                return Err(ControllerError::NoLibrarySelected);
-            };
-            let library_paths = library
-                .library_config
-                .as_ref()
-                .map(|config| {
-                    config
-                        .library_paths
-                        .iter()
-                        .filter_map(|path| path.path.clone())
-                        .collect::<Vec<_>>()
-                })
-                .unwrap_or_default();
-            (
-                Arc::clone(&workspace.database_manager),
-                Arc::clone(&workspace.file_operations),
-                library_paths,
-            )
-        };
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        self.kind.as_str()
+    }
 
-        Migrator::up(database_manager.get_connection().as_ref(), None)
-            .await
-            .context("database migration failed")
-            .map_err(|error| {
-                ControllerError::operation(ControllerOperation::InitializeLibrary, error)
-            })?;
-        if library_paths.is_empty() {
-            return Err(ControllerError::NoContentFolders);
-        }
-        file_operations
-            .upsert_root_folders(library_paths)
-            .await
-            .map_err(|error| {
-                ControllerError::operation(ControllerOperation::InitializeLibrary, error)
-            })?;
-        Ok(())
+    #[must_use]
+    pub fn source(&self) -> &'static str {
+        self.source.as_str()
     }
 
-    pub async fn scan(&self) -> ControllerResult<ScanReport> {
-        let (file_operations, library_paths) = {
-            let state = self.state.lock().await;
-            let AppState::Ready { library, workspace } = &*state else {
-                return Err(ControllerError::NoLibrarySelected);
-            };
-            let paths = library
-                .library_config
-                .as_ref()
-                .map(|config| {
-                    config
-                        .library_paths
-                        .iter()
-                        .filter_map(|path| path.path.clone())
-                        .collect::<Vec<_>>()
-                })
-                .unwrap_or_default();
-            (Arc::clone(&workspace.file_operations), paths)
-        };
+    #[must_use]
+    pub fn file_id(&self) -> Option<i32> {
+        self.file_id
+    }
 
-        let scanner = DirectoryScanner::new(file_operations);
-        let mut result = ScanReport {
-            files_scanned: 0,
-            changed: 0,
-        };
-        for path in library_paths {
-            let report = scanner.sync_directory(&path).await.map_err(|error| {
-                ControllerError::operation(ControllerOperation::ScanLibrary, error)
-            })?;
-            result.files_scanned += report.files_scanned;
-            result.changed += report.files_inserted
-                + report.files_updated
-                + report.files_deleted
-                + report.folders_inserted
-                + report.folders_updated
-                + report.folders_deleted;
-        }
-        Ok(result)
+    #[must_use]
+    pub fn old_path(&self) -> Option<&Path> {
+        self.old_path.as_deref()
     }
 
-    pub async fn generate_thumbnails(&self) -> ControllerResult<()> {
-        let state = self.state.lock().await;
-        let AppState::Ready { workspace, .. } = &*state else {
-            return Err(ControllerError::NoLibrarySelected);
-        };
-        workspace
-            .thumbnail_processor
-            .queue_missing_files()
-            .await
-            .map_err(|error| {
-                ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
-            })
+    #[must_use]
+    pub fn new_path(&self) -> Option<&Path> {
+        self.new_path.as_deref()
     }
 
-    pub async fn start_watching(&self) -> ControllerResult<mpsc::UnboundedReceiver<()>> {
-        let (database_manager, paths) = {
-            let mut state = self.state.lock().await;
-            let AppState::Ready { library, workspace } = &mut *state else {
-                return Err(ControllerError::NoLibrarySelected);
-            };
-            if workspace.watcher_started {
-                return Err(ControllerError::operation(
-                    ControllerOperation::StartWatcher,
-                    anyhow::anyhow!("the library is already being watched"),
-                ));
-            }
-            workspace.watcher_started = true;
-            let paths = library
-                .library_config
-                .as_ref()
-                .map(|config| {
-                    config
-                        .library_paths
-                        .iter()
-                        .filter_map(|path| path.path.clone())
-                        .collect::<Vec<_>>()
-                })
-                .unwrap_or_default();
-            (Arc::clone(&workspace.database_manager), paths)
-        };
+    #[must_use]
+    pub fn old_hash(&self) -> Option<&str> {
+        self.old_hash.as_deref()
+    }
 
-        let (watcher_sender, watcher_receiver) = mpsc::unbounded_channel();
-        let (changes_sender, changes_receiver) = mpsc::unbounded_channel();
-        let event_handler = DatabaseFileWatcherEventHandler {
-            db_operations: FileRepository::new(database_manager),
-            changes: Some(changes_sender),
-        };
-        tokio::spawn(async move {
-            if let Err(error) = FileWatcher::new(watcher_receiver)
-                .run(Box::new(event_handler))
-                .await
-            {
-                tracing::error!(%error, "File watcher stopped");
-            }
-        });
-        for path in paths {
-            watcher_sender
-                .send(FileWatcherMessage::WatchPath(CanonPath::from(path)))
-                .map_err(|error| {
-                    ControllerError::operation(ControllerOperation::StartWatcher, error)
-                })?;
-        }
-        Ok(changes_receiver)
+    #[must_use]
+    pub fn new_hash(&self) -> Option<&str> {
+        self.new_hash.as_deref()
     }
 
-    pub async fn list_folders(&self) -> ControllerResult<Vec<FolderInfo>> {
-        let database_manager = self.database_manager().await?;
-        folders::Entity::find()
-            .order_by_asc(folders::Column::Name)
-            .all(database_manager.get_connection().as_ref())
-            .await
-            .map(|items| items.into_iter().map(Into::into).collect())
-            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    /// Extra context, such as the name of the assigned or removed tag
+    #[must_use]
+    pub fn detail(&self) -> Option<&str> {
+        self.detail.as_deref()
     }
+}
 
-    pub async fn list_files(
-        &self,
-        folder_id: Option<i32>,
-        search: &str,
-    ) -> ControllerResult<Vec<FileInfo>> {
-        let database_manager = self.database_manager().await?;
-        let connection = database_manager.get_connection();
-        let mut condition = Condition::all();
-        if let Some(folder_id) = folder_id {
-            let folder = folders::Entity::find_by_id(folder_id)
-                .one(connection.as_ref())
-                .await
-                .map_err(|error| {
-                    ControllerError::operation(ControllerOperation::QueryLibrary, error)
-                })?
-                .ok_or(ControllerError::FileNotFound)?;
-            condition = condition.add(files::Column::Path.starts_with(folder.path));
-        }
-        let search = search.trim();
-        if !search.is_empty() {
-            condition = condition.add(
-                Condition::any()
-                    .add(files::Column::Name.contains(search))
-                    .add(files::Column::Path.contains(search)),
-            );
+impl From<JournalEntry> for ActivityInfo {
+    fn from(entry: JournalEntry) -> Self {
+        Self {
+            occurred_at: entry.occurred_at,
+            kind: entry.kind,
+            source: entry.source,
+            file_id: entry.file_id,
+            old_path: entry.old_path,
+            new_path: entry.new_path,
+            old_hash: entry.old_hash,
+            new_hash: entry.new_hash,
+            detail: entry.detail,
         }
-
-        files::Entity::find()
-            .filter(condition)
-            .order_by_asc(files::Column::Name)
-            .all(connection.as_ref())
-            .await
-            .map(|items| items.into_iter().map(Into::into).collect())
-            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ScanReport {
+    files_scanned: usize,
+    changed: usize,
+    tags_assigned: usize,
+}
+
+impl ScanReport {
+    #[must_use]
+    pub fn files_scanned(self) -> usize {
+        self.files_scanned
+    }
+
+    #[must_use]
+    pub fn changed(self) -> usize {
+        self.changed
+    }
+
+    /// Tags assigned by the auto-tag rules of the library
+    #[must_use]
+    pub fn tags_assigned(self) -> usize {
+        self.tags_assigned
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ThumbnailMaintenanceReport {
+    migrated: u64,
+    files_removed: u64,
+    bytes_freed: u64,
+}
+
+impl ThumbnailMaintenanceReport {
+    #[must_use]
+    pub fn migrated(self) -> u64 {
+        self.migrated
+    }
+
+    #[must_use]
+    pub fn files_removed(self) -> u64 {
+        self.files_removed
+    }
+
+    #[must_use]
+    pub fn bytes_freed(self) -> u64 {
+        self.bytes_freed
+    }
+}
+
+/// Progress of thumbnail generation and the space used by stored thumbnails
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ThumbnailStatus {
+    pending: usize,
+    processing: usize,
+    completed: u64,
+    failed: u64,
+    workers: usize,
+    throughput_per_second: f64,
+    average_processing_time: Duration,
+    stored: u64,
+    storage_bytes: u64,
+    missing: u64,
+}
+
+impl ThumbnailStatus {
+    #[must_use]
+    pub fn pending(self) -> usize {
+        self.pending
+    }
+
+    #[must_use]
+    pub fn processing(self) -> usize {
+        self.processing
+    }
+
+    #[must_use]
+    pub fn completed(self) -> u64 {
+        self.completed
+    }
+
+    #[must_use]
+    pub fn failed(self) -> u64 {
+        self.failed
+    }
+
+    #[must_use]
+    pub fn workers(self) -> usize {
+        self.workers
+    }
+
+    #[must_use]
+    pub fn throughput_per_second(self) -> f64 {
+        self.throughput_per_second
+    }
+
+    #[must_use]
+    pub fn average_processing_time(self) -> Duration {
+        self.average_processing_time
+    }
+
+    /// Number of thumbnails stored in the library
+    #[must_use]
+    pub fn stored(self) -> u64 {
+        self.stored
+    }
+
+    #[must_use]
+    pub fn storage_bytes(self) -> u64 {
+        self.storage_bytes
+    }
+
+    /// Number of files that have no thumbnail to show yet
+    #[must_use]
+    pub fn missing(self) -> u64 {
+        self.missing
+    }
+
+    /// Work is queued but no worker has finished a job recently
+    #[must_use]
+    pub fn is_stalled(self) -> bool {
+        self.pending > 0 && self.processing == 0 && self.throughput_per_second == 0.0
+    }
+}
+
+/// A thumbnail that could not be generated after all retries
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FailedThumbnailInfo {
+    file_id: i32,
+    path: PathBuf,
+    size: ThumbnailSize,
+    reason: String,
+    attempts: u32,
+}
+
+impl FailedThumbnailInfo {
+    #[must_use]
+    pub fn file_id(&self) -> i32 {
+        self.file_id
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn size(&self) -> ThumbnailSize {
+        self.size
+    }
+
+    #[must_use]
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    #[must_use]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+impl From<FailedJob> for FailedThumbnailInfo {
+    fn from(job: FailedJob) -> Self {
+        Self {
+            file_id: job.file_id,
+            path: job.file_path,
+            size: job.size,
+            reason: job.reason,
+            attempts: job.attempts,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ControllerOperation {
+    OpenDataDirectory,
+    ListLibraries,
+    OpenLibrary,
+    CreateLibrary,
+    InitializeLibrary,
+    QueryLibrary,
+    ScanLibrary,
+    StartWatcher,
+    ControlWatcher,
+    GenerateThumbnails,
+    MaintainThumbnails,
+    ManageTags,
+    EditHistory,
+    AutoTag,
+    ManageSmartFolders,
+    DecorateFolder,
+    ManageFields,
+    CurateFiles,
+    SyncTags,
+    VerifyLibrary,
+    BackupLibrary,
+    RestoreLibrary,
+}
+
+impl ControllerOperation {
+    fn action(self) -> &'static str {
+        match self {
+            Self::OpenDataDirectory => "Could not open the application data directory",
+            Self::ListLibraries => "Could not list libraries",
+            Self::OpenLibrary => "Could not open the library",
+            Self::CreateLibrary => "Could not create the library",
+            Self::InitializeLibrary => "Could not initialize the library",
+            Self::QueryLibrary => "Could not query the library",
+            Self::ScanLibrary => "Could not scan the library",
+            Self::StartWatcher => "Could not watch the library folders",
+            Self::ControlWatcher => "Could not change the state of the file watcher",
+            Self::GenerateThumbnails => "Could not generate thumbnails",
+            Self::MaintainThumbnails => "Could not clean up the thumbnail cache",
+            Self::ManageTags => "Could not update tags",
+            Self::EditHistory => "Could not undo or redo the change",
+            Self::AutoTag => "Could not apply the auto-tag rules",
+            Self::ManageSmartFolders => "Could not update the smart folders",
+            Self::DecorateFolder => "Could not change how the folder is shown",
+            Self::ManageFields => "Could not update the custom fields",
+            Self::CurateFiles => "Could not update the rating, favorite or label of the files",
+            Self::SyncTags => "Could not sync the tags with the files",
+            Self::VerifyLibrary => "Could not verify the library",
+            Self::BackupLibrary => "Could not back up the library",
+            Self::RestoreLibrary => "Could not restore the library",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ControllerError {
+    InvalidLibraryName,
+    InvalidLibraryFolderName,
+    NotHestiaLibrary,
+    InvalidContentFolder,
+    LibraryAlreadyExists,
+    NoLibrarySelected,
+    MissingStorageFolder,
+    NoContentFolders,
+    InvalidTagName,
+    TagNameTaken,
+    FileNotFound,
+    TagNotFound,
+    InvalidSmartFolderName,
+    SmartFolderNameTaken,
+    SmartFolderNotFound,
+    FolderNotFound,
+    InvalidFieldName,
+    FieldNameTaken,
+    FieldNotFound,
+    TagSyncDisabled,
+    IncompatibleBackup,
+    OperationFailed {
+        operation: ControllerOperation,
+        source: anyhow::Error,
+    },
+}
+
+impl ControllerError {
+    fn operation(operation: ControllerOperation, source: impl Into<anyhow::Error>) -> Self {
+        Self::OperationFailed {
+            operation,
+            source: source.into(),
+        }
+    }
+}
+
+impl Display for ControllerError {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLibraryName => {
+                formatter.write_str("Library name must be a single folder name.")
+            }
+            Self::InvalidLibraryFolderName => {
+                formatter.write_str("A library has an invalid folder name.")
+            }
+            Self::NotHestiaLibrary => {
+                formatter.write_str("The selected folder is not a Hestia library.")
+            }
+            Self::InvalidContentFolder => {
+                formatter.write_str("The library content path must be a folder.")
+            }
+            Self::LibraryAlreadyExists => {
+                formatter.write_str("A library with that name already exists.")
+            }
+            Self::NoLibrarySelected => {
+                formatter.write_str("Select a library before initializing it.")
+            }
+            Self::MissingStorageFolder => formatter.write_str("The library has no storage folder."),
+            Self::NoContentFolders => formatter.write_str("The library has no content folders."),
+            Self::InvalidTagName => formatter.write_str("Tag names cannot be empty."),
+            Self::TagNameTaken => {
+                formatter.write_str("Another tag already uses this name as its name or alias.")
+            }
+            Self::FileNotFound => formatter.write_str("The selected file no longer exists."),
+            Self::TagNotFound => formatter.write_str("The selected tag no longer exists."),
+            Self::InvalidSmartFolderName => {
+                formatter.write_str("Smart folder names cannot be empty.")
+            }
+            Self::SmartFolderNameTaken => {
+                formatter.write_str("A smart folder with that name already exists.")
+            }
+            Self::SmartFolderNotFound => {
+                formatter.write_str("The selected smart folder no longer exists.")
+            }
+            Self::FolderNotFound => formatter.write_str("The selected folder no longer exists."),
+            Self::InvalidFieldName => formatter.write_str("Field names cannot be empty."),
+            Self::FieldNameTaken => formatter.write_str("A field with that name already exists."),
+            Self::FieldNotFound => formatter.write_str("The selected field no longer exists."),
+            Self::TagSyncDisabled => formatter.write_str(
+                "Tag sync is turned off, enable xattr or xmp under [tag_sync] in the library \
+                 configuration.",
+            ),
+            Self::IncompatibleBackup => {
+                formatter.write_str("The backup was made by a newer version of Hestia.")
+            }
+            Self::OperationFailed { operation, source } => {
+                write!(formatter, "{}: {source:#}", operation.action())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ControllerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OperationFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+type ControllerResult<T> = std::result::Result<T, ControllerError>;
+
+#[derive(Debug)]
+struct Workspace {
+    database_manager: Arc<DatabaseManager>,
+    file_operations: Arc<FileRepository>,
+    thumbnail_operations: Arc<ThumbnailOperations>,
+    /// `None` while the library is open for maintenance only
+    thumbnail_processor: Option<ThumbnailProcessorHandler>,
+    watcher: Option<FileWatcherHandler>,
+    /// `None` when the library has no auto-tag rules or one of them is invalid
+    auto_tagger: Option<Arc<AutoTagger>>,
+    /// `None` when the library keeps its tags only in the database
+    tag_sync: Option<Arc<TagSync>>,
+    /// Writes tags changed in the library to the files, while tag sync is enabled
+    tag_writer: Option<JoinHandle<()>>,
+    backups: Arc<LibraryBackups>,
+    /// Backs the library up at the configured interval, while automatic backups are enabled
+    backup_scheduler: Option<JoinHandle<()>>,
+}
+
+#[derive(Debug)]
+enum AppState {
+    AwaitingLibrary,
+    Ready {
+        library: Library,
+        workspace: Workspace,
+    },
+}
+
+#[derive(Debug)]
+pub struct AppController {
+    data_home: PathBuf,
+    state: Mutex<AppState>,
+    changes: ChangeBus,
+}
+
+impl AppController {
+    pub fn new() -> ControllerResult<Self> {
+        let data_home = library::io::create_or_validate_data_directory().map_err(|error| {
+            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
+        })?;
+        Self::new_in(data_home)
+    }
+
+    pub fn new_in(data_home: impl AsRef<Path>) -> ControllerResult<Self> {
+        std::fs::create_dir_all(data_home.as_ref()).map_err(|error| {
+            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
+        })?;
+        let data_home = data_home.as_ref().canonicalize().map_err(|error| {
+            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
+        })?;
+        std::fs::create_dir_all(data_home.join("hestia")).map_err(|error| {
+            ControllerError::operation(ControllerOperation::OpenDataDirectory, error)
+        })?;
+
+        Ok(Self {
+            data_home,
+            state: Mutex::new(AppState::AwaitingLibrary),
+            changes: ChangeBus::new(),
+        })
+    }
+
+    pub fn list_libraries(&self) -> ControllerResult<Vec<LibraryInfo>> {
+        let mut libraries = Library::list_libraries_in(&self.data_home)
+            .map_err(|error| ControllerError::operation(ControllerOperation::ListLibraries, error))?
+            .into_iter()
+            .map(PathBuf::from)
+            .map(LibraryInfo::from_path)
+            .collect::<ControllerResult<Vec<_>>>()?;
+        libraries.sort_by(|left, right| left.name.as_str().cmp(right.name.as_str()));
+        Ok(libraries)
+    }
+
+    pub async fn select_library(&self, path: impl AsRef<Path>) -> ControllerResult<LibraryInfo> {
+        let library = self.find_library(path)?;
+        self.activate(library).await
+    }
+
+    /// Open a library for command-line maintenance such as `hestia verify`
+    ///
+    /// The database is migrated to the current schema, but no thumbnails are generated, no
+    /// folders are watched and no backups are scheduled. Call [`Self::close_library`] when done.
+    pub async fn open_library_for_maintenance(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> ControllerResult<LibraryInfo> {
+        let library = self.find_library(path)?;
+        let info = LibraryInfo::from_path(
+            library
+                .share_path
+                .clone()
+                .ok_or(ControllerError::MissingStorageFolder)?,
+        )?;
+        let workspace = Workspace::open_database(&library)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))?;
+        if let Err(error) = Migrator::up(workspace.database_manager.get_connection().as_ref(), None)
+            .await
+            .context("database migration failed")
+        {
+            workspace.close().await;
+            return Err(ControllerError::operation(
+                ControllerOperation::OpenLibrary,
+                error,
+            ));
+        }
+        self.replace_workspace(library, workspace).await;
+        Ok(info)
+    }
+
+    /// Stop the background tasks of the open library and close its database
+    pub async fn close_library(&self) {
+        let previous = std::mem::replace(&mut *self.state.lock().await, AppState::AwaitingLibrary);
+        if let AppState::Ready { workspace, .. } = previous {
+            workspace.close().await;
+        }
+    }
+
+    fn find_library(&self, path: impl AsRef<Path>) -> ControllerResult<Library> {
+        let path = path
+            .as_ref()
+            .canonicalize()
+            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))?;
+        let libraries_root = self.data_home.join("hestia");
+        if !path.starts_with(&libraries_root)
+            || !path.is_dir()
+            || !path.join("config.toml").is_file()
+        {
+            return Err(ControllerError::NotHestiaLibrary);
+        }
+
+        Library::new_in(&self.data_home)
+            .switch_or_create_lib_in(&path, &self.data_home)
+            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))
+    }
+
+    pub async fn create_library(
+        &self,
+        name: &str,
+        content_path: impl AsRef<Path>,
+    ) -> ControllerResult<LibraryInfo> {
+        let name = LibraryName::parse(name)?;
+        let content_path = content_path
+            .as_ref()
+            .canonicalize()
+            .map_err(|_| ControllerError::InvalidContentFolder)?;
+        if !content_path.is_dir() {
+            return Err(ControllerError::InvalidContentFolder);
+        }
+
+        let share_path = self.data_home.join("hestia").join(name.as_str());
+        if share_path.try_exists().map_err(|error| {
+            ControllerError::operation(ControllerOperation::CreateLibrary, error)
+        })? {
+            return Err(ControllerError::LibraryAlreadyExists);
+        }
+
+        let content_name = content_path
+            .file_name()
+            .and_then(|value| value.to_str())
+            .unwrap_or("Folder")
+            .to_string();
+        let mut config = LibraryConfig::default();
+        config.name = name.to_string();
+        config.library_paths = vec![LibraryPathConfig {
+            name: Some(content_name),
+            path: Some(content_path),
+            ..LibraryPathConfig::default()
+        }];
+
+        let mut library = Library::new_in(&self.data_home);
+        library.share_path = Some(share_path);
+        library.library_config = Some(config);
+        library.save_config().map_err(|error| {
+            ControllerError::operation(ControllerOperation::CreateLibrary, error)
+        })?;
+
+        self.activate(library).await
+    }
+
+    pub async fn initialize_workspace(&self) -> ControllerResult<()> {
+        let (database_manager, file_operations, library_paths) = {
+            let state = self.state.lock().await;
+            let AppState::Ready { library, workspace } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            let library_paths = library
+                .library_config
+                .as_ref()
+                .map(|config| {
+                    config
+                        .library_paths
+                        .iter()
+                        .filter_map(|path| path.path.clone())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            (
+                Arc::clone(&workspace.database_manager),
+                Arc::clone(&workspace.file_operations),
+                library_paths,
+            )
+        };
+
+        Migrator::up(database_manager.get_connection().as_ref(), None)
+            .await
+            .context("database migration failed")
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::InitializeLibrary, error)
+            })?;
+        if library_paths.is_empty() {
+            return Err(ControllerError::NoContentFolders);
+        }
+        file_operations
+            .upsert_root_folders(library_paths)
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::InitializeLibrary, error)
+            })?;
+        Ok(())
+    }
+
+    pub async fn scan(&self) -> ControllerResult<ScanReport> {
+        let (file_operations, auto_tagger, tag_sync, library_paths) = {
+            let state = self.state.lock().await;
+            let AppState::Ready { library, workspace } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            (
+                Arc::clone(&workspace.file_operations),
+                workspace.auto_tagger.clone(),
+                workspace.tag_sync.clone(),
+                Self::library_paths(library),
+            )
+        };
+
+        let mut scanner = DirectoryScanner::new(file_operations);
+        if let Some(auto_tagger) = auto_tagger {
+            scanner = scanner.with_auto_tagger(auto_tagger);
+        }
+        let mut result = ScanReport {
+            files_scanned: 0,
+            changed: 0,
+            tags_assigned: 0,
+        };
+        for path in library_paths {
+            let report = scanner.sync_directory(&path).await.map_err(|error| {
+                ControllerError::operation(ControllerOperation::ScanLibrary, error)
+            })?;
+            result.files_scanned += report.files_scanned;
+            result.changed += report.files_inserted
+                + report.files_updated
+                + report.files_deleted
+                + report.folders_inserted
+                + report.folders_updated
+                + report.folders_deleted;
+            result.tags_assigned += report.tags_assigned;
+            for tag_id in report.created_tags {
+                self.changes.publish(LibraryChange::TagsChanged { tag_id });
+            }
+            self.changes
+                .publish(LibraryChange::FolderResynced { root: path });
+        }
+        if let Some(tag_sync) = tag_sync {
+            let report = self
+                .import_tags(
+                    &tag_sync,
+                    JournalSource::Scanner,
+                    ControllerOperation::ScanLibrary,
+                )
+                .await?;
+            for problem in &report.problems {
+                tracing::warn!("Tags were not synced: {problem}");
+            }
+            result.tags_assigned += report.assigned.len();
+        }
+        Ok(result)
+    }
+
+    /// Read the tags of every file from its extended attribute and XMP sidecar and write the
+    /// result back, following the conflict policy of the library
+    ///
+    /// Scans do the same; changes made in the library are written to the files as they happen.
+    pub async fn sync_tags(&self) -> ControllerResult<TagSyncReport> {
+        let tag_sync = {
+            let state = self.state.lock().await;
+            let AppState::Ready { workspace, .. } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            workspace
+                .tag_sync
+                .clone()
+                .ok_or(ControllerError::TagSyncDisabled)?
+        };
+        self.import_tags(
+            &tag_sync,
+            JournalSource::User,
+            ControllerOperation::SyncTags,
+        )
+        .await
+    }
+
+    async fn import_tags(
+        &self,
+        tag_sync: &TagSync,
+        source: JournalSource,
+        operation: ControllerOperation,
+    ) -> ControllerResult<TagSyncReport> {
+        let database_manager = self.database_manager().await?;
+        let files = files::Entity::find()
+            .all(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| ControllerError::operation(operation, error))?;
+        let report = tag_sync
+            .import(&files, source)
+            .await
+            .map_err(|error| ControllerError::operation(operation, error))?;
+        publish_tag_sync(&self.changes, &report);
+        Ok(report)
+    }
+
+    /// Check the database, the files and folders on disk and the rows referring to them
+    ///
+    /// With `repair` every repairable problem is fixed: files and folders missing on disk are
+    /// removed, wrong parents corrected and orphaned rows deleted. Nothing below a library
+    /// folder that cannot be reached is touched, it may only be unmounted.
+    pub async fn verify_library(&self, repair: bool) -> ControllerResult<IntegrityReport> {
+        let (integrity, roots) = {
+            let state = self.state.lock().await;
+            let AppState::Ready { library, workspace } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            let mut integrity = IntegrityOperations::new(Arc::clone(&workspace.database_manager));
+            if let Some(cache) = workspace.thumbnail_operations.cache() {
+                integrity = integrity.with_cache(cache.clone());
+            }
+            (integrity, Self::library_paths(library))
+        };
+        let mut report = integrity.verify(&roots).await.map_err(|error| {
+            ControllerError::operation(ControllerOperation::VerifyLibrary, error)
+        })?;
+        if !repair || !report.problems.iter().any(IntegrityProblem::is_repairable) {
+            return Ok(report);
+        }
+        integrity.repair(&report).await.map_err(|error| {
+            ControllerError::operation(ControllerOperation::VerifyLibrary, error)
+        })?;
+        report.repaired = true;
+        for problem in &report.problems {
+            match *problem {
+                IntegrityProblem::MissingFile { file_id, .. } => {
+                    self.changes.publish(LibraryChange::FileRemoved { file_id });
+                }
+                IntegrityProblem::MissingFolder { folder_id, .. }
+                | IntegrityProblem::DetachedFolder { folder_id, .. } => {
+                    self.changes
+                        .publish(LibraryChange::FolderRemoved { folder_id });
+                }
+                _ => {}
+            }
+        }
+        Ok(report)
+    }
+
+    /// Back up the database and configuration of the library while it stays in use
+    ///
+    /// Backups beyond the number the library keeps are removed, oldest first.
+    pub async fn backup_library(&self) -> ControllerResult<Backup> {
+        self.backups()
+            .await?
+            .create()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::BackupLibrary, error))
+    }
+
+    /// The backups of the library, newest first
+    pub async fn list_backups(&self) -> ControllerResult<Vec<Backup>> {
+        self.backups()
+            .await?
+            .list()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::BackupLibrary, error))
+    }
+
+    /// Put a backup back in place of the library it was made of, then open that library
+    ///
+    /// The backup is checked before anything is replaced. Backups from newer versions of
+    /// Hestia are refused, older ones are migrated when the library is initialized.
+    pub async fn restore_library(&self, backup: impl AsRef<Path>) -> ControllerResult<LibraryInfo> {
+        let backup = Backup::inspect(backup.as_ref()).await.map_err(|error| {
+            ControllerError::operation(ControllerOperation::RestoreLibrary, error)
+        })?;
+        if !Migrator::migrations()
+            .iter()
+            .any(|migration| migration.name() == backup.manifest.schema_version)
+        {
+            return Err(ControllerError::IncompatibleBackup);
+        }
+        let name = Path::new(&backup.manifest.library);
+        if name.file_name() != Some(name.as_os_str()) {
+            return Err(ControllerError::InvalidLibraryFolderName);
+        }
+        let share_path = self.data_home.join("hestia").join(name);
+        let share_path = share_path.canonicalize().unwrap_or(share_path);
+
+        {
+            let mut state = self.state.lock().await;
+            let restores_open_library = matches!(
+                &*state,
+                AppState::Ready { library, .. } if library.share_path.as_ref() == Some(&share_path)
+            );
+            if restores_open_library
+                && let AppState::Ready { workspace, .. } =
+                    std::mem::replace(&mut *state, AppState::AwaitingLibrary)
+            {
+                workspace.close().await;
+            }
+            Library::restore_files(&share_path, &backup.database(), &backup.config()).map_err(
+                |error| ControllerError::operation(ControllerOperation::RestoreLibrary, error),
+            )?;
+        }
+        self.select_library(&share_path).await
+    }
+
+    pub async fn generate_thumbnails(&self) -> ControllerResult<()> {
+        self.thumbnail_processor()
+            .await?
+            .queue_missing_files()
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
+            })
+    }
+
+    /// Generate the thumbnails of the files currently on screen before any background work
+    ///
+    /// Each call replaces the previous visible set, so files scrolled out of view lose their
+    /// precedence again.
+    pub async fn request_thumbnails(&self, file_ids: Vec<i32>) -> ControllerResult<()> {
+        let (database_manager, thumbnail_processor) = {
+            let state = self.state.lock().await;
+            let AppState::Ready { workspace, .. } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            (
+                Arc::clone(&workspace.database_manager),
+                workspace.thumbnail_processor()?,
+            )
+        };
+        let file_infos = files::Entity::find()
+            .filter(files::Column::Id.is_in(file_ids))
+            .all(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
+            })?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        thumbnail_processor
+            .queue_visible_files(file_infos, ThumbnailSize::Medium)
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
+            })
+    }
+
+    /// Queue depth, throughput and failures of thumbnail generation with the storage in use
+    ///
+    /// A library open for maintenance generates nothing, so only its storage is reported.
+    pub async fn thumbnail_status(&self) -> ControllerResult<ThumbnailStatus> {
+        let (thumbnail_operations, thumbnail_processor) = {
+            let state = self.state.lock().await;
+            let AppState::Ready { workspace, .. } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            (
+                Arc::clone(&workspace.thumbnail_operations),
+                workspace.thumbnail_processor.clone(),
+            )
+        };
+        let stats = match thumbnail_processor {
+            Some(thumbnail_processor) => {
+                thumbnail_processor.get_stats().await.map_err(|error| {
+                    ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
+                })?
+            }
+            None => ProcessingStats::default(),
+        };
+        let storage = thumbnail_operations
+            .get_thumbnail_stats()
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::QueryLibrary, error)
+            })?;
+        let missing = thumbnail_operations
+            .count_files_without_thumbnail_size(ThumbnailSize::fallback())
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::QueryLibrary, error)
+            })?;
+        Ok(ThumbnailStatus {
+            pending: stats.pending_jobs,
+            processing: stats.processing_jobs,
+            completed: stats.completed_jobs,
+            failed: stats.failed_jobs,
+            workers: stats.active_workers,
+            throughput_per_second: stats.throughput_per_second,
+            average_processing_time: stats.avg_processing_time,
+            stored: storage.total_thumbnails,
+            storage_bytes: storage.total_storage_bytes,
+            missing,
+        })
+    }
+
+    pub async fn failed_thumbnails(&self) -> ControllerResult<Vec<FailedThumbnailInfo>> {
+        self.thumbnail_processor()
+            .await?
+            .list_failed_jobs()
+            .await
+            .map(|jobs| jobs.into_iter().map(Into::into).collect())
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
+            })
+    }
+
+    /// Queue failed thumbnails again, all of them when `file_ids` is `None`
+    ///
+    /// Returns the number of jobs that were queued.
+    pub async fn retry_failed_thumbnails(
+        &self,
+        file_ids: Option<Vec<i32>>,
+    ) -> ControllerResult<usize> {
+        self.thumbnail_processor()
+            .await?
+            .retry_failed_jobs(file_ids)
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::GenerateThumbnails, error)
+            })
+    }
+
+    /// Move every thumbnail into the storage configured for the library
+    pub async fn migrate_thumbnail_storage(&self) -> ControllerResult<ThumbnailMaintenanceReport> {
+        let thumbnail_operations = self.thumbnail_operations().await?;
+        let migrated = thumbnail_operations
+            .migrate_storage()
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::MaintainThumbnails, error)
+            })?;
+        let mut report = self.collect_thumbnail_garbage().await?;
+        report.migrated = migrated;
+        Ok(report)
+    }
+
+    /// Delete cached thumbnails that no longer belong to a file in the library
+    pub async fn collect_thumbnail_garbage(&self) -> ControllerResult<ThumbnailMaintenanceReport> {
+        let thumbnail_operations = self.thumbnail_operations().await?;
+        let report = thumbnail_operations
+            .collect_garbage()
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::MaintainThumbnails, error)
+            })?;
+        Ok(ThumbnailMaintenanceReport {
+            migrated: 0,
+            files_removed: report.files_removed,
+            bytes_freed: report.bytes_freed,
+        })
+    }
+
+    /// Watch the library folders; what the watcher stores is announced on [`Self::subscribe_changes`]
+    pub async fn start_watching(&self) -> ControllerResult<()> {
+        let (watcher, watcher_receiver) = FileWatcherHandler::new();
+        let (file_operations, thumbnail_processor, auto_tagger, paths) = {
+            let mut state = self.state.lock().await;
+            let AppState::Ready { library, workspace } = &mut *state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            if workspace.watcher.is_some() {
+                return Err(ControllerError::operation(
+                    ControllerOperation::StartWatcher,
+                    anyhow::anyhow!("the library is already being watched"),
+                ));
+            }
+            workspace.watcher = Some(watcher.clone());
+            let paths = library
+                .library_config
+                .as_ref()
+                .map(|config| {
+                    config
+                        .library_paths
+                        .iter()
+                        .filter_map(|path| path.path.clone().map(|root| (root, path.watch)))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            (
+                Arc::clone(&workspace.file_operations),
+                workspace.thumbnail_processor.clone(),
+                workspace.auto_tagger.clone(),
+                paths,
+            )
+        };
+
+        let event_handler = DatabaseFileWatcherEventHandler {
+            db_operations: file_operations,
+            changes: Some(self.changes.clone()),
+            thumbnails: thumbnail_processor,
+            auto_tagger,
+        };
+        tokio::spawn(async move {
+            if let Err(error) = FileWatcher::new(watcher_receiver)
+                .run(Box::new(event_handler))
+                .await
+            {
+                tracing::error!(%error, "File watcher stopped");
+            }
+        });
+        let watching = async {
+            for (path, mode) in paths {
+                watcher.watch_path(CanonPath::from(path), mode)?;
+            }
+            // Messages are handled in order, so this returns once every folder is being watched
+            watcher.get_watch_paths().await.map(|_| ())
+        }
+        .await;
+        if let Err(error) = watching {
+            // Forget the failed watcher, unless the library was switched meanwhile
+            if let AppState::Ready { workspace, .. } = &mut *self.state.lock().await
+                && workspace
+                    .watcher
+                    .as_ref()
+                    .is_some_and(|current| current.sender.same_channel(&watcher.sender))
+            {
+                workspace.watcher = None;
+            }
+            if let Err(error) = watcher.stop().await {
+                tracing::debug!(%error, "File watcher was already stopped");
+            }
+            return Err(ControllerError::operation(
+                ControllerOperation::StartWatcher,
+                error,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Receive every change made to the library, whichever library is selected
+    ///
+    /// A receiver that falls behind gets [`broadcast::error::RecvError::Lagged`] and should
+    /// reload what it shows.
+    #[must_use]
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<LibraryChange> {
+        self.changes.subscribe()
+    }
+
+    /// Stop watching the library folders; [`Self::start_watching`] starts a fresh watcher
+    pub async fn stop_watching(&self) -> ControllerResult<()> {
+        let watcher = {
+            let mut state = self.state.lock().await;
+            let AppState::Ready { workspace, .. } = &mut *state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            workspace.watcher.take()
+        };
+        match watcher {
+            Some(watcher) => watcher.stop().await.map_err(|error| {
+                ControllerError::operation(ControllerOperation::ControlWatcher, error)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Ignore changes in the library folders until [`Self::resume_watching`] is called
+    pub async fn pause_watching(&self) -> ControllerResult<()> {
+        self.watcher()
+            .await?
+            .pause()
+            .map_err(|error| ControllerError::operation(ControllerOperation::ControlWatcher, error))
+    }
+
+    /// Handle changes again, synchronizing every folder to catch up on the paused period
+    pub async fn resume_watching(&self) -> ControllerResult<()> {
+        self.watcher()
+            .await?
+            .resume()
+            .map_err(|error| ControllerError::operation(ControllerOperation::ControlWatcher, error))
+    }
+
+    pub async fn list_folders(&self) -> ControllerResult<Vec<FolderInfo>> {
+        let database_manager = self.database_manager().await?;
+        FolderOperations::new(database_manager)
+            .list()
+            .await
+            .map(|items| items.into_iter().map(Into::into).collect())
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    /// Replace the color, icon and display name of a folder or content root
+    pub async fn decorate_folder(
+        &self,
+        folder_id: i32,
+        decoration: FolderDecoration,
+    ) -> ControllerResult<FolderInfo> {
+        let database_manager = self.database_manager().await?;
+        let folder = FolderOperations::new(database_manager)
+            .decorate(folder_id, &decoration)
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::DecorateFolder, error)
+            })?
+            .ok_or(ControllerError::FolderNotFound)?;
+        self.changes
+            .publish(LibraryChange::FolderDecorated { folder_id });
+        Ok(folder.into())
+    }
+
+    /// Every folder nested below the watched folders, with the files below each folder
+    pub async fn folder_tree(&self) -> ControllerResult<Vec<WatchedFolderTree>> {
+        let database_manager = self.database_manager().await?;
+        FolderOperations::new(database_manager)
+            .tree()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    /// The folders directly below a folder, or the watched folders for `None`
+    ///
+    /// The children of the returned folders are not loaded, see
+    /// [`WatchedFolderTree::has_children`].
+    pub async fn folder_children(
+        &self,
+        parent_id: Option<i32>,
+    ) -> ControllerResult<Vec<WatchedFolderTree>> {
+        let database_manager = self.database_manager().await?;
+        FolderOperations::new(database_manager)
+            .children(parent_id)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    pub async fn list_smart_folders(&self) -> ControllerResult<Vec<SmartFolderInfo>> {
+        let database_manager = self.database_manager().await?;
+        SavedSearchOperations::new(database_manager)
+            .list()
+            .await
+            .map(|items| items.into_iter().map(Into::into).collect())
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    /// Save a filter under a name that no other smart folder uses
+    pub async fn create_smart_folder(
+        &self,
+        name: &str,
+        filter: Filter,
+    ) -> ControllerResult<SmartFolderInfo> {
+        let name = Self::smart_folder_name(name)?;
+        let searches = SavedSearchOperations::new(self.database_manager().await?);
+        if searches
+            .find_by_name(name)
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::ManageSmartFolders, error)
+            })?
+            .is_some()
+        {
+            return Err(ControllerError::SmartFolderNameTaken);
+        }
+        searches
+            .create(name, &filter)
+            .await
+            .map(Into::into)
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::ManageSmartFolders, error)
+            })
+    }
+
+    pub async fn update_smart_folder(
+        &self,
+        smart_folder_id: i32,
+        name: &str,
+        filter: Filter,
+    ) -> ControllerResult<SmartFolderInfo> {
+        let name = Self::smart_folder_name(name)?;
+        let searches = SavedSearchOperations::new(self.database_manager().await?);
+        let manage_error =
+            |error| ControllerError::operation(ControllerOperation::ManageSmartFolders, error);
+        if searches
+            .get(smart_folder_id)
+            .await
+            .map_err(manage_error)?
+            .is_none()
+        {
+            return Err(ControllerError::SmartFolderNotFound);
+        }
+        if searches
+            .find_by_name(name)
+            .await
+            .map_err(manage_error)?
+            .is_some_and(|other| other.id != smart_folder_id)
+        {
+            return Err(ControllerError::SmartFolderNameTaken);
+        }
+        searches
+            .update(smart_folder_id, name, &filter)
+            .await
+            .map(Into::into)
+            .map_err(manage_error)
+    }
+
+    pub async fn delete_smart_folder(&self, smart_folder_id: i32) -> ControllerResult<()> {
+        SavedSearchOperations::new(self.database_manager().await?)
+            .delete(smart_folder_id)
+            .await
+            .map(drop)
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::ManageSmartFolders, error)
+            })
+    }
+
+    /// Every custom field of the library, ordered by name
+    pub async fn list_fields(&self) -> ControllerResult<Vec<FieldDefinition>> {
+        FieldOperations::new(self.database_manager().await?)
+            .list()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    /// Define a field under a name that no other field uses
+    ///
+    /// Only enum fields keep their options, and they need at least one.
+    pub async fn create_field(
+        &self,
+        name: &str,
+        field_type: FieldType,
+        options: &[String],
+    ) -> ControllerResult<FieldDefinition> {
+        let name = Self::field_name(name)?;
+        let fields = FieldOperations::new(self.database_manager().await?);
+        let manage_error =
+            |error| ControllerError::operation(ControllerOperation::ManageFields, error);
+        if fields
+            .find_by_name(name)
+            .await
+            .map_err(manage_error)?
+            .is_some()
+        {
+            return Err(ControllerError::FieldNameTaken);
+        }
+        let field = fields
+            .create(name, field_type, options)
+            .await
+            .map_err(manage_error)?;
+        self.changes.publish(LibraryChange::FieldsChanged);
+        Ok(field)
+    }
+
+    /// Rename a field and replace its options, the type of a field cannot change
+    pub async fn update_field(
+        &self,
+        field_id: i32,
+        name: &str,
+        options: &[String],
+    ) -> ControllerResult<FieldDefinition> {
+        let name = Self::field_name(name)?;
+        let fields = FieldOperations::new(self.database_manager().await?);
+        let manage_error =
+            |error| ControllerError::operation(ControllerOperation::ManageFields, error);
+        if fields.get(field_id).await.map_err(manage_error)?.is_none() {
+            return Err(ControllerError::FieldNotFound);
+        }
+        if fields
+            .find_by_name(name)
+            .await
+            .map_err(manage_error)?
+            .is_some_and(|other| other.id != field_id)
+        {
+            return Err(ControllerError::FieldNameTaken);
+        }
+        let field = fields
+            .update(field_id, name, options)
+            .await
+            .map_err(manage_error)?;
+        self.changes.publish(LibraryChange::FieldsChanged);
+        Ok(field)
+    }
+
+    /// Delete a field together with the values files have for it
+    pub async fn delete_field(&self, field_id: i32) -> ControllerResult<()> {
+        let deleted = FieldOperations::new(self.database_manager().await?)
+            .delete(field_id)
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::ManageFields, error)
+            })?;
+        if deleted {
+            self.changes.publish(LibraryChange::FieldsChanged);
+        }
+        Ok(())
+    }
+
+    /// The fields a file has a value for, ordered by field name
+    pub async fn file_fields(
+        &self,
+        file_id: i32,
+    ) -> ControllerResult<Vec<(FieldDefinition, FieldValue)>> {
+        FieldOperations::new(self.database_manager().await?)
+            .values_of(file_id)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    /// Give files a value for a field, written as text such as `2025-11-01` for a date field
+    ///
+    /// A blank value removes the value of the files.
+    pub async fn set_field_value(
+        &self,
+        file_ids: &[i32],
+        field_id: i32,
+        value: &str,
+    ) -> ControllerResult<()> {
+        let fields = FieldOperations::new(self.database_manager().await?);
+        let manage_error =
+            |error| ControllerError::operation(ControllerOperation::ManageFields, error);
+        let field = fields
+            .get(field_id)
+            .await
+            .map_err(manage_error)?
+            .ok_or(ControllerError::FieldNotFound)?;
+        let value = if value.trim().is_empty() {
+            None
+        } else {
+            Some(field.parse_value(value).map_err(manage_error)?)
+        };
+        let changed = fields
+            .set_values(file_ids, field_id, value.as_ref())
+            .await
+            .map_err(manage_error)?;
+        for file_id in changed {
+            self.changes
+                .publish(LibraryChange::FieldValueChanged { file_id, field_id });
+        }
+        Ok(())
+    }
+
+    /// Write every custom field and value of the library to a JSON file
+    pub async fn export_fields(&self, path: &Path) -> ControllerResult<()> {
+        let manage_error =
+            |error| ControllerError::operation(ControllerOperation::ManageFields, error);
+        let export = FieldOperations::new(self.database_manager().await?)
+            .export()
+            .await
+            .map_err(manage_error)?;
+        let json = serde_json::to_string_pretty(&export)
+            .context("Failed to write the custom fields as JSON")
+            .map_err(manage_error)?;
+        tokio::fs::write(path, json)
+            .await
+            .with_context(|| format!("Failed to write {}", path.display()))
+            .map_err(manage_error)
+    }
+
+    /// Read custom fields and values from a JSON file written by [`Self::export_fields`]
+    ///
+    /// Fields are matched by name and files by path, see [`FieldImportReport`] for what was
+    /// left out.
+    pub async fn import_fields(&self, path: &Path) -> ControllerResult<FieldImportReport> {
+        let manage_error =
+            |error| ControllerError::operation(ControllerOperation::ManageFields, error);
+        let json = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))
+            .map_err(manage_error)?;
+        let export: FieldExport = serde_json::from_str(&json)
+            .with_context(|| format!("{} is not a custom field export", path.display()))
+            .map_err(manage_error)?;
+        let report = FieldOperations::new(self.database_manager().await?)
+            .import(&export)
+            .await
+            .map_err(manage_error)?;
+        self.changes.publish(LibraryChange::FieldsChanged);
+        Ok(report)
+    }
+
+    /// Rate files from 1 to [`MAX_RATING`] stars, 0 removes their rating
+    pub async fn set_rating(&self, file_ids: &[i32], rating: u8) -> ControllerResult<()> {
+        let changed = CurationOperations::new(self.database_manager().await?)
+            .set_rating(file_ids, rating)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::CurateFiles, error))?;
+        self.publish_modified(changed);
+        Ok(())
+    }
+
+    pub async fn set_favorite(&self, file_ids: &[i32], favorite: bool) -> ControllerResult<()> {
+        let changed = CurationOperations::new(self.database_manager().await?)
+            .set_favorite(file_ids, favorite)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::CurateFiles, error))?;
+        self.publish_modified(changed);
+        Ok(())
+    }
+
+    /// Label files with a color, `None` removes their label
+    pub async fn set_color_label(
+        &self,
+        file_ids: &[i32],
+        label: Option<ColorLabel>,
+    ) -> ControllerResult<()> {
+        let changed = CurationOperations::new(self.database_manager().await?)
+            .set_color_label(file_ids, label)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::CurateFiles, error))?;
+        self.publish_modified(changed);
+        Ok(())
+    }
+
+    fn publish_modified(&self, file_ids: Vec<i32>) {
+        for file_id in file_ids {
+            self.changes
+                .publish(LibraryChange::FileModified { file_id });
+        }
+    }
+
+    pub async fn list_files(
+        &self,
+        folder_id: Option<i32>,
+        search: &str,
+    ) -> ControllerResult<Vec<FileInfo>> {
+        let database_manager = self.database_manager().await?;
+        let condition = Self::file_condition(&database_manager, folder_id, search).await?;
+        self.query_files(&database_manager, condition, None).await
+    }
+
+    /// The files among `file_ids` that [`Self::list_files`] would return for the same arguments
+    ///
+    /// Lets a view apply [`LibraryChange`]s to the rows it shows without reloading all of them.
+    pub async fn list_matching_files(
+        &self,
+        folder_id: Option<i32>,
+        search: &str,
+        file_ids: Vec<i32>,
+    ) -> ControllerResult<Vec<FileInfo>> {
+        let database_manager = self.database_manager().await?;
+        let condition = Self::file_condition(&database_manager, folder_id, search).await?;
+        self.query_files(&database_manager, condition, Some(file_ids))
+            .await
+    }
+
+    /// The files a smart folder shows, further narrowed down by the search
+    ///
+    /// Relative date ranges such as "the last 7 days" are evaluated on every call.
+    pub async fn list_smart_folder_files(
+        &self,
+        smart_folder_id: i32,
+        search: &str,
+    ) -> ControllerResult<Vec<FileInfo>> {
+        let database_manager = self.database_manager().await?;
+        let condition =
+            Self::smart_folder_condition(&database_manager, smart_folder_id, search).await?;
+        self.query_files(&database_manager, condition, None).await
+    }
+
+    /// The files among `file_ids` that [`Self::list_smart_folder_files`] would return
+    pub async fn list_matching_smart_folder_files(
+        &self,
+        smart_folder_id: i32,
+        search: &str,
+        file_ids: Vec<i32>,
+    ) -> ControllerResult<Vec<FileInfo>> {
+        let database_manager = self.database_manager().await?;
+        let condition =
+            Self::smart_folder_condition(&database_manager, smart_folder_id, search).await?;
+        self.query_files(&database_manager, condition, Some(file_ids))
+            .await
+    }
+
+    /// One page of the selected files in the given order, and how many files are selected
+    ///
+    /// Pages are counted from `offset`, so a view can load more rows as it is scrolled instead of
+    /// holding every file of a large folder.
+    pub async fn list_file_page(
+        &self,
+        selection: FileSelection,
+        sort: FileSort,
+        offset: u64,
+        limit: u64,
+    ) -> ControllerResult<FilePage> {
+        let database_manager = self.database_manager().await?;
+        let condition = Self::selection_condition(&database_manager, selection).await?;
+        let total = files::Entity::find()
+            .filter(condition.clone())
+            .count(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::QueryLibrary, error)
+            })?;
+        let select = Self::sorted_files(files::Entity::find().filter(condition), sort)
+            .offset(offset)
+            .limit(limit);
+        let files = self.load_files(&database_manager, select).await?;
+        Ok(FilePage { files, total })
+    }
+
+    async fn query_files(
+        &self,
+        database_manager: &DatabaseManager,
+        mut condition: Condition,
+        file_ids: Option<Vec<i32>>,
+    ) -> ControllerResult<Vec<FileInfo>> {
+        if let Some(file_ids) = file_ids {
+            condition = condition.add(files::Column::Id.is_in(file_ids));
+        }
+        let select =
+            Self::sorted_files(files::Entity::find().filter(condition), FileSort::default());
+        self.load_files(database_manager, select).await
+    }
+
+    fn sorted_files(select: Select<files::Entity>, sort: FileSort) -> Select<files::Entity> {
+        let order = if sort.descending {
+            Order::Desc
+        } else {
+            Order::Asc
+        };
+        let select = match sort.key {
+            FileSortKey::Name => select,
+            FileSortKey::Path => select.order_by(files::Column::Path, order.clone()),
+            FileSortKey::Size => select.order_by(files::Column::Size, order.clone()),
+            FileSortKey::Modified => select.order_by(files::Column::ModifiedAt, order.clone()),
+            FileSortKey::Type => select
+                .join(JoinType::LeftJoin, files::Relation::FileTypes.def())
+                .order_by(file_types::Column::Name, order.clone()),
+            FileSortKey::TagCount => {
+                let tag_count = Query::select()
+                    .expr(Expr::col(file_has_tags::Column::TagId).count())
+                    .from(file_has_tags::Entity)
+                    .and_where(
+                        Expr::col((file_has_tags::Entity, file_has_tags::Column::FileId))
+                            .equals((files::Entity, files::Column::Id)),
+                    )
+                    .to_owned();
+                select.order_by(
+                    SimpleExpr::SubQuery(None, Box::new(tag_count.into_sub_query_statement())),
+                    order.clone(),
+                )
+            }
+            FileSortKey::Rating => select.order_by(files::Column::Rating, order.clone()),
+            FileSortKey::Favorite => select.order_by(files::Column::Favorite, order.clone()),
+            FileSortKey::ColorLabel => {
+                let position = ColorLabel::ALL.into_iter().zip(0..).fold(
+                    CaseStatement::new(),
+                    |position, (label, index)| {
+                        position.case(files::Column::ColorLabel.eq(label.as_str()), index)
+                    },
+                );
+                select.order_by(
+                    SimpleExpr::Case(Box::new(position.finally(i32::MAX))),
+                    order.clone(),
+                )
+            }
+        };
+        // The id keeps the order of files with the same name stable across pages
+        select
+            .order_by(files::Column::Name, order.clone())
+            .order_by(files::Column::Id, order)
+    }
+
+    async fn load_files(
+        &self,
+        database_manager: &DatabaseManager,
+        select: Select<files::Entity>,
+    ) -> ControllerResult<Vec<FileInfo>> {
+        let mut files: Vec<FileInfo> = select
+            .all(database_manager.get_connection().as_ref())
+            .await
+            .map(|items| items.into_iter().map(Into::into).collect())
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::QueryLibrary, error)
+            })?;
+
+        let mut thumbnail_paths = self
+            .thumbnail_operations()
+            .await?
+            .get_cached_thumbnail_paths(
+                files.iter().map(FileInfo::id).collect(),
+                ThumbnailSize::Medium,
+            )
+            .await
+            .map_err(|error| {
+                ControllerError::operation(ControllerOperation::QueryLibrary, error)
+            })?;
+        for file in &mut files {
+            file.thumbnail_path = thumbnail_paths.remove(&file.id);
+        }
+        Ok(files)
+    }
+
+    /// Files below the folder, if any, that match the search
+    async fn file_condition(
+        database_manager: &DatabaseManager,
+        folder_id: Option<i32>,
+        search: &str,
+    ) -> ControllerResult<Condition> {
+        let mut condition = Condition::all();
+        if let Some(folder_id) = folder_id {
+            let folder = folders::Entity::find_by_id(folder_id)
+                .one(database_manager.get_connection().as_ref())
+                .await
+                .map_err(|error| {
+                    ControllerError::operation(ControllerOperation::QueryLibrary, error)
+                })?
+                .ok_or(ControllerError::FileNotFound)?;
+            condition = condition.add(files::Column::Path.starts_with(folder.path));
+        }
+        Ok(condition.add(Self::search_condition(&SearchQuery::parse(search))))
+    }
+
+    async fn smart_folder_condition(
+        database_manager: &Arc<DatabaseManager>,
+        smart_folder_id: i32,
+        search: &str,
+    ) -> ControllerResult<Condition> {
+        let smart_folder = SavedSearchOperations::new(Arc::clone(database_manager))
+            .get(smart_folder_id)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))?
+            .ok_or(ControllerError::SmartFolderNotFound)?;
+        Ok(
+            Self::filter_condition(&smart_folder.filter, Utc::now().naive_utc())
+                .add(Self::search_condition(&SearchQuery::parse(search))),
+        )
+    }
+
+    /// Photo metadata of a file, if it is an image whose metadata has been extracted
+    pub async fn file_metadata(&self, file_id: i32) -> ControllerResult<Option<MediaInfo>> {
+        let database_manager = self.database_manager().await?;
+        MediaMetadataOperations::new(database_manager)
+            .get_for_file(file_id)
+            .await
+            .map(|metadata| metadata.map(Into::into))
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
     }
 
     pub async fn list_tags(&self) -> ControllerResult<Vec<TagInfo>> {
         let database_manager = self.database_manager().await?;
-        tags::Entity::find()
-            .order_by_asc(tags::Column::Name)
-            .all(database_manager.get_connection().as_ref())
-            .await
-            .map(|items| items.into_iter().map(Into::into).collect())
+        TagOperations::new(database_manager)
+            .list()
+            .await
+            .map(|items| items.into_iter().map(Into::into).collect())
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    pub async fn create_tag(&self, name: &str) -> ControllerResult<()> {
+        let name = Self::tag_name(name)?;
+        let database_manager = self.database_manager().await?;
+        if tags::Entity::find()
+            .filter(tags::Column::Name.eq(name))
+            .one(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
+            .is_some()
+        {
+            return Ok(());
+        }
+        self.execute_tag_command(
+            database_manager,
+            &format!("Create tag {name}"),
+            TagCommand::CreateTag {
+                tag_id: None,
+                name: name.to_string(),
+                file_ids: Vec::new(),
+                details: TagDetails::default(),
+            },
+        )
+        .await
+    }
+
+    pub async fn update_tag(&self, tag_id: i32, name: &str) -> ControllerResult<()> {
+        let name = Self::tag_name(name)?;
+        let database_manager = self.database_manager().await?;
+        let tag = Self::find_tag(&database_manager, tag_id).await?;
+        self.execute_tag_command(
+            database_manager,
+            &format!("Rename tag {} to {name}", tag.name),
+            TagCommand::RenameTag {
+                tag_id,
+                from: tag.name,
+                to: name.to_string(),
+            },
+        )
+        .await
+    }
+
+    /// Delete a tag and its assignments, which can be restored with [`Self::undo`]
+    pub async fn delete_tag(&self, tag_id: i32) -> ControllerResult<()> {
+        let database_manager = self.database_manager().await?;
+        let tag = match Self::find_tag(&database_manager, tag_id).await {
+            Ok(tag) => tag,
+            Err(ControllerError::TagNotFound) => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        self.execute_tag_command(
+            database_manager,
+            &format!("Delete tag {}", tag.name),
+            TagCommand::DeleteTag {
+                tag_id,
+                name: tag.name,
+                file_ids: Vec::new(),
+                details: TagDetails::default(),
+            },
+        )
+        .await
+    }
+
+    /// Change the color, icon, description and aliases of a tag as one undoable change
+    ///
+    /// Aliases are trimmed and deduplicated. An alias may not be the name or alias of another tag.
+    pub async fn update_tag_details(
+        &self,
+        tag_id: i32,
+        details: TagDetails,
+    ) -> ControllerResult<()> {
+        let database_manager = self.database_manager().await?;
+        let tag = Self::find_tag(&database_manager, tag_id).await?;
+        let mut aliases: Vec<String> = Vec::new();
+        for alias in &details.aliases {
+            let alias = alias.trim();
+            if !alias.is_empty() && alias != tag.name && !aliases.iter().any(|seen| seen == alias) {
+                aliases.push(alias.to_string());
+            }
+        }
+        let taken = TagOperations::new(Arc::clone(&database_manager))
+            .list()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
+            .into_iter()
+            .filter(|(other, _)| other.id != tag_id)
+            .any(|(other, other_details)| {
+                aliases
+                    .iter()
+                    .any(|alias| *alias == other.name || other_details.aliases.contains(alias))
+            });
+        if taken {
+            return Err(ControllerError::TagNameTaken);
+        }
+        let details = TagDetails {
+            description: details
+                .description
+                .map(|description| description.trim().to_string())
+                .filter(|description| !description.is_empty()),
+            aliases,
+            ..details
+        };
+        self.execute_tag_command(
+            database_manager,
+            &format!("Edit tag {}", tag.name),
+            TagCommand::EditTag {
+                tag_id,
+                from: TagDetails::default(),
+                to: details,
+            },
+        )
+        .await
+    }
+
+    /// Tags the file does not have yet but probably should, the most likely first
+    ///
+    /// Suggestions come from the tags of other files in the same folder, tags that often appear
+    /// together with the tags of the file, and tag names or aliases in the file name.
+    pub async fn suggest_tags(
+        &self,
+        file_id: i32,
+        limit: usize,
+    ) -> ControllerResult<Vec<TagSuggestionInfo>> {
+        let database_manager = self.database_manager().await?;
+        let context = TagOperations::new(database_manager)
+            .suggestion_context(file_id)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))?
+            .ok_or(ControllerError::FileNotFound)?;
+        Ok(context.suggest(limit).into_iter().map(Into::into).collect())
+    }
+
+    /// Merge tags into another one as one undoable change
+    ///
+    /// The files and hierarchy of the merged tags move to the target, their names become aliases
+    /// of the target and they are deleted.
+    pub async fn merge_tags(&self, tag_ids: &[i32], into: i32) -> ControllerResult<()> {
+        let database_manager = self.database_manager().await?;
+        let target = Self::find_tag(&database_manager, into).await?;
+        let mut names = Vec::new();
+        let mut commands = Vec::new();
+        for &tag_id in tag_ids {
+            if tag_id == into || commands.contains(&TagCommand::MergeTag { tag_id, into }) {
+                continue;
+            }
+            names.push(Self::find_tag(&database_manager, tag_id).await?.name);
+            commands.push(TagCommand::MergeTag { tag_id, into });
+        }
+        if commands.is_empty() {
+            return Ok(());
+        }
+        self.execute_tag_command(
+            database_manager,
+            &format!("Merge {} into {}", names.join(", "), target.name),
+            TagCommand::Batch { commands },
+        )
+        .await
+    }
+
+    /// Move some files of a tag to a new tag as one undoable change
+    ///
+    /// Only the selected files that have the tag are moved. Returns the number of moved files.
+    pub async fn split_tag(
+        &self,
+        tag_id: i32,
+        selection: FileSelection,
+        name: &str,
+    ) -> ControllerResult<usize> {
+        let name = Self::tag_name(name)?;
+        let database_manager = self.database_manager().await?;
+        let tag = Self::find_tag(&database_manager, tag_id).await?;
+        let tags = TagOperations::new(Arc::clone(&database_manager));
+        let taken = tags
+            .list()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
+            .into_iter()
+            .any(|(other, details)| {
+                other.name == name || details.aliases.iter().any(|alias| alias == name)
+            });
+        if taken {
+            return Err(ControllerError::TagNameTaken);
+        }
+        let selected = Self::selected_file_ids(&database_manager, selection).await?;
+        let file_ids = tags
+            .files_with_tag(tag_id, &selected)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
+        if file_ids.is_empty() {
+            return Ok(0);
+        }
+        let moved = file_ids.len();
+        self.execute_tag_command(
+            database_manager,
+            &format!("Split {name} from {}", tag.name),
+            TagCommand::Batch {
+                commands: vec![
+                    TagCommand::CreateTag {
+                        tag_id: None,
+                        name: name.to_string(),
+                        file_ids: file_ids.clone(),
+                        details: TagDetails::default(),
+                    },
+                    TagCommand::RemoveTag { tag_id, file_ids },
+                ],
+            },
+        )
+        .await?;
+        Ok(moved)
+    }
+
+    pub async fn assign_tag(&self, file_id: i32, tag_id: i32) -> ControllerResult<()> {
+        let database_manager = self.database_manager().await?;
+        if files::Entity::find_by_id(file_id)
+            .one(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
+            .is_none()
+        {
+            return Err(ControllerError::FileNotFound);
+        }
+        let tag = Self::find_tag(&database_manager, tag_id).await?;
+        self.execute_tag_command(
+            database_manager,
+            &format!("Assign tag {}", tag.name),
+            TagCommand::AssignTag {
+                tag_id,
+                file_ids: vec![file_id],
+            },
+        )
+        .await
+    }
+
+    pub async fn remove_tag(&self, file_id: i32, tag_id: i32) -> ControllerResult<()> {
+        let database_manager = self.database_manager().await?;
+        let tag = match Self::find_tag(&database_manager, tag_id).await {
+            Ok(tag) => tag,
+            Err(ControllerError::TagNotFound) => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        self.execute_tag_command(
+            database_manager,
+            &format!("Remove tag {}", tag.name),
+            TagCommand::RemoveTag {
+                tag_id,
+                file_ids: vec![file_id],
+            },
+        )
+        .await
+    }
+
+    /// Assign every tag to every selected file as a single undoable change
+    pub async fn assign_tags(
+        &self,
+        selection: FileSelection,
+        tag_ids: &[i32],
+    ) -> ControllerResult<BulkTagReport> {
+        self.bulk_tag(selection, tag_ids, true).await
+    }
+
+    /// Remove every tag from every selected file as a single undoable change
+    pub async fn remove_tags(
+        &self,
+        selection: FileSelection,
+        tag_ids: &[i32],
+    ) -> ControllerResult<BulkTagReport> {
+        self.bulk_tag(selection, tag_ids, false).await
+    }
+
+    async fn bulk_tag(
+        &self,
+        selection: FileSelection,
+        tag_ids: &[i32],
+        assign: bool,
+    ) -> ControllerResult<BulkTagReport> {
+        let database_manager = self.database_manager().await?;
+        let tags = tags::Entity::find()
+            .filter(tags::Column::Id.is_in(tag_ids.iter().copied()))
+            .order_by_asc(tags::Column::Name)
+            .all(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
+        if tag_ids
+            .iter()
+            .any(|tag_id| !tags.iter().any(|tag| tag.id == *tag_id))
+        {
+            return Err(ControllerError::TagNotFound);
+        }
+        let file_ids = Self::selected_file_ids(&database_manager, selection).await?;
+        let files_selected = file_ids.len();
+        if files_selected == 0 || tags.is_empty() {
+            return Ok(BulkTagReport::default());
+        }
+
+        let names: Vec<&str> = tags.iter().map(|tag| tag.name.as_str()).collect();
+        let label = if assign {
+            format!("Assign {} to {files_selected} files", names.join(", "))
+        } else {
+            format!("Remove {} from {files_selected} files", names.join(", "))
+        };
+        let commands = tags
+            .iter()
+            .map(|tag| {
+                let file_ids = file_ids.clone();
+                if assign {
+                    TagCommand::AssignTag {
+                        tag_id: tag.id,
+                        file_ids,
+                    }
+                } else {
+                    TagCommand::RemoveTag {
+                        tag_id: tag.id,
+                        file_ids,
+                    }
+                }
+            })
+            .collect();
+        let applied = HistoryOperations::new(database_manager)
+            .execute(&label, TagCommand::Batch { commands })
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
+        self.publish_tag_command(&applied);
+        Ok(BulkTagReport {
+            files_selected,
+            changed: applied.assignment_count(),
+        })
+    }
+
+    async fn selected_file_ids(
+        database_manager: &Arc<DatabaseManager>,
+        selection: FileSelection,
+    ) -> ControllerResult<Vec<i32>> {
+        if let FileSelection::Files(file_ids) = selection {
+            return Ok(file_ids);
+        }
+        let condition = Self::selection_condition(database_manager, selection).await?;
+        files::Entity::find()
+            .select_only()
+            .column(files::Column::Id)
+            .filter(condition)
+            .into_tuple()
+            .all(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    async fn selection_condition(
+        database_manager: &Arc<DatabaseManager>,
+        selection: FileSelection,
+    ) -> ControllerResult<Condition> {
+        match selection {
+            FileSelection::Files(file_ids) => {
+                Ok(Condition::all().add(files::Column::Id.is_in(file_ids)))
+            }
+            FileSelection::Matching { folder_id, search } => {
+                Self::file_condition(database_manager, folder_id, &search).await
+            }
+            FileSelection::SmartFolder {
+                smart_folder_id,
+                search,
+            } => Self::smart_folder_condition(database_manager, smart_folder_id, &search).await,
+        }
+    }
+
+    /// Show which tags the auto-tag rules of the library would assign, without assigning them
+    ///
+    /// The rules are read from the library configuration again, so invalid rules are reported.
+    pub async fn preview_auto_tags(
+        &self,
+        selection: FileSelection,
+    ) -> ControllerResult<Vec<AutoTagPreview>> {
+        let (auto_tagger, files) = self.auto_tag_selection(selection).await?;
+        Ok(auto_tagger
+            .preview(&files)
+            .await
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    /// Apply the auto-tag rules of the library to files that are already in the library
+    ///
+    /// Like tags assigned during a scan, these assignments are not part of the undo history.
+    pub async fn apply_auto_tags(
+        &self,
+        selection: FileSelection,
+    ) -> ControllerResult<BulkTagReport> {
+        let (auto_tagger, files) = self.auto_tag_selection(selection).await?;
+        let assignments = auto_tagger
+            .apply(&files, JournalSource::User)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::AutoTag, error))?;
+        for &tag_id in &assignments.created_tags {
+            self.changes.publish(LibraryChange::TagsChanged { tag_id });
+        }
+        for &(file_id, tag_id) in &assignments.assigned {
+            self.changes
+                .publish(LibraryChange::TagAssigned { file_id, tag_id });
+        }
+        Ok(BulkTagReport {
+            files_selected: files.len(),
+            changed: assignments.assigned.len(),
+        })
+    }
+
+    async fn auto_tag_selection(
+        &self,
+        selection: FileSelection,
+    ) -> ControllerResult<(AutoTagger, Vec<files::Model>)> {
+        let (database_manager, rules) = {
+            let state = self.state.lock().await;
+            let AppState::Ready { library, workspace } = &*state else {
+                return Err(ControllerError::NoLibrarySelected);
+            };
+            let rules = library
+                .library_config
+                .as_ref()
+                .map(|config| config.auto_tag.clone())
+                .unwrap_or_default();
+            (Arc::clone(&workspace.database_manager), rules)
+        };
+        let auto_tagger = AutoTagger::new(Arc::clone(&database_manager), &rules)
+            .map_err(|error| ControllerError::operation(ControllerOperation::AutoTag, error))?;
+        let file_ids = Self::selected_file_ids(&database_manager, selection).await?;
+        let mut files = Vec::with_capacity(file_ids.len());
+        for chunk in file_ids.chunks(1_000) {
+            files.extend(
+                files::Entity::find()
+                    .filter(files::Column::Id.is_in(chunk.iter().copied()))
+                    .order_by_asc(files::Column::Id)
+                    .all(database_manager.get_connection().as_ref())
+                    .await
+                    .map_err(|error| {
+                        ControllerError::operation(ControllerOperation::QueryLibrary, error)
+                    })?,
+            );
+        }
+        Ok((auto_tagger, files))
+    }
+
+    /// Revert the most recent tag change, returning its description
+    pub async fn undo(&self) -> ControllerResult<Option<String>> {
+        let database_manager = self.database_manager().await?;
+        let applied = HistoryOperations::new(database_manager)
+            .undo()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::EditHistory, error))?;
+        Ok(applied.map(|applied| {
+            self.publish_tag_command(&applied.command);
+            applied.label
+        }))
+    }
+
+    /// Reapply the most recently undone tag change, returning its description
+    pub async fn redo(&self) -> ControllerResult<Option<String>> {
+        let database_manager = self.database_manager().await?;
+        let applied = HistoryOperations::new(database_manager)
+            .redo()
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::EditHistory, error))?;
+        Ok(applied.map(|applied| {
+            self.publish_tag_command(&applied.command);
+            applied.label
+        }))
+    }
+
+    /// The most recent tag changes, newest first
+    pub async fn history(&self, limit: u64) -> ControllerResult<Vec<HistoryInfo>> {
+        let database_manager = self.database_manager().await?;
+        HistoryOperations::new(database_manager)
+            .list(limit)
+            .await
+            .map(|entries| entries.into_iter().map(Into::into).collect())
+            .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
+    }
+
+    async fn find_tag(
+        database_manager: &DatabaseManager,
+        tag_id: i32,
+    ) -> ControllerResult<tags::Model> {
+        tags::Entity::find_by_id(tag_id)
+            .one(database_manager.get_connection().as_ref())
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
+            .ok_or(ControllerError::TagNotFound)
+    }
+
+    async fn execute_tag_command(
+        &self,
+        database_manager: Arc<DatabaseManager>,
+        label: &str,
+        command: TagCommand,
+    ) -> ControllerResult<()> {
+        let applied = HistoryOperations::new(database_manager)
+            .execute(label, command)
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
+        self.publish_tag_command(&applied);
+        Ok(())
+    }
+
+    fn publish_tag_command(&self, command: &TagCommand) {
+        match command {
+            TagCommand::CreateTag {
+                tag_id, file_ids, ..
+            } => {
+                let tag_id = tag_id.unwrap_or_default();
+                self.changes.publish(LibraryChange::TagsChanged { tag_id });
+                for &file_id in file_ids {
+                    self.changes
+                        .publish(LibraryChange::TagAssigned { file_id, tag_id });
+                }
+            }
+            TagCommand::RenameTag { tag_id, .. } | TagCommand::EditTag { tag_id, .. } => {
+                self.changes
+                    .publish(LibraryChange::TagsChanged { tag_id: *tag_id });
+            }
+            TagCommand::LinkTags { links } | TagCommand::UnlinkTags { links } => {
+                for link in links {
+                    self.changes.publish(LibraryChange::TagsChanged {
+                        tag_id: link.super_tag_id,
+                    });
+                    self.changes.publish(LibraryChange::TagsChanged {
+                        tag_id: link.sub_tag_id,
+                    });
+                }
+            }
+            TagCommand::MergeTag { tag_id, into } => {
+                self.changes
+                    .publish(LibraryChange::TagsChanged { tag_id: *tag_id });
+                self.changes
+                    .publish(LibraryChange::TagsChanged { tag_id: *into });
+            }
+            TagCommand::DeleteTag {
+                tag_id, file_ids, ..
+            } => {
+                self.changes
+                    .publish(LibraryChange::TagsChanged { tag_id: *tag_id });
+                for &file_id in file_ids {
+                    self.changes.publish(LibraryChange::TagRemoved {
+                        file_id,
+                        tag_id: *tag_id,
+                    });
+                }
+            }
+            TagCommand::AssignTag { tag_id, file_ids } => {
+                for &file_id in file_ids {
+                    self.changes.publish(LibraryChange::TagAssigned {
+                        file_id,
+                        tag_id: *tag_id,
+                    });
+                }
+            }
+            TagCommand::RemoveTag { tag_id, file_ids } => {
+                for &file_id in file_ids {
+                    self.changes.publish(LibraryChange::TagRemoved {
+                        file_id,
+                        tag_id: *tag_id,
+                    });
+                }
+            }
+            TagCommand::Batch { commands } => {
+                for command in commands {
+                    self.publish_tag_command(command);
+                }
+            }
+        }
+    }
+
+    /// Recent activity of the whole library, a single file or the files below a folder
+    pub async fn list_activity(
+        &self,
+        scope: ActivityScope,
+        limit: u64,
+    ) -> ControllerResult<Vec<ActivityInfo>> {
+        let database_manager = self.database_manager().await?;
+        let journal = JournalOperations::new(Arc::clone(&database_manager));
+        let entries = match scope {
+            ActivityScope::Library => journal.list_recent(limit).await,
+            ActivityScope::File(file_id) => journal.list_for_file(file_id, limit).await,
+            ActivityScope::Folder(folder_id) => {
+                let folder = folders::Entity::find_by_id(folder_id)
+                    .one(database_manager.get_connection().as_ref())
+                    .await
+                    .map_err(|error| {
+                        ControllerError::operation(ControllerOperation::QueryLibrary, error)
+                    })?
+                    .ok_or(ControllerError::FileNotFound)?;
+                journal.list_below(Path::new(&folder.path), limit).await
+            }
+        };
+        entries
+            .map(|entries| entries.into_iter().map(Into::into).collect())
             .map_err(|error| ControllerError::operation(ControllerOperation::QueryLibrary, error))
     }
 
-    pub async fn create_tag(&self, name: &str) -> ControllerResult<()> {
-        let name = Self::tag_name(name)?;
-        let database_manager = self.database_manager().await?;
-        let connection = database_manager.get_connection();
-        if tags::Entity::find()
-            .filter(tags::Column::Name.eq(name))
-            .one(connection.as_ref())
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
-            .is_some()
-        {
-            return Ok(());
+    async fn database_manager(&self) -> ControllerResult<Arc<DatabaseManager>> {
+        let state = self.state.lock().await;
+        let AppState::Ready { workspace, .. } = &*state else {
+            return Err(ControllerError::NoLibrarySelected);
+        };
+        Ok(Arc::clone(&workspace.database_manager))
+    }
+
+    async fn thumbnail_processor(&self) -> ControllerResult<ThumbnailProcessorHandler> {
+        let state = self.state.lock().await;
+        let AppState::Ready { workspace, .. } = &*state else {
+            return Err(ControllerError::NoLibrarySelected);
+        };
+        workspace.thumbnail_processor()
+    }
+
+    async fn watcher(&self) -> ControllerResult<FileWatcherHandler> {
+        let state = self.state.lock().await;
+        let AppState::Ready { workspace, .. } = &*state else {
+            return Err(ControllerError::NoLibrarySelected);
+        };
+        workspace.watcher.clone().ok_or_else(|| {
+            ControllerError::operation(
+                ControllerOperation::ControlWatcher,
+                anyhow::anyhow!("the library is not being watched"),
+            )
+        })
+    }
+
+    /// The content folders configured for the library
+    fn library_paths(library: &Library) -> Vec<PathBuf> {
+        library
+            .library_config
+            .as_ref()
+            .map(|config| {
+                config
+                    .library_paths
+                    .iter()
+                    .filter_map(|path| path.path.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    async fn backups(&self) -> ControllerResult<Arc<LibraryBackups>> {
+        let state = self.state.lock().await;
+        let AppState::Ready { workspace, .. } = &*state else {
+            return Err(ControllerError::NoLibrarySelected);
+        };
+        Ok(Arc::clone(&workspace.backups))
+    }
+
+    async fn thumbnail_operations(&self) -> ControllerResult<Arc<ThumbnailOperations>> {
+        let state = self.state.lock().await;
+        let AppState::Ready { workspace, .. } = &*state else {
+            return Err(ControllerError::NoLibrarySelected);
+        };
+        Ok(Arc::clone(&workspace.thumbnail_operations))
+    }
+
+    fn filter_condition(filter: &Filter, now: NaiveDateTime) -> Condition {
+        let mut condition = Condition::all();
+        if let Some(folder_filter) = &filter.folder_filter {
+            condition = condition.add(folder_filter.folders.iter().fold(
+                Condition::any(),
+                |folders, folder| {
+                    folders.add(files::Column::Path.starts_with(folder.to_string_lossy()))
+                },
+            ));
+        }
+        if let Some(tag_filter) = &filter.tag_filter {
+            for tag in &tag_filter.tags {
+                condition = condition.add(
+                    files::Column::Id.in_subquery(
+                        file_has_tags::Entity::find()
+                            .select_only()
+                            .column(file_has_tags::Column::FileId)
+                            .filter(file_has_tags::Column::TagId.eq(tag.id))
+                            .into_query(),
+                    ),
+                );
+            }
+            if tag_filter.untagged {
+                condition = condition.add(
+                    files::Column::Id.not_in_subquery(
+                        file_has_tags::Entity::find()
+                            .select_only()
+                            .column(file_has_tags::Column::FileId)
+                            .into_query(),
+                    ),
+                );
+            }
+        }
+        if !filter.extensions.is_empty() {
+            condition = condition.add(filter.extensions.iter().fold(
+                Condition::any(),
+                |extensions, extension| {
+                    let extension = extension.trim_start_matches('.');
+                    extensions.add(files::Column::Name.ends_with(format!(".{extension}")))
+                },
+            ));
+        }
+        for (range, column) in [
+            (filter.added, files::Column::CreatedAt),
+            (filter.modified, files::Column::ModifiedAt),
+        ] {
+            let Some(range) = range else {
+                continue;
+            };
+            let (start, end) = range.bounds(now);
+            if let Some(start) = start {
+                condition = condition.add(column.gte(start));
+            }
+            if let Some(end) = end {
+                condition = condition.add(column.lt(end));
+            }
+        }
+        condition.add(Self::search_condition(&SearchQuery::parse(&filter.text)))
+    }
+
+    fn search_condition(query: &SearchQuery) -> Condition {
+        let mut condition = Condition::all();
+        for term in &query.terms {
+            condition = condition.add(
+                Condition::any()
+                    .add(files::Column::Name.contains(term))
+                    .add(files::Column::Path.contains(term)),
+            );
+        }
+        for filter in &query.filters {
+            let metadata = match filter {
+                SearchFilter::Taken(range) => Condition::all()
+                    .add(media_metadata::Column::TakenAt.gte(range.start))
+                    .add(media_metadata::Column::TakenAt.lt(range.end)),
+                SearchFilter::Camera(camera) => Condition::any()
+                    .add(media_metadata::Column::CameraMake.contains(camera))
+                    .add(media_metadata::Column::CameraModel.contains(camera)),
+                SearchFilter::Field(field) => {
+                    condition = condition.add(Self::field_condition(field));
+                    continue;
+                }
+                SearchFilter::Rating(comparison, rating) => {
+                    let rating = i16::from(*rating);
+                    let column = files::Column::Rating;
+                    condition = condition.add(match comparison {
+                        Comparison::Equal => column.eq(rating),
+                        Comparison::NotEqual => column.ne(rating),
+                        Comparison::Less => column.lt(rating),
+                        Comparison::LessOrEqual => column.lte(rating),
+                        Comparison::Greater => column.gt(rating),
+                        Comparison::GreaterOrEqual => column.gte(rating),
+                    });
+                    continue;
+                }
+                SearchFilter::Favorite(favorite) => {
+                    condition = condition.add(files::Column::Favorite.eq(*favorite));
+                    continue;
+                }
+                SearchFilter::Label(label) => {
+                    condition = condition.add(match label {
+                        Some(label) => files::Column::ColorLabel.eq(label.as_str()),
+                        None => files::Column::ColorLabel.is_null(),
+                    });
+                    continue;
+                }
+            };
+            condition = condition.add(
+                files::Column::Id.in_subquery(
+                    media_metadata::Entity::find()
+                        .select_only()
+                        .column(media_metadata::Column::FileId)
+                        .filter(metadata)
+                        .into_query(),
+                ),
+            );
+        }
+        condition
+    }
+
+    /// Files whose value for a custom field compares to the value of the filter
+    ///
+    /// `!=` also matches the files without a value for the field.
+    fn field_condition(filter: &FieldFilter) -> Condition {
+        let mut values = Condition::all().add(custom_fields::Column::Name.eq(&filter.field));
+        let mut comparison = None;
+        if let Some((compared, value)) = &filter.comparison {
+            let text = Expr::expr(Func::lower(Expr::col((
+                file_field_values::Entity,
+                file_field_values::Column::Value,
+            ))));
+            let value = value.to_lowercase();
+            let number = file_field_values::Column::Number;
+            values = values.add(match (compared, filter.number()) {
+                (Comparison::Equal | Comparison::NotEqual, Some(parsed)) => {
+                    Condition::any().add(number.eq(parsed)).add(text.eq(value))
+                }
+                (Comparison::Equal | Comparison::NotEqual, None) => {
+                    Condition::all().add(text.eq(value))
+                }
+                (Comparison::Less, Some(parsed)) => Condition::all().add(number.lt(parsed)),
+                (Comparison::LessOrEqual, Some(parsed)) => Condition::all().add(number.lte(parsed)),
+                (Comparison::Greater, Some(parsed)) => Condition::all().add(number.gt(parsed)),
+                (Comparison::GreaterOrEqual, Some(parsed)) => {
+                    Condition::all().add(number.gte(parsed))
+                }
+                (Comparison::Less, None) => Condition::all().add(text.lt(value)),
+                (Comparison::LessOrEqual, None) => Condition::all().add(text.lte(value)),
+                (Comparison::Greater, None) => Condition::all().add(text.gt(value)),
+                (Comparison::GreaterOrEqual, None) => Condition::all().add(text.gte(value)),
+            });
+            comparison = Some(*compared);
+        }
+        let matching = file_field_values::Entity::find()
+            .select_only()
+            .column(file_field_values::Column::FileId)
+            .inner_join(custom_fields::Entity)
+            .filter(values)
+            .into_query();
+        if comparison == Some(Comparison::NotEqual) {
+            Condition::all().add(files::Column::Id.not_in_subquery(matching))
+        } else {
+            Condition::all().add(files::Column::Id.in_subquery(matching))
+        }
+    }
+
+    fn field_name(name: &str) -> ControllerResult<&str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ControllerError::InvalidFieldName);
+        }
+        Ok(name)
+    }
+
+    fn smart_folder_name(name: &str) -> ControllerResult<&str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ControllerError::InvalidSmartFolderName);
+        }
+        Ok(name)
+    }
+
+    fn tag_name(name: &str) -> ControllerResult<&str> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ControllerError::InvalidTagName);
+        }
+        Ok(name)
+    }
+
+    async fn activate(&self, library: Library) -> ControllerResult<LibraryInfo> {
+        let info = LibraryInfo::from_path(
+            library
+                .share_path
+                .clone()
+                .ok_or(ControllerError::MissingStorageFolder)?,
+        )?;
+        let workspace = Workspace::open(&library, self.changes.clone())
+            .await
+            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))?;
+        self.replace_workspace(library, workspace).await;
+        Ok(info)
+    }
+
+    async fn replace_workspace(&self, library: Library, workspace: Workspace) {
+        let previous = std::mem::replace(
+            &mut *self.state.lock().await,
+            AppState::Ready { library, workspace },
+        );
+        if let AppState::Ready { workspace, .. } = previous {
+            workspace.close().await;
+        }
+    }
+}
+
+impl Workspace {
+    /// Open the database of a library without starting any background task
+    async fn open_database(library: &Library) -> Result<Self> {
+        let database_path = library.get_canon_database_path()?;
+        let connection_string = format!("sqlite:///{}", database_path.as_str()?);
+        let settings = DatabaseSettings {
+            con_string: connection_string,
+            timeout: 30_000,
+            ..DatabaseSettings::default()
+        };
+        let database_manager = Arc::new(DatabaseManager::new(settings).await?);
+        database_manager.test_connection().await?;
+        let file_operations = Arc::new(FileRepository::new(Arc::clone(&database_manager)));
+
+        let share_path = library
+            .share_path
+            .as_ref()
+            .context("library has no storage folder")?;
+        let storage = library
+            .library_config
+            .as_ref()
+            .map(|config| config.thumbnails.storage)
+            .unwrap_or_default();
+        let thumbnail_operations = Arc::new(
+            ThumbnailOperations::new(Arc::clone(&database_manager))
+                .with_cache(ThumbnailCache::new(share_path.join("thumbs")))
+                .with_storage(storage),
+        );
+        let rules = library
+            .library_config
+            .as_ref()
+            .map(|config| config.auto_tag.as_slice())
+            .unwrap_or_default();
+        // A broken rule must not keep the library from opening, the preview reports it instead
+        let auto_tagger = match AutoTagger::new(Arc::clone(&database_manager), rules) {
+            Ok(auto_tagger) if !auto_tagger.is_empty() => Some(Arc::new(auto_tagger)),
+            Ok(_) => None,
+            Err(error) => {
+                tracing::warn!("Auto-tag rules are disabled: {error:#}");
+                None
+            }
+        };
+        let tag_sync = library
+            .library_config
+            .as_ref()
+            .map(|config| config.tag_sync)
+            .filter(|config| config.is_enabled())
+            .map(|config| Arc::new(TagSync::new(Arc::clone(&database_manager), config)));
+        let backup_config = library
+            .library_config
+            .as_ref()
+            .map(|config| config.backup.clone())
+            .unwrap_or_default();
+        let backups = Arc::new(LibraryBackups::new(
+            Arc::clone(&database_manager),
+            share_path,
+            backup_config,
+        ));
+        Ok(Self {
+            database_manager,
+            file_operations,
+            thumbnail_operations,
+            thumbnail_processor: None,
+            watcher: None,
+            auto_tagger,
+            tag_sync,
+            tag_writer: None,
+            backups,
+            backup_scheduler: None,
+        })
+    }
+
+    /// Open the database of a library and start generating thumbnails, syncing tags and
+    /// backing up in the background
+    async fn open(library: &Library, changes: ChangeBus) -> Result<Self> {
+        let mut workspace = Self::open_database(library).await?;
+        let storage = workspace.thumbnail_operations.storage();
+        // Cached thumbnails are served as files, where the smaller WebP encoding pays off
+        let thumbnail_generator = match storage {
+            ThumbnailStorageKind::Database => ThumbnailGenerator::new(),
+            ThumbnailStorageKind::Directory => {
+                ThumbnailGenerator::new().with_format(image::ImageFormat::WebP)
+            }
+        };
+
+        let (thumbnail_processor, receiver) = ThumbnailProcessorHandler::new();
+        let processor = ThumbnailProcessor::new(
+            receiver,
+            Arc::clone(&workspace.thumbnail_operations),
+            Arc::new(thumbnail_generator),
+        )
+        .with_metadata_repository(Arc::new(MediaMetadataOperations::new(Arc::clone(
+            &workspace.database_manager,
+        ))))
+        .with_changes(changes.clone());
+        tokio::spawn(async move {
+            if let Err(error) = processor.run().await {
+                tracing::error!(%error, "Thumbnail processor stopped");
+            }
+        });
+        workspace.thumbnail_processor = Some(thumbnail_processor);
+        workspace.tag_writer = workspace.tag_sync.as_ref().map(|tag_sync| {
+            tokio::spawn(write_tag_changes(
+                Arc::clone(tag_sync),
+                Arc::clone(&workspace.database_manager),
+                changes.clone(),
+                changes.subscribe(),
+            ))
+        });
+        workspace.backup_scheduler = workspace.backups.config().interval().map(|interval| {
+            tokio::spawn(back_up_regularly(Arc::clone(&workspace.backups), interval))
+        });
+        Ok(workspace)
+    }
+
+    fn thumbnail_processor(&self) -> ControllerResult<ThumbnailProcessorHandler> {
+        self.thumbnail_processor.clone().ok_or_else(|| {
+            ControllerError::operation(
+                ControllerOperation::GenerateThumbnails,
+                anyhow::anyhow!("the library is open for maintenance only"),
+            )
+        })
+    }
+
+    /// Stop the background tasks writing into this library's database, then close it
+    async fn close(self) {
+        if let Some(tag_writer) = self.tag_writer {
+            tag_writer.abort();
+        }
+        if let Some(backup_scheduler) = self.backup_scheduler {
+            backup_scheduler.abort();
+        }
+        self.backups.wait_until_idle().await;
+        if let Some(watcher) = self.watcher
+            && let Err(error) = watcher.stop().await
+        {
+            tracing::warn!(%error, "File watcher was already stopped");
+        }
+        if let Some(thumbnail_processor) = self.thumbnail_processor
+            && let Err(error) = thumbnail_processor.shutdown().await
+        {
+            tracing::warn!(%error, "Thumbnail processor was already stopped");
+        }
+        if let Err(error) = self.database_manager.close().await {
+            tracing::warn!(%error, "Library database was not closed cleanly");
+        }
+    }
+}
+
+/// Back the library up whenever the interval has passed since its last backup
+///
+/// A library that was closed for longer than the interval is backed up right after opening.
+async fn back_up_regularly(backups: Arc<LibraryBackups>, interval: Duration) {
+    loop {
+        let last = match backups.list().await {
+            Ok(list) => list.first().map(|backup| backup.manifest.created_at),
+            Err(error) => {
+                tracing::warn!("Could not list the backups of the library: {error:#}");
+                None
+            }
+        };
+        let elapsed = last.and_then(|last| (Utc::now().naive_utc() - last).to_std().ok());
+        tokio::time::sleep(
+            elapsed.map_or(Duration::ZERO, |elapsed| interval.saturating_sub(elapsed)),
+        )
+        .await;
+        // Written by its own task, so stopping the schedule does not cut a backup short
+        let backup = tokio::spawn({
+            let backups = Arc::clone(&backups);
+            async move { backups.create().await }
+        })
+        .await
+        .unwrap_or_else(|error| Err(error.into()));
+        match backup {
+            Ok(backup) => tracing::info!(path = %backup.path.display(), "Backed up the library"),
+            Err(error) => {
+                tracing::warn!("Could not back up the library: {error:#}");
+                tokio::time::sleep(interval).await;
+            }
+        }
+    }
+}
+
+/// Publish the tags a sync created, assigned and removed in the library
+fn publish_tag_sync(changes: &ChangeBus, report: &TagSyncReport) {
+    for &tag_id in &report.created_tags {
+        changes.publish(LibraryChange::TagsChanged { tag_id });
+    }
+    for &(file_id, tag_id) in &report.assigned {
+        changes.publish(LibraryChange::TagAssigned { file_id, tag_id });
+    }
+    for &(file_id, tag_id) in &report.removed {
+        changes.publish(LibraryChange::TagRemoved { file_id, tag_id });
+    }
+}
+
+/// Write the tags of files to their attributes and sidecars as they change in the library
+///
+/// Changes that arrive together, such as those of a bulk assignment, are written together. A
+/// changed tag is written to the files that carry it. When changes were missed, the files may
+/// have changed as well, so every file is synced following the conflict policy instead.
+async fn write_tag_changes(
+    tag_sync: Arc<TagSync>,
+    database_manager: Arc<DatabaseManager>,
+    bus: ChangeBus,
+    mut changes: broadcast::Receiver<LibraryChange>,
+) {
+    loop {
+        let mut file_ids = Vec::new();
+        let mut tag_ids = Vec::new();
+        let mut lagged = false;
+        let mut received = Some(changes.recv().await);
+        while let Some(change) = received.take() {
+            match change {
+                Ok(
+                    LibraryChange::TagAssigned { file_id, .. }
+                    | LibraryChange::TagRemoved { file_id, .. },
+                ) => {
+                    file_ids.push(file_id);
+                }
+                Ok(LibraryChange::TagsChanged { tag_id }) => tag_ids.push(tag_id),
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => lagged = true,
+                Err(RecvError::Closed) => return,
+            }
+            received = match changes.try_recv() {
+                Ok(change) => Some(Ok(change)),
+                Err(TryRecvError::Lagged(skipped)) => Some(Err(RecvError::Lagged(skipped))),
+                Err(TryRecvError::Closed) => Some(Err(RecvError::Closed)),
+                Err(TryRecvError::Empty) => None,
+            };
+        }
+        let connection = database_manager.get_connection();
+        let written = if lagged {
+            match files::Entity::find().all(connection.as_ref()).await {
+                Ok(files) => tag_sync
+                    .import(&files, JournalSource::Scanner)
+                    .await
+                    .inspect(|report| publish_tag_sync(&bus, report)),
+                Err(error) => Err(error.into()),
+            }
+        } else if file_ids.is_empty() && tag_ids.is_empty() {
+            continue;
+        } else {
+            let tagged = Query::select()
+                .column(file_has_tags::Column::FileId)
+                .from(file_has_tags::Entity)
+                .and_where(file_has_tags::Column::TagId.is_in(tag_ids))
+                .to_owned();
+            let select = files::Entity::find().filter(
+                Condition::any()
+                    .add(files::Column::Id.is_in(file_ids))
+                    .add(files::Column::Id.in_subquery(tagged)),
+            );
+            match select.all(connection.as_ref()).await {
+                Ok(files) => tag_sync.export(&files).await,
+                Err(error) => Err(error.into()),
+            }
+        };
+        match written {
+            Ok(report) => {
+                for problem in report.problems {
+                    tracing::warn!("Tags were not written: {problem}");
+                }
+            }
+            Err(error) => tracing::warn!("Tags were not written: {error:#}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ActivityInfo, ActivityScope, AppController, Color, ColorLabel, ControllerError, DateFilter,
+        FieldType, FileInfo, FilePage, FileSelection, FileSort, FileSortKey, Filter,
+        FolderDecoration, HistoryInfo, Icon, IconType, IntegrityProblem, LibraryChange, MAX_RATING,
+        MediaMetadata, MediaMetadataOperations, SuggestionSource, Tag, TagDetails, TagFilter,
+        TagInfo, TagOperations, TagSuggestionInfo, files,
+    };
+    use anyhow::{Context, Result};
+    use chrono::NaiveDate;
+    use sea_orm::sea_query::Expr;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+    use std::cmp::Ordering;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn create_library_returns_an_owned_summary() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+
+        let library = controller.create_library("Photos", content.path()).await?;
+
+        assert_eq!(library.name().as_str(), "Photos");
+        assert_eq!(library.path(), data_home.path().join("hestia/Photos"));
+        assert!(library.path().join("config.toml").is_file());
+        assert!(library.path().join("db.sqlite").is_file());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_libraries_returns_only_library_directories() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        std::fs::write(data_home.path().join("hestia/not-a-library"), "ignored")?;
+
+        let libraries = controller.list_libraries()?;
+
+        assert_eq!(libraries.len(), 1);
+        assert_eq!(libraries[0].name().as_str(), "Photos");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn select_library_opens_an_existing_library() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let creator = AppController::new_in(data_home.path())?;
+        let created = creator.create_library("Photos", content.path()).await?;
+        let controller = AppController::new_in(data_home.path())?;
+
+        let selected = controller.select_library(created.path()).await?;
+
+        assert_eq!(selected, created);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn initialize_workspace_migrates_the_selected_database() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        let library = controller.create_library("Photos", content.path()).await?;
+
+        controller.initialize_workspace().await?;
+
+        assert!(std::fs::metadata(library.path().join("db.sqlite"))?.len() > 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn initialized_library_supports_the_qt_controller_api() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        std::fs::write(content.path().join("notes.txt"), "hello")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Notes", content.path()).await?;
+        controller.initialize_workspace().await?;
+
+        let report = controller.scan().await?;
+        assert_eq!(report.files_scanned(), 1);
+        let folders = controller.list_folders().await?;
+        let files = controller
+            .list_files(Some(folders[0].id()), "notes")
+            .await?;
+        assert_eq!(files.len(), 1);
+
+        controller.create_tag("Important").await?;
+        let tags = controller.list_tags().await?;
+        assert_eq!(tags[0].name(), "Important");
+        controller.assign_tag(files[0].id(), tags[0].id()).await?;
+        controller.remove_tag(files[0].id(), tags[0].id()).await?;
+        controller.delete_tag(tags[0].id()).await?;
+        assert!(controller.list_tags().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn thumbnail_status_is_available_once_the_workspace_is_ready() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        assert!(matches!(
+            controller.thumbnail_status().await,
+            Err(ControllerError::NoLibrarySelected)
+        ));
+
+        controller.create_library("Empty", content.path()).await?;
+        controller.initialize_workspace().await?;
+
+        let status = controller.thumbnail_status().await?;
+        assert_eq!(status.pending(), 0);
+        assert_eq!(status.stored(), 0);
+        assert_eq!(status.storage_bytes(), 0);
+        assert!(!status.is_stalled());
+        assert!(controller.failed_thumbnails().await?.is_empty());
+        assert_eq!(controller.retry_failed_thumbnails(None).await?, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watcher_can_be_paused_stopped_and_restarted() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let other_content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+
+        let mut changes = controller.subscribe_changes();
+        controller.start_watching().await?;
+        assert!(controller.start_watching().await.is_err());
+        controller.pause_watching().await?;
+        controller.resume_watching().await?;
+        // Resuming resyncs the folders to catch up on what happened while paused
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.recv()).await??;
+        assert!(matches!(change, LibraryChange::FolderResynced { .. }));
+        controller.stop_watching().await?;
+        assert!(controller.pause_watching().await.is_err());
+
+        controller.start_watching().await?;
+        std::fs::write(content.path().join("watched.txt"), "watched")?;
+        loop {
+            let change = tokio::time::timeout(Duration::from_secs(10), changes.recv()).await??;
+            if matches!(change, LibraryChange::FileAdded { .. }) {
+                break;
+            }
         }
-        tags::ActiveModel {
-            id: sea_orm::ActiveValue::NotSet,
-            name: Set(name.to_string()),
-            created_at: Set(chrono::Utc::now().naive_utc()),
-            updated_at: Set(chrono::Utc::now().naive_utc()),
+
+        // Selecting another library stops the watcher of the previous one
+        controller
+            .create_library("Archive", other_content.path())
+            .await?;
+        while changes.try_recv().is_ok() {}
+        std::fs::write(content.path().join("unwatched.txt"), "unwatched")?;
+        assert!(
+            tokio::time::timeout(Duration::from_secs(5), changes.recv())
+                .await
+                .is_err()
+        );
+        assert!(controller.pause_watching().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watcher_and_tags_publish_library_changes() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+
+        let mut changes = controller.subscribe_changes();
+        controller.start_watching().await?;
+        std::fs::write(content.path().join("new.jpg"), "not really a photo")?;
+        let file_id = loop {
+            let change = tokio::time::timeout(Duration::from_secs(10), changes.recv()).await??;
+            if let LibraryChange::FileAdded { file_id } = change {
+                break file_id;
+            }
+        };
+        let matching = controller
+            .list_matching_files(None, "new", vec![file_id])
+            .await?;
+        assert_eq!(matching.len(), 1);
+        assert!(
+            controller
+                .list_matching_files(None, "other", vec![file_id])
+                .await?
+                .is_empty()
+        );
+
+        controller.create_tag("Holiday").await?;
+        let tag_id = controller.list_tags().await?[0].id();
+        controller.assign_tag(file_id, tag_id).await?;
+        let mut tag_changes = Vec::new();
+        while let Ok(change) = changes.try_recv() {
+            // Thumbnails and further watcher batches may be reported in between
+            if matches!(
+                change,
+                LibraryChange::TagsChanged { .. } | LibraryChange::TagAssigned { .. }
+            ) {
+                tag_changes.push(change);
+            }
         }
-        .insert(connection.as_ref())
-        .await
-        .map(|_| ())
-        .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))
+        assert_eq!(
+            tag_changes,
+            vec![
+                LibraryChange::TagsChanged { tag_id },
+                LibraryChange::TagAssigned { file_id, tag_id }
+            ]
+        );
+        controller.stop_watching().await?;
+        Ok(())
     }
 
-    pub async fn update_tag(&self, tag_id: i32, name: &str) -> ControllerResult<()> {
-        let name = Self::tag_name(name)?;
-        let database_manager = self.database_manager().await?;
-        let connection = database_manager.get_connection();
-        let tag = tags::Entity::find_by_id(tag_id)
-            .one(connection.as_ref())
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
-            .ok_or(ControllerError::TagNotFound)?;
-        let mut tag = tag.into_active_model();
-        tag.name = Set(name.to_string());
-        tag.updated_at = Set(chrono::Utc::now().naive_utc());
-        tag.update(connection.as_ref())
-            .await
-            .map(|_| ())
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))
+    #[tokio::test]
+    async fn scans_and_tag_changes_are_listed_as_activity() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        std::fs::create_dir(content.path().join("trips"))?;
+        std::fs::write(
+            content.path().join("trips").join("beach.jpg"),
+            "not a photo",
+        )?;
+        std::fs::write(content.path().join("notes.txt"), "hello")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+
+        let beach = controller.list_files(None, "beach").await?[0].id();
+        controller.create_tag("Holiday").await?;
+        let tag_id = controller.list_tags().await?[0].id();
+        controller.assign_tag(beach, tag_id).await?;
+        controller.remove_tag(beach, tag_id).await?;
+        // Removing a tag that is not assigned is not recorded
+        controller.remove_tag(beach, tag_id).await?;
+
+        let activity = controller
+            .list_activity(ActivityScope::File(beach), 10)
+            .await?;
+        let kinds: Vec<_> = activity.iter().map(ActivityInfo::kind).collect();
+        assert_eq!(kinds, vec!["tag_removed", "tag_assigned", "created"]);
+        assert_eq!(activity[0].source(), "user");
+        assert_eq!(activity[0].detail(), Some("Holiday"));
+        assert_eq!(activity[2].source(), "scanner");
+
+        let trips = controller
+            .list_folders()
+            .await?
+            .into_iter()
+            .find(|folder| folder.name() == "trips")
+            .map(|folder| folder.id())
+            .expect("the trips folder is scanned");
+        let below = controller
+            .list_activity(ActivityScope::Folder(trips), 10)
+            .await?;
+        assert!(below.iter().all(|entry| entry.file_id() == Some(beach)));
+        assert_eq!(below.len(), 3);
+        assert_eq!(
+            controller
+                .list_activity(ActivityScope::Library, 10)
+                .await?
+                .len(),
+            4
+        );
+        Ok(())
     }
 
-    pub async fn delete_tag(&self, tag_id: i32) -> ControllerResult<()> {
-        let database_manager = self.database_manager().await?;
-        let connection = database_manager.get_connection();
-        let transaction = connection
-            .begin()
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
-        file_has_tags::Entity::delete_many()
-            .filter(file_has_tags::Column::TagId.eq(tag_id))
-            .exec(&transaction)
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
-        tags::Entity::delete_by_id(tag_id)
-            .exec(&transaction)
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?;
-        transaction
-            .commit()
+    #[tokio::test]
+    async fn tag_details_are_listed_and_undoable() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.create_tag("Holiday").await?;
+        controller.create_tag("Work").await?;
+        let tags = controller.list_tags().await?;
+        let (holiday, work) = (tags[0].id(), tags[1].id());
+
+        controller
+            .update_tag_details(
+                holiday,
+                TagDetails {
+                    color: Some(Color::new("Red", "#E5484D")?),
+                    icon: Some(Icon::new("Beach", "🏖", IconType::Text)),
+                    description: Some("  Trips away from home ".to_string()),
+                    aliases: vec![
+                        " Vacation".to_string(),
+                        "Vacation".to_string(),
+                        String::new(),
+                    ],
+                },
+            )
+            .await?;
+        let tag = controller.list_tags().await?.remove(0);
+        assert_eq!(tag.color().map(Color::hex), Some("#e5484d"));
+        assert_eq!(tag.icon().map(Icon::content), Some("🏖"));
+        assert_eq!(tag.description(), Some("Trips away from home"));
+        assert_eq!(tag.aliases(), ["Vacation".to_string()]);
+
+        assert!(matches!(
+            controller
+                .update_tag_details(
+                    work,
+                    TagDetails {
+                        aliases: vec!["vacation".to_string(), "Vacation".to_string()],
+                        ..TagDetails::default()
+                    },
+                )
+                .await,
+            Err(ControllerError::TagNameTaken)
+        ));
+        assert_eq!(
+            controller.undo().await?.as_deref(),
+            Some("Edit tag Holiday")
+        );
+        assert_eq!(
+            controller.list_tags().await?[0].details(),
+            &TagDetails::default()
+        );
+        Ok(())
+    }
+
+    async fn tagged(controller: &AppController, tag_id: i32, file_ids: &[i32]) -> Result<Vec<i32>> {
+        TagOperations::new(controller.database_manager().await?)
+            .files_with_tag(tag_id, file_ids)
             .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))
     }
 
-    pub async fn assign_tag(&self, file_id: i32, tag_id: i32) -> ControllerResult<()> {
-        let database_manager = self.database_manager().await?;
-        let connection = database_manager.get_connection();
-        if files::Entity::find_by_id(file_id)
-            .one(connection.as_ref())
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
-            .is_none()
-        {
-            return Err(ControllerError::FileNotFound);
-        }
-        if tags::Entity::find_by_id(tag_id)
-            .one(connection.as_ref())
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
-            .is_none()
-        {
-            return Err(ControllerError::TagNotFound);
-        }
-        if file_has_tags::Entity::find()
-            .filter(file_has_tags::Column::FileId.eq(file_id))
-            .filter(file_has_tags::Column::TagId.eq(tag_id))
-            .one(connection.as_ref())
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))?
-            .is_some()
-        {
-            return Ok(());
-        }
-        file_has_tags::ActiveModel {
-            id: sea_orm::ActiveValue::NotSet,
-            file_id: Set(file_id),
-            tag_id: Set(tag_id),
+    #[tokio::test]
+    async fn tags_are_suggested_from_folder_co_occurrence_and_name() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let trips = content.path().join("trips");
+        std::fs::create_dir(&trips)?;
+        for name in ["beach.jpg", "dunes.jpg", "lisbon-harbour.jpg"] {
+            std::fs::write(trips.join(name), "not a photo")?;
+        }
+        std::fs::write(content.path().join("taxes.pdf"), "not a pdf")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let id_of = |files: &[FileInfo], name: &str| {
+            files
+                .iter()
+                .find(|file| file.name() == name)
+                .map(FileInfo::id)
+        };
+        let files = controller.list_files(None, "").await?;
+        let (Some(beach), Some(dunes), Some(harbour), Some(taxes)) = (
+            id_of(&files, "beach.jpg"),
+            id_of(&files, "dunes.jpg"),
+            id_of(&files, "lisbon-harbour.jpg"),
+            id_of(&files, "taxes.pdf"),
+        ) else {
+            panic!("the files were not scanned: {files:?}");
+        };
+        for name in ["Holiday", "Sea", "Lisbon", "Finance"] {
+            controller.create_tag(name).await?;
+        }
+        let tag_id = |tags: &[TagInfo], name: &str| {
+            tags.iter()
+                .find(|tag| tag.name() == name)
+                .map_or(-1, TagInfo::id)
+        };
+        let tags = controller.list_tags().await?;
+        let (holiday, sea) = (tag_id(&tags, "Holiday"), tag_id(&tags, "Sea"));
+        for file_id in [beach, dunes] {
+            controller.assign_tag(file_id, holiday).await?;
+            controller.assign_tag(file_id, sea).await?;
+        }
+        controller
+            .assign_tag(taxes, tag_id(&tags, "Finance"))
+            .await?;
+
+        let suggestions = controller.suggest_tags(harbour, 10).await?;
+        let names: Vec<&str> = suggestions.iter().map(TagSuggestionInfo::name).collect();
+        assert_eq!(names, vec!["Lisbon", "Holiday", "Sea"]);
+        assert_eq!(suggestions[0].sources(), [SuggestionSource::FileName]);
+        assert_eq!(suggestions[1].sources(), [SuggestionSource::Folder]);
+
+        controller.assign_tag(harbour, holiday).await?;
+        let suggestions = controller.suggest_tags(harbour, 10).await?;
+        let sea = suggestions
+            .iter()
+            .find(|suggestion| suggestion.name() == "Sea")
+            .map(TagSuggestionInfo::sources);
+        assert_eq!(
+            sea,
+            Some(&[SuggestionSource::Folder, SuggestionSource::CoOccurrence][..])
+        );
+        assert!(
+            suggestions
+                .iter()
+                .all(|suggestion| suggestion.name() != "Holiday")
+        );
+        assert!(matches!(
+            controller.suggest_tags(-1, 10).await,
+            Err(ControllerError::FileNotFound)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn smart_folders_are_saved_and_evaluated_live() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let invoices = content.path().join("invoices");
+        std::fs::create_dir(&invoices)?;
+        for name in ["march.pdf", "april.PDF", "notes.txt"] {
+            std::fs::write(invoices.join(name), "not a document")?;
+        }
+        std::fs::write(content.path().join("manual.pdf"), "not a pdf")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller
+            .create_library("Documents", content.path())
+            .await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+
+        let untagged_pdfs = Filter {
+            tag_filter: Some(TagFilter {
+                untagged: true,
+                ..TagFilter::default()
+            }),
+            extensions: vec!["pdf".to_string()],
+            added: Some(DateFilter::LastDays { days: 7 }),
+            ..Filter::default()
+        };
+        let smart_folder = controller
+            .create_smart_folder(" Untagged PDFs ", untagged_pdfs.clone())
+            .await?;
+        assert_eq!(smart_folder.name(), "Untagged PDFs");
+        assert!(matches!(
+            controller
+                .create_smart_folder("Untagged PDFs", Filter::default())
+                .await,
+            Err(ControllerError::SmartFolderNameTaken)
+        ));
+        let names = |files: Vec<FileInfo>| -> Vec<String> {
+            files.iter().map(|file| file.name().to_string()).collect()
+        };
+        assert_eq!(
+            names(
+                controller
+                    .list_smart_folder_files(smart_folder.id(), "")
+                    .await?
+            ),
+            vec!["april.PDF", "manual.pdf", "march.pdf"]
+        );
+        assert_eq!(
+            names(
+                controller
+                    .list_smart_folder_files(smart_folder.id(), "invoices")
+                    .await?
+            ),
+            vec!["april.PDF", "march.pdf"]
+        );
+
+        // Tagging a file takes it out of the smart folder
+        let files = controller.list_files(None, "manual").await?;
+        controller.create_tag("Manuals").await?;
+        let manuals = controller.list_tags().await?[0].id();
+        controller.assign_tag(files[0].id(), manuals).await?;
+        assert!(
+            controller
+                .list_matching_smart_folder_files(smart_folder.id(), "", vec![files[0].id()])
+                .await?
+                .is_empty()
+        );
+
+        let tagged = Filter {
+            tag_filter: Some(TagFilter {
+                tags: vec![Tag {
+                    id: u32::try_from(manuals)?,
+                    name: "Manuals".to_string(),
+                }],
+                untagged: false,
+            }),
+            ..Filter::default()
+        };
+        controller
+            .update_smart_folder(smart_folder.id(), "Manuals", tagged)
+            .await?;
+        assert_eq!(
+            names(
+                controller
+                    .list_smart_folder_files(smart_folder.id(), "")
+                    .await?
+            ),
+            vec!["manual.pdf"]
+        );
+        assert_eq!(controller.list_smart_folders().await?[0].name(), "Manuals");
+        controller.delete_smart_folder(smart_folder.id()).await?;
+        assert!(matches!(
+            controller
+                .list_smart_folder_files(smart_folder.id(), "")
+                .await,
+            Err(ControllerError::SmartFolderNotFound)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn folder_tree_counts_files_below_each_folder() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let summer = content.path().join("2024").join("summer");
+        std::fs::create_dir_all(&summer)?;
+        std::fs::write(content.path().join("2024").join("notes.txt"), "1234")?;
+        std::fs::write(summer.join("beach.txt"), "12")?;
+        std::fs::write(summer.join("sunset.txt"), "12")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let files = controller.list_files(None, "beach").await?;
+        controller.create_tag("Holiday").await?;
+        let holiday = controller.list_tags().await?[0].id();
+        controller.assign_tag(files[0].id(), holiday).await?;
+
+        let tree = controller.folder_tree().await?;
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].stats().files, 3);
+        let year = &tree[0].children()[0];
+        assert_eq!(year.name(), "2024");
+        assert_eq!(year.stats().direct_files, 1);
+        assert_eq!(year.stats().size, 8);
+        assert_eq!(year.children()[0].stats().tagged_files, 1);
+
+        let roots = controller.folder_children(None).await?;
+        assert!(roots[0].children().is_empty());
+        assert!(roots[0].has_children());
+        let children = controller.folder_children(Some(roots[0].id())).await?;
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].stats(), year.stats());
+        let grandchildren = controller.folder_children(Some(children[0].id())).await?;
+        assert_eq!(grandchildren[0].name(), "summer");
+        assert!((grandchildren[0].stats().tag_coverage() - 0.5).abs() < 1e-9);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn folders_keep_their_decoration() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        std::fs::create_dir(content.path().join("2024"))?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let year = controller
+            .list_folders()
+            .await?
+            .into_iter()
+            .find(|folder| folder.name() == "2024")
+            .ok_or_else(|| anyhow::anyhow!("folder was not scanned"))?;
+        assert_eq!(year.label(), "2024");
+        let mut changes = controller.subscribe_changes();
+
+        let decoration = FolderDecoration {
+            color: Some(Color::new("Sun", "#FFAA00")?),
+            icon: Some(Icon::new("Star", "★", IconType::Text)),
+            display_name: Some("  This year ".to_string()),
+        };
+        let decorated = controller.decorate_folder(year.id(), decoration).await?;
+        assert_eq!(decorated.label(), "This year");
+        assert_eq!(
+            changes.try_recv().ok(),
+            Some(LibraryChange::FolderDecorated {
+                folder_id: year.id()
+            })
+        );
+        let listed = controller.list_folders().await?;
+        assert!(listed.contains(&decorated));
+        let tree = controller.folder_tree().await?;
+        let node = &tree[0].children()[0];
+        assert_eq!(node.label(), "This year");
+        assert_eq!(
+            node.decoration().color.as_ref().map(Color::hex),
+            Some("#ffaa00")
+        );
+
+        let cleared = controller
+            .decorate_folder(year.id(), FolderDecoration::default())
+            .await?;
+        assert_eq!(cleared.label(), "2024");
+        assert_eq!(cleared.decoration(), &FolderDecoration::default());
+        assert!(matches!(
+            controller
+                .decorate_folder(-1, FolderDecoration::default())
+                .await,
+            Err(ControllerError::FolderNotFound)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn files_can_be_rated_favorited_and_labeled() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            std::fs::write(content.path().join(name), name)?;
+        }
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let files = controller.list_files(None, "").await?;
+        let ids: Vec<i32> = files.iter().map(FileInfo::id).collect();
+        let mut changes = controller.subscribe_changes();
+
+        controller.set_rating(&ids[..2], 3).await?;
+        controller.set_rating(&ids[1..2], 5).await?;
+        assert!(controller.set_rating(&ids, MAX_RATING + 1).await.is_err());
+        controller.set_favorite(&ids[2..], true).await?;
+        controller
+            .set_color_label(&ids[..1], Some(ColorLabel::Green))
+            .await?;
+        controller
+            .set_color_label(&ids[1..2], Some(ColorLabel::Red))
+            .await?;
+        let mut modified = Vec::new();
+        while let Ok(LibraryChange::FileModified { file_id }) = changes.try_recv() {
+            modified.push(file_id);
+        }
+        let mut expected = vec![ids[0], ids[0], ids[1], ids[1], ids[1], ids[2]];
+        modified.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(modified, expected);
+
+        let names = |files: Vec<FileInfo>| -> Vec<String> {
+            files
+                .into_iter()
+                .map(|file| file.name().to_string())
+                .collect()
+        };
+        let search = |query: &'static str| controller.list_files(None, query);
+        assert_eq!(names(search("rating:>=3").await?), vec!["a.jpg", "b.jpg"]);
+        assert_eq!(names(search("rating:0").await?), vec!["c.jpg"]);
+        assert_eq!(names(search("favorite:yes").await?), vec!["c.jpg"]);
+        assert_eq!(names(search("label:red").await?), vec!["b.jpg"]);
+        assert_eq!(names(search("label:none").await?), vec!["c.jpg"]);
+
+        let b = controller.list_files(None, "b.jpg").await?;
+        assert_eq!(b[0].rating(), 5);
+        assert!(!b[0].favorite());
+        assert_eq!(b[0].color_label(), Some(ColorLabel::Red));
+        let everything = FileSelection::Matching {
+            folder_id: None,
+            search: String::new(),
+        };
+        for (key, expected) in [
+            (FileSortKey::Rating, ["c.jpg", "a.jpg", "b.jpg"]),
+            (FileSortKey::Favorite, ["a.jpg", "b.jpg", "c.jpg"]),
+            (FileSortKey::ColorLabel, ["b.jpg", "a.jpg", "c.jpg"]),
+        ] {
+            let sort = FileSort {
+                key,
+                descending: false,
+            };
+            let page = controller
+                .list_file_page(everything.clone(), sort, 0, 3)
+                .await?;
+            let sorted: Vec<&str> = page.files().iter().map(FileInfo::name).collect();
+            assert_eq!(sorted, expected);
+            assert!(
+                page.files()
+                    .is_sorted_by(|a, b| sort.compare(a, b) == Some(Ordering::Less))
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn custom_fields_can_be_searched_and_exported() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        for name in ["invoice.pdf", "contract.pdf", "notes.txt"] {
+            std::fs::write(content.path().join(name), name)?;
+        }
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Office", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let file_id = |files: &[FileInfo], name: &str| {
+            files
+                .iter()
+                .find(|file| file.name() == name)
+                .map(FileInfo::id)
+                .ok_or_else(|| anyhow::anyhow!("{name} was not scanned"))
+        };
+        let files = controller.list_files(None, "").await?;
+        let invoice = file_id(&files, "invoice.pdf")?;
+        let contract = file_id(&files, "contract.pdf")?;
+
+        let client = controller
+            .create_field("client", FieldType::String, &[])
+            .await?;
+        let due = controller.create_field("due", FieldType::Date, &[]).await?;
+        let rating = controller
+            .create_field("rating", FieldType::Number, &[])
+            .await?;
+        assert!(matches!(
+            controller
+                .create_field(" client ", FieldType::Bool, &[])
+                .await,
+            Err(ControllerError::FieldNameTaken)
+        ));
+        controller
+            .set_field_value(&[invoice, contract], client.id, "ACME")
+            .await?;
+        controller
+            .set_field_value(&[invoice], due.id, "2025-11-01")
+            .await?;
+        controller
+            .set_field_value(&[contract], due.id, "2026-01-15")
+            .await?;
+        controller
+            .set_field_value(&[invoice], rating.id, "4")
+            .await?;
+        controller
+            .set_field_value(&[contract], rating.id, "10")
+            .await?;
+        assert!(
+            controller
+                .set_field_value(&[invoice], rating.id, "great")
+                .await
+                .is_err()
+        );
+
+        let names = |files: Vec<FileInfo>| -> Vec<String> {
+            let mut names: Vec<String> = files
+                .into_iter()
+                .map(|file| file.name().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+        let search = |query: &'static str| controller.list_files(None, query);
+        assert_eq!(
+            names(search("field:client=acme").await?),
+            vec!["contract.pdf", "invoice.pdf"]
+        );
+        assert_eq!(
+            names(search("field:rating>=5").await?),
+            vec!["contract.pdf"]
+        );
+        assert_eq!(
+            names(search("field:due<2026-01-01").await?),
+            vec!["invoice.pdf"]
+        );
+        assert_eq!(
+            names(search("field:rating!=4").await?),
+            vec!["contract.pdf", "notes.txt"]
+        );
+        assert_eq!(names(search("field:client").await?).len(), 2);
+
+        let export = data_home.path().join("fields.json");
+        controller.export_fields(&export).await?;
+        controller
+            .set_field_value(&[invoice], client.id, "")
+            .await?;
+        controller.delete_field(rating.id).await?;
+        let report = controller.import_fields(&export).await?;
+        assert_eq!(report.fields_created, 1);
+        assert_eq!(report.values_set, 3);
+        assert!(report.problems.is_empty());
+        let values: Vec<String> = controller
+            .file_fields(invoice)
+            .await?
+            .into_iter()
+            .map(|(field, value)| format!("{}={value}", field.name))
+            .collect();
+        assert_eq!(values, vec!["client=ACME", "due=2025-11-01", "rating=4"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn file_listings_are_paged_and_sorted() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let days = |days: u64| std::time::UNIX_EPOCH + Duration::from_secs(days * 86_400);
+        for (name, size, modified) in [
+            ("b.txt", 30, days(2)),
+            ("a.txt", 10, days(4)),
+            ("d.txt", 40, days(1)),
+            ("c.txt", 20, days(3)),
+        ] {
+            let path = content.path().join(name);
+            std::fs::write(&path, "x".repeat(size))?;
+            std::fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(modified)?;
         }
-        .insert(connection.as_ref())
-        .await
-        .map(|_| ())
-        .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))
-    }
+        let controller = AppController::new_in(data_home.path())?;
+        controller
+            .create_library("Documents", content.path())
+            .await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let everything = || FileSelection::Matching {
+            folder_id: None,
+            search: String::new(),
+        };
+        let names = |page: &FilePage| -> Vec<String> {
+            page.files()
+                .iter()
+                .map(|file| file.name().to_string())
+                .collect()
+        };
 
-    pub async fn remove_tag(&self, file_id: i32, tag_id: i32) -> ControllerResult<()> {
-        let database_manager = self.database_manager().await?;
-        file_has_tags::Entity::delete_many()
-            .filter(file_has_tags::Column::FileId.eq(file_id))
-            .filter(file_has_tags::Column::TagId.eq(tag_id))
+        let first = controller
+            .list_file_page(everything(), FileSort::default(), 0, 3)
+            .await?;
+        assert_eq!(first.total(), 4);
+        assert_eq!(names(&first), vec!["a.txt", "b.txt", "c.txt"]);
+        assert!(
+            first
+                .files()
+                .is_sorted_by(|a, b| FileSort::default().compare(a, b) == Some(Ordering::Less))
+        );
+        let rest = controller
+            .list_file_page(everything(), FileSort::default(), 3, 3)
+            .await?;
+        assert_eq!(names(&rest), vec!["d.txt"]);
+
+        let largest_first = FileSort {
+            key: FileSortKey::Size,
+            descending: true,
+        };
+        let by_size = controller
+            .list_file_page(everything(), largest_first, 0, 2)
+            .await?;
+        assert_eq!(names(&by_size), vec!["d.txt", "b.txt"]);
+        assert_eq!(
+            largest_first.compare(&by_size.files()[0], &by_size.files()[1]),
+            Some(Ordering::Less)
+        );
+        assert_eq!(by_size.files()[0].size(), 40);
+
+        // Rewriting a row must not move it, the order follows the files on disk
+        let database_manager = controller.database_manager().await?;
+        files::Entity::update_many()
+            .col_expr(
+                files::Column::UpdatedAt,
+                Expr::value(chrono::Utc::now().naive_utc()),
+            )
+            .filter(files::Column::Name.eq("d.txt"))
             .exec(database_manager.get_connection().as_ref())
-            .await
-            .map(|_| ())
-            .map_err(|error| ControllerError::operation(ControllerOperation::ManageTags, error))
-    }
+            .await?;
+        let oldest_first = FileSort {
+            key: FileSortKey::Modified,
+            descending: false,
+        };
+        let by_modified = controller
+            .list_file_page(everything(), oldest_first, 0, 4)
+            .await?;
+        assert_eq!(
+            names(&by_modified),
+            vec!["d.txt", "b.txt", "c.txt", "a.txt"]
+        );
+        assert!(
+            by_modified
+                .files()
+                .is_sorted_by(|a, b| oldest_first.compare(a, b) == Some(Ordering::Less))
+        );
+        assert_eq!(
+            by_modified.files()[0].modified_at(),
+            NaiveDate::from_ymd_opt(1970, 1, 2)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .context("valid date")?
+        );
 
-    async fn database_manager(&self) -> ControllerResult<Arc<DatabaseManager>> {
-        let state = self.state.lock().await;
-        let AppState::Ready { workspace, .. } = &*state else {
-            return Err(ControllerError::NoLibrarySelected);
+        let files = controller.list_files(None, "").await?;
+        controller.create_tag("Drafts").await?;
+        let drafts = controller.list_tags().await?[0].id();
+        controller.assign_tag(files[2].id(), drafts).await?;
+        let most_tagged_first = FileSort {
+            key: FileSortKey::TagCount,
+            descending: true,
         };
-        Ok(Arc::clone(&workspace.database_manager))
+        let by_tags = controller
+            .list_file_page(everything(), most_tagged_first, 0, 1)
+            .await?;
+        assert_eq!(names(&by_tags), vec!["c.txt"]);
+        assert_eq!(most_tagged_first.compare(&files[0], &files[1]), None);
+        let by_type = controller
+            .list_file_page(
+                everything(),
+                FileSort {
+                    key: FileSortKey::Type,
+                    descending: false,
+                },
+                0,
+                4,
+            )
+            .await?;
+        assert_eq!(by_type.files().len(), 4);
+        Ok(())
     }
 
-    fn tag_name(name: &str) -> ControllerResult<&str> {
-        let name = name.trim();
-        if name.is_empty() {
-            return Err(ControllerError::InvalidTagName);
+    #[tokio::test]
+    async fn tags_can_be_merged_and_split() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        for name in ["march.pdf", "april.pdf", "may.pdf"] {
+            std::fs::write(content.path().join(name), "not a pdf")?;
         }
-        Ok(name)
-    }
+        let controller = AppController::new_in(data_home.path())?;
+        controller
+            .create_library("Documents", content.path())
+            .await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        let files = controller.list_files(None, "").await?;
+        let file_ids: Vec<i32> = files.iter().map(FileInfo::id).collect();
+        controller.create_tag("invoice").await?;
+        controller.create_tag("invoices").await?;
+        let tags = controller.list_tags().await?;
+        let (invoice, invoices) = (tags[0].id(), tags[1].id());
+        controller.assign_tag(files[0].id(), invoice).await?;
+        controller.assign_tag(files[1].id(), invoice).await?;
+        controller.assign_tag(files[2].id(), invoices).await?;
 
-    async fn activate(&self, library: Library) -> ControllerResult<LibraryInfo> {
-        let info = LibraryInfo::from_path(
-            library
-                .share_path
-                .clone()
-                .ok_or(ControllerError::MissingStorageFolder)?,
-        )?;
-        let workspace = Workspace::open(&library)
-            .await
-            .map_err(|error| ControllerError::operation(ControllerOperation::OpenLibrary, error))?;
-        *self.state.lock().await = AppState::Ready { library, workspace };
-        Ok(info)
-    }
-}
+        controller
+            .merge_tags(&[invoice, invoices], invoices)
+            .await?;
+        let tags = controller.list_tags().await?;
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].aliases(), ["invoice".to_string()]);
+        assert_eq!(tagged(&controller, invoices, &file_ids).await?.len(), 3);
 
-impl Workspace {
-    async fn open(library: &Library) -> Result<Self> {
-        let database_path = library.get_canon_database_path()?;
-        let connection_string = format!("sqlite:///{}", database_path.as_str()?);
-        let settings = DatabaseSettings {
-            con_string: connection_string,
-            timeout: 30_000,
-            ..DatabaseSettings::default()
-        };
-        let database_manager = Arc::new(DatabaseManager::new(settings).await?);
-        database_manager.test_connection().await?;
-        let file_operations = Arc::new(FileRepository::new(Arc::clone(&database_manager)));
-        let (thumbnail_processor, receiver) = ThumbnailProcessorHandler::new();
-        let processor = ThumbnailProcessor::new(
-            receiver,
-            Arc::new(ThumbnailOperations::new(Arc::clone(&database_manager))),
-            Arc::new(ThumbnailGenerator::new()),
+        let moved = controller
+            .split_tag(
+                invoices,
+                FileSelection::Files(vec![files[0].id(), files[1].id()]),
+                "receipts",
+            )
+            .await?;
+        assert_eq!(moved, 2);
+        assert!(matches!(
+            controller
+                .split_tag(invoices, FileSelection::Files(Vec::new()), "invoice")
+                .await,
+            Err(ControllerError::TagNameTaken)
+        ));
+        let tags = controller.list_tags().await?;
+        assert_eq!(tags[1].name(), "receipts");
+        assert_eq!(tagged(&controller, tags[1].id(), &file_ids).await?.len(), 2);
+        assert_eq!(tagged(&controller, invoices, &file_ids).await?.len(), 1);
+
+        assert_eq!(
+            controller.undo().await?.as_deref(),
+            Some("Split receipts from invoices")
         );
-        tokio::spawn(async move {
-            if let Err(error) = processor.run().await {
-                tracing::error!(%error, "Thumbnail processor stopped");
-            }
-        });
-        Ok(Self {
-            database_manager,
-            file_operations,
-            thumbnail_processor,
-            watcher_started: false,
-        })
+        assert_eq!(
+            controller.undo().await?.as_deref(),
+            Some("Merge invoice into invoices")
+        );
+        assert_eq!(controller.list_tags().await?.len(), 2);
+        assert_eq!(tagged(&controller, invoice, &file_ids).await?.len(), 2);
+        assert_eq!(tagged(&controller, invoices, &file_ids).await?.len(), 1);
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::{AppController, ControllerError};
-    use anyhow::Result;
-    use tempfile::TempDir;
 
     #[tokio::test]
-    async fn create_library_returns_an_owned_summary() -> Result<()> {
+    async fn deleting_a_tag_can_be_undone_and_redone() -> Result<()> {
         let data_home = TempDir::new()?;
         let content = TempDir::new()?;
+        std::fs::write(content.path().join("beach.jpg"), "not a photo")?;
+        std::fs::write(content.path().join("forest.jpg"), "not a photo")?;
         let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        assert_eq!(controller.undo().await?, None);
 
-        let library = controller.create_library("Photos", content.path()).await?;
+        let files = controller.list_files(None, "").await?;
+        controller.create_tag("Holiday").await?;
+        let tag_id = controller.list_tags().await?[0].id();
+        for file in &files {
+            controller.assign_tag(file.id(), tag_id).await?;
+        }
+        let mut changes = controller.subscribe_changes();
+        controller.delete_tag(tag_id).await?;
+        assert!(controller.list_tags().await?.is_empty());
 
-        assert_eq!(library.name().as_str(), "Photos");
-        assert_eq!(library.path(), data_home.path().join("hestia/Photos"));
-        assert!(library.path().join("config.toml").is_file());
-        assert!(library.path().join("db.sqlite").is_file());
+        assert_eq!(
+            controller.undo().await?.as_deref(),
+            Some("Delete tag Holiday")
+        );
+        assert_eq!(controller.list_tags().await?[0].id(), tag_id);
+        let mut reassigned = Vec::new();
+        while let Ok(change) = changes.try_recv() {
+            if let LibraryChange::TagAssigned { file_id, .. } = change {
+                reassigned.push(file_id);
+            }
+        }
+        assert_eq!(reassigned.len(), files.len());
+
+        assert_eq!(
+            controller.redo().await?.as_deref(),
+            Some("Delete tag Holiday")
+        );
+        assert!(controller.list_tags().await?.is_empty());
+        let history = controller.history(10).await?;
+        let labels: Vec<&str> = history.iter().map(HistoryInfo::label).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "Delete tag Holiday",
+                "Assign tag Holiday",
+                "Assign tag Holiday",
+                "Create tag Holiday"
+            ]
+        );
+        assert!(history.iter().all(|entry| !entry.undone()));
         Ok(())
     }
 
     #[tokio::test]
-    async fn list_libraries_returns_only_library_directories() -> Result<()> {
+    async fn bulk_tagging_is_one_undoable_change() -> Result<()> {
         let data_home = TempDir::new()?;
         let content = TempDir::new()?;
+        for name in ["a.jpg", "b.jpg", "c.jpg", "notes.txt"] {
+            std::fs::write(content.path().join(name), name)?;
+        }
         let controller = AppController::new_in(data_home.path())?;
         controller.create_library("Photos", content.path()).await?;
-        std::fs::write(data_home.path().join("hestia/not-a-library"), "ignored")?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        controller.create_tag("Holiday").await?;
+        controller.create_tag("Family").await?;
+        let tag_ids: Vec<i32> = controller
+            .list_tags()
+            .await?
+            .iter()
+            .map(TagInfo::id)
+            .collect();
 
-        let libraries = controller.list_libraries()?;
+        let photos = FileSelection::Matching {
+            folder_id: None,
+            search: "jpg".to_string(),
+        };
+        let report = controller.assign_tags(photos.clone(), &tag_ids).await?;
+        assert_eq!(report.files_selected(), 3);
+        assert_eq!(report.changed(), 6);
+        // Files that already have the tags are left alone
+        assert_eq!(controller.assign_tags(photos, &tag_ids).await?.changed(), 0);
 
-        assert_eq!(libraries.len(), 1);
-        assert_eq!(libraries[0].name().as_str(), "Photos");
+        let notes = controller.list_files(None, "notes").await?[0].id();
+        let first = controller.list_files(None, "a.jpg").await?[0].id();
+        let report = controller
+            .remove_tags(FileSelection::Files(vec![first, notes]), &tag_ids[..1])
+            .await?;
+        assert_eq!(report.files_selected(), 2);
+        assert_eq!(report.changed(), 1);
+
+        assert!(
+            controller
+                .undo()
+                .await?
+                .is_some_and(|label| label.starts_with("Remove"))
+        );
+        assert_eq!(
+            controller.undo().await?.as_deref(),
+            Some("Assign Family, Holiday to 3 files")
+        );
+        let activity = controller
+            .list_activity(ActivityScope::File(first), 10)
+            .await?;
+        assert_eq!(activity[0].kind(), "tag_removed");
+        assert!(matches!(
+            controller
+                .assign_tags(FileSelection::Files(vec![first]), &[-1])
+                .await,
+            Err(ControllerError::TagNotFound)
+        ));
         Ok(())
     }
 
     #[tokio::test]
-    async fn select_library_opens_an_existing_library() -> Result<()> {
+    async fn auto_tag_rules_are_applied_on_scan_and_previewed() -> Result<()> {
         let data_home = TempDir::new()?;
         let content = TempDir::new()?;
-        let creator = AppController::new_in(data_home.path())?;
-        let created = creator.create_library("Photos", content.path()).await?;
+        std::fs::create_dir(content.path().join("Scans"))?;
+        for name in ["Scans/receipt-1.pdf", "Scans/letter.pdf", "beach.jpg"] {
+            std::fs::write(content.path().join(name), name)?;
+        }
         let controller = AppController::new_in(data_home.path())?;
+        let library = controller.create_library("Photos", content.path()).await?;
+        let config = library.path().join("config.toml");
+        let original = std::fs::read_to_string(&config)?;
+        std::fs::write(
+            &config,
+            format!(
+                "{original}\n[[auto_tag]]\nname = \"Receipts\"\nglob = \"**/Scans/*\"\n\
+                 name_regex = \"(?i)receipt\"\ntags = [\"receipt\", \"scan/{{year}}\"]\n"
+            ),
+        )?;
+        controller.select_library(library.path()).await?;
+        controller.initialize_workspace().await?;
 
-        let selected = controller.select_library(created.path()).await?;
+        let report = controller.scan().await?;
+        assert_eq!(report.tags_assigned(), 2);
+        let tags = controller.list_tags().await?;
+        assert_eq!(tags.len(), 2);
+        assert!(tags.iter().any(|tag| tag.name() == "receipt"));
 
-        assert_eq!(selected, created);
+        let everything = FileSelection::Matching {
+            folder_id: None,
+            search: String::new(),
+        };
+        let preview = controller.preview_auto_tags(everything.clone()).await?;
+        assert_eq!(preview.len(), 1);
+        assert!(preview[0].path().ends_with("Scans/receipt-1.pdf"));
+        assert_eq!(preview[0].tags()[0], "receipt");
+        // The scan already assigned every tag
+        let report = controller.apply_auto_tags(everything.clone()).await?;
+        assert_eq!(report.files_selected(), 3);
+        assert_eq!(report.changed(), 0);
+
+        std::fs::write(
+            &config,
+            format!("{original}\n[[auto_tag]]\nname_regex = \"(\"\ntags = [\"broken\"]\n"),
+        )?;
+        controller.select_library(library.path()).await?;
+        assert!(matches!(
+            controller.preview_auto_tags(everything).await,
+            Err(ControllerError::OperationFailed { .. })
+        ));
         Ok(())
     }
 
     #[tokio::test]
-    async fn initialize_workspace_migrates_the_selected_database() -> Result<()> {
+    async fn tags_are_synced_with_xmp_sidecars() -> Result<()> {
         let data_home = TempDir::new()?;
         let content = TempDir::new()?;
+        std::fs::write(content.path().join("beach.jpg"), "beach")?;
+        std::fs::write(content.path().join("forest.jpg"), "forest")?;
+        let sidecar = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF>\
+            <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+            <dc:subject><rdf:Bag><rdf:li>sunset</rdf:li><rdf:li>family</rdf:li></rdf:Bag>\
+            </dc:subject></rdf:Description></rdf:RDF></x:xmpmeta>";
+        std::fs::write(content.path().join("beach.xmp"), sidecar)?;
         let controller = AppController::new_in(data_home.path())?;
         let library = controller.create_library("Photos", content.path()).await?;
-
+        assert!(matches!(
+            controller.sync_tags().await,
+            Err(ControllerError::TagSyncDisabled)
+        ));
+        let config = library.path().join("config.toml");
+        let original = std::fs::read_to_string(&config)?;
+        std::fs::write(&config, original.replace("xmp = false", "xmp = true"))?;
+        controller.select_library(library.path()).await?;
         controller.initialize_workspace().await?;
 
-        assert!(std::fs::metadata(library.path().join("db.sqlite"))?.len() > 0);
+        let report = controller.scan().await?;
+        assert_eq!(report.tags_assigned(), 2);
+        let mut names: Vec<String> = controller
+            .list_tags()
+            .await?
+            .iter()
+            .map(|tag| tag.name().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["family", "sunset"]);
+
+        let forest = controller.list_files(None, "forest").await?[0].id();
+        controller.create_tag("trees").await?;
+        let trees = controller
+            .list_tags()
+            .await?
+            .into_iter()
+            .find(|tag| tag.name() == "trees")
+            .map(|tag| tag.id())
+            .ok_or_else(|| anyhow::anyhow!("trees was created"))?;
+        controller.assign_tag(forest, trees).await?;
+        let written = content.path().join("forest.xmp");
+        for _ in 0..50 {
+            if written.is_file() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let xmp = std::fs::read_to_string(&written)?;
+        assert!(xmp.contains("<rdf:li>trees</rdf:li>"));
+
+        let report = controller.sync_tags().await?;
+        assert_eq!(report.files_written, 0);
+        assert!(report.assigned.is_empty());
+        assert!(report.problems.is_empty());
+
+        // A renamed tag is only written to the files carrying it, so the edit is kept
+        let beach = content.path().join("beach.xmp");
+        let edited = std::fs::read_to_string(&beach)?.replace(
+            "<rdf:li>family</rdf:li>",
+            "<rdf:li>family</rdf:li><rdf:li>boat</rdf:li>",
+        );
+        std::fs::write(&beach, &edited)?;
+        controller.update_tag(trees, "woods").await?;
+        for _ in 0..50 {
+            if std::fs::read_to_string(&written)?.contains("<rdf:li>woods</rdf:li>") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(std::fs::read_to_string(&written)?.contains("<rdf:li>woods</rdf:li>"));
+        assert_eq!(std::fs::read_to_string(&beach)?, edited);
         Ok(())
     }
 
     #[tokio::test]
-    async fn initialized_library_supports_the_qt_controller_api() -> Result<()> {
+    async fn files_sharing_a_stem_get_their_own_sidecars() -> Result<()> {
         let data_home = TempDir::new()?;
         let content = TempDir::new()?;
-        std::fs::write(content.path().join("notes.txt"), "hello")?;
+        std::fs::write(content.path().join("IMG_1.jpg"), "jpeg")?;
+        std::fs::write(content.path().join("IMG_1.CR2"), "raw")?;
+        let sidecar = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"><rdf:RDF>\
+            <rdf:Description rdf:about=\"\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+            <dc:subject><rdf:Bag><rdf:li>sunset</rdf:li></rdf:Bag>\
+            </dc:subject></rdf:Description></rdf:RDF></x:xmpmeta>";
+        // Could be the sidecar of either file, so it is not read for both
+        std::fs::write(content.path().join("IMG_1.xmp"), sidecar)?;
         let controller = AppController::new_in(data_home.path())?;
-        controller.create_library("Notes", content.path()).await?;
+        let library = controller.create_library("Photos", content.path()).await?;
+        let config = library.path().join("config.toml");
+        let original = std::fs::read_to_string(&config)?;
+        std::fs::write(&config, original.replace("xmp = false", "xmp = true"))?;
+        controller.select_library(library.path()).await?;
         controller.initialize_workspace().await?;
 
         let report = controller.scan().await?;
-        assert_eq!(report.files_scanned(), 1);
-        let folders = controller.list_folders().await?;
-        let files = controller
-            .list_files(Some(folders[0].id()), "notes")
+        assert_eq!(report.tags_assigned(), 0);
+
+        let jpeg = controller.list_files(None, "IMG_1.jpg").await?[0].id();
+        controller.create_tag("trees").await?;
+        let trees = controller
+            .list_tags()
+            .await?
+            .into_iter()
+            .find(|tag| tag.name() == "trees")
+            .map(|tag| tag.id())
+            .ok_or_else(|| anyhow::anyhow!("trees was created"))?;
+        controller.assign_tag(jpeg, trees).await?;
+        let written = content.path().join("IMG_1.jpg.xmp");
+        for _ in 0..50 {
+            if written.is_file() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let xmp = std::fs::read_to_string(&written)?;
+        assert!(xmp.contains("<rdf:li>trees</rdf:li>"));
+        assert_eq!(
+            std::fs::read_to_string(content.path().join("IMG_1.xmp"))?,
+            sidecar
+        );
+
+        let report = controller.sync_tags().await?;
+        assert_eq!(report.files_written, 0);
+        assert!(report.assigned.is_empty());
+        assert!(report.problems.is_empty());
+        let names: Vec<String> = controller
+            .list_tags()
+            .await?
+            .iter()
+            .map(|tag| tag.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["trees"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_files_filters_on_photo_metadata() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        std::fs::write(content.path().join("beach.jpg"), "not really a photo")?;
+        std::fs::write(content.path().join("forest.jpg"), "not really a photo")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+
+        let files = controller.list_files(None, "beach").await?;
+        let metadata = MediaMetadata {
+            taken_at: NaiveDate::from_ymd_opt(2024, 7, 14)
+                .and_then(|date| date.and_hms_opt(9, 0, 0)),
+            camera_model: Some("Pixel 8".to_string()),
+            ..MediaMetadata::default()
+        };
+        MediaMetadataOperations::new(controller.database_manager().await?)
+            .upsert_metadata(files[0].id(), metadata)
             .await?;
-        assert_eq!(files.len(), 1);
 
-        controller.create_tag("Important").await?;
-        let tags = controller.list_tags().await?;
-        assert_eq!(tags[0].name(), "Important");
-        controller.assign_tag(files[0].id(), tags[0].id()).await?;
-        controller.remove_tag(files[0].id(), tags[0].id()).await?;
-        controller.delete_tag(tags[0].id()).await?;
-        assert!(controller.list_tags().await?.is_empty());
+        let taken = controller.list_files(None, "taken:2024").await?;
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].name(), "beach.jpg");
+        assert!(controller.list_files(None, "taken:2023").await?.is_empty());
+        assert_eq!(
+            controller.list_files(None, "camera:pixel jpg").await?.len(),
+            1
+        );
+        assert_eq!(controller.list_files(None, "jpg").await?.len(), 2);
+
+        let info = controller
+            .file_metadata(files[0].id())
+            .await?
+            .expect("metadata was stored");
+        assert_eq!(info.camera_model(), Some("Pixel 8"));
         Ok(())
     }
 
@@ -934,4 +4813,106 @@ mod tests {
         assert!(matches!(error, ControllerError::InvalidLibraryName));
         Ok(())
     }
+
+    #[tokio::test]
+    async fn verify_library_repairs_files_missing_on_disk() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        std::fs::create_dir(content.path().join("albums"))?;
+        std::fs::write(content.path().join("albums/kept.txt"), "kept")?;
+        std::fs::write(content.path().join("albums/gone.txt"), "gone")?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Notes", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.scan().await?;
+        assert!(controller.verify_library(false).await?.is_healthy());
+
+        let gone = controller.list_files(None, "gone").await?[0].id();
+        std::fs::remove_file(content.path().join("albums/gone.txt"))?;
+        let report = controller.verify_library(false).await?;
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(
+            report.problems,
+            vec![IntegrityProblem::MissingFile {
+                file_id: gone,
+                path: content.path().join("albums/gone.txt"),
+            }]
+        );
+        assert!(!report.is_healthy());
+
+        let mut changes = controller.subscribe_changes();
+        let report = controller.verify_library(true).await?;
+        assert!(report.repaired);
+        assert!(report.is_healthy());
+        assert_eq!(
+            changes.recv().await?,
+            LibraryChange::FileRemoved { file_id: gone }
+        );
+        assert!(controller.list_files(None, "gone").await?.is_empty());
+        assert!(controller.verify_library(false).await?.problems.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn maintenance_opens_migrate_without_background_tasks() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let library = AppController::new_in(data_home.path())?
+            .create_library("Notes", content.path())
+            .await?;
+
+        // The library was never initialized, so only the maintenance open creates its tables
+        let controller = AppController::new_in(data_home.path())?;
+        controller
+            .open_library_for_maintenance(library.path())
+            .await?;
+        assert!(controller.verify_library(false).await?.is_healthy());
+        assert!(controller.generate_thumbnails().await.is_err());
+        let status = controller.thumbnail_status().await?;
+        assert_eq!((status.workers(), status.stored()), (0, 0));
+
+        controller.close_library().await;
+        assert!(matches!(
+            controller.verify_library(false).await,
+            Err(ControllerError::NoLibrarySelected)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restore_library_puts_a_backup_back_in_place() -> Result<()> {
+        let data_home = TempDir::new()?;
+        let content = TempDir::new()?;
+        let controller = AppController::new_in(data_home.path())?;
+        controller.create_library("Photos", content.path()).await?;
+        controller.initialize_workspace().await?;
+        controller.create_tag("Holiday").await?;
+
+        let backup = controller.backup_library().await?;
+        assert_eq!(backup.manifest.library, "Photos");
+        assert!(
+            backup
+                .path
+                .starts_with(data_home.path().join("hestia/Photos/backups"))
+        );
+        assert_eq!(controller.list_backups().await?, vec![backup.clone()]);
+        controller.create_tag("Work").await?;
+        assert_eq!(controller.list_tags().await?.len(), 2);
+
+        let library = controller.restore_library(&backup.path).await?;
+        assert_eq!(library.name().as_str(), "Photos");
+        controller.initialize_workspace().await?;
+        let tags = controller.list_tags().await?;
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name(), "Holiday");
+        assert_eq!(controller.list_backups().await?, vec![backup.clone()]);
+
+        std::fs::write(
+            backup.path.join("backup.toml"),
+            std::fs::read_to_string(backup.path.join("backup.toml"))?
+                .replace(&backup.manifest.schema_version, "m29991231_000000_future"),
+        )?;
+        assert!(controller.restore_library(&backup.path).await.is_err());
+        Ok(())
+    }
 }
@@ -15,10 +15,17 @@ pub struct Model {
     pub file_type_id: i32,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub size: i64,
+    pub modified_at: DateTime,
+    pub rating: i16,
+    pub favorite: bool,
+    pub color_label: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::file_field_values::Entity")]
+    FileFieldValues,
     #[sea_orm(has_many = "super::file_has_tags::Entity")]
     FileHasTags,
     #[sea_orm(
@@ -37,10 +44,18 @@ pub enum Relation {
         on_delete = "Restrict"
     )]
     FileTypes,
+    #[sea_orm(has_one = "super::media_metadata::Entity")]
+    MediaMetadata,
     #[sea_orm(has_many = "super::thumbnails::Entity")]
     Thumbnails,
 }
 
+impl Related<super::file_field_values::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FileFieldValues.def()
+    }
+}
+
 impl Related<super::file_has_tags::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::FileHasTags.def()
@@ -59,6 +74,12 @@ impl Related<super::file_types::Entity> for Entity {
     }
 }
 
+impl Related<super::media_metadata::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MediaMetadata.def()
+    }
+}
+
 impl Related<super::thumbnails::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Thumbnails.def()
@@ -17,6 +17,9 @@ pub struct Model {
     pub path: String,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub color_id: Option<i32>,
+    pub icon_id: Option<i32>,
+    pub display_name: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -3,12 +3,19 @@
 pub mod prelude;
 
 pub mod color;
+pub mod command_history;
+pub mod custom_fields;
+pub mod file_events;
+pub mod file_field_values;
 pub mod file_has_tags;
 pub mod file_system_identifier;
 pub mod file_types;
 pub mod files;
 pub mod folders;
 pub mod icon;
+pub mod media_metadata;
+pub mod saved_searches;
+pub mod tag_aliases;
 pub mod tag_has_tags;
 pub mod tags;
 pub mod thumbnails;
@@ -10,12 +10,18 @@ pub struct Model {
     pub name: String,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub color_id: Option<i32>,
+    pub icon_id: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::file_has_tags::Entity")]
     FileHasTags,
+    #[sea_orm(has_many = "super::tag_aliases::Entity")]
+    TagAliases,
 }
 
 impl Related<super::file_has_tags::Entity> for Entity {
@@ -24,4 +30,10 @@ impl Related<super::file_has_tags::Entity> for Entity {
     }
 }
 
+impl Related<super::tag_aliases::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TagAliases.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -15,6 +15,8 @@ pub struct Model {
     pub file_size: i32,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    pub content_hash: Option<String>,
+    pub storage: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
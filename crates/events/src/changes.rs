@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use tokio::sync::broadcast;
+
+/// Number of changes a subscriber may fall behind before it starts missing them
+const CHANGE_BUS_CAPACITY: usize = 1024;
+
+/// A change to the library, identified by the ids of the affected rows
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryChange {
+    FileAdded {
+        file_id: i32,
+    },
+    FileModified {
+        file_id: i32,
+    },
+    FileMoved {
+        file_id: i32,
+        from: PathBuf,
+        to: PathBuf,
+    },
+    FileRemoved {
+        file_id: i32,
+    },
+    FolderAdded {
+        folder_id: i32,
+    },
+    FolderMoved {
+        folder_id: i32,
+        from: PathBuf,
+        to: PathBuf,
+    },
+    FolderRemoved {
+        folder_id: i32,
+    },
+    /// The color, icon or display name of the folder changed
+    FolderDecorated {
+        folder_id: i32,
+    },
+    TagAssigned {
+        file_id: i32,
+        tag_id: i32,
+    },
+    TagRemoved {
+        file_id: i32,
+        tag_id: i32,
+    },
+    /// The tag itself was created, renamed, edited, linked, merged or deleted
+    TagsChanged {
+        tag_id: i32,
+    },
+    /// A custom field was defined, changed or deleted
+    FieldsChanged,
+    FieldValueChanged {
+        file_id: i32,
+        field_id: i32,
+    },
+    ThumbnailReady {
+        file_id: i32,
+    },
+    /// Many rows below the folder may have changed at once, e.g. after a scan or resync
+    FolderResynced {
+        root: PathBuf,
+    },
+}
+
+impl LibraryChange {
+    /// The file whose row is affected, if the change concerns a single file
+    #[must_use]
+    pub fn file_id(&self) -> Option<i32> {
+        match self {
+            Self::FileAdded { file_id }
+            | Self::FileModified { file_id }
+            | Self::FileMoved { file_id, .. }
+            | Self::FileRemoved { file_id }
+            | Self::TagAssigned { file_id, .. }
+            | Self::TagRemoved { file_id, .. }
+            | Self::FieldValueChanged { file_id, .. }
+            | Self::ThumbnailReady { file_id } => Some(*file_id),
+            _ => None,
+        }
+    }
+}
+
+/// Broadcasts library changes to any number of subscribers
+///
+/// Publishing never blocks. A subscriber that falls more than the bus capacity behind receives
+/// [`broadcast::error::RecvError::Lagged`] and should reload everything it shows.
+#[derive(Debug, Clone)]
+pub struct ChangeBus {
+    sender: broadcast::Sender<LibraryChange>,
+}
+
+impl Default for ChangeBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeBus {
+    #[must_use]
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANGE_BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Send a change to every current subscriber
+    pub fn publish(&self, change: LibraryChange) {
+        // Having no subscribers is not an error, nobody is interested right now
+        drop(self.sender.send(change));
+    }
+
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<LibraryChange> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_every_subscriber_receives_changes() {
+        let bus = ChangeBus::new();
+        // Publishing without subscribers is fine
+        bus.publish(LibraryChange::TagsChanged { tag_id: 1 });
+
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        bus.publish(LibraryChange::FileAdded { file_id: 3 });
+
+        assert_eq!(
+            first.recv().await.ok(),
+            Some(LibraryChange::FileAdded { file_id: 3 })
+        );
+        assert_eq!(
+            second.recv().await.ok().and_then(|change| change.file_id()),
+            Some(3)
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod changes;
+
 use std::path::PathBuf;
 
 use hash::hash::{FileHash, FolderHash};
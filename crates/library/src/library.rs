@@ -7,6 +7,11 @@ use anyhow::{Context, Result, bail};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+use model::services::auto_tag::AutoTagRule;
+use model::services::backup::{BackupConfig, CONFIG_FILE, DATABASE_FILE};
+use model::services::tag_sync::TagSyncConfig;
+use model::services::thumbnail::ThumbnailStorageKind;
+use model::services::watch::WatchMode;
 use model::services::{CanonPath, decorations};
 
 use crate::io;
@@ -40,12 +45,34 @@ pub struct LibraryConfig {
     pub color: decorations::Color,
     pub icon: decorations::Icon,
     pub library_paths: Vec<LibraryPathConfig>,
+    #[serde(default)]
+    pub thumbnails: ThumbnailConfig,
+    /// Rules that tag files as the scanner and the watcher add or change them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub auto_tag: Vec<AutoTagRule>,
+    /// Whether tags are also kept in extended attributes and XMP sidecars of the files
+    #[serde(default)]
+    pub tag_sync: TagSyncConfig,
+    /// Where and how often the database and configuration are backed up
+    #[serde(default)]
+    pub backup: BackupConfig,
+}
+
+/// Thumbnail settings of a library
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ThumbnailConfig {
+    /// Where generated thumbnails are stored
+    #[serde(default)]
+    pub storage: ThumbnailStorageKind,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct LibraryPathConfig {
     pub name: Option<String>,
     pub path: Option<PathBuf>,
+    /// How changes inside the folder are detected; network shares need polling
+    #[serde(default)]
+    pub watch: WatchMode,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -58,6 +85,7 @@ impl Default for LibraryPathConfig {
         LibraryPathConfig {
             name: Some(String::from("")),
             path: Some(PathBuf::new().join("")),
+            watch: WatchMode::default(),
         }
     }
 }
@@ -69,6 +97,10 @@ impl Default for LibraryConfig {
             color: decorations::Color::default(),
             icon: decorations::Icon::default(),
             library_paths: vec![LibraryPathConfig::default()],
+            thumbnails: ThumbnailConfig::default(),
+            auto_tag: Vec::new(),
+            tag_sync: TagSyncConfig::default(),
+            backup: BackupConfig::default(),
         }
     }
 }
@@ -249,6 +281,33 @@ impl Library {
         Ok(self)
     }
 
+    /// Replace the database and configuration of the library at `share_path` with copies
+    ///
+    /// Nothing may have the database open. The configuration must parse before anything is
+    /// replaced, and the WAL of the replaced database is removed so that it is not replayed
+    /// onto the copy.
+    pub fn restore_files(share_path: &Path, database: &Path, config: &Path) -> Result<()> {
+        let config_content = io::read_file_to_string(config)?;
+        toml::from_str::<LibraryConfig>(&config_content)
+            .context("the configuration of the backup is not a library configuration")?;
+        io::ensure_directory_exists(share_path)?;
+
+        let restored = share_path.join(format!("{DATABASE_FILE}.restoring"));
+        std::fs::copy(database, &restored)
+            .with_context(|| format!("failed to copy {}", database.display()))?;
+        for suffix in ["-wal", "-shm"] {
+            let path = share_path.join(format!("{DATABASE_FILE}{suffix}"));
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+            }
+        }
+        std::fs::rename(&restored, share_path.join(DATABASE_FILE))
+            .context("failed to replace the library database")?;
+        io::write_string_to_file(&share_path.join(CONFIG_FILE), &config_content)?;
+        Ok(())
+    }
+
     /// Delete the library and all its files
     pub fn delete(self) -> Result<()> {
         if let Some(path) = self.share_path.as_deref() {
@@ -311,6 +370,53 @@ mod tests {
         assert!(path_config.path.is_some());
     }
 
+    #[test]
+    fn test_library_config_without_thumbnail_section() -> Result<()> {
+        let mut config = LibraryConfig::default();
+        config.thumbnails.storage = ThumbnailStorageKind::Directory;
+        let serialized = toml::to_string(&config)?;
+        assert!(serialized.contains("storage = \"directory\""));
+
+        // Configs written before thumbnail settings existed keep loading
+        let legacy = serialized
+            .split("[thumbnails]")
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let parsed: LibraryConfig = toml::from_str(&legacy)?;
+        assert_eq!(parsed.name, config.name);
+        assert_eq!(parsed.thumbnails, ThumbnailConfig::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_library_config_with_auto_tag_rules() -> Result<()> {
+        let rule: AutoTagRule = toml::from_str(
+            "name = \"Receipts\"\nglob = \"**/Scans/**\"\nextensions = [\"pdf\"]\ntags = [\"receipt\"]",
+        )?;
+        assert_eq!(rule.glob.as_deref(), Some("**/Scans/**"));
+        assert_eq!(rule.mime, None);
+
+        let mut config = LibraryConfig::default();
+        config.auto_tag.push(rule);
+        let serialized = toml::to_string(&config)?;
+        assert!(serialized.contains("[[auto_tag]]"));
+        assert_eq!(toml::from_str::<LibraryConfig>(&serialized)?, config);
+
+        // Configs written before auto-tagging existed have no rules
+        let legacy = serialized
+            .split("[[auto_tag]]")
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        assert!(
+            toml::from_str::<LibraryConfig>(&legacy)?
+                .auto_tag
+                .is_empty()
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_get_canon_database_path_without_share_path() {
         let lib = Library::new();
@@ -354,4 +460,26 @@ mod tests {
         lib.delete()?;
         Ok(())
     }
+
+    #[test]
+    fn test_library_path_watch_modes() -> Result<()> {
+        // Paths configured before watch modes existed are watched natively
+        let legacy: LibraryPathConfig = toml::from_str("name = \"Local\"\npath = \"/photos\"")?;
+        assert_eq!(legacy.watch, WatchMode::Native);
+
+        let rescan: LibraryPathConfig =
+            toml::from_str("path = \"/mnt/archive\"\nwatch = { mode = \"rescan\" }")?;
+        assert_eq!(
+            rescan.watch,
+            WatchMode::Rescan {
+                interval_secs: 15 * 60
+            }
+        );
+
+        let mut config = LibraryConfig::default();
+        config.library_paths[0].watch = WatchMode::Poll { interval_secs: 10 };
+        let parsed: LibraryConfig = toml::from_str(&toml::to_string(&config)?)?;
+        assert_eq!(parsed, config);
+        Ok(())
+    }
 }
@@ -10,6 +10,17 @@ mod m20250831_100006_create_file_has_tags;
 mod m20250831_100007_create_tag_has_tags;
 mod m20250831_181914_icon_color;
 mod m20250904_133644_create_thumbnails;
+mod m20261018_090000_thumbnail_cache_storage;
+mod m20261018_100000_create_media_metadata;
+mod m20261018_110000_create_file_events;
+mod m20261018_120000_create_command_history;
+mod m20261018_130000_tag_metadata;
+mod m20261018_140000_create_saved_searches;
+mod m20261018_150000_file_sizes;
+mod m20261018_151000_file_modified_times;
+mod m20261018_160000_folder_decorations;
+mod m20261018_170000_create_custom_fields;
+mod m20261018_180000_file_curation;
 
 pub struct Migrator;
 
@@ -30,6 +41,18 @@ impl MigratorTrait for Migrator {
             // Additional feature tables
             Box::new(m20250831_181914_icon_color::Migration),
             Box::new(m20250904_133644_create_thumbnails::Migration),
+            // Schema changes to existing tables
+            Box::new(m20261018_090000_thumbnail_cache_storage::Migration),
+            Box::new(m20261018_100000_create_media_metadata::Migration),
+            Box::new(m20261018_110000_create_file_events::Migration),
+            Box::new(m20261018_120000_create_command_history::Migration),
+            Box::new(m20261018_130000_tag_metadata::Migration),
+            Box::new(m20261018_140000_create_saved_searches::Migration),
+            Box::new(m20261018_150000_file_sizes::Migration),
+            Box::new(m20261018_151000_file_modified_times::Migration),
+            Box::new(m20261018_160000_folder_decorations::Migration),
+            Box::new(m20261018_170000_create_custom_fields::Migration),
+            Box::new(m20261018_180000_file_curation::Migration),
         ]
     }
 }
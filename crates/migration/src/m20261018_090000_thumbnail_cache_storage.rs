@@ -0,0 +1,87 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Thumbnails {
+    Table,
+    ContentHash,
+    Storage,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per ALTER TABLE statement
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Thumbnails::Table)
+                    .add_column(string_null(Thumbnails::ContentHash))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Existing thumbnails keep their data in the database
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Thumbnails::Table)
+                    .add_column(
+                        ColumnDef::new(Thumbnails::Storage)
+                            .string_len(16)
+                            .not_null()
+                            .default("database"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for garbage collection of the thumbnail cache directory
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_thumbnails_storage_content_hash")
+                    .table(Thumbnails::Table)
+                    .col(Thumbnails::Storage)
+                    .col(Thumbnails::ContentHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Drop index first
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_thumbnails_storage_content_hash")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Thumbnails::Table)
+                    .drop_column(Thumbnails::Storage)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Thumbnails::Table)
+                    .drop_column(Thumbnails::ContentHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum MediaMetadata {
+    Table,
+    Id,
+    FileId,
+    TakenAt,
+    CameraMake,
+    CameraModel,
+    Latitude,
+    Longitude,
+    Width,
+    Height,
+    Orientation,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Files {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create MediaMetadata table
+        manager
+            .create_table(
+                Table::create()
+                    .table(MediaMetadata::Table)
+                    .if_not_exists()
+                    .col(pk_auto(MediaMetadata::Id))
+                    .col(integer_uniq(MediaMetadata::FileId))
+                    .col(date_time_null(MediaMetadata::TakenAt))
+                    .col(string_null(MediaMetadata::CameraMake))
+                    .col(string_null(MediaMetadata::CameraModel))
+                    .col(double_null(MediaMetadata::Latitude))
+                    .col(double_null(MediaMetadata::Longitude))
+                    .col(integer_null(MediaMetadata::Width))
+                    .col(integer_null(MediaMetadata::Height))
+                    .col(small_integer_null(MediaMetadata::Orientation))
+                    .col(date_time(MediaMetadata::CreatedAt))
+                    .col(date_time(MediaMetadata::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_media_metadata_files")
+                            .from(MediaMetadata::Table, MediaMetadata::FileId)
+                            .to(Files::Table, Files::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create indexes for the `taken:` and `camera:` search qualifiers
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_media_metadata_taken_at")
+                    .table(MediaMetadata::Table)
+                    .col(MediaMetadata::TakenAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_media_metadata_camera_model")
+                    .table(MediaMetadata::Table)
+                    .col(MediaMetadata::CameraModel)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Drop indexes first
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_media_metadata_camera_model")
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(Index::drop().name("idx_media_metadata_taken_at").to_owned())
+            .await?;
+
+        // Drop MediaMetadata table (foreign keys will be dropped automatically)
+        manager
+            .drop_table(Table::drop().table(MediaMetadata::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
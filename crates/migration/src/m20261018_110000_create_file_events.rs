@@ -0,0 +1,86 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum FileEvents {
+    Table,
+    Id,
+    OccurredAt,
+    Kind,
+    Source,
+    FileId,
+    OldPath,
+    NewPath,
+    OldHash,
+    NewHash,
+    Detail,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The journal outlives the files it describes, so `file_id` has no foreign key
+        manager
+            .create_table(
+                Table::create()
+                    .table(FileEvents::Table)
+                    .if_not_exists()
+                    .col(pk_auto(FileEvents::Id))
+                    .col(date_time(FileEvents::OccurredAt))
+                    .col(string(FileEvents::Kind))
+                    .col(string(FileEvents::Source))
+                    .col(integer_null(FileEvents::FileId))
+                    .col(string_null(FileEvents::OldPath))
+                    .col(string_null(FileEvents::NewPath))
+                    .col(string_null(FileEvents::OldHash))
+                    .col(string_null(FileEvents::NewHash))
+                    .col(string_null(FileEvents::Detail))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create indexes for the per-file and most-recent-first activity queries
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_file_events_file_id")
+                    .table(FileEvents::Table)
+                    .col(FileEvents::FileId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_file_events_occurred_at")
+                    .table(FileEvents::Table)
+                    .col(FileEvents::OccurredAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Drop indexes first
+        manager
+            .drop_index(Index::drop().name("idx_file_events_occurred_at").to_owned())
+            .await?;
+
+        manager
+            .drop_index(Index::drop().name("idx_file_events_file_id").to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(FileEvents::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
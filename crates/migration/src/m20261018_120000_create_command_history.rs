@@ -0,0 +1,43 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CommandHistory {
+    Table,
+    Id,
+    CreatedAt,
+    Label,
+    Command,
+    Undone,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CommandHistory::Table)
+                    .if_not_exists()
+                    .col(pk_auto(CommandHistory::Id))
+                    .col(date_time(CommandHistory::CreatedAt))
+                    .col(string(CommandHistory::Label))
+                    .col(text(CommandHistory::Command))
+                    .col(boolean(CommandHistory::Undone).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CommandHistory::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
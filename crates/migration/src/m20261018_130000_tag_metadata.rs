@@ -0,0 +1,114 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Tags {
+    Table,
+    Id,
+    ColorId,
+    IconId,
+    Description,
+}
+
+#[derive(DeriveIden)]
+enum TagAliases {
+    Table,
+    Id,
+    TagId,
+    Alias,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per ALTER TABLE statement and no foreign keys on
+        // added columns, so colors and icons are looked up by id
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tags::Table)
+                    .add_column(integer_null(Tags::ColorId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tags::Table)
+                    .add_column(integer_null(Tags::IconId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Tags::Table)
+                    .add_column(text_null(Tags::Description))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create TagAliases table
+        manager
+            .create_table(
+                Table::create()
+                    .table(TagAliases::Table)
+                    .if_not_exists()
+                    .col(pk_auto(TagAliases::Id))
+                    .col(integer(TagAliases::TagId))
+                    .col(string(TagAliases::Alias))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_tag_aliases_tags")
+                            .from(TagAliases::Table, TagAliases::TagId)
+                            .to(Tags::Table, Tags::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for looking up tags by alias
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_tag_aliases_alias")
+                    .table(TagAliases::Table)
+                    .col(TagAliases::Alias)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Drop index first
+        manager
+            .drop_index(Index::drop().name("idx_tag_aliases_alias").to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(TagAliases::Table).to_owned())
+            .await?;
+
+        for column in [Tags::Description, Tags::IconId, Tags::ColorId] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Tags::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
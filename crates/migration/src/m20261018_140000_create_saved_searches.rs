@@ -0,0 +1,43 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum SavedSearches {
+    Table,
+    Id,
+    Name,
+    Filter,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(SavedSearches::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SavedSearches::Id))
+                    .col(string_uniq(SavedSearches::Name))
+                    .col(text(SavedSearches::Filter))
+                    .col(date_time(SavedSearches::CreatedAt))
+                    .col(date_time(SavedSearches::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SavedSearches::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Files {
+    Table,
+    Name,
+    Size,
+    UpdatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Files scanned before this migration keep a size of 0 until they are scanned again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .add_column(big_integer(Files::Size).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create indexes for sorting file listings
+        for (name, column) in [
+            ("idx_files_name", Files::Name),
+            ("idx_files_size", Files::Size),
+            ("idx_files_updated_at", Files::UpdatedAt),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name(name)
+                        .table(Files::Table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for index in ["idx_files_updated_at", "idx_files_size", "idx_files_name"] {
+            manager
+                .drop_index(Index::drop().name(index).to_owned())
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .drop_column(Files::Size)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Files {
+    Table,
+    ModifiedAt,
+    UpdatedAt,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .add_column(timestamp(Files::ModifiedAt).default("1970-01-01 00:00:00"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Files scanned before this migration use the time their row last changed until they
+        // are scanned again
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Files::Table)
+                    .value(Files::ModifiedAt, Expr::col(Files::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create an index for sorting file listings
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_files_modified_at")
+                    .table(Files::Table)
+                    .col(Files::ModifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(Index::drop().name("idx_files_modified_at").to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .drop_column(Files::ModifiedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Folders {
+    Table,
+    ColorId,
+    IconId,
+    DisplayName,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Colors and icons are looked up by id like for tags, see the tag metadata migration
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Folders::Table)
+                    .add_column(integer_null(Folders::ColorId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Folders::Table)
+                    .add_column(integer_null(Folders::IconId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Folders::Table)
+                    .add_column(string_null(Folders::DisplayName))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Folders::DisplayName, Folders::IconId, Folders::ColorId] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Folders::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CustomFields {
+    Table,
+    Id,
+    Name,
+    FieldType,
+    Options,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum FileFieldValues {
+    Table,
+    Id,
+    FileId,
+    FieldId,
+    Value,
+    Number,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Files {
+    Table,
+    Id,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create CustomFields table, the options are a JSON array for enum fields
+        manager
+            .create_table(
+                Table::create()
+                    .table(CustomFields::Table)
+                    .if_not_exists()
+                    .col(pk_auto(CustomFields::Id))
+                    .col(string_uniq(CustomFields::Name))
+                    .col(string(CustomFields::FieldType))
+                    .col(text(CustomFields::Options))
+                    .col(date_time(CustomFields::CreatedAt))
+                    .col(date_time(CustomFields::UpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create FileFieldValues table, numbers are stored twice so they compare as numbers
+        manager
+            .create_table(
+                Table::create()
+                    .table(FileFieldValues::Table)
+                    .if_not_exists()
+                    .col(pk_auto(FileFieldValues::Id))
+                    .col(integer(FileFieldValues::FileId))
+                    .col(integer(FileFieldValues::FieldId))
+                    .col(string(FileFieldValues::Value))
+                    .col(double_null(FileFieldValues::Number))
+                    .col(date_time(FileFieldValues::CreatedAt))
+                    .col(date_time(FileFieldValues::UpdatedAt))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_file_field_values_files")
+                            .from(FileFieldValues::Table, FileFieldValues::FileId)
+                            .to(Files::Table, Files::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_file_field_values_custom_fields")
+                            .from(FileFieldValues::Table, FileFieldValues::FieldId)
+                            .to(CustomFields::Table, CustomFields::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A file has at most one value per field
+        manager
+            .create_index(
+                Index::create()
+                    .table(FileFieldValues::Table)
+                    .if_not_exists()
+                    .col(FileFieldValues::FileId)
+                    .col(FileFieldValues::FieldId)
+                    .name("idx_file_field_values_unique_file_field")
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create index for the `field:` search qualifier
+        manager
+            .create_index(
+                Index::create()
+                    .table(FileFieldValues::Table)
+                    .if_not_exists()
+                    .col(FileFieldValues::FieldId)
+                    .col(FileFieldValues::Value)
+                    .name("idx_file_field_values_field_value")
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FileFieldValues::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(CustomFields::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Files {
+    Table,
+    Rating,
+    Favorite,
+    ColorLabel,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A rating of 0 means the file is not rated
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .add_column(small_integer(Files::Rating).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .add_column(boolean(Files::Favorite).default(false))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Files::Table)
+                    .add_column(string_null(Files::ColorLabel))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Create indexes for filtering and sorting file listings
+        for (name, column) in [
+            ("idx_files_rating", Files::Rating),
+            ("idx_files_favorite", Files::Favorite),
+            ("idx_files_color_label", Files::ColorLabel),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name(name)
+                        .table(Files::Table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for index in [
+            "idx_files_color_label",
+            "idx_files_favorite",
+            "idx_files_rating",
+        ] {
+            manager
+                .drop_index(Index::drop().name(index).to_owned())
+                .await?;
+        }
+
+        for column in [Files::ColorLabel, Files::Favorite, Files::Rating] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Files::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
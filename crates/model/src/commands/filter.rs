@@ -1,18 +1,130 @@
 use crate::commands::tag::Tag;
+use anyhow::Error;
+use chrono::{Duration, NaiveDateTime};
+use entity::saved_searches;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug)]
+/// Which files to show, every part that is set has to match
+///
+/// Filters are stored as JSON in saved searches, so new fields need a default.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Filter {
     pub tag_filter: Option<TagFilter>,
     pub folder_filter: Option<FolderFilter>,
+    /// Search text, including qualifiers such as `taken:2024`
+    pub text: String,
+    /// File extensions without the dot, a file matches any of them
+    pub extensions: Vec<String>,
+    /// When the file was added to the library
+    pub added: Option<DateFilter>,
+    /// When the file was last changed
+    pub modified: Option<DateFilter>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TagFilter {
+    /// The file has all of these tags
     pub tags: Vec<Tag>,
+    /// The file has no tags at all
+    pub untagged: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FolderFilter {
+    /// The file is below one of these folders
     pub folders: Vec<PathBuf>,
 }
+
+/// A range of timestamps, fixed or relative to when the filter is evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DateFilter {
+    /// `start <= t < end`, a missing bound is open
+    Between {
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    },
+    /// Within the last days before now, e.g. 7 for "this week"
+    LastDays { days: u32 },
+}
+
+impl DateFilter {
+    /// The bounds `start <= t < end` when evaluated at `now`
+    #[must_use]
+    pub fn bounds(&self, now: NaiveDateTime) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+        match *self {
+            Self::Between { start, end } => (start, end),
+            Self::LastDays { days } => (Some(now - Duration::days(i64::from(days))), None),
+        }
+    }
+}
+
+/// A filter stored under a name, shown as a smart folder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub id: i32,
+    pub name: String,
+    pub filter: Filter,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl TryFrom<saved_searches::Model> for SavedSearch {
+    type Error = Error;
+
+    fn try_from(model: saved_searches::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: model.id,
+            name: model.name,
+            filter: serde_json::from_str(&model.filter)?,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_filter_round_trips_through_json() -> anyhow::Result<()> {
+        let filter = Filter {
+            tag_filter: Some(TagFilter {
+                untagged: true,
+                ..TagFilter::default()
+            }),
+            extensions: vec!["pdf".to_string()],
+            added: Some(DateFilter::LastDays { days: 7 }),
+            ..Filter::default()
+        };
+        let json = serde_json::to_string(&filter)?;
+        assert_eq!(serde_json::from_str::<Filter>(&json)?, filter);
+        // Fields that are missing keep their default
+        assert_eq!(
+            serde_json::from_str::<Filter>(r#"{"text":"beach"}"#)?,
+            Filter {
+                text: "beach".to_string(),
+                ..Filter::default()
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_dates_count_back_from_now() {
+        let now = NaiveDate::from_ymd_opt(2026, 10, 18)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .expect("valid date");
+        let (start, end) = DateFilter::LastDays { days: 7 }.bounds(now);
+        assert_eq!(
+            start.map(|start| start.date().to_string()).as_deref(),
+            Some("2026-10-11")
+        );
+        assert_eq!(end, None);
+    }
+}
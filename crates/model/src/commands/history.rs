@@ -0,0 +1,295 @@
+use anyhow::{Error, Result, bail};
+use chrono::NaiveDateTime;
+use entity::command_history;
+use serde::{Deserialize, Serialize};
+
+use crate::services::tag::{TagDetails, TagLink};
+
+/// A reversible change to the tags of the library
+///
+/// Commands are stored in the command history as JSON, so each one must carry everything that
+/// is needed to apply its inverse later, e.g. the files a deleted tag was assigned to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum TagCommand {
+    /// Create a tag and assign it to the files
+    ///
+    /// Without an id the database picks one. Undoing a deletion recreates the tag with its old id.
+    CreateTag {
+        tag_id: Option<i32>,
+        name: String,
+        #[serde(default)]
+        file_ids: Vec<i32>,
+        #[serde(default)]
+        details: TagDetails,
+    },
+    RenameTag {
+        tag_id: i32,
+        from: String,
+        to: String,
+    },
+    /// Change the color, icon, description or aliases of a tag
+    EditTag {
+        tag_id: i32,
+        from: TagDetails,
+        to: TagDetails,
+    },
+    /// Delete a tag together with its assignments
+    ///
+    /// The name, details and files are read from the database when the command is applied.
+    DeleteTag {
+        tag_id: i32,
+        name: String,
+        #[serde(default)]
+        file_ids: Vec<i32>,
+        #[serde(default)]
+        details: TagDetails,
+    },
+    AssignTag {
+        tag_id: i32,
+        file_ids: Vec<i32>,
+    },
+    RemoveTag {
+        tag_id: i32,
+        file_ids: Vec<i32>,
+    },
+    /// Add edges to the tag hierarchy
+    LinkTags {
+        links: Vec<TagLink>,
+    },
+    /// Remove edges from the tag hierarchy
+    UnlinkTags {
+        links: Vec<TagLink>,
+    },
+    /// Move the files and hierarchy edges of a tag to another one and delete it
+    ///
+    /// The name and aliases of the merged tag become aliases of the tag it is merged into. The
+    /// merge is applied as the batch of simpler commands it consists of, which is what gets
+    /// recorded, so it can only be reverted once it has been applied.
+    MergeTag {
+        tag_id: i32,
+        into: i32,
+    },
+    /// Several commands that are undone and redone as one unit
+    Batch {
+        commands: Vec<TagCommand>,
+    },
+}
+
+impl TagCommand {
+    /// The command that reverts this one
+    ///
+    /// Fails for a tag creation that has not been applied yet, as its id is still unknown.
+    pub fn inverse(&self) -> Result<Self> {
+        Ok(match self {
+            Self::CreateTag {
+                tag_id: Some(tag_id),
+                name,
+                file_ids,
+                details,
+            } => Self::DeleteTag {
+                tag_id: *tag_id,
+                name: name.clone(),
+                file_ids: file_ids.clone(),
+                details: details.clone(),
+            },
+            Self::CreateTag {
+                tag_id: None, name, ..
+            } => {
+                bail!("the tag {name} has not been created yet")
+            }
+            Self::RenameTag { tag_id, from, to } => Self::RenameTag {
+                tag_id: *tag_id,
+                from: to.clone(),
+                to: from.clone(),
+            },
+            Self::EditTag { tag_id, from, to } => Self::EditTag {
+                tag_id: *tag_id,
+                from: to.clone(),
+                to: from.clone(),
+            },
+            Self::DeleteTag {
+                tag_id,
+                name,
+                file_ids,
+                details,
+            } => Self::CreateTag {
+                tag_id: Some(*tag_id),
+                name: name.clone(),
+                file_ids: file_ids.clone(),
+                details: details.clone(),
+            },
+            Self::AssignTag { tag_id, file_ids } => Self::RemoveTag {
+                tag_id: *tag_id,
+                file_ids: file_ids.clone(),
+            },
+            Self::RemoveTag { tag_id, file_ids } => Self::AssignTag {
+                tag_id: *tag_id,
+                file_ids: file_ids.clone(),
+            },
+            Self::LinkTags { links } => Self::UnlinkTags {
+                links: links.clone(),
+            },
+            Self::UnlinkTags { links } => Self::LinkTags {
+                links: links.clone(),
+            },
+            Self::MergeTag { tag_id, into } => {
+                bail!("the merge of tag {tag_id} into tag {into} has not been applied yet")
+            }
+            Self::Batch { commands } => Self::Batch {
+                commands: commands
+                    .iter()
+                    .rev()
+                    .map(Self::inverse)
+                    .collect::<Result<_>>()?,
+            },
+        })
+    }
+
+    /// Number of tag assignments the command adds or removes
+    #[must_use]
+    pub fn assignment_count(&self) -> usize {
+        match self {
+            Self::CreateTag { file_ids, .. }
+            | Self::DeleteTag { file_ids, .. }
+            | Self::AssignTag { file_ids, .. }
+            | Self::RemoveTag { file_ids, .. } => file_ids.len(),
+            Self::RenameTag { .. }
+            | Self::EditTag { .. }
+            | Self::LinkTags { .. }
+            | Self::UnlinkTags { .. }
+            | Self::MergeTag { .. } => 0,
+            Self::Batch { commands } => commands.iter().map(Self::assignment_count).sum(),
+        }
+    }
+
+    /// Whether applying the command would not change anything
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::AssignTag { file_ids, .. } | Self::RemoveTag { file_ids, .. } => {
+                file_ids.is_empty()
+            }
+            Self::LinkTags { links } | Self::UnlinkTags { links } => links.is_empty(),
+            Self::RenameTag { from, to, .. } => from == to,
+            Self::EditTag { from, to, .. } => from == to,
+            Self::Batch { commands } => commands.iter().all(Self::is_empty),
+            Self::CreateTag { .. } | Self::DeleteTag { .. } | Self::MergeTag { .. } => false,
+        }
+    }
+}
+
+/// One entry of the undo history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    /// Human readable description, e.g. "Delete tag Holiday"
+    pub label: String,
+    pub command: TagCommand,
+    /// Undone entries can be redone until a new command is recorded
+    pub undone: bool,
+}
+
+impl TryFrom<command_history::Model> for HistoryEntry {
+    type Error = Error;
+
+    fn try_from(model: command_history::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: model.id,
+            created_at: model.created_at,
+            label: model.label,
+            command: serde_json::from_str(&model.command)?,
+            undone: model.undone,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::decorations::Color;
+
+    #[test]
+    fn test_batch_inverse_reverts_in_reverse_order() -> Result<()> {
+        let batch = TagCommand::Batch {
+            commands: vec![
+                TagCommand::CreateTag {
+                    tag_id: Some(4),
+                    name: "Holiday".to_string(),
+                    file_ids: Vec::new(),
+                    details: TagDetails::default(),
+                },
+                TagCommand::AssignTag {
+                    tag_id: 4,
+                    file_ids: vec![1, 2],
+                },
+            ],
+        };
+        assert_eq!(
+            batch.inverse()?,
+            TagCommand::Batch {
+                commands: vec![
+                    TagCommand::RemoveTag {
+                        tag_id: 4,
+                        file_ids: vec![1, 2],
+                    },
+                    TagCommand::DeleteTag {
+                        tag_id: 4,
+                        name: "Holiday".to_string(),
+                        file_ids: Vec::new(),
+                        details: TagDetails::default(),
+                    },
+                ],
+            }
+        );
+        assert_eq!(batch.inverse()?.inverse()?, batch);
+        assert!(
+            TagCommand::CreateTag {
+                tag_id: None,
+                name: "Holiday".to_string(),
+                file_ids: Vec::new(),
+                details: TagDetails::default(),
+            }
+            .inverse()
+            .is_err()
+        );
+        assert!(
+            TagCommand::MergeTag { tag_id: 4, into: 5 }
+                .inverse()
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_commands_round_trip_through_json() -> Result<()> {
+        let command = TagCommand::DeleteTag {
+            tag_id: 7,
+            name: "Work".to_string(),
+            file_ids: vec![3, 5, 8],
+            details: TagDetails {
+                color: Some(Color::new("Red", "#E5484D")?),
+                description: Some("Everything for the office".to_string()),
+                aliases: vec!["Office".to_string()],
+                ..TagDetails::default()
+            },
+        };
+        let json = serde_json::to_string(&command)?;
+        assert!(json.contains("\"command\":\"delete_tag\""));
+        assert_eq!(serde_json::from_str::<TagCommand>(&json)?, command);
+        // Commands recorded before tags had details still load
+        assert_eq!(
+            serde_json::from_str::<TagCommand>(
+                r#"{"command":"create_tag","tag_id":7,"name":"Work"}"#
+            )?,
+            TagCommand::CreateTag {
+                tag_id: Some(7),
+                name: "Work".to_string(),
+                file_ids: Vec::new(),
+                details: TagDetails::default(),
+            }
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 pub mod filter;
 pub mod folder_info;
+pub mod history;
+pub mod query;
 pub mod tag;
 pub mod watched_folders;
@@ -0,0 +1,426 @@
+use crate::services::curation::{self, ColorLabel};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A parsed library search
+///
+/// Plain words match file names and paths. Words of the form `key:value` are qualifiers that
+/// filter on file metadata, e.g. `taken:2024 camera:"Pixel 8" rating:>=4 field:status=approved
+/// holiday`.
+/// Qualifiers with an unknown key or an invalid value are treated as plain words.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub filters: Vec<SearchFilter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SearchFilter {
+    /// Photo was taken within the range
+    Taken(DateRange),
+    /// Camera make or model contains the value
+    Camera(String),
+    /// A custom field of the file compares to the value
+    Field(FieldFilter),
+    /// The rating of the file compares to the value, e.g. `rating:>=4`, `rating:0` for unrated
+    Rating(Comparison, u8),
+    /// `favorite:yes` or `favorite:no`
+    Favorite(bool),
+    /// The file has the color label, `label:none` for files without one
+    Label(Option<ColorLabel>),
+}
+
+/// `field:name`, or `field:name` followed by a comparison and a value, e.g. `field:rating>=4`
+///
+/// Values compare as numbers if they are numbers and as text otherwise, which also orders dates
+/// written as `YYYY-MM-DD`. Text is compared ignoring case.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldFilter {
+    pub field: String,
+    /// `None` matches every file that has a value for the field
+    pub comparison: Option<(Comparison, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// Longer operators first, so that `<=` is not read as `<`
+    const OPERATORS: [(&'static str, Self); 6] = [
+        ("!=", Self::NotEqual),
+        ("<=", Self::LessOrEqual),
+        (">=", Self::GreaterOrEqual),
+        ("=", Self::Equal),
+        ("<", Self::Less),
+        (">", Self::Greater),
+    ];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+        }
+    }
+}
+
+impl FieldFilter {
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        let Some(start) = value.find(['=', '!', '<', '>']) else {
+            return Some(Self {
+                field: value.trim().to_string(),
+                comparison: None,
+            });
+        };
+        let (field, rest) = value.split_at(start);
+        let (operator, comparison) = Comparison::OPERATORS
+            .into_iter()
+            .find(|(operator, _)| rest.starts_with(operator))?;
+        let field = field.trim();
+        let compared = rest.strip_prefix(operator)?.trim();
+        if field.is_empty() || compared.is_empty() {
+            return None;
+        }
+        Some(Self {
+            field: field.to_string(),
+            comparison: Some((comparison, compared.to_string())),
+        })
+    }
+
+    /// The compared value as a number, if it is one
+    #[must_use]
+    pub fn number(&self) -> Option<f64> {
+        self.comparison
+            .as_ref()
+            .and_then(|(_, value)| value.parse::<f64>().ok())
+            .filter(|number| number.is_finite())
+    }
+}
+
+impl fmt::Display for FieldFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.comparison {
+            Some((comparison, value)) => f.write_str(&quote(&format!(
+                "{}{}{value}",
+                self.field,
+                comparison.as_str()
+            ))),
+            None => f.write_str(&quote(&self.field)),
+        }
+    }
+}
+
+/// Half-open range of timestamps, `start <= t < end`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateRange {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    granularity: DateGranularity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum DateGranularity {
+    Year,
+    Month,
+    Day,
+}
+
+impl DateRange {
+    /// Parse `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the range it covers
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<&str> = value.split('-').collect();
+        let number = |index: usize| parts.get(index)?.parse::<u32>().ok();
+        let year = parts
+            .first()
+            .filter(|year| year.len() == 4)?
+            .parse::<i32>()
+            .ok()?;
+
+        let (start, granularity) = match parts.len() {
+            1 => (NaiveDate::from_ymd_opt(year, 1, 1)?, DateGranularity::Year),
+            2 => (
+                NaiveDate::from_ymd_opt(year, number(1)?, 1)?,
+                DateGranularity::Month,
+            ),
+            3 => (
+                NaiveDate::from_ymd_opt(year, number(1)?, number(2)?)?,
+                DateGranularity::Day,
+            ),
+            _ => return None,
+        };
+        let end = match granularity {
+            DateGranularity::Year => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?,
+            DateGranularity::Month => start.checked_add_months(chrono::Months::new(1))?,
+            DateGranularity::Day => start.succ_opt()?,
+        };
+
+        Some(Self {
+            start: start.and_hms_opt(0, 0, 0)?,
+            end: end.and_hms_opt(0, 0, 0)?,
+            granularity,
+        })
+    }
+
+    #[must_use]
+    pub fn contains(&self, timestamp: NaiveDateTime) -> bool {
+        self.start <= timestamp && timestamp < self.end
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.granularity {
+            DateGranularity::Year => write!(f, "{}", self.start.format("%Y")),
+            DateGranularity::Month => write!(f, "{}", self.start.format("%Y-%m")),
+            DateGranularity::Day => write!(f, "{}", self.start.format("%Y-%m-%d")),
+        }
+    }
+}
+
+impl SearchQuery {
+    #[must_use]
+    pub fn parse(input: &str) -> Self {
+        let mut query = Self::default();
+
+        for token in tokenize(input) {
+            match token.split_once(':') {
+                Some((key, value)) if !value.is_empty() => {
+                    match Self::parse_filter(&key.to_lowercase(), value) {
+                        Some(filter) => query.filters.push(filter),
+                        None => query.terms.push(token),
+                    }
+                }
+                _ => query.terms.push(token),
+            }
+        }
+
+        query
+    }
+
+    fn parse_filter(key: &str, value: &str) -> Option<SearchFilter> {
+        match key {
+            "taken" => DateRange::parse(value).map(SearchFilter::Taken),
+            "camera" => Some(SearchFilter::Camera(value.to_string())),
+            "field" => FieldFilter::parse(value).map(SearchFilter::Field),
+            "rating" => {
+                let (comparison, rating) = Comparison::OPERATORS
+                    .into_iter()
+                    .find_map(|(operator, comparison)| {
+                        value
+                            .strip_prefix(operator)
+                            .map(|rating| (comparison, rating))
+                    })
+                    .unwrap_or((Comparison::Equal, value));
+                let rating = curation::rating(rating.parse().ok()?).ok()?;
+                Some(SearchFilter::Rating(comparison, rating))
+            }
+            "favorite" => match value.to_lowercase().as_str() {
+                "yes" | "true" => Some(SearchFilter::Favorite(true)),
+                "no" | "false" => Some(SearchFilter::Favorite(false)),
+                _ => None,
+            },
+            "label" if value.eq_ignore_ascii_case("none") => Some(SearchFilter::Label(None)),
+            "label" => ColorLabel::parse(value)
+                .ok()
+                .map(|label| SearchFilter::Label(Some(label))),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.filters.is_empty()
+    }
+
+    /// Plain words joined back into a single string
+    #[must_use]
+    pub fn text(&self) -> String {
+        self.terms.join(" ")
+    }
+}
+
+impl fmt::Display for SearchQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts: Vec<String> = self.filters.iter().map(ToString::to_string).collect();
+        parts.extend(self.terms.iter().map(|term| quote(term)));
+        f.write_str(&parts.join(" "))
+    }
+}
+
+impl fmt::Display for SearchFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Taken(range) => write!(f, "taken:{range}"),
+            Self::Camera(camera) => write!(f, "camera:{}", quote(camera)),
+            Self::Field(field) => write!(f, "field:{field}"),
+            Self::Rating(Comparison::Equal, rating) => write!(f, "rating:{rating}"),
+            Self::Rating(comparison, rating) => write!(f, "rating:{}{rating}", comparison.as_str()),
+            Self::Favorite(true) => f.write_str("favorite:yes"),
+            Self::Favorite(false) => f.write_str("favorite:no"),
+            Self::Label(Some(label)) => write!(f, "label:{}", label.as_str()),
+            Self::Label(None) => f.write_str("label:none"),
+        }
+    }
+}
+
+/// Split on whitespace, keeping double-quoted sections together
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for character in input.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+fn quote(value: &str) -> String {
+    if value.chars().any(char::is_whitespace) {
+        format!("\"{value}\"")
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_terms() {
+        let query = SearchQuery::parse("  holiday   beach ");
+        assert_eq!(query.terms, vec!["holiday", "beach"]);
+        assert!(query.filters.is_empty());
+        assert_eq!(query.text(), "holiday beach");
+        assert!(SearchQuery::parse("   ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_qualifiers() {
+        let query = SearchQuery::parse("taken:2024 camera:\"Pixel 8\" beach");
+        assert_eq!(query.terms, vec!["beach"]);
+        assert_eq!(query.filters.len(), 2);
+
+        let SearchFilter::Taken(range) = &query.filters[0] else {
+            panic!("expected a taken filter");
+        };
+        let inside = NaiveDate::from_ymd_opt(2024, 12, 31)
+            .and_then(|date| date.and_hms_opt(23, 59, 59))
+            .expect("valid date");
+        let outside = NaiveDate::from_ymd_opt(2025, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .expect("valid date");
+        assert!(range.contains(inside));
+        assert!(!range.contains(outside));
+        assert_eq!(
+            query.filters[1],
+            SearchFilter::Camera("Pixel 8".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_date_ranges() {
+        let month = DateRange::parse("2024-02").expect("valid month");
+        assert_eq!(
+            month.end.date(),
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        );
+        let day = DateRange::parse("2024-02-29").expect("valid day");
+        assert_eq!(day.end.date(), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+
+        assert!(DateRange::parse("20").is_none());
+        assert!(DateRange::parse("2023-02-29").is_none());
+        assert!(DateRange::parse("2024-13").is_none());
+    }
+
+    #[test]
+    fn test_invalid_qualifiers_are_plain_terms() {
+        let query = SearchQuery::parse("taken:soon color:red note:");
+        assert_eq!(query.terms, vec!["taken:soon", "color:red", "note:"]);
+        assert!(query.filters.is_empty());
+    }
+
+    #[test]
+    fn test_parse_field_qualifiers() {
+        let query =
+            SearchQuery::parse("field:rating>=4 field:\"due date<2025-11-01\" field:client");
+        assert_eq!(
+            query.filters,
+            vec![
+                SearchFilter::Field(FieldFilter {
+                    field: "rating".to_string(),
+                    comparison: Some((Comparison::GreaterOrEqual, "4".to_string())),
+                }),
+                SearchFilter::Field(FieldFilter {
+                    field: "due date".to_string(),
+                    comparison: Some((Comparison::Less, "2025-11-01".to_string())),
+                }),
+                SearchFilter::Field(FieldFilter {
+                    field: "client".to_string(),
+                    comparison: None,
+                }),
+            ]
+        );
+        assert_eq!(SearchQuery::parse(&query.to_string()), query);
+        let SearchFilter::Field(rating) = &query.filters[0] else {
+            panic!("expected a field filter");
+        };
+        assert_eq!(rating.number(), Some(4.0));
+
+        let invalid = SearchQuery::parse("field:=4 field:rating= field:rating<");
+        assert!(invalid.filters.is_empty());
+        assert_eq!(invalid.terms.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_curation_qualifiers() {
+        let query = SearchQuery::parse("rating:>=4 rating:0 favorite:Yes label:red label:none");
+        assert_eq!(
+            query.filters,
+            vec![
+                SearchFilter::Rating(Comparison::GreaterOrEqual, 4),
+                SearchFilter::Rating(Comparison::Equal, 0),
+                SearchFilter::Favorite(true),
+                SearchFilter::Label(Some(ColorLabel::Red)),
+                SearchFilter::Label(None),
+            ]
+        );
+        assert_eq!(SearchQuery::parse(&query.to_string()), query);
+        let invalid = SearchQuery::parse("rating:6 rating:>=x favorite:maybe label:teal");
+        assert!(invalid.filters.is_empty());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let query = SearchQuery::parse("beach taken:2024-05 camera:\"EOS R5\"");
+        assert_eq!(query.to_string(), "taken:2024-05 camera:\"EOS R5\" beach");
+        assert_eq!(SearchQuery::parse(&query.to_string()), query);
+    }
+}
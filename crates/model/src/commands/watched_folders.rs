@@ -1,68 +1,317 @@
+use crate::services::folder::FolderDecoration;
+use entity::folders;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Files in a folder, counted for the folder alone or together with the folders below it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FolderStats {
+    /// Files directly in the folder
+    pub direct_files: u64,
+    /// Files in the folder and every folder below it
+    pub files: u64,
+    /// Size in bytes of those files
+    pub size: u64,
+    /// Those files that have at least one tag
+    pub tagged_files: u64,
+}
+
+impl FolderStats {
+    /// Count a file that is directly in the folder
+    pub fn add_file(&mut self, size: u64, tagged: bool) {
+        self.direct_files += 1;
+        self.files += 1;
+        self.size += size;
+        self.tagged_files += u64::from(tagged);
+    }
+
+    /// Share of the files that have a tag, 0 for an empty folder
+    #[must_use]
+    pub fn tag_coverage(&self) -> f64 {
+        let as_float = |value: u64| f64::from(u32::try_from(value).unwrap_or(u32::MAX));
+        if self.files == 0 {
+            0.0
+        } else {
+            as_float(self.tagged_files) / as_float(self.files)
+        }
+    }
+
+    /// Count the files of a folder below this one
+    fn include(&mut self, below: &Self) {
+        self.files += below.files;
+        self.size += below.size;
+        self.tagged_files += below.tagged_files;
+    }
+}
+
+/// A watched folder with the folders below it and what they contain
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WatchedFolderTree {
+    id: i32,
+    parent_id: Option<i32>,
     name: String,
     path: String,
-    children: Option<Vec<String>>,
-    icon: Option<String>,
-    color: Option<String>,
+    stats: FolderStats,
+    /// Empty if the children are not loaded, see [`Self::has_children`]
+    children: Vec<WatchedFolderTree>,
+    has_children: bool,
+    decoration: FolderDecoration,
 }
 
 impl WatchedFolderTree {
-    pub fn new(name: String, path: String) -> WatchedFolderTree {
-        WatchedFolderTree {
+    #[must_use]
+    pub fn new(id: i32, name: String, path: String) -> Self {
+        Self {
+            id,
+            parent_id: None,
             name,
             path,
-            children: None,
-            icon: None,
-            color: None,
+            stats: FolderStats::default(),
+            children: Vec::new(),
+            has_children: false,
+            decoration: FolderDecoration::default(),
         }
     }
 
-    pub fn with_icon_and_color(
-        name: String,
-        path: String,
-        icon: Option<String>,
-        color: Option<String>,
-    ) -> WatchedFolderTree {
-        WatchedFolderTree {
-            name,
-            path,
-            children: None,
-            icon,
-            color,
-        }
+    #[must_use]
+    pub fn with_parent(mut self, parent_id: Option<i32>) -> Self {
+        self.parent_id = parent_id;
+        self
     }
 
-    pub fn with(
-        name: String,
-        path: String,
-        children: Option<Vec<String>>,
-        icon: Option<String>,
-        color: Option<String>,
-    ) -> WatchedFolderTree {
-        WatchedFolderTree {
-            name,
-            path,
-            children,
-            icon,
-            color,
-        }
+    #[must_use]
+    pub fn with_stats(mut self, stats: FolderStats) -> Self {
+        self.stats = stats;
+        self
     }
 
-    pub fn children(mut self, children: Vec<String>) -> Self {
-        self.children = Some(children);
+    #[must_use]
+    pub fn with_children(mut self, children: Vec<WatchedFolderTree>) -> Self {
+        self.has_children = !children.is_empty();
+        self.children = children;
         self
     }
 
-    pub fn icon(mut self, icon: String) -> Self {
-        self.icon = Some(icon);
+    #[must_use]
+    pub fn with_decoration(mut self, decoration: FolderDecoration) -> Self {
+        self.decoration = decoration;
         self
     }
 
-    pub fn color(mut self, color: String) -> Self {
-        self.color = Some(color);
+    /// Nest folders under their parents and add up the files below every folder
+    ///
+    /// `direct_stats` holds the files directly in each folder, keyed by the folder path. Folders
+    /// whose parent is not among `folders` are returned as roots, ordered by name like their
+    /// children.
+    #[must_use]
+    pub fn build(
+        folders: Vec<folders::Model>,
+        direct_stats: &HashMap<String, FolderStats>,
+        decorations: &HashMap<i32, FolderDecoration>,
+    ) -> Vec<Self> {
+        let ids: Vec<i32> = folders.iter().map(|folder| folder.id).collect();
+        let mut by_parent: HashMap<Option<i32>, Vec<folders::Model>> = HashMap::new();
+        for folder in folders {
+            let parent = folder
+                .parent_folder_id
+                .filter(|parent_id| ids.contains(parent_id));
+            by_parent.entry(parent).or_default().push(folder);
+        }
+        Self::nest(None, &mut by_parent, direct_stats, decorations)
+    }
+
+    fn nest(
+        parent: Option<i32>,
+        by_parent: &mut HashMap<Option<i32>, Vec<folders::Model>>,
+        direct_stats: &HashMap<String, FolderStats>,
+        decorations: &HashMap<i32, FolderDecoration>,
+    ) -> Vec<Self> {
+        let mut folders = by_parent.remove(&parent).unwrap_or_default();
+        folders.sort_by(|left, right| left.name.cmp(&right.name));
+        folders
+            .into_iter()
+            .map(|folder| {
+                let children = Self::nest(Some(folder.id), by_parent, direct_stats, decorations);
+                let mut stats = direct_stats.get(&folder.path).copied().unwrap_or_default();
+                for child in &children {
+                    stats.include(&child.stats);
+                }
+                Self::new(folder.id, folder.name, folder.path)
+                    .with_parent(folder.parent_folder_id)
+                    .with_stats(stats)
+                    .with_children(children)
+                    .with_decoration(decorations.get(&folder.id).cloned().unwrap_or_default())
+            })
+            .collect()
+    }
+
+    /// Drop the folders more than `depth` levels below this one, keeping their counts
+    #[must_use]
+    pub fn pruned(mut self, depth: usize) -> Self {
+        self.children = match depth {
+            0 => Vec::new(),
+            depth => self
+                .children
+                .into_iter()
+                .map(|child| child.pruned(depth - 1))
+                .collect(),
+        };
         self
     }
+
+    /// The folder a file path is directly in, as it is stored for folders
+    #[must_use]
+    pub fn folder_of(file_path: &str) -> Option<String> {
+        Path::new(file_path)
+            .parent()
+            .map(|parent| parent.to_string_lossy().to_string())
+    }
+
+    #[must_use]
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    #[must_use]
+    pub fn parent_id(&self) -> Option<i32> {
+        self.parent_id
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn stats(&self) -> &FolderStats {
+        &self.stats
+    }
+
+    #[must_use]
+    pub fn children(&self) -> &[WatchedFolderTree] {
+        &self.children
+    }
+
+    /// Whether there are folders below this one, also when they were not loaded
+    #[must_use]
+    pub fn has_children(&self) -> bool {
+        self.has_children
+    }
+
+    #[must_use]
+    pub fn decoration(&self) -> &FolderDecoration {
+        &self.decoration
+    }
+
+    /// The display name if the folder has one, its name otherwise
+    #[must_use]
+    pub fn label(&self) -> &str {
+        self.decoration
+            .display_name
+            .as_deref()
+            .unwrap_or(&self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn folder(id: i32, parent_folder_id: Option<i32>, path: &str) -> folders::Model {
+        folders::Model {
+            id,
+            content_hash: String::new(),
+            identity_hash: String::new(),
+            structure_hash: String::new(),
+            file_system_id: 1,
+            parent_folder_id,
+            name: path.rsplit('/').next().unwrap_or_default().to_string(),
+            path: path.to_string(),
+            created_at: NaiveDateTime::default(),
+            updated_at: NaiveDateTime::default(),
+            color_id: None,
+            icon_id: None,
+            display_name: None,
+        }
+    }
+
+    #[test]
+    fn test_counts_add_up_through_the_tree() {
+        let folders = vec![
+            folder(3, Some(1), "/photos/2024"),
+            folder(1, None, "/photos"),
+            folder(4, Some(3), "/photos/2024/summer"),
+            folder(2, Some(1), "/photos/2023"),
+        ];
+        let mut direct_stats: HashMap<String, FolderStats> = HashMap::new();
+        for (folder, size, tagged) in [
+            ("/photos", 10, false),
+            ("/photos/2024", 100, true),
+            ("/photos/2024", 100, false),
+            ("/photos/2024/summer", 1000, true),
+            ("/photos/2024/summer", 1000, true),
+            ("/photos/2024/summer", 1000, true),
+        ] {
+            direct_stats
+                .entry(folder.to_string())
+                .or_default()
+                .add_file(size, tagged);
+        }
+        let decorations = HashMap::from([(
+            3,
+            FolderDecoration {
+                display_name: Some("This year".to_string()),
+                ..FolderDecoration::default()
+            },
+        )]);
+        let tree = WatchedFolderTree::build(folders, &direct_stats, &decorations);
+        assert_eq!(tree.len(), 1);
+        let photos = &tree[0];
+        assert_eq!(
+            photos.stats(),
+            &FolderStats {
+                direct_files: 1,
+                files: 6,
+                size: 3210,
+                tagged_files: 4,
+            }
+        );
+        let names: Vec<&str> = photos
+            .children()
+            .iter()
+            .map(WatchedFolderTree::name)
+            .collect();
+        assert_eq!(names, vec!["2023", "2024"]);
+        assert_eq!(photos.children()[1].label(), "This year");
+        assert_eq!(photos.label(), "photos");
+        assert!(!photos.children()[0].has_children());
+        assert!((photos.children()[1].stats().tag_coverage() - 0.8).abs() < 1e-9);
+
+        let pruned = photos.clone().pruned(1);
+        assert!(pruned.children()[1].children().is_empty());
+        assert!(pruned.children()[1].has_children());
+        assert_eq!(pruned.children()[1].stats().files, 5);
+    }
+
+    #[test]
+    fn test_folders_without_their_parent_are_roots() {
+        let tree = WatchedFolderTree::build(
+            vec![folder(2, Some(1), "/photos/2024")],
+            &HashMap::new(),
+            &HashMap::new(),
+        );
+        assert_eq!(tree[0].id(), 2);
+        assert_eq!(tree[0].parent_id(), Some(1));
+        assert_eq!(
+            WatchedFolderTree::folder_of("/photos/2024/beach.jpg").as_deref(),
+            Some("/photos/2024")
+        );
+    }
 }
@@ -0,0 +1,280 @@
+use anyhow::{Context, Result, bail};
+use chrono::{Datelike, NaiveDateTime};
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::commands::query::DateRange;
+
+/// A rule of the library configuration that tags files when they are added or changed
+///
+/// Every condition that is set must match. Tags may contain `{year}` and `{month}`, which are
+/// replaced with the date the photo was taken, or the date the file was last modified.
+///
+/// ```toml
+/// [[auto_tag]]
+/// name = "Scanned receipts"
+/// glob = "**/Scans/**"
+/// extensions = ["pdf"]
+/// tags = ["receipt", "year/{year}"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoTagRule {
+    pub name: String,
+    /// Glob on the full path, `**` matches any number of folders
+    pub glob: Option<String>,
+    /// Extensions without the dot, compared ignoring case
+    pub extensions: Vec<String>,
+    /// Detected MIME type, `image/*` matches every image
+    pub mime: Option<String>,
+    /// Size in bytes
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD` the photo was taken in according to its EXIF data
+    pub taken: Option<String>,
+    /// Regular expression on the file name
+    pub name_regex: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// What rules are matched against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoTagCandidate {
+    pub path: PathBuf,
+    pub mime_type: String,
+    pub size: u64,
+    pub modified: Option<NaiveDateTime>,
+    pub taken_at: Option<NaiveDateTime>,
+}
+
+/// Auto-tag rules with their patterns compiled, ready to be matched against many files
+#[derive(Debug, Clone, Default)]
+pub struct AutoTagRules {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    glob: Option<Pattern>,
+    extensions: Vec<String>,
+    mime: Option<String>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    taken: Option<DateRange>,
+    name_regex: Option<Regex>,
+    tags: Vec<String>,
+}
+
+impl AutoTagRules {
+    /// Compile the rules, failing on the first rule with an invalid pattern
+    pub fn compile(rules: &[AutoTagRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                CompiledRule::compile(rule)
+                    .with_context(|| format!("invalid auto-tag rule {:?}", rule.name))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether matching needs the date a photo was taken, which requires reading its EXIF data
+    #[must_use]
+    pub fn needs_taken_date(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.taken.is_some() || rule.tags.iter().any(|tag| tag.contains('{')))
+    }
+
+    /// Tags of every matching rule, without duplicates and in the order of the rules
+    #[must_use]
+    pub fn tags_for(&self, candidate: &AutoTagCandidate) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(candidate)) {
+            for tag in rule.tags.iter().filter_map(|tag| expand(tag, candidate)) {
+                if !tags.contains(&tag) {
+                    tags.push(tag);
+                }
+            }
+        }
+        tags
+    }
+}
+
+impl CompiledRule {
+    fn compile(rule: &AutoTagRule) -> Result<Self> {
+        let tags: Vec<String> = rule
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if tags.is_empty() {
+            bail!("the rule adds no tags");
+        }
+        let taken = rule
+            .taken
+            .as_deref()
+            .map(|taken| DateRange::parse(taken).with_context(|| format!("invalid date {taken}")))
+            .transpose()?;
+        Ok(Self {
+            glob: rule.glob.as_deref().map(Pattern::new).transpose()?,
+            extensions: rule
+                .extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect(),
+            mime: rule.mime.as_ref().map(|mime| mime.to_lowercase()),
+            min_size: rule.min_size,
+            max_size: rule.max_size,
+            taken,
+            name_regex: rule.name_regex.as_deref().map(Regex::new).transpose()?,
+            tags,
+        })
+    }
+
+    fn matches(&self, candidate: &AutoTagCandidate) -> bool {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::default()
+        };
+        let name = candidate
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let extension = candidate
+            .path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+
+        self.glob
+            .as_ref()
+            .is_none_or(|glob| glob.matches_path_with(&candidate.path, options))
+            && (self.extensions.is_empty()
+                || extension.is_some_and(|extension| self.extensions.contains(&extension)))
+            && self
+                .mime
+                .as_deref()
+                .is_none_or(|mime| match mime.strip_suffix('*') {
+                    Some(prefix) => candidate.mime_type.starts_with(prefix),
+                    None => candidate.mime_type == mime,
+                })
+            && self
+                .min_size
+                .is_none_or(|min_size| candidate.size >= min_size)
+            && self
+                .max_size
+                .is_none_or(|max_size| candidate.size <= max_size)
+            && self.taken.as_ref().is_none_or(|range| {
+                candidate
+                    .taken_at
+                    .is_some_and(|taken_at| range.contains(taken_at))
+            })
+            && self
+                .name_regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&name))
+    }
+}
+
+/// Replace the date placeholders of a tag, or `None` if the file has no date to use
+fn expand(tag: &str, candidate: &AutoTagCandidate) -> Option<String> {
+    if !tag.contains('{') {
+        return Some(tag.to_string());
+    }
+    let date = candidate.taken_at.or(candidate.modified)?;
+    Some(
+        tag.replace("{year}", &date.year().to_string())
+            .replace("{month}", &format!("{:02}", date.month())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn candidate(path: &str, mime_type: &str) -> AutoTagCandidate {
+        AutoTagCandidate {
+            path: PathBuf::from(path),
+            mime_type: mime_type.to_string(),
+            size: 2_048,
+            modified: NaiveDate::from_ymd_opt(2025, 3, 9)
+                .and_then(|date| date.and_hms_opt(8, 0, 0)),
+            taken_at: None,
+        }
+    }
+
+    #[test]
+    fn test_rules_match_on_every_condition_and_expand_dates() -> Result<()> {
+        let rules = AutoTagRules::compile(&[
+            AutoTagRule {
+                name: "Receipts".to_string(),
+                glob: Some("/library/**/Scans/**".to_string()),
+                extensions: vec!["PDF".to_string()],
+                name_regex: Some("(?i)receipt|invoice".to_string()),
+                tags: vec!["receipt".to_string(), "year/{year}".to_string()],
+                ..AutoTagRule::default()
+            },
+            AutoTagRule {
+                name: "Large images".to_string(),
+                mime: Some("image/*".to_string()),
+                min_size: Some(1_024),
+                tags: vec!["photo".to_string()],
+                ..AutoTagRule::default()
+            },
+        ])?;
+
+        assert_eq!(
+            rules.tags_for(&candidate(
+                "/library/2025/Scans/march/Invoice-17.pdf",
+                "application/pdf"
+            )),
+            vec!["receipt".to_string(), "year/2025".to_string()]
+        );
+        // The name does not match
+        assert!(
+            rules
+                .tags_for(&candidate("/library/Scans/letter.pdf", "application/pdf"))
+                .is_empty()
+        );
+        assert_eq!(
+            rules.tags_for(&candidate("/library/beach.jpg", "image/jpeg")),
+            vec!["photo".to_string()]
+        );
+        assert!(rules.needs_taken_date());
+        Ok(())
+    }
+
+    #[test]
+    fn test_taken_date_only_matches_photos_with_exif_data() -> Result<()> {
+        let rules = AutoTagRules::compile(&[AutoTagRule {
+            taken: Some("2024-07".to_string()),
+            tags: vec!["summer/{year}".to_string()],
+            ..AutoTagRule::default()
+        }])?;
+        let mut photo = candidate("/library/beach.jpg", "image/jpeg");
+        assert!(rules.tags_for(&photo).is_empty());
+        photo.taken_at =
+            NaiveDate::from_ymd_opt(2024, 7, 14).and_then(|date| date.and_hms_opt(9, 0, 0));
+        assert_eq!(rules.tags_for(&photo), vec!["summer/2024".to_string()]);
+
+        assert!(
+            AutoTagRules::compile(&[AutoTagRule {
+                name_regex: Some("(".to_string()),
+                tags: vec!["broken".to_string()],
+                ..AutoTagRule::default()
+            }])
+            .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Describes a backup, the file sits next to the copied database and configuration
+pub const MANIFEST_FILE: &str = "backup.toml";
+
+pub const DATABASE_FILE: &str = "db.sqlite";
+
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Automatic backups of the database and configuration of a library
+///
+/// ```toml
+/// [backup]
+/// folder = "/mnt/nas/hestia-backups"
+/// interval_hours = 24
+/// keep = 7
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// Where backups are written, the `backups` folder of the library when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder: Option<PathBuf>,
+    /// Hours between automatic backups, 0 turns them off
+    pub interval_hours: u32,
+    /// How many backups of the library are kept, 0 keeps all of them
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            folder: None,
+            interval_hours: 0,
+            keep: 10,
+        }
+    }
+}
+
+impl BackupConfig {
+    /// Time between automatic backups, `None` when they are turned off
+    #[must_use]
+    pub fn interval(&self) -> Option<Duration> {
+        (self.interval_hours > 0)
+            .then(|| Duration::from_secs(u64::from(self.interval_hours) * 3600))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Folder name of the library the backup belongs to
+    pub library: String,
+    /// When the backup was made, in UTC
+    pub created_at: NaiveDateTime,
+    /// Name of the last migration applied to the database
+    pub schema_version: String,
+}
+
+impl BackupManifest {
+    /// Name of the folder holding the backup, backups of a library sort by age
+    #[must_use]
+    pub fn folder_name(&self) -> String {
+        format!(
+            "{}-{}",
+            self.library,
+            self.created_at.format("%Y%m%dT%H%M%S%.3f")
+        )
+    }
+}
+
+/// The backups to remove so that only the `keep` newest remain, all are kept with 0
+#[must_use]
+pub fn expired<T>(mut backups: Vec<(NaiveDateTime, T)>, keep: usize) -> Vec<T> {
+    if keep == 0 {
+        return Vec::new();
+    }
+    backups.sort_by_key(|(created_at, _)| Reverse(*created_at));
+    backups
+        .into_iter()
+        .skip(keep)
+        .map(|(_, backup)| backup)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 10, day)
+            .and_then(|date| date.and_hms_milli_opt(9, 30, 0, 250))
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_only_the_newest_backups_are_kept() {
+        let backups = vec![(at(3), "third"), (at(1), "first"), (at(2), "second")];
+        assert_eq!(expired(backups.clone(), 2), vec!["first"]);
+        assert_eq!(expired(backups.clone(), 5), Vec::<&str>::new());
+        assert_eq!(expired(backups, 0), Vec::<&str>::new());
+
+        let manifest = BackupManifest {
+            library: "Photos".to_string(),
+            created_at: at(18),
+            schema_version: "m20261018_180000_file_curation".to_string(),
+        };
+        assert_eq!(manifest.folder_name(), "Photos-20261018T093000.250");
+        assert_eq!(BackupConfig::default().interval(), None);
+    }
+}
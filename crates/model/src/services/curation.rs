@@ -0,0 +1,81 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// The highest rating, a rating of 0 means the file is not rated
+pub const MAX_RATING: u8 = 5;
+
+/// A color a file can be labeled with, in the order the labels are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl ColorLabel {
+    pub const ALL: [Self; 7] = [
+        Self::Red,
+        Self::Orange,
+        Self::Yellow,
+        Self::Green,
+        Self::Blue,
+        Self::Purple,
+        Self::Gray,
+    ];
+
+    /// Name stored in the `color_label` column of the file table
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Red => "red",
+            Self::Orange => "orange",
+            Self::Yellow => "yellow",
+            Self::Green => "green",
+            Self::Blue => "blue",
+            Self::Purple => "purple",
+            Self::Gray => "gray",
+        }
+    }
+
+    /// Read a label name, ignoring case
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        match Self::ALL
+            .into_iter()
+            .find(|label| label.as_str().eq_ignore_ascii_case(value))
+        {
+            Some(label) => Ok(label),
+            None => bail!("unknown color label {value}"),
+        }
+    }
+}
+
+/// Check that a rating is between 0 and [`MAX_RATING`]
+pub fn rating(value: u8) -> Result<u8> {
+    if value > MAX_RATING {
+        bail!("a rating is at most {MAX_RATING} stars, not {value}");
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_round_trip_through_their_names() -> Result<()> {
+        for label in ColorLabel::ALL {
+            assert_eq!(ColorLabel::parse(label.as_str())?, label);
+        }
+        assert_eq!(ColorLabel::parse(" Purple ")?, ColorLabel::Purple);
+        assert!(ColorLabel::parse("teal").is_err());
+        assert_eq!(rating(MAX_RATING)?, 5);
+        assert!(rating(6).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use crate::services::curation::ColorLabel;
+use anyhow::{Error, Result, bail};
+use chrono::NaiveDate;
+use entity::custom_fields;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The kind of values a custom field holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Number,
+    /// A day written as `YYYY-MM-DD`
+    Date,
+    /// One of the options of the field
+    Enum,
+    Bool,
+}
+
+impl FieldType {
+    /// Name stored in the `field_type` column of the custom field table
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Date => "date",
+            Self::Enum => "enum",
+            Self::Bool => "bool",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        Ok(match value {
+            "string" => Self::String,
+            "number" => Self::Number,
+            "date" => Self::Date,
+            "enum" => Self::Enum,
+            "bool" => Self::Bool,
+            _ => bail!("unknown field type {value}"),
+        })
+    }
+
+    /// The options a field of this type keeps, trimmed and without duplicates
+    ///
+    /// Only enum fields have options, and they need at least one.
+    pub fn options(self, options: &[String]) -> Result<Vec<String>> {
+        if self != Self::Enum {
+            return Ok(Vec::new());
+        }
+        let mut kept: Vec<String> = Vec::new();
+        for option in options.iter().map(|option| option.trim()) {
+            if !option.is_empty() && !kept.iter().any(|kept| kept.eq_ignore_ascii_case(option)) {
+                kept.push(option.to_string());
+            }
+        }
+        if kept.is_empty() {
+            bail!("an enum field needs at least one option");
+        }
+        Ok(kept)
+    }
+}
+
+/// A field that files of the library can have a value for, e.g. `due` of type date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDefinition {
+    pub id: i32,
+    pub name: String,
+    pub field_type: FieldType,
+    /// The allowed values of an enum field, empty for other types
+    pub options: Vec<String>,
+}
+
+impl FieldDefinition {
+    /// Read a value of this field from text, e.g. `2025-11-01` for a date field
+    pub fn parse_value(&self, text: &str) -> Result<FieldValue> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("{} needs a value", self.name);
+        }
+        Ok(match self.field_type {
+            FieldType::String => FieldValue::String(text.to_string()),
+            FieldType::Number => match text.parse::<f64>() {
+                Ok(number) if number.is_finite() => FieldValue::Number(number),
+                _ => bail!("{text} is not a number for {}", self.name),
+            },
+            FieldType::Date => match NaiveDate::parse_from_str(text, DATE_FORMAT) {
+                Ok(date) => FieldValue::Date(date),
+                Err(_) => bail!(
+                    "{text} is not a date of the form YYYY-MM-DD for {}",
+                    self.name
+                ),
+            },
+            FieldType::Enum => match self
+                .options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(text))
+            {
+                Some(option) => FieldValue::Enum(option.clone()),
+                None => bail!(
+                    "{text} is not one of the options of {}: {}",
+                    self.name,
+                    self.options.join(", ")
+                ),
+            },
+            FieldType::Bool => match text.to_lowercase().as_str() {
+                "true" | "yes" | "1" => FieldValue::Bool(true),
+                "false" | "no" | "0" => FieldValue::Bool(false),
+                _ => bail!("{text} is not true or false for {}", self.name),
+            },
+        })
+    }
+}
+
+impl TryFrom<custom_fields::Model> for FieldDefinition {
+    type Error = Error;
+
+    fn try_from(model: custom_fields::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: model.id,
+            name: model.name,
+            field_type: FieldType::parse(&model.field_type)?,
+            options: serde_json::from_str(&model.options)?,
+        })
+    }
+}
+
+/// The value a file has for a custom field
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldValue {
+    String(String),
+    Number(f64),
+    Date(NaiveDate),
+    Enum(String),
+    Bool(bool),
+}
+
+impl FieldValue {
+    /// The number to compare by, for number fields
+    #[must_use]
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Self::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+}
+
+/// The text stored for a value, dates are written so that they sort as text
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(text) | Self::Enum(text) => f.write_str(text),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Date(date) => write!(f, "{}", date.format(DATE_FORMAT)),
+            Self::Bool(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Custom fields and their values, in a form that can be edited outside the library
+///
+/// Values are written as text and files are identified by their path, so an export can be
+/// imported into another library with the same content. Files also carry their rating,
+/// favorite flag and color label.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FieldExport {
+    pub fields: Vec<ExportedField>,
+    pub files: Vec<ExportedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedField {
+    pub name: String,
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+impl From<&FieldDefinition> for ExportedField {
+    fn from(field: &FieldDefinition) -> Self {
+        Self {
+            name: field.name.clone(),
+            field_type: field.field_type,
+            options: field.options.clone(),
+        }
+    }
+}
+
+/// The values of a file keyed by field name, an empty value removes the value on import
+///
+/// The rating, favorite flag and color label are only written when they are set, and are left
+/// as they are on import when missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedFile {
+    pub path: String,
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favorite: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_label: Option<ColorLabel>,
+}
+
+/// What importing custom fields changed, and what it had to leave out
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldImportReport {
+    pub fields_created: usize,
+    pub values_set: usize,
+    pub values_cleared: usize,
+    /// Files whose rating, favorite flag or color label changed
+    pub files_curated: usize,
+    /// Paths of files that are not in the library
+    pub missing_files: Vec<String>,
+    /// Why values or fields were skipped
+    pub problems: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(field_type: FieldType, options: &[&str]) -> FieldDefinition {
+        FieldDefinition {
+            id: 1,
+            name: "field".to_string(),
+            field_type,
+            options: options.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_values_are_parsed_by_type() -> Result<()> {
+        assert_eq!(
+            field(FieldType::Number, &[]).parse_value(" 4.5 ")?,
+            FieldValue::Number(4.5)
+        );
+        assert!(field(FieldType::Number, &[]).parse_value("four").is_err());
+        let due = field(FieldType::Date, &[]).parse_value("2025-11-01")?;
+        assert_eq!(due.to_string(), "2025-11-01");
+        assert!(
+            field(FieldType::Date, &[])
+                .parse_value("01.11.2025")
+                .is_err()
+        );
+        assert_eq!(
+            field(FieldType::Bool, &[]).parse_value("Yes")?,
+            FieldValue::Bool(true)
+        );
+        assert_eq!(
+            field(FieldType::Enum, &["Approved", "Rejected"]).parse_value("approved")?,
+            FieldValue::Enum("Approved".to_string())
+        );
+        assert!(
+            field(FieldType::Enum, &["Approved"])
+                .parse_value("pending")
+                .is_err()
+        );
+        assert!(field(FieldType::String, &[]).parse_value("  ").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_only_enum_fields_keep_options() -> Result<()> {
+        let options = vec![
+            " a ".to_string(),
+            "A".to_string(),
+            String::new(),
+            "b".to_string(),
+        ];
+        assert_eq!(FieldType::Enum.options(&options)?, vec!["a", "b"]);
+        assert!(FieldType::String.options(&options)?.is_empty());
+        assert!(FieldType::Enum.options(&[]).is_err());
+        assert_eq!(FieldType::parse(FieldType::Bool.as_str())?, FieldType::Bool);
+        Ok(())
+    }
+}
@@ -1,27 +1,101 @@
+use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct Color {
     name: String,
     hex: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+impl Color {
+    /// A color given as `#rrggbb`, stored in lower case
+    pub fn new(name: impl Into<String>, hex: &str) -> Result<Self> {
+        let hex = hex.trim();
+        let valid = hex.strip_prefix('#').is_some_and(|digits| {
+            digits.len() == 6 && digits.chars().all(|digit| digit.is_ascii_hexdigit())
+        });
+        if !valid {
+            bail!("{hex} is not a color of the form #rrggbb");
+        }
+        Ok(Self {
+            name: name.into(),
+            hex: hex.to_lowercase(),
+        })
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn hex(&self) -> &str {
+        &self.hex
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub struct Icon {
     name: String,
     content: String,
     content_type: IconType,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+impl Icon {
+    pub fn new(
+        name: impl Into<String>,
+        content: impl Into<String>,
+        content_type: IconType,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            content: content.into(),
+            content_type,
+        }
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Icon name, image path or text, depending on the content type
+    #[must_use]
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    #[must_use]
+    pub fn content_type(&self) -> IconType {
+        self.content_type
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IconType {
+    #[default]
     Icon,
     Image,
     Text,
 }
 
-impl Default for IconType {
-    fn default() -> Self {
-        IconType::Icon
+impl IconType {
+    /// Name stored in the `type` column of the icon table
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Icon => "icon",
+            Self::Image => "image",
+            Self::Text => "text",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self> {
+        Ok(match value {
+            "icon" => Self::Icon,
+            "image" => Self::Image,
+            "text" => Self::Text,
+            _ => bail!("unknown icon type {value}"),
+        })
     }
 }
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use entity::files;
 use hash::hash::FileHash;
 use std::path::{Path, PathBuf};
@@ -12,6 +13,10 @@ pub struct FileSystemFile {
     pub identity_hash: String,
     pub file_type_name: String,
     pub file_system_id: Option<i32>,
+    /// Size in bytes
+    pub size: u64,
+    /// When the content was last modified on disk
+    pub modified_at: NaiveDateTime,
 }
 
 impl From<files::Model> for FileSystemFile {
@@ -31,6 +36,8 @@ impl From<files::Model> for FileSystemFile {
             identity_hash: value.identity_hash,
             file_type_name,
             file_system_id: Some(value.file_system_id),
+            size: u64::try_from(value.size).unwrap_or_default(),
+            modified_at: value.modified_at,
         }
     }
 }
@@ -43,6 +50,8 @@ impl FileSystemFile {
             .with_context(|| format!("path {} has no valid file name", path.display()))?
             .to_string();
         let file_hash = FileHash::hash(path).await?;
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("could not read the size of {}", path.display()))?;
 
         Ok(Self {
             id: None,
@@ -55,10 +64,21 @@ impl FileSystemFile {
                 |kind| kind.mime_type().to_string(),
             ),
             file_system_id: None,
+            size: metadata.len(),
+            modified_at: modified_time(&metadata),
         })
     }
 }
 
+/// Modification time of a file in UTC, or now if the file system does not record one
+#[must_use]
+pub fn modified_time(metadata: &std::fs::Metadata) -> NaiveDateTime {
+    metadata
+        .modified()
+        .map_or_else(|_| Utc::now(), DateTime::<Utc>::from)
+        .naive_utc()
+}
+
 #[derive(Debug, Clone)]
 pub struct PersistedFile {
     pub id: i32,
@@ -1,8 +1,21 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
 use hash::hash::FolderHash;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
+use crate::services::decorations::{Color, Icon};
+
+/// How a folder is presented in the library, it keeps its name on disk
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FolderDecoration {
+    pub color: Option<Color>,
+    pub icon: Option<Icon>,
+    /// Shown instead of the folder name
+    pub display_name: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct FileSystemFolder {
     pub id: Option<i32>,
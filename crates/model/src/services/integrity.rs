@@ -0,0 +1,163 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Something in the library database that no longer matches the disk or the other tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityProblem {
+    /// `PRAGMA integrity_check` found the database file damaged
+    Corruption {
+        message: String,
+    },
+    /// A folder of the library cannot be reached, nothing inside it is checked
+    MissingRoot {
+        folder_id: i32,
+        path: PathBuf,
+    },
+    MissingFolder {
+        folder_id: i32,
+        path: PathBuf,
+    },
+    MissingFile {
+        file_id: i32,
+        path: PathBuf,
+    },
+    /// `parent_folder_id` does not point to the folder that contains the folder
+    WrongParent {
+        folder_id: i32,
+        path: PathBuf,
+        parent_folder_id: Option<i32>,
+        expected: Option<i32>,
+    },
+    /// Neither a folder of the library nor inside a registered folder, a scan adds it back
+    DetachedFolder {
+        folder_id: i32,
+        path: PathBuf,
+    },
+    /// A `file_system_identifier` row no file or folder refers to
+    OrphanedIdentifier {
+        identifier_id: i32,
+    },
+    /// A thumbnail of a file that no longer exists
+    OrphanedThumbnail {
+        thumbnail_id: i32,
+        file_id: i32,
+    },
+    /// A thumbnail whose image is gone, it is generated again once removed
+    MissingThumbnailData {
+        thumbnail_id: i32,
+        file_id: i32,
+    },
+    /// A tag assignment whose file or tag no longer exists
+    OrphanedTagLink {
+        link_id: i32,
+        file_id: i32,
+        tag_id: i32,
+    },
+}
+
+impl IntegrityProblem {
+    /// Whether repairing the library fixes the problem
+    ///
+    /// A damaged database needs a backup, an unreachable library folder may only be unmounted.
+    #[must_use]
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Self::Corruption { .. } | Self::MissingRoot { .. })
+    }
+}
+
+impl fmt::Display for IntegrityProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Corruption { message } => write!(f, "The database is damaged: {message}"),
+            Self::MissingRoot { path, .. } => {
+                write!(f, "Library folder {} cannot be reached", path.display())
+            }
+            Self::MissingFolder { path, .. } => {
+                write!(f, "Folder {} no longer exists", path.display())
+            }
+            Self::MissingFile { path, .. } => write!(f, "File {} no longer exists", path.display()),
+            Self::WrongParent { path, .. } => {
+                write!(f, "Folder {} has the wrong parent folder", path.display())
+            }
+            Self::DetachedFolder { path, .. } => {
+                write!(
+                    f,
+                    "Folder {} is outside the library folders",
+                    path.display()
+                )
+            }
+            Self::OrphanedIdentifier { identifier_id } => {
+                write!(f, "File system identifier {identifier_id} is not used")
+            }
+            Self::OrphanedThumbnail { file_id, .. } => {
+                write!(f, "Thumbnail of removed file {file_id}")
+            }
+            Self::MissingThumbnailData { file_id, .. } => {
+                write!(f, "Thumbnail of file {file_id} has no image")
+            }
+            Self::OrphanedTagLink {
+                file_id, tag_id, ..
+            } => write!(
+                f,
+                "Tag {tag_id} is assigned to removed file {file_id} or was removed"
+            ),
+        }
+    }
+}
+
+/// Everything a verification of the library found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub files_checked: usize,
+    pub folders_checked: usize,
+    pub problems: Vec<IntegrityProblem>,
+    /// Whether the repairable problems were repaired
+    pub repaired: bool,
+}
+
+impl IntegrityReport {
+    /// Problems the library still has
+    pub fn remaining(&self) -> impl Iterator<Item = &IntegrityProblem> {
+        self.problems
+            .iter()
+            .filter(|problem| !self.repaired || !problem.is_repairable())
+    }
+
+    #[must_use]
+    pub fn is_healthy(&self) -> bool {
+        self.remaining().next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repairing_leaves_only_unrepairable_problems() {
+        let corruption = IntegrityProblem::Corruption {
+            message: "row 3 missing from index".to_string(),
+        };
+        let mut report = IntegrityReport {
+            files_checked: 1,
+            folders_checked: 1,
+            problems: vec![
+                IntegrityProblem::MissingFile {
+                    file_id: 1,
+                    path: PathBuf::from("/library/gone.txt"),
+                },
+                corruption.clone(),
+            ],
+            repaired: false,
+        };
+        assert_eq!(report.remaining().count(), 2);
+        report.repaired = true;
+        assert_eq!(report.remaining().collect::<Vec<_>>(), vec![&corruption]);
+        assert!(!report.is_healthy());
+        assert_eq!(
+            corruption.to_string(),
+            "The database is damaged: row 3 missing from index"
+        );
+        assert!(IntegrityReport::default().is_healthy());
+    }
+}
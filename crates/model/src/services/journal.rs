@@ -0,0 +1,187 @@
+use anyhow::{Error, Result, bail};
+use chrono::{NaiveDateTime, Utc};
+use entity::file_events;
+use sea_orm::{ActiveValue, Set};
+use std::fmt;
+use std::path::PathBuf;
+
+/// What happened to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JournalEventKind {
+    Created,
+    Modified,
+    Moved,
+    Deleted,
+    TagAssigned,
+    TagRemoved,
+}
+
+impl JournalEventKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Moved => "moved",
+            Self::Deleted => "deleted",
+            Self::TagAssigned => "tag_assigned",
+            Self::TagRemoved => "tag_removed",
+        }
+    }
+}
+
+impl fmt::Display for JournalEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for JournalEventKind {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "created" => Ok(Self::Created),
+            "modified" => Ok(Self::Modified),
+            "moved" => Ok(Self::Moved),
+            "deleted" => Ok(Self::Deleted),
+            "tag_assigned" => Ok(Self::TagAssigned),
+            "tag_removed" => Ok(Self::TagRemoved),
+            _ => bail!("unsupported journal event kind: {value}"),
+        }
+    }
+}
+
+/// Which part of the application noticed or made the change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JournalSource {
+    /// A full scan or a resync of a folder
+    Scanner,
+    /// A file system notification, poll or scheduled rescan
+    Watcher,
+    /// An action taken in the application
+    User,
+}
+
+impl JournalSource {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Scanner => "scanner",
+            Self::Watcher => "watcher",
+            Self::User => "user",
+        }
+    }
+}
+
+impl fmt::Display for JournalSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for JournalSource {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "scanner" => Ok(Self::Scanner),
+            "watcher" => Ok(Self::Watcher),
+            "user" => Ok(Self::User),
+            _ => bail!("unsupported journal source: {value}"),
+        }
+    }
+}
+
+/// One entry of the append-only change journal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub occurred_at: NaiveDateTime,
+    pub kind: JournalEventKind,
+    pub source: JournalSource,
+    pub file_id: Option<i32>,
+    pub old_path: Option<PathBuf>,
+    pub new_path: Option<PathBuf>,
+    pub old_hash: Option<String>,
+    pub new_hash: Option<String>,
+    /// Free-form context, such as the name of an assigned tag
+    pub detail: Option<String>,
+}
+
+impl JournalEntry {
+    #[must_use]
+    pub fn new(kind: JournalEventKind, source: JournalSource) -> Self {
+        Self {
+            occurred_at: Utc::now().naive_utc(),
+            kind,
+            source,
+            file_id: None,
+            old_path: None,
+            new_path: None,
+            old_hash: None,
+            new_hash: None,
+            detail: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_file(mut self, file_id: i32) -> Self {
+        self.file_id = Some(file_id);
+        self
+    }
+
+    #[must_use]
+    pub fn with_paths(mut self, old_path: Option<PathBuf>, new_path: Option<PathBuf>) -> Self {
+        self.old_path = old_path;
+        self.new_path = new_path;
+        self
+    }
+
+    #[must_use]
+    pub fn with_hashes(mut self, old_hash: Option<String>, new_hash: Option<String>) -> Self {
+        self.old_hash = old_hash;
+        self.new_hash = new_hash;
+        self
+    }
+
+    #[must_use]
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Converts to `SeaORM` `ActiveModel` for database insertion
+    #[must_use]
+    pub fn to_active_model(self) -> file_events::ActiveModel {
+        file_events::ActiveModel {
+            id: ActiveValue::NotSet,
+            occurred_at: Set(self.occurred_at),
+            kind: Set(self.kind.to_string()),
+            source: Set(self.source.to_string()),
+            file_id: Set(self.file_id),
+            old_path: Set(self.old_path.map(|path| path.to_string_lossy().to_string())),
+            new_path: Set(self.new_path.map(|path| path.to_string_lossy().to_string())),
+            old_hash: Set(self.old_hash),
+            new_hash: Set(self.new_hash),
+            detail: Set(self.detail),
+        }
+    }
+}
+
+impl TryFrom<file_events::Model> for JournalEntry {
+    type Error = Error;
+
+    fn try_from(model: file_events::Model) -> Result<Self, Self::Error> {
+        Ok(Self {
+            occurred_at: model.occurred_at,
+            kind: JournalEventKind::try_from(model.kind.as_str())?,
+            source: JournalSource::try_from(model.source.as_str())?,
+            file_id: model.file_id,
+            old_path: model.old_path.map(PathBuf::from),
+            new_path: model.new_path.map(PathBuf::from),
+            old_hash: model.old_hash,
+            new_hash: model.new_hash,
+            detail: model.detail,
+        })
+    }
+}
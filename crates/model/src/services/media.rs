@@ -0,0 +1,104 @@
+use chrono::{Local, NaiveDateTime};
+use entity::media_metadata;
+use sea_orm::{ActiveValue, Set};
+use serde::{Deserialize, Serialize};
+
+/// Photo metadata extracted from the EXIF data and header of an image
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MediaMetadata {
+    pub taken_at: Option<NaiveDateTime>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// EXIF orientation tag (1-8) as stored in the file
+    pub orientation: Option<u16>,
+}
+
+impl MediaMetadata {
+    /// Returns true if no metadata could be extracted
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Converts to `SeaORM` `ActiveModel` for database insertion
+    #[must_use]
+    pub fn to_active_model(self, file_id: i32) -> media_metadata::ActiveModel {
+        let now = Local::now().naive_local();
+
+        media_metadata::ActiveModel {
+            id: ActiveValue::NotSet,
+            file_id: Set(file_id),
+            taken_at: Set(self.taken_at),
+            camera_make: Set(self.camera_make),
+            camera_model: Set(self.camera_model),
+            latitude: Set(self.latitude),
+            longitude: Set(self.longitude),
+            width: Set(self.width.map(|width| width as i32)),
+            height: Set(self.height.map(|height| height as i32)),
+            orientation: Set(self.orientation.map(|orientation| orientation as i16)),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+    }
+}
+
+impl From<media_metadata::Model> for MediaMetadata {
+    fn from(model: media_metadata::Model) -> Self {
+        Self {
+            taken_at: model.taken_at,
+            camera_make: model.camera_make,
+            camera_model: model.camera_model,
+            latitude: model.latitude,
+            longitude: model.longitude,
+            width: model.width.map(|width| width as u32),
+            height: model.height.map(|height| height as u32),
+            orientation: model.orientation.map(|orientation| orientation as u16),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_media_metadata_seaorm_conversion() {
+        let taken_at = NaiveDate::from_ymd_opt(2024, 5, 3)
+            .and_then(|date| date.and_hms_opt(14, 30, 0))
+            .expect("valid date");
+        let metadata = MediaMetadata {
+            taken_at: Some(taken_at),
+            camera_model: Some("Pixel 8".to_string()),
+            width: Some(4000),
+            height: Some(3000),
+            orientation: Some(6),
+            ..MediaMetadata::default()
+        };
+        assert!(!metadata.is_empty());
+
+        let active_model = metadata.clone().to_active_model(7);
+        let model = media_metadata::Model {
+            id: 1,
+            file_id: active_model.file_id.unwrap(),
+            taken_at: active_model.taken_at.unwrap(),
+            camera_make: active_model.camera_make.unwrap(),
+            camera_model: active_model.camera_model.unwrap(),
+            latitude: active_model.latitude.unwrap(),
+            longitude: active_model.longitude.unwrap(),
+            width: active_model.width.unwrap(),
+            height: active_model.height.unwrap(),
+            orientation: active_model.orientation.unwrap(),
+            created_at: active_model.created_at.unwrap(),
+            updated_at: active_model.updated_at.unwrap(),
+        };
+
+        assert_eq!(model.file_id, 7);
+        assert_eq!(MediaMetadata::from(model), metadata);
+        assert!(MediaMetadata::default().is_empty());
+    }
+}
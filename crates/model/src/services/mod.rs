@@ -1,11 +1,21 @@
 use anyhow::{Result, bail};
 use std::path::{Path, PathBuf};
 
+pub mod auto_tag;
+pub mod backup;
+pub mod curation;
+pub mod custom_field;
 pub mod decorations;
 pub mod file;
 pub mod folder;
+pub mod integrity;
+pub mod journal;
+pub mod media;
 pub mod tag;
+pub mod tag_suggestion;
+pub mod tag_sync;
 pub mod thumbnail;
+pub mod watch;
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CanonPath {
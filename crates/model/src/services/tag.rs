@@ -1,5 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::decorations::{Color, Icon};
+
 #[derive(Debug)]
 pub struct Tag {
     pub id: i32,
     pub name: String,
 }
+
+/// How a tag is presented, besides its name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagDetails {
+    pub color: Option<Color>,
+    pub icon: Option<Icon>,
+    pub description: Option<String>,
+    /// Other names the tag is known by
+    pub aliases: Vec<String>,
+}
+
+/// An edge of the tag hierarchy, the sub tag is a kind of the super tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TagLink {
+    pub super_tag_id: i32,
+    pub sub_tag_id: i32,
+}
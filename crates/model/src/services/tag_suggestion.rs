@@ -0,0 +1,201 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+/// Confidence of a tag whose name or alias appears in the file name
+const FILE_NAME_CONFIDENCE: f64 = 0.8;
+
+/// Why a tag was suggested
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SuggestionSource {
+    /// Other files in the same folder have the tag
+    Folder,
+    /// Files with the same tags as this one often have the tag too
+    CoOccurrence,
+    /// The file name contains the name or an alias of the tag
+    FileName,
+}
+
+/// A tag that may fit a file, with a confidence between 0 and 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagSuggestion {
+    pub tag_id: i32,
+    pub name: String,
+    pub confidence: f64,
+    pub sources: Vec<SuggestionSource>,
+}
+
+/// A tag that can be suggested, with the names it is known by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestableTag {
+    pub tag_id: i32,
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+/// What the library knows about a file and its surroundings, read from the database
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SuggestionContext {
+    pub file_name: String,
+    /// Tags the file already has, they are never suggested
+    pub file_tags: HashSet<i32>,
+    /// Number of other files in the same folder
+    pub siblings: usize,
+    /// Number of other files in the same folder that have each tag
+    pub sibling_tags: HashMap<i32, usize>,
+    /// For each tag of the file, the number of other files that have it and how many of those
+    /// have each other tag
+    pub co_occurrences: HashMap<i32, (usize, HashMap<i32, usize>)>,
+    pub tags: Vec<SuggestableTag>,
+}
+
+impl SuggestionContext {
+    /// The most likely tags first, at most `limit` of them
+    ///
+    /// Each source gives a score that is damped for small samples, so a single sibling with a tag
+    /// is weak evidence. Scores of different sources are combined as independent evidence.
+    #[must_use]
+    pub fn suggest(&self, limit: usize) -> Vec<TagSuggestion> {
+        let mut scores: HashMap<i32, Vec<(SuggestionSource, f64)>> = HashMap::new();
+        for (&tag_id, &count) in &self.sibling_tags {
+            let score = damped_ratio(count, self.siblings);
+            scores
+                .entry(tag_id)
+                .or_default()
+                .push((SuggestionSource::Folder, score));
+        }
+        let mut co_occurrence: HashMap<i32, f64> = HashMap::new();
+        for (files_with_tag, together) in self.co_occurrences.values() {
+            for (&tag_id, &count) in together {
+                let score = damped_ratio(count, *files_with_tag);
+                let best = co_occurrence.entry(tag_id).or_default();
+                *best = best.max(score);
+            }
+        }
+        for (tag_id, score) in co_occurrence {
+            scores
+                .entry(tag_id)
+                .or_default()
+                .push((SuggestionSource::CoOccurrence, score));
+        }
+        let stem = Path::new(&self.file_name)
+            .file_stem()
+            .map_or(self.file_name.as_str(), |stem| {
+                stem.to_str().unwrap_or_default()
+            });
+        let file_tokens = tokens(stem);
+        for tag in &self.tags {
+            let named = std::iter::once(&tag.name)
+                .chain(&tag.aliases)
+                .any(|name| contains_sequence(&file_tokens, &tokens(name)));
+            if named {
+                scores
+                    .entry(tag.tag_id)
+                    .or_default()
+                    .push((SuggestionSource::FileName, FILE_NAME_CONFIDENCE));
+            }
+        }
+
+        let mut suggestions: Vec<TagSuggestion> = self
+            .tags
+            .iter()
+            .filter(|tag| !self.file_tags.contains(&tag.tag_id))
+            .filter_map(|tag| {
+                let scores = scores.remove(&tag.tag_id)?;
+                let doubt: f64 = scores.iter().map(|(_, score)| 1.0 - score).product();
+                let sources: BTreeSet<SuggestionSource> =
+                    scores.into_iter().map(|(source, _)| source).collect();
+                Some(TagSuggestion {
+                    tag_id: tag.tag_id,
+                    name: tag.name.clone(),
+                    confidence: 1.0 - doubt,
+                    sources: sources.into_iter().collect(),
+                })
+            })
+            .filter(|suggestion| suggestion.confidence > 0.0)
+            .collect();
+        suggestions.sort_by(|left, right| {
+            right
+                .confidence
+                .total_cmp(&left.confidence)
+                .then_with(|| left.name.cmp(&right.name))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// Share of `total` with a property, one extra sample keeps small samples from being certain
+fn damped_ratio(count: usize, total: usize) -> f64 {
+    let as_float = |value: usize| f64::from(u32::try_from(value).unwrap_or(u32::MAX));
+    as_float(count) / (as_float(total) + 1.0)
+}
+
+/// Lower case words and numbers of a name, e.g. `["invoice", "2024", "03"]` for `Invoice_2024-03`
+fn tokens(name: &str) -> Vec<String> {
+    name.split(|character: char| !character.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn contains_sequence(tokens: &[String], sequence: &[String]) -> bool {
+    !sequence.is_empty()
+        && tokens
+            .windows(sequence.len())
+            .any(|window| window == sequence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag_id: i32, name: &str, aliases: &[&str]) -> SuggestableTag {
+        SuggestableTag {
+            tag_id,
+            name: name.to_string(),
+            aliases: aliases.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn test_sources_are_combined_and_existing_tags_skipped() {
+        let context = SuggestionContext {
+            file_name: "Invoice_ACME-2024.pdf".to_string(),
+            file_tags: HashSet::from([1]),
+            siblings: 3,
+            sibling_tags: HashMap::from([(1, 3), (2, 3), (3, 1)]),
+            co_occurrences: HashMap::from([(1, (4, HashMap::from([(2, 4), (4, 1)])))]),
+            tags: vec![
+                tag(1, "Work", &[]),
+                tag(2, "Finance", &[]),
+                tag(3, "Holiday", &[]),
+                tag(4, "Office", &[]),
+                tag(5, "Bills", &["invoice"]),
+                tag(6, "Beach", &[]),
+            ],
+        };
+        let suggestions = context.suggest(10);
+        let names: Vec<&str> = suggestions
+            .iter()
+            .map(|suggestion| suggestion.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Finance", "Bills", "Holiday", "Office"]);
+        assert_eq!(
+            suggestions[0].sources,
+            vec![SuggestionSource::Folder, SuggestionSource::CoOccurrence]
+        );
+        // 3 of 3 siblings and 4 of 4 files with Work, each damped by one
+        assert!((suggestions[0].confidence - (1.0 - 0.25 * 0.2)).abs() < 1e-9);
+        assert_eq!(suggestions[1].sources, vec![SuggestionSource::FileName]);
+        assert_eq!(context.suggest(1).len(), 1);
+    }
+
+    #[test]
+    fn test_names_match_whole_words() {
+        assert_eq!(tokens("Invoice_2024-03"), ["invoice", "2024", "03"]);
+        let file = tokens("new-york-trip");
+        assert!(contains_sequence(&file, &tokens("New York")));
+        assert!(!contains_sequence(&file, &tokens("York New")));
+        assert!(!contains_sequence(&file, &tokens("rip")));
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Extended attribute holding the tags of a file as a comma separated list
+pub const XATTR_NAME: &str = "user.xdg.tags";
+
+/// Extension of the sidecar files that hold the XMP keywords of a file
+pub const SIDECAR_EXTENSION: &str = "xmp";
+
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+
+/// Keeps the tags of the library in the files themselves, so that other tools can read them
+///
+/// ```toml
+/// [tag_sync]
+/// xattr = true
+/// xmp = true
+/// conflict = "merge"
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagSyncConfig {
+    /// Tags in the `user.xdg.tags` extended attribute, only on Linux
+    pub xattr: bool,
+    /// Tags as `dc:subject` keywords of `.xmp` sidecar files next to the files
+    pub xmp: bool,
+    /// What happens when a scan finds tags in the files that differ from the library
+    pub conflict: TagSyncConflict,
+}
+
+impl TagSyncConfig {
+    #[must_use]
+    pub fn is_enabled(self) -> bool {
+        self.xattr || self.xmp
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagSyncConflict {
+    /// Files and library both end up with the tags of either, nothing is removed
+    #[default]
+    Merge,
+    /// The tags of the library replace the tags of the files
+    Library,
+    /// The tags of the files replace the tags of the library
+    Files,
+}
+
+/// The tags a file should have both in the library and in the file
+///
+/// `files` is `None` when the file has neither the attribute nor a sidecar, its library tags
+/// are kept then. Tags that only differ in case are the same tag, spelled as in the library.
+#[must_use]
+pub fn resolve(
+    conflict: TagSyncConflict,
+    library: &[String],
+    files: Option<&[String]>,
+) -> Vec<String> {
+    let Some(files) = files else {
+        return library.to_vec();
+    };
+    let spelled = |tag: &String| {
+        library
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(tag))
+            .unwrap_or(tag)
+            .clone()
+    };
+    let mut tags = match conflict {
+        TagSyncConflict::Library => return library.to_vec(),
+        TagSyncConflict::Files => Vec::new(),
+        TagSyncConflict::Merge => library.to_vec(),
+    };
+    for tag in files {
+        if !tags.iter().any(|known| known.eq_ignore_ascii_case(tag)) {
+            tags.push(spelled(tag));
+        }
+    }
+    tags
+}
+
+/// Read the value of the `user.xdg.tags` attribute
+#[must_use]
+pub fn xattr_tags(value: &str) -> Vec<String> {
+    unique(value.split(','))
+}
+
+/// Write tags as the value of the `user.xdg.tags` attribute
+///
+/// The attribute has no way to escape commas, so a tag containing one reads back as two.
+#[must_use]
+pub fn xattr_value(tags: &[String]) -> String {
+    tags.join(",")
+}
+
+/// The `dc:subject` keywords of an XMP packet, in their order
+#[must_use]
+pub fn xmp_keywords(xmp: &str) -> Vec<String> {
+    let Some((_, subject)) = xmp.split_once("<dc:subject") else {
+        return Vec::new();
+    };
+    let Some((open, subject)) = subject.split_once('>') else {
+        return Vec::new();
+    };
+    if open.ends_with('/') {
+        return Vec::new();
+    }
+    let subject = subject.split("</dc:subject>").next().unwrap_or_default();
+    unique(
+        subject
+            .split("<rdf:li")
+            .skip(1)
+            .filter_map(|item| item.split_once('>'))
+            .filter(|(attributes, _)| !attributes.ends_with('/'))
+            .filter_map(|(_, item)| item.split("</rdf:li>").next())
+            .map(unescape)
+            .collect::<Vec<_>>()
+            .iter()
+            .map(String::as_str),
+    )
+}
+
+/// An XMP packet with the keywords as its `dc:subject`, keeping everything else of `existing`
+///
+/// Without an existing packet a minimal one is written. Fails if `existing` is not XMP.
+pub fn xmp_with_keywords(existing: Option<&str>, keywords: &[String]) -> Result<String> {
+    let subject = subject_element(keywords);
+    let Some(existing) = existing else {
+        return Ok(format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+             <rdf:Description rdf:about=\"\" xmlns:dc=\"{DC_NAMESPACE}\">\n{subject}  \
+             </rdf:Description>\n \
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n\
+             <?xpacket end=\"w\"?>\n"
+        ));
+    };
+    if let Some((before, after)) = existing.split_once("<dc:subject") {
+        let rest = match after.split_once('>') {
+            Some((open, rest)) if open.ends_with('/') => rest,
+            _ => match after.split_once("</dc:subject>") {
+                Some((_, rest)) => rest,
+                None => bail!("the dc:subject of the sidecar is not closed"),
+            },
+        };
+        // The replaced element brings its own indentation and line break
+        let before = before.trim_end_matches([' ', '\t']);
+        let rest = rest.strip_prefix('\n').unwrap_or(rest);
+        return Ok(format!("{before}{subject}{rest}"));
+    }
+    if subject.is_empty() {
+        return Ok(existing.to_string());
+    }
+    if let Some((before, after)) = existing.split_once("</rdf:Description>") {
+        let before = if before.contains("xmlns:dc=") {
+            before.to_string()
+        } else {
+            before.replacen(
+                "<rdf:Description",
+                &format!("<rdf:Description xmlns:dc=\"{DC_NAMESPACE}\""),
+                1,
+            )
+        };
+        return Ok(format!("{before}{subject}  </rdf:Description>{after}"));
+    }
+    if let Some((before, after)) = existing.split_once("</rdf:RDF>") {
+        return Ok(format!(
+            "{before}  <rdf:Description rdf:about=\"\" xmlns:dc=\"{DC_NAMESPACE}\">\n{subject}  \
+             </rdf:Description>\n </rdf:RDF>{after}"
+        ));
+    }
+    bail!("the sidecar is not an XMP packet")
+}
+
+/// The `dc:subject` element on lines of its own, empty without keywords
+fn subject_element(keywords: &[String]) -> String {
+    if keywords.is_empty() {
+        return String::new();
+    }
+    let element = keywords.iter().fold(
+        String::from("   <dc:subject>\n    <rdf:Bag>\n"),
+        |mut element, keyword| {
+            // Writing into a `String` cannot fail
+            let _ = writeln!(element, "     <rdf:li>{}</rdf:li>", escape(keyword));
+            element
+        },
+    );
+    element + "    </rdf:Bag>\n   </dc:subject>\n"
+}
+
+/// Trimmed, non-empty tags without duplicates, in their order
+fn unique<'a>(tags: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for tag in tags.into_iter().map(str::trim) {
+        if !tag.is_empty() && !unique.iter().any(|known| known == tag) {
+            unique.push(tag.to_string());
+        }
+    }
+    unique
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_conflicts_are_resolved_by_policy() {
+        let library = tags(&["Beach", "2024"]);
+        let files = tags(&["beach", "family"]);
+        assert_eq!(
+            resolve(TagSyncConflict::Merge, &library, Some(&files)),
+            tags(&["Beach", "2024", "family"])
+        );
+        assert_eq!(
+            resolve(TagSyncConflict::Files, &library, Some(&files)),
+            tags(&["Beach", "family"])
+        );
+        assert_eq!(
+            resolve(TagSyncConflict::Library, &library, Some(&files)),
+            library
+        );
+        assert_eq!(resolve(TagSyncConflict::Files, &library, None), library);
+        assert_eq!(xattr_tags(" a,b,,a "), tags(&["a", "b"]));
+        assert_eq!(xattr_value(&tags(&["a", "b"])), "a,b");
+    }
+
+    #[test]
+    fn test_keywords_round_trip_through_a_new_sidecar() -> Result<()> {
+        let keywords = tags(&["Tom & Jerry", "<draft>"]);
+        let xmp = xmp_with_keywords(None, &keywords)?;
+        assert!(xmp.contains("<rdf:li>Tom &amp; Jerry</rdf:li>"));
+        assert_eq!(xmp_keywords(&xmp), keywords);
+        let cleared = xmp_with_keywords(Some(&xmp), &[])?;
+        assert!(!cleared.contains("dc:subject"));
+        assert!(cleared.contains("</rdf:Description>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keywords_are_written_into_existing_sidecars() -> Result<()> {
+        let lightroom = "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+            <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n  \
+            <rdf:Description rdf:about=\"\" xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">\n   \
+            <xmp:Rating>4</xmp:Rating>\n  \
+            </rdf:Description>\n \
+            </rdf:RDF>\n</x:xmpmeta>\n";
+        let updated = xmp_with_keywords(Some(lightroom), &tags(&["sunset"]))?;
+        assert!(updated.contains("<xmp:Rating>4</xmp:Rating>"));
+        assert!(updated.contains(&format!("xmlns:dc=\"{DC_NAMESPACE}\"")));
+        assert_eq!(xmp_keywords(&updated), tags(&["sunset"]));
+        let replaced = xmp_with_keywords(Some(&updated), &tags(&["dusk", "sea"]))?;
+        assert_eq!(xmp_keywords(&replaced), tags(&["dusk", "sea"]));
+        assert_eq!(replaced.matches("<dc:subject>").count(), 1);
+
+        let attributes_only = "<x:xmpmeta><rdf:RDF><rdf:Description rdf:about=\"\" \
+            xmp:Rating=\"2\"/></rdf:RDF></x:xmpmeta>";
+        let added = xmp_with_keywords(Some(attributes_only), &tags(&["forest"]))?;
+        assert_eq!(xmp_keywords(&added), tags(&["forest"]));
+        assert!(added.contains("xmp:Rating=\"2\""));
+        assert!(xmp_with_keywords(Some("not xmp"), &tags(&["a"])).is_err());
+        Ok(())
+    }
+}
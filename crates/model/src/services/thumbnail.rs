@@ -70,6 +70,44 @@ impl TryFrom<String> for ThumbnailSize {
     }
 }
 
+/// Where the image data of a thumbnail is kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbnailStorageKind {
+    /// Image data is stored in the `thumbnails.data` column
+    #[default]
+    Database,
+    /// Image data is stored in the content-addressed cache directory of the library
+    Directory,
+}
+
+impl ThumbnailStorageKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Database => "database",
+            Self::Directory => "directory",
+        }
+    }
+}
+
+impl fmt::Display for ThumbnailStorageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<&str> for ThumbnailStorageKind {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "database" => Ok(Self::Database),
+            "directory" => Ok(Self::Directory),
+            _ => bail!("unsupported thumbnail storage: {value}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Thumbnail {
     pub size: ThumbnailSize,
@@ -126,6 +164,8 @@ impl Thumbnail {
             file_size: Set(self.file_size as i32),
             created_at: Set(now),
             updated_at: Set(now),
+            content_hash: Set(None),
+            storage: Set(ThumbnailStorageKind::Database.to_string()),
         }
     }
 
@@ -251,6 +291,8 @@ mod tests {
             file_size: file_size as i32,
             created_at: now,
             updated_at: now,
+            content_hash: None,
+            storage: "database".to_string(),
         };
 
         // Test from_model
@@ -292,6 +334,20 @@ mod tests {
         assert_eq!(size_str, "medium");
     }
 
+    #[test]
+    fn test_thumbnail_storage_kind_conversions() {
+        assert_eq!(
+            ThumbnailStorageKind::try_from("directory").unwrap(),
+            ThumbnailStorageKind::Directory
+        );
+        assert_eq!(ThumbnailStorageKind::Database.to_string(), "database");
+        assert_eq!(
+            ThumbnailStorageKind::default(),
+            ThumbnailStorageKind::Database
+        );
+        assert!(ThumbnailStorageKind::try_from("s3").is_err());
+    }
+
     #[test]
     fn test_thumbnail_is_image() {
         let png_thumbnail = Thumbnail::with_image_data(ThumbnailSize::Small, vec![1, 2, 3]);
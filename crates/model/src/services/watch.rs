@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+/// How changes below a content folder are detected
+///
+/// Native notifications do not fire for changes made through network shares (NFS, SMB) or many
+/// FUSE filesystems, so those folders are polled or rescanned on a schedule instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum WatchMode {
+    /// Operating system notifications (inotify, `FSEvents`, `ReadDirectoryChangesW`)
+    #[default]
+    Native,
+    /// Compare the folder tree with its previous state every `interval_secs`
+    Poll {
+        #[serde(default = "WatchMode::default_poll_secs")]
+        interval_secs: u64,
+    },
+    /// No watching; synchronize the folder with the database every `interval_secs`
+    Rescan {
+        #[serde(default = "WatchMode::default_rescan_secs")]
+        interval_secs: u64,
+    },
+}
+
+impl WatchMode {
+    const fn default_poll_secs() -> u64 {
+        30
+    }
+
+    const fn default_rescan_secs() -> u64 {
+        15 * 60
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::Poll { .. } => "poll",
+            Self::Rescan { .. } => "rescan",
+        }
+    }
+
+    /// Time between polls or rescans; native watching has none
+    #[must_use]
+    pub fn interval(self) -> Option<Duration> {
+        match self {
+            Self::Native => None,
+            // A zero interval would spin, so wait at least a second
+            Self::Poll { interval_secs } | Self::Rescan { interval_secs } => {
+                Some(Duration::from_secs(interval_secs.max(1)))
+            }
+        }
+    }
+}
+
+impl fmt::Display for WatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.interval() {
+            Some(interval) => write!(f, "{} every {}s", self.as_str(), interval.as_secs()),
+            None => f.write_str(self.as_str()),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail, ensure};
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, Statement};
+
+use crate::config::DatabaseSettings;
+use crate::manager::DatabaseManager;
+
+/// Repository for consistent copies of a library database
+#[derive(Debug)]
+pub struct BackupOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl BackupOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Write a copy of the database to `destination`, which must not exist yet
+    ///
+    /// `VACUUM INTO` reads the database in a single transaction, so the copy is consistent
+    /// while the library keeps writing to its WAL.
+    pub async fn copy_to(&self, destination: &Path) -> Result<()> {
+        ensure!(
+            !destination.exists(),
+            "backup database {} already exists",
+            destination.display()
+        );
+        let db = self.database_manager.get_connection();
+        db.execute(Statement::from_sql_and_values(
+            DatabaseBackend::Sqlite,
+            "VACUUM INTO ?",
+            [destination.to_string_lossy().to_string().into()],
+        ))
+        .await
+        .with_context(|| format!("Failed to copy the database to {}", destination.display()))?;
+        Ok(())
+    }
+
+    /// Name of the last migration applied to the database
+    pub async fn schema_version(&self) -> Result<String> {
+        schema_version(self.database_manager.get_connection().as_ref()).await
+    }
+
+    /// Check a copy of a database without changing it, returns its schema version
+    pub async fn inspect(database: &Path) -> Result<String> {
+        ensure!(
+            database.is_file(),
+            "database {} does not exist",
+            database.display()
+        );
+        let settings = DatabaseSettings {
+            con_string: format!("sqlite://{}?mode=ro", database.display()),
+            timeout: 30_000,
+            ..DatabaseSettings::default()
+        };
+        let database_manager = DatabaseManager::new(settings).await?;
+        let db = database_manager.get_connection();
+        let rows = db
+            .query_all(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                "PRAGMA integrity_check".to_string(),
+            ))
+            .await
+            .context("Failed to check the backup database")?;
+        for row in rows {
+            let message: String = row
+                .try_get_by_index(0)
+                .context("Failed to read the integrity check of the backup database")?;
+            if message != "ok" {
+                bail!("the backup database is damaged: {message}");
+            }
+        }
+        let version = schema_version(db.as_ref()).await;
+        database_manager.close().await?;
+        version
+    }
+}
+
+async fn schema_version(db: &DatabaseConnection) -> Result<String> {
+    let row = db
+        .query_one(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            "SELECT version FROM seaql_migrations ORDER BY version DESC LIMIT 1".to_string(),
+        ))
+        .await
+        .context("Failed to read the schema version of the database")?
+        .context("the database has no schema")?;
+    row.try_get_by_index(0)
+        .context("Failed to read the schema version of the database")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn copies_are_consistent_and_keep_their_schema() -> Result<()> {
+        let directory = TempDir::new()?;
+        let settings = DatabaseSettings::new(
+            format!(
+                "sqlite://{}?mode=rwc",
+                directory.path().join("db.sqlite").display()
+            ),
+            1_000,
+            SqliteJournalMode::Wal,
+            SqliteSynchronous::Normal,
+        );
+        let database_manager = Arc::new(DatabaseManager::new(settings).await?);
+        Migrator::up(database_manager.get_connection().as_ref(), None).await?;
+        let backups = BackupOperations::new(Arc::clone(&database_manager));
+
+        let copy = directory.path().join("copy's.sqlite");
+        backups.copy_to(&copy).await?;
+        assert!(backups.copy_to(&copy).await.is_err());
+        let version = backups.schema_version().await?;
+        assert_eq!(BackupOperations::inspect(&copy).await?, version);
+        assert!(version.starts_with('m'));
+
+        std::fs::write(directory.path().join("broken.sqlite"), "not a database")?;
+        assert!(
+            BackupOperations::inspect(&directory.path().join("broken.sqlite"))
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
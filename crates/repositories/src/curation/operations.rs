@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use sea_orm::sea_query::SimpleExpr;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
+
+use entity::{files, prelude::*};
+use model::services::curation::{self, ColorLabel};
+
+use crate::manager::DatabaseManager;
+use crate::tags::operations::ID_CHUNK_SIZE;
+
+/// Repository for the ratings, favorites and color labels of files
+#[derive(Debug)]
+pub struct CurationOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl CurationOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Rate files from 0, not rated, to [`curation::MAX_RATING`], returns the files that changed
+    pub async fn set_rating(&self, file_ids: &[i32], rating: u8) -> Result<Vec<i32>> {
+        let rating = i16::from(curation::rating(rating)?);
+        self.update(
+            file_ids,
+            files::Column::Rating,
+            Condition::all().add(files::Column::Rating.ne(rating)),
+            rating.into(),
+        )
+        .await
+    }
+
+    /// Mark or unmark files as favorites, returns the files that changed
+    pub async fn set_favorite(&self, file_ids: &[i32], favorite: bool) -> Result<Vec<i32>> {
+        self.update(
+            file_ids,
+            files::Column::Favorite,
+            Condition::all().add(files::Column::Favorite.ne(favorite)),
+            favorite.into(),
+        )
+        .await
+    }
+
+    /// Label files with a color or remove their label, returns the files that changed
+    pub async fn set_color_label(
+        &self,
+        file_ids: &[i32],
+        label: Option<ColorLabel>,
+    ) -> Result<Vec<i32>> {
+        let differs = match label {
+            Some(label) => Condition::any()
+                .add(files::Column::ColorLabel.ne(label.as_str()))
+                .add(files::Column::ColorLabel.is_null()),
+            None => Condition::all().add(files::Column::ColorLabel.is_not_null()),
+        };
+        self.update(
+            file_ids,
+            files::Column::ColorLabel,
+            differs,
+            label.map(ColorLabel::as_str).into(),
+        )
+        .await
+    }
+
+    /// Set a column of the files for which `differs` holds
+    async fn update(
+        &self,
+        file_ids: &[i32],
+        column: files::Column,
+        differs: Condition,
+        value: SimpleExpr,
+    ) -> Result<Vec<i32>> {
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        let mut changed = Vec::new();
+        for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+            let ids = changing(&transaction, chunk, differs.clone()).await?;
+            if ids.is_empty() {
+                continue;
+            }
+            Files::update_many()
+                .col_expr(column, value.clone())
+                .filter(files::Column::Id.is_in(ids.clone()))
+                .exec(&transaction)
+                .await
+                .context("Failed to update files")?;
+            changed.extend(ids);
+        }
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        changed.sort_unstable();
+        Ok(changed)
+    }
+}
+
+/// The files among the given ones for which `differs` holds
+async fn changing<C>(connection: &C, file_ids: &[i32], differs: Condition) -> Result<Vec<i32>>
+where
+    C: ConnectionTrait,
+{
+    Files::find()
+        .select_only()
+        .column(files::Column::Id)
+        .filter(files::Column::Id.is_in(file_ids.to_vec()))
+        .filter(differs)
+        .into_tuple()
+        .all(connection)
+        .await
+        .context("Failed to query files")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::operations::FieldOperations;
+    use crate::test_support::{insert_files, migrated_database};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn only_files_that_differ_are_changed() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 3).await?;
+        let curation = CurationOperations::new(Arc::clone(&database_manager));
+
+        assert_eq!(curation.set_rating(&file_ids[..2], 4).await?, file_ids[..2]);
+        assert_eq!(curation.set_rating(&file_ids, 4).await?, file_ids[2..]);
+        assert!(curation.set_rating(&file_ids, 6).await.is_err());
+        assert_eq!(
+            curation.set_favorite(&file_ids[..1], true).await?,
+            file_ids[..1]
+        );
+        assert!(
+            curation
+                .set_favorite(&file_ids[1..], false)
+                .await?
+                .is_empty()
+        );
+        let blue = Some(ColorLabel::Blue);
+        assert_eq!(
+            curation.set_color_label(&file_ids[..2], blue).await?,
+            file_ids[..2]
+        );
+        assert_eq!(
+            curation.set_color_label(&file_ids, None).await?,
+            file_ids[..2]
+        );
+        assert!(curation.set_color_label(&file_ids, None).await?.is_empty());
+
+        let first = Files::find_by_id(file_ids[0])
+            .one(database_manager.get_connection().as_ref())
+            .await?
+            .context("file exists")?;
+        assert_eq!(
+            (first.rating, first.favorite, first.color_label),
+            (4, true, None)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn curation_is_exported_and_imported_with_the_fields() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 3).await?;
+        let curation = CurationOperations::new(Arc::clone(&database_manager));
+        curation.set_rating(&file_ids[..1], 5).await?;
+        curation
+            .set_color_label(&file_ids[1..2], Some(ColorLabel::Red))
+            .await?;
+
+        let fields = FieldOperations::new(Arc::clone(&database_manager));
+        let mut export = fields.export().await?;
+        assert_eq!(export.files.len(), 2);
+        assert_eq!(export.files[0].rating, Some(5));
+        assert_eq!(export.files[0].color_label, None);
+        assert_eq!(export.files[1].color_label, Some(ColorLabel::Red));
+        assert_eq!(export.files[1].rating, None);
+
+        export.files[0].rating = Some(2);
+        export.files[1].favorite = Some(true);
+        export.files[1].rating = Some(9);
+        let report = fields.import(&export).await?;
+        assert_eq!(report.files_curated, 2);
+        assert_eq!(report.problems.len(), 1);
+        assert_eq!(fields.import(&export).await?.files_curated, 0);
+        let second = Files::find_by_id(file_ids[1])
+            .one(database_manager.get_connection().as_ref())
+            .await?
+            .context("file exists")?;
+        assert_eq!(second.rating, 0);
+        assert!(second.favorite);
+        assert_eq!(second.color_label.as_deref(), Some("red"));
+        Ok(())
+    }
+}
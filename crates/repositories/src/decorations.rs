@@ -0,0 +1,104 @@
+//! Colors and icons shared by tags and folders, each stored once and referenced by id
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Result};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+
+use entity::{color, icon, prelude::*};
+use model::services::decorations::{self, IconType};
+
+/// The colors with the given ids
+pub(crate) async fn colors<C>(
+    connection: &C,
+    color_ids: HashSet<i32>,
+) -> Result<HashMap<i32, decorations::Color>>
+where
+    C: ConnectionTrait,
+{
+    Color::find()
+        .filter(color::Column::Id.is_in(color_ids))
+        .all(connection)
+        .await
+        .context("Failed to query colors")?
+        .into_iter()
+        .map(|color| Ok((color.id, decorations::Color::new(color.name, &color.hex)?)))
+        .collect()
+}
+
+/// The icons with the given ids
+pub(crate) async fn icons<C>(
+    connection: &C,
+    icon_ids: HashSet<i32>,
+) -> Result<HashMap<i32, decorations::Icon>>
+where
+    C: ConnectionTrait,
+{
+    Icon::find()
+        .filter(icon::Column::Id.is_in(icon_ids))
+        .all(connection)
+        .await
+        .context("Failed to query icons")?
+        .into_iter()
+        .map(|icon| {
+            let content_type = IconType::parse(&icon.r#type)?;
+            Ok((
+                icon.id,
+                decorations::Icon::new(icon.name, icon.content, content_type),
+            ))
+        })
+        .collect()
+}
+
+/// The id of a color, stored if no equal color is stored yet
+pub(crate) async fn color_id<C>(connection: &C, color: &decorations::Color) -> Result<i32>
+where
+    C: ConnectionTrait,
+{
+    let existing = Color::find()
+        .filter(color::Column::Name.eq(color.name()))
+        .filter(color::Column::Hex.eq(color.hex()))
+        .one(connection)
+        .await
+        .context("Failed to query colors")?;
+    if let Some(existing) = existing {
+        return Ok(existing.id);
+    }
+    let inserted = color::ActiveModel {
+        id: NotSet,
+        name: Set(color.name().to_string()),
+        hex: Set(color.hex().to_string()),
+    }
+    .insert(connection)
+    .await
+    .context("Failed to store color")?;
+    Ok(inserted.id)
+}
+
+/// The id of an icon, stored if no equal icon is stored yet
+pub(crate) async fn icon_id<C>(connection: &C, icon: &decorations::Icon) -> Result<i32>
+where
+    C: ConnectionTrait,
+{
+    let existing = Icon::find()
+        .filter(icon::Column::Name.eq(icon.name()))
+        .filter(icon::Column::Type.eq(icon.content_type().as_str()))
+        .filter(icon::Column::Content.eq(icon.content()))
+        .one(connection)
+        .await
+        .context("Failed to query icons")?;
+    if let Some(existing) = existing {
+        return Ok(existing.id);
+    }
+    let inserted = icon::ActiveModel {
+        id: NotSet,
+        name: Set(icon.name().to_string()),
+        r#type: Set(icon.content_type().as_str().to_string()),
+        content: Set(icon.content().to_string()),
+    }
+    .insert(connection)
+    .await
+    .context("Failed to store icon")?;
+    Ok(inserted.id)
+}
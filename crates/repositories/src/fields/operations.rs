@@ -0,0 +1,533 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+
+use entity::{custom_fields, file_field_values, files, prelude::*};
+use model::services::curation::{self, ColorLabel};
+use model::services::custom_field::{
+    ExportedField, ExportedFile, FieldDefinition, FieldExport, FieldImportReport, FieldType,
+    FieldValue,
+};
+
+use crate::manager::DatabaseManager;
+
+/// Repository for the custom fields of a library and the values files have for them
+#[derive(Debug)]
+pub struct FieldOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl FieldOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Every field, ordered by name
+    pub async fn list(&self) -> Result<Vec<FieldDefinition>> {
+        let db = self.database_manager.get_connection();
+        definitions(db.as_ref()).await
+    }
+
+    pub async fn get(&self, field_id: i32) -> Result<Option<FieldDefinition>> {
+        let db = self.database_manager.get_connection();
+        CustomFields::find_by_id(field_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query custom field")?
+            .map(FieldDefinition::try_from)
+            .transpose()
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<FieldDefinition>> {
+        let db = self.database_manager.get_connection();
+        CustomFields::find()
+            .filter(custom_fields::Column::Name.eq(name))
+            .one(db.as_ref())
+            .await
+            .context("Failed to query custom field")?
+            .map(FieldDefinition::try_from)
+            .transpose()
+    }
+
+    /// Define a field, see [`FieldType::options`] for the options that are kept
+    pub async fn create(
+        &self,
+        name: &str,
+        field_type: FieldType,
+        options: &[String],
+    ) -> Result<FieldDefinition> {
+        let db = self.database_manager.get_connection();
+        insert_field(db.as_ref(), name, field_type, options).await
+    }
+
+    /// Rename a field and replace its options, its type stays the same
+    ///
+    /// Values that are no longer one of the options of an enum field are kept.
+    pub async fn update(
+        &self,
+        field_id: i32,
+        name: &str,
+        options: &[String],
+    ) -> Result<FieldDefinition> {
+        let db = self.database_manager.get_connection();
+        let Some(field) = CustomFields::find_by_id(field_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query custom field")?
+        else {
+            bail!("custom field {field_id} does not exist");
+        };
+        let options = FieldType::parse(&field.field_type)?.options(options)?;
+        let mut field = field.into_active_model();
+        field.name = Set(name.to_string());
+        field.options = Set(serde_json::to_string(&options)?);
+        field.updated_at = Set(Utc::now().naive_utc());
+        field
+            .update(db.as_ref())
+            .await
+            .context("Failed to update custom field")?
+            .try_into()
+    }
+
+    /// Returns whether the field existed, the values of the field are deleted with it
+    pub async fn delete(&self, field_id: i32) -> Result<bool> {
+        let db = self.database_manager.get_connection();
+        let result = CustomFields::delete_by_id(field_id)
+            .exec(db.as_ref())
+            .await
+            .context("Failed to delete custom field")?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// The fields a file has a value for, ordered by field name
+    pub async fn values_of(&self, file_id: i32) -> Result<Vec<(FieldDefinition, FieldValue)>> {
+        let db = self.database_manager.get_connection();
+        let mut values: HashMap<i32, String> = FileFieldValues::find()
+            .select_only()
+            .column(file_field_values::Column::FieldId)
+            .column(file_field_values::Column::Value)
+            .filter(file_field_values::Column::FileId.eq(file_id))
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query custom field values")?
+            .into_iter()
+            .collect();
+        Ok(definitions(db.as_ref())
+            .await?
+            .into_iter()
+            .filter_map(|field| {
+                let value = values.remove(&field.id)?;
+                // Keep values that no longer fit the field, e.g. after an option was removed
+                let value = field
+                    .parse_value(&value)
+                    .unwrap_or(FieldValue::String(value));
+                Some((field, value))
+            })
+            .collect())
+    }
+
+    /// Set the value of a field for files, or remove it for `None`
+    ///
+    /// Returns the files whose value changed.
+    pub async fn set_values(
+        &self,
+        file_ids: &[i32],
+        field_id: i32,
+        value: Option<&FieldValue>,
+    ) -> Result<Vec<i32>> {
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        let mut changed = Vec::new();
+        for &file_id in file_ids {
+            if store_value(&transaction, file_id, field_id, value).await? {
+                changed.push(file_id);
+            }
+        }
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(changed)
+    }
+
+    /// Every field and every value, with the files ordered by path
+    ///
+    /// Files also carry their rating, favorite flag and color label when they have one.
+    pub async fn export(&self) -> Result<FieldExport> {
+        let db = self.database_manager.get_connection();
+        let fields = definitions(db.as_ref()).await?;
+        let names: HashMap<i32, &str> = fields
+            .iter()
+            .map(|field| (field.id, field.name.as_str()))
+            .collect();
+        let rows: Vec<(String, i32, String)> = FileFieldValues::find()
+            .select_only()
+            .column(files::Column::Path)
+            .column(file_field_values::Column::FieldId)
+            .column(file_field_values::Column::Value)
+            .inner_join(Files)
+            .order_by_asc(files::Column::Path)
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query custom field values")?;
+
+        let curated = Files::find()
+            .filter(
+                Condition::any()
+                    .add(files::Column::Rating.gt(0))
+                    .add(files::Column::Favorite.eq(true))
+                    .add(files::Column::ColorLabel.is_not_null()),
+            )
+            .all(db.as_ref())
+            .await
+            .context("Failed to query curated files")?;
+
+        let mut files: BTreeMap<String, ExportedFile> = BTreeMap::new();
+        for (path, field_id, value) in rows {
+            let Some(name) = names.get(&field_id) else {
+                continue;
+            };
+            exported_file(&mut files, path)
+                .values
+                .insert((*name).to_string(), value);
+        }
+        for file in curated {
+            let exported = exported_file(&mut files, file.path);
+            exported.rating = u8::try_from(file.rating).ok().filter(|rating| *rating > 0);
+            exported.favorite = file.favorite.then_some(true);
+            exported.color_label = file
+                .color_label
+                .and_then(|label| ColorLabel::parse(&label).ok());
+        }
+        Ok(FieldExport {
+            fields: fields.iter().map(ExportedField::from).collect(),
+            files: files.into_values().collect(),
+        })
+    }
+
+    /// Create the exported fields that do not exist yet and set the exported values
+    ///
+    /// Fields are matched by name and files by path. What does not fit the library, such as a
+    /// value that is not a number for a number field, is skipped and reported.
+    pub async fn import(&self, export: &FieldExport) -> Result<FieldImportReport> {
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        let mut report = FieldImportReport::default();
+        let mut fields: HashMap<String, FieldDefinition> = definitions(&transaction)
+            .await?
+            .into_iter()
+            .map(|field| (field.name.clone(), field))
+            .collect();
+        for exported in &export.fields {
+            match fields.get(&exported.name) {
+                Some(field) if field.field_type != exported.field_type => {
+                    report.problems.push(format!(
+                        "{} is a {} field in the library, not a {} field",
+                        exported.name,
+                        field.field_type.as_str(),
+                        exported.field_type.as_str()
+                    ));
+                    fields.remove(&exported.name);
+                }
+                Some(_) => {}
+                None => match insert_field(
+                    &transaction,
+                    &exported.name,
+                    exported.field_type,
+                    &exported.options,
+                )
+                .await
+                {
+                    Ok(field) => {
+                        report.fields_created += 1;
+                        fields.insert(field.name.clone(), field);
+                    }
+                    Err(error) => report
+                        .problems
+                        .push(format!("{} was not created: {error:#}", exported.name)),
+                },
+            }
+        }
+
+        for file in &export.files {
+            let Some(model) = Files::find()
+                .filter(files::Column::Path.eq(&file.path))
+                .one(&transaction)
+                .await
+                .context("Failed to query file")?
+            else {
+                report.missing_files.push(file.path.clone());
+                continue;
+            };
+            let file_id = model.id;
+            if curate(&transaction, model, file, &mut report.problems).await? {
+                report.files_curated += 1;
+            }
+            for (name, text) in &file.values {
+                let Some(field) = fields.get(name) else {
+                    report
+                        .problems
+                        .push(format!("{}: there is no field {name}", file.path));
+                    continue;
+                };
+                if text.trim().is_empty() {
+                    if store_value(&transaction, file_id, field.id, None).await? {
+                        report.values_cleared += 1;
+                    }
+                    continue;
+                }
+                match field.parse_value(text) {
+                    Ok(value) => {
+                        if store_value(&transaction, file_id, field.id, Some(&value)).await? {
+                            report.values_set += 1;
+                        }
+                    }
+                    Err(error) => report.problems.push(format!("{}: {error}", file.path)),
+                }
+            }
+        }
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(report)
+    }
+}
+
+/// Give a file the exported rating, favorite flag and color label, returns whether it changed
+async fn curate<C>(
+    connection: &C,
+    model: files::Model,
+    file: &ExportedFile,
+    problems: &mut Vec<String>,
+) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    let mut curated = model.into_active_model();
+    if let Some(rating) = file.rating {
+        match curation::rating(rating) {
+            Ok(rating) => curated.rating.set_if_not_equals(i16::from(rating)),
+            Err(error) => problems.push(format!("{}: {error}", file.path)),
+        }
+    }
+    if let Some(favorite) = file.favorite {
+        curated.favorite.set_if_not_equals(favorite);
+    }
+    if let Some(label) = file.color_label {
+        curated
+            .color_label
+            .set_if_not_equals(Some(label.as_str().to_string()));
+    }
+    if !curated.is_changed() {
+        return Ok(false);
+    }
+    curated
+        .update(connection)
+        .await
+        .context("Failed to update file")?;
+    Ok(true)
+}
+
+/// The export of a file, added in path order the first time the file is seen
+fn exported_file(files: &mut BTreeMap<String, ExportedFile>, path: String) -> &mut ExportedFile {
+    files.entry(path.clone()).or_insert_with(|| ExportedFile {
+        path,
+        ..ExportedFile::default()
+    })
+}
+
+async fn definitions<C>(connection: &C) -> Result<Vec<FieldDefinition>>
+where
+    C: ConnectionTrait,
+{
+    CustomFields::find()
+        .order_by_asc(custom_fields::Column::Name)
+        .all(connection)
+        .await
+        .context("Failed to query custom fields")?
+        .into_iter()
+        .map(FieldDefinition::try_from)
+        .collect()
+}
+
+async fn insert_field<C>(
+    connection: &C,
+    name: &str,
+    field_type: FieldType,
+    options: &[String],
+) -> Result<FieldDefinition>
+where
+    C: ConnectionTrait,
+{
+    let options = field_type.options(options)?;
+    let now = Utc::now().naive_utc();
+    custom_fields::ActiveModel {
+        id: NotSet,
+        name: Set(name.to_string()),
+        field_type: Set(field_type.as_str().to_string()),
+        options: Set(serde_json::to_string(&options)?),
+        created_at: Set(now),
+        updated_at: Set(now),
+    }
+    .insert(connection)
+    .await
+    .context("Failed to create custom field")?
+    .try_into()
+}
+
+/// Set or remove the value of a field for a file, returns whether it changed
+async fn store_value<C>(
+    connection: &C,
+    file_id: i32,
+    field_id: i32,
+    value: Option<&FieldValue>,
+) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    let existing = FileFieldValues::find()
+        .filter(file_field_values::Column::FileId.eq(file_id))
+        .filter(file_field_values::Column::FieldId.eq(field_id))
+        .one(connection)
+        .await
+        .context("Failed to query custom field value")?;
+    let now = Utc::now().naive_utc();
+    match (existing, value) {
+        (None, None) => Ok(false),
+        (Some(existing), None) => {
+            FileFieldValues::delete_by_id(existing.id)
+                .exec(connection)
+                .await
+                .context("Failed to remove custom field value")?;
+            Ok(true)
+        }
+        (Some(existing), Some(value)) => {
+            let text = value.to_string();
+            if existing.value == text && existing.number == value.number() {
+                return Ok(false);
+            }
+            let mut existing = existing.into_active_model();
+            existing.value = Set(text);
+            existing.number = Set(value.number());
+            existing.updated_at = Set(now);
+            existing
+                .update(connection)
+                .await
+                .context("Failed to update custom field value")?;
+            Ok(true)
+        }
+        (None, Some(value)) => {
+            file_field_values::ActiveModel {
+                id: NotSet,
+                file_id: Set(file_id),
+                field_id: Set(field_id),
+                value: Set(value.to_string()),
+                number: Set(value.number()),
+                created_at: Set(now),
+                updated_at: Set(now),
+            }
+            .insert(connection)
+            .await
+            .context("Failed to store custom field value")?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_files, migrated_database};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn values_are_exported_and_imported_by_path() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 2).await?;
+        let fields = FieldOperations::new(Arc::clone(&database_manager));
+        let status = fields
+            .create(
+                "status",
+                FieldType::Enum,
+                &["approved".to_string(), "rejected".to_string()],
+            )
+            .await?;
+        let rating = fields.create("rating", FieldType::Number, &[]).await?;
+        assert!(rating.options.is_empty());
+        assert!(fields.create("due", FieldType::Enum, &[]).await.is_err());
+
+        let approved = status.parse_value("Approved")?;
+        assert_eq!(
+            fields
+                .set_values(&file_ids, status.id, Some(&approved))
+                .await?,
+            file_ids
+        );
+        assert!(
+            fields
+                .set_values(&file_ids, status.id, Some(&approved))
+                .await?
+                .is_empty()
+        );
+        fields
+            .set_values(&file_ids[..1], rating.id, Some(&FieldValue::Number(4.0)))
+            .await?;
+        assert_eq!(
+            fields.values_of(file_ids[0]).await?,
+            vec![
+                (rating.clone(), FieldValue::Number(4.0)),
+                (status.clone(), approved.clone()),
+            ]
+        );
+
+        let mut export = fields.export().await?;
+        assert_eq!(export.fields.len(), 2);
+        assert_eq!(export.files.len(), 2);
+        assert_eq!(
+            export.files[0].values.get("rating").map(String::as_str),
+            Some("4")
+        );
+
+        fields.delete(rating.id).await?;
+        export.files[0]
+            .values
+            .insert("status".to_string(), String::new());
+        export.files[1]
+            .values
+            .insert("status".to_string(), "pending".to_string());
+        export.files.push(ExportedFile {
+            path: "/elsewhere/file.txt".to_string(),
+            ..ExportedFile::default()
+        });
+        let report = fields.import(&export).await?;
+        assert_eq!(report.fields_created, 1);
+        assert_eq!(report.values_set, 1);
+        assert_eq!(report.values_cleared, 1);
+        assert_eq!(report.missing_files, vec!["/elsewhere/file.txt"]);
+        assert_eq!(report.problems.len(), 1);
+        let rating = fields
+            .find_by_name("rating")
+            .await?
+            .context("rating was imported")?;
+        assert_eq!(
+            fields.values_of(file_ids[0]).await?,
+            vec![(rating, FieldValue::Number(4.0))]
+        );
+        assert_eq!(
+            fields.values_of(file_ids[1]).await?,
+            vec![(status, approved)]
+        );
+        Ok(())
+    }
+}
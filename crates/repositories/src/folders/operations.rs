@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::path::MAIN_SEPARATOR;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+
+use entity::{file_has_tags, files, folders, prelude::*};
+use model::commands::watched_folders::{FolderStats, WatchedFolderTree};
+use model::services::folder::FolderDecoration;
+
+use crate::decorations;
+use crate::manager::DatabaseManager;
+
+/// Repository for the folder hierarchy of a library and what the folders contain
+#[derive(Debug)]
+pub struct FolderOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl FolderOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Every folder, nested below the watched folders
+    pub async fn tree(&self) -> Result<Vec<WatchedFolderTree>> {
+        let db = self.database_manager.get_connection();
+        let folders = Folders::find()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query folders")?;
+        let direct_stats = self.direct_stats(None).await?;
+        let decorations = decorations_of(db.as_ref(), &folders).await?;
+        Ok(WatchedFolderTree::build(
+            folders,
+            &direct_stats,
+            &decorations,
+        ))
+    }
+
+    /// The folders directly below a folder, or the watched folders for `None`
+    ///
+    /// Their counts include every folder below them, but only the files below the parent are
+    /// read, so a view can expand a large hierarchy one level at a time.
+    pub async fn children(&self, parent_id: Option<i32>) -> Result<Vec<WatchedFolderTree>> {
+        let Some(parent_id) = parent_id else {
+            return Ok(self
+                .tree()
+                .await?
+                .into_iter()
+                .map(|root| root.pruned(0))
+                .collect());
+        };
+        let db = self.database_manager.get_connection();
+        let Some(parent) = Folders::find_by_id(parent_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query folder")?
+        else {
+            return Ok(Vec::new());
+        };
+        let prefix = below(&parent.path);
+        let folders = Folders::find()
+            .filter(folders::Column::Path.starts_with(&prefix))
+            .all(db.as_ref())
+            .await
+            .context("Failed to query folders")?;
+        let direct_stats = self.direct_stats(Some(&prefix)).await?;
+        let decorations = decorations_of(db.as_ref(), &folders).await?;
+        Ok(
+            WatchedFolderTree::build(folders, &direct_stats, &decorations)
+                .into_iter()
+                .filter(|child| child.parent_id() == Some(parent_id))
+                .map(|child| child.pruned(0))
+                .collect(),
+        )
+    }
+
+    /// Every folder with its decoration, ordered by name
+    pub async fn list(&self) -> Result<Vec<(folders::Model, FolderDecoration)>> {
+        let db = self.database_manager.get_connection();
+        let folders = Folders::find()
+            .order_by_asc(folders::Column::Name)
+            .all(db.as_ref())
+            .await
+            .context("Failed to query folders")?;
+        let mut decorations = decorations_of(db.as_ref(), &folders).await?;
+        Ok(folders
+            .into_iter()
+            .map(|folder| {
+                let decoration = decorations.remove(&folder.id).unwrap_or_default();
+                (folder, decoration)
+            })
+            .collect())
+    }
+
+    /// Replace the color, icon and display name of a folder
+    ///
+    /// A blank display name is stored as none. Returns `None` if the folder does not exist.
+    pub async fn decorate(
+        &self,
+        folder_id: i32,
+        decoration: &FolderDecoration,
+    ) -> Result<Option<(folders::Model, FolderDecoration)>> {
+        let db = self.database_manager.get_connection();
+        let Some(folder) = Folders::find_by_id(folder_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query folder")?
+        else {
+            return Ok(None);
+        };
+        let color_id = match &decoration.color {
+            Some(color) => Some(decorations::color_id(db.as_ref(), color).await?),
+            None => None,
+        };
+        let icon_id = match &decoration.icon {
+            Some(icon) => Some(decorations::icon_id(db.as_ref(), icon).await?),
+            None => None,
+        };
+        let display_name = decoration
+            .display_name
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+
+        let mut folder = folder.into_active_model();
+        folder.color_id = Set(color_id);
+        folder.icon_id = Set(icon_id);
+        folder.display_name = Set(display_name.clone());
+        folder.updated_at = Set(Utc::now().naive_utc());
+        let folder = folder
+            .update(db.as_ref())
+            .await
+            .context("Failed to decorate folder")?;
+        let decoration = FolderDecoration {
+            display_name,
+            ..decoration.clone()
+        };
+        Ok(Some((folder, decoration)))
+    }
+
+    /// Files directly in each folder, for the files whose path starts with `prefix`
+    async fn direct_stats(&self, prefix: Option<&str>) -> Result<HashMap<String, FolderStats>> {
+        let db = self.database_manager.get_connection();
+        let mut condition = Condition::all();
+        if let Some(prefix) = prefix {
+            condition = condition.add(files::Column::Path.starts_with(prefix));
+        }
+        let tagged = Expr::exists(
+            Query::select()
+                .expr(Expr::val(1))
+                .from(FileHasTags)
+                .and_where(
+                    Expr::col((FileHasTags, file_has_tags::Column::FileId))
+                        .equals((Files, files::Column::Id)),
+                )
+                .to_owned(),
+        );
+        let rows: Vec<(String, i64, bool)> = Files::find()
+            .select_only()
+            .column(files::Column::Path)
+            .column(files::Column::Size)
+            .expr(tagged)
+            .filter(condition)
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query file sizes")?;
+
+        let mut stats: HashMap<String, FolderStats> = HashMap::new();
+        for (path, size, tagged) in rows {
+            let Some(folder) = WatchedFolderTree::folder_of(&path) else {
+                continue;
+            };
+            stats
+                .entry(folder)
+                .or_default()
+                .add_file(u64::try_from(size).unwrap_or_default(), tagged);
+        }
+        Ok(stats)
+    }
+}
+
+/// The decorations of the folders that have a color, an icon or a display name
+async fn decorations_of<C>(
+    connection: &C,
+    folders: &[folders::Model],
+) -> Result<HashMap<i32, FolderDecoration>>
+where
+    C: ConnectionTrait,
+{
+    let colors = decorations::colors(
+        connection,
+        folders
+            .iter()
+            .filter_map(|folder| folder.color_id)
+            .collect(),
+    )
+    .await?;
+    let icons = decorations::icons(
+        connection,
+        folders.iter().filter_map(|folder| folder.icon_id).collect(),
+    )
+    .await?;
+    Ok(folders
+        .iter()
+        .filter(|folder| {
+            folder.color_id.is_some() || folder.icon_id.is_some() || folder.display_name.is_some()
+        })
+        .map(|folder| {
+            let decoration = FolderDecoration {
+                color: folder.color_id.and_then(|id| colors.get(&id).cloned()),
+                icon: folder.icon_id.and_then(|id| icons.get(&id).cloned()),
+                display_name: folder.display_name.clone(),
+            };
+            (folder.id, decoration)
+        })
+        .collect())
+}
+
+/// Prefix of the paths below a folder, without matching `/photos2` for `/photos`
+fn below(path: &str) -> String {
+    format!("{}{MAIN_SEPARATOR}", path.trim_end_matches(MAIN_SEPARATOR))
+}
@@ -7,19 +7,20 @@ use entity::{folders, prelude::Folders};
 use events::{FileEvent, FolderEvent};
 use hash::file_id::FileId;
 use model::commands::filter::{Filter, FolderFilter, TagFilter};
-use model::commands::watched_folders::WatchedFolderTree;
-use model::services::file::FileSystemFile as File;
+use model::services::file::{FileSystemFile as File, modified_time};
 use model::services::folder::FileSystemFolder as Folder;
+use model::services::journal::{JournalEntry, JournalEventKind, JournalSource};
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, IntoActiveModel,
-    QueryFilter, QuerySelect, TransactionTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
-use tracing::{info, instrument};
+use tracing::instrument;
 
+use crate::journal::operations::JournalOperations;
 use crate::manager::DatabaseManager;
 use crate::thumbnail::operations::ThumbnailOperations;
 
@@ -48,6 +49,14 @@ pub struct UpsertFolderBatchReport {
     pub folder_updated: usize,
 }
 
+/// A row written from a watcher event along with the path it was stored under before
+#[derive(Debug, Clone)]
+pub struct UpsertedRow<M> {
+    pub model: M,
+    /// `None` when the row was inserted
+    pub previous_path: Option<String>,
+}
+
 /// Database operations for file management with caching and bulk operations
 #[derive(Debug)]
 pub struct FileRepository {
@@ -168,6 +177,9 @@ impl FileRepository {
                     file_system_id: Set(file_system_id),
                     created_at: Set(chrono::Local::now().naive_local()),
                     updated_at: Set(chrono::Local::now().naive_local()),
+                    color_id: Set(None),
+                    icon_id: Set(None),
+                    display_name: Set(None),
                 };
 
                 tracing::info!("Inserting existing root folder {path:#?}");
@@ -234,7 +246,10 @@ impl FileRepository {
         Ok(subfolders)
     }
 
-    pub async fn upsert_folder_from_event(&self, event: &FolderEvent) -> Result<folders::Model> {
+    pub async fn upsert_folder_from_event(
+        &self,
+        event: &FolderEvent,
+    ) -> Result<UpsertedRow<folders::Model>> {
         let connection = self.database_manager.get_connection();
         let transaction = connection.begin().await?;
 
@@ -252,9 +267,9 @@ impl FileRepository {
         let path_str = folder_path.to_string_lossy().to_string();
 
         // Get proper content and identity hashes from the FileHash struct
-        let content_hash_str = format!("{:?}", event.hash.as_ref().unwrap().content_hash);
-        let identity_hash_str = format!("{:?}", event.hash.as_ref().unwrap().identity_hash);
-        let structure_hash_str = format!("{:?}", event.hash.as_ref().unwrap().structure_hash);
+        let content_hash_str = event.hash.as_ref().unwrap().content_hash.to_string();
+        let identity_hash_str = event.hash.as_ref().unwrap().identity_hash.to_string();
+        let structure_hash_str = event.hash.as_ref().unwrap().structure_hash.to_string();
 
         // Get file system identifier
         let file_system_id = self
@@ -271,6 +286,9 @@ impl FileRepository {
             .one(&transaction)
             .await?;
 
+        let previous_path = folder_with_fsi
+            .as_ref()
+            .map(|existing| existing.path.clone());
         let folder_model = if let Some(existing) = folder_with_fsi {
             // Update existing file
             let mut active_model = existing.into_active_model();
@@ -297,6 +315,9 @@ impl FileRepository {
                 file_system_id: Set(file_system_id),
                 created_at: Set(chrono::Utc::now().naive_utc()),
                 updated_at: Set(chrono::Utc::now().naive_utc()),
+                color_id: Set(None),
+                icon_id: Set(None),
+                display_name: Set(None),
             };
 
             new_folder.insert(&transaction).await?
@@ -304,10 +325,16 @@ impl FileRepository {
 
         transaction.commit().await?;
 
-        Ok(folder_model)
+        Ok(UpsertedRow {
+            model: folder_model,
+            previous_path,
+        })
     }
     /// Insert or update a file in the database based on FileEvent
-    pub async fn upsert_file_from_event(&self, event: &FileEvent) -> Result<files::Model> {
+    pub async fn upsert_file_from_event(
+        &self,
+        event: &FileEvent,
+    ) -> Result<UpsertedRow<files::Model>> {
         let connection = self.database_manager.get_connection();
         let transaction = connection.begin().await?;
 
@@ -324,6 +351,14 @@ impl FileRepository {
             .to_string();
 
         let path_str = file_path.to_string_lossy().to_string();
+        // The file may already be gone again, it is then removed by a later event
+        let metadata = tokio::fs::metadata(file_path).await.ok();
+        let size = metadata
+            .as_ref()
+            .map_or(0, |metadata| database_size(metadata.len()));
+        let modified_at = metadata
+            .as_ref()
+            .map_or_else(|| Utc::now().naive_utc(), modified_time);
 
         // Get or create file type
         let file_type_id = self
@@ -331,8 +366,8 @@ impl FileRepository {
             .await?;
 
         // Get proper content and identity hashes from the FileHash struct
-        let content_hash_str = format!("{:?}", event.hash.as_ref().unwrap().content_hash);
-        let identity_hash_str = format!("{:?}", event.hash.as_ref().unwrap().identity_hash);
+        let content_hash_str = event.hash.as_ref().unwrap().content_hash.to_string();
+        let identity_hash_str = event.hash.as_ref().unwrap().identity_hash.to_string();
 
         // Get file system identifier
         let file_system_id = self
@@ -345,6 +380,9 @@ impl FileRepository {
             .one(&transaction)
             .await?;
 
+        let previous = file_with_fsi
+            .as_ref()
+            .map(|existing| (existing.path.clone(), existing.content_hash.clone()));
         let file_model = if let Some(existing) = file_with_fsi {
             // Update existing file
             let mut active_model = existing.into_active_model();
@@ -354,7 +392,9 @@ impl FileRepository {
             active_model.identity_hash = Set(identity_hash_str);
             active_model.file_type_id = Set(file_type_id);
             active_model.file_system_id = Set(file_system_id);
-            active_model.updated_at = Set(chrono::Local::now().naive_local());
+            active_model.size = Set(size);
+            active_model.modified_at = Set(modified_at);
+            active_model.updated_at = Set(chrono::Utc::now().naive_utc());
 
             active_model.update(&transaction).await?
         } else {
@@ -367,6 +407,11 @@ impl FileRepository {
                 identity_hash: Set(identity_hash_str),
                 file_type_id: Set(file_type_id),
                 file_system_id: Set(file_system_id),
+                size: Set(size),
+                modified_at: Set(modified_at),
+                rating: Set(0),
+                favorite: Set(false),
+                color_label: Set(None),
                 created_at: Set(chrono::Utc::now().naive_utc()),
                 updated_at: Set(chrono::Utc::now().naive_utc()),
             };
@@ -374,35 +419,82 @@ impl FileRepository {
             new_file.insert(&transaction).await?
         };
 
+        let entry = match &previous {
+            None => JournalEntry::new(JournalEventKind::Created, JournalSource::Watcher)
+                .with_paths(None, Some(PathBuf::from(&file_model.path)))
+                .with_hashes(None, Some(file_model.content_hash.clone())),
+            Some((old_path, old_hash)) => {
+                let kind = if *old_path == file_model.path {
+                    JournalEventKind::Modified
+                } else {
+                    JournalEventKind::Moved
+                };
+                JournalEntry::new(kind, JournalSource::Watcher)
+                    .with_paths(
+                        Some(PathBuf::from(old_path)),
+                        Some(PathBuf::from(&file_model.path)),
+                    )
+                    .with_hashes(
+                        Some(old_hash.clone()),
+                        Some(file_model.content_hash.clone()),
+                    )
+            }
+        };
+        JournalOperations::record(&transaction, vec![entry.with_file(file_model.id)]).await?;
+
         transaction.commit().await?;
-        Ok(file_model)
+        Ok(UpsertedRow {
+            model: file_model,
+            previous_path: previous.map(|(path, _)| path),
+        })
     }
 
-    /// Delete a file record from the database
-    pub async fn delete_file_by_path(&self, file_path: &Path) -> Result<bool> {
+    /// Delete a file record from the database and return the ids of the removed rows
+    pub async fn delete_file_by_path(&self, file_path: &Path) -> Result<Vec<i32>> {
         tracing::info!("FileOperations: Deleting path {file_path:#?} from database");
         let path_str = file_path.to_string_lossy().to_string();
         let connection = self.database_manager.get_connection();
+        let transaction = connection.begin().await?;
 
-        let result = Files::delete_many()
+        let removed = Files::find()
             .filter(files::Column::Path.eq(&path_str))
-            .exec(&*connection)
+            .all(&transaction)
+            .await?;
+        let file_ids: Vec<i32> = removed.iter().map(|file| file.id).collect();
+        Files::delete_many()
+            .filter(files::Column::Id.is_in(file_ids.clone()))
+            .exec(&transaction)
             .await?;
+        JournalOperations::record(
+            &transaction,
+            deleted_entries(removed, JournalSource::Watcher),
+        )
+        .await?;
+        transaction.commit().await?;
 
-        Ok(result.rows_affected > 0)
+        Ok(file_ids)
     }
 
-    /// Delete a file record from the database
-    pub async fn delete_folder_by_path(&self, folder_path: &Path) -> Result<bool> {
+    /// Delete a folder record from the database and return the ids of the removed rows
+    pub async fn delete_folder_by_path(&self, folder_path: &Path) -> Result<Vec<i32>> {
         let path_str = folder_path.to_string_lossy().to_string();
         let connection = self.database_manager.get_connection();
+        let transaction = connection.begin().await?;
 
-        let result = Folders::delete_many()
+        let folder_ids: Vec<i32> = Folders::find()
+            .select_only()
+            .column(folders::Column::Id)
             .filter(folders::Column::Path.eq(&path_str))
-            .exec(&*connection)
+            .into_tuple()
+            .all(&transaction)
+            .await?;
+        Folders::delete_many()
+            .filter(folders::Column::Id.is_in(folder_ids.clone()))
+            .exec(&transaction)
             .await?;
+        transaction.commit().await?;
 
-        Ok(result.rows_affected > 0)
+        Ok(folder_ids)
     }
 
     /// Get or create a file type based on file extension
@@ -508,6 +600,26 @@ impl FileRepository {
         Ok(files)
     }
 
+    /// Get the files stored under the given paths, ignoring paths that are not in the database
+    pub async fn get_files_by_paths(&self, file_paths: &[PathBuf]) -> Result<Vec<files::Model>> {
+        let connection = self.database_manager.get_connection();
+        let mut found = Vec::with_capacity(file_paths.len());
+        for chunk in file_paths.chunks(1_000) {
+            let paths: Vec<String> = chunk
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            found.extend(
+                Files::find()
+                    .filter(files::Column::Path.is_in(paths))
+                    .order_by_asc(files::Column::Id)
+                    .all(&*connection)
+                    .await?,
+            );
+        }
+        Ok(found)
+    }
+
     /// Get all files in a directory
     pub async fn get_files_in_directory(&self, dir_path: &Path) -> Result<Vec<files::Model>> {
         let dir_str = dir_path.to_string_lossy().to_string();
@@ -564,49 +676,6 @@ impl FileRepository {
         Ok(hashes)
     }
 
-    pub async fn get_watched_folder_map(&self) -> Result<HashMap<String, WatchedFolderTree>> {
-        let mut map = HashMap::new();
-
-        let connection = self.database_manager.get_connection();
-        let folders = Folders::find().all(&*connection).await?;
-
-        let (with_parent, without_parent): (Vec<folders::Model>, Vec<folders::Model>) = folders
-            .into_iter()
-            .partition(|model| model.parent_folder_id.is_some());
-
-        let root_children = without_parent
-            .into_iter()
-            .map(|model| model.id.to_string())
-            .collect();
-        let root = WatchedFolderTree::with(
-            "".to_string(),
-            "".to_string(),
-            Some(root_children),
-            None,
-            None,
-        );
-        map.insert("0".to_string(), root);
-        tracing::info!("First map value inserted {map:#?}");
-
-        for folder in with_parent {
-            let children = Folders::find()
-                .select_only()
-                .column(folders::Column::Id)
-                .filter(folders::Column::ParentFolderId.eq(folder.id))
-                .all(&*connection)
-                .await?;
-            let children_array: Option<Vec<String>> = if children.is_empty() {
-                None
-            } else {
-                Some(children.into_iter().map(|v| v.id.to_string()).collect())
-            };
-            let wf = WatchedFolderTree::with(folder.name, folder.path, children_array, None, None);
-            map.insert(folder.id.to_string(), wf);
-        }
-        info!("Complete map {map:#?}");
-        Ok(map)
-    }
-
     /// Batch insert/update files with transaction
     pub async fn batch_upsert_files(&self, files: Vec<File>) -> Result<UpsertFileBatchReport> {
         if files.is_empty() {
@@ -621,6 +690,7 @@ impl FileRepository {
 
         let mut file_inserted = 0;
         let mut file_updated = 0;
+        let mut journal = Vec::new();
 
         for file_info in files {
             // Get or create file type (with caching)
@@ -643,6 +713,7 @@ impl FileRepository {
                 .await?;
 
             if let Some(existing) = existing_file {
+                let old_hash = existing.content_hash.clone();
                 // Update existing file
                 let mut active_model = existing.into_active_model();
                 active_model.name = Set(file_info.name);
@@ -650,9 +721,22 @@ impl FileRepository {
                 active_model.identity_hash = Set(file_info.identity_hash);
                 active_model.file_type_id = Set(file_type_id);
                 active_model.file_system_id = Set(file_system_id);
+                active_model.size = Set(database_size(file_info.size));
+                active_model.modified_at = Set(file_info.modified_at);
                 active_model.updated_at = Set(chrono::Utc::now().naive_utc());
 
-                active_model.update(&transaction).await?;
+                let updated = active_model.update(&transaction).await?;
+                if updated.content_hash != old_hash {
+                    journal.push(
+                        JournalEntry::new(JournalEventKind::Modified, JournalSource::Scanner)
+                            .with_file(updated.id)
+                            .with_paths(
+                                Some(PathBuf::from(&updated.path)),
+                                Some(PathBuf::from(&updated.path)),
+                            )
+                            .with_hashes(Some(old_hash), Some(updated.content_hash)),
+                    );
+                }
                 file_inserted += 1;
             } else {
                 // Insert new file
@@ -664,16 +748,28 @@ impl FileRepository {
                     identity_hash: Set(file_info.identity_hash),
                     file_type_id: Set(file_type_id),
                     file_system_id: Set(file_system_id),
+                    size: Set(database_size(file_info.size)),
+                    modified_at: Set(file_info.modified_at),
+                    rating: Set(0),
+                    favorite: Set(false),
+                    color_label: Set(None),
                     created_at: Set(chrono::Utc::now().naive_utc()),
                     updated_at: Set(chrono::Utc::now().naive_utc()),
                 };
 
-                new_file.insert(&transaction).await?;
+                let inserted = new_file.insert(&transaction).await?;
+                journal.push(
+                    JournalEntry::new(JournalEventKind::Created, JournalSource::Scanner)
+                        .with_file(inserted.id)
+                        .with_paths(None, Some(PathBuf::from(&inserted.path)))
+                        .with_hashes(None, Some(inserted.content_hash)),
+                );
 
                 file_updated += 1;
             }
         }
 
+        JournalOperations::record(&transaction, journal).await?;
         transaction.commit().await?;
 
         Ok(UpsertFileBatchReport {
@@ -746,6 +842,9 @@ impl FileRepository {
                     file_system_id: Set(file_system_id),
                     created_at: Set(chrono::Utc::now().naive_utc()),
                     updated_at: Set(chrono::Utc::now().naive_utc()),
+                    color_id: Set(None),
+                    icon_id: Set(None),
+                    display_name: Set(None),
                 };
 
                 new_folder.insert(&transaction).await?;
@@ -773,10 +872,21 @@ impl FileRepository {
             .collect();
 
         let connection = self.database_manager.get_connection();
+        let transaction = connection.begin().await?;
+        let removed = Files::find()
+            .filter(files::Column::Path.is_in(path_strings.clone()))
+            .all(&transaction)
+            .await?;
         let result = Files::delete_many()
             .filter(files::Column::Path.is_in(path_strings))
-            .exec(&*connection)
+            .exec(&transaction)
             .await?;
+        JournalOperations::record(
+            &transaction,
+            deleted_entries(removed, JournalSource::Scanner),
+        )
+        .await?;
+        transaction.commit().await?;
 
         Ok(result.rows_affected as usize)
     }
@@ -1008,3 +1118,21 @@ impl FileRepository {
         Ok(root_folders)
     }
 }
+
+/// SQLite stores integers signed, no real file reaches the limit
+fn database_size(size: u64) -> i64 {
+    i64::try_from(size).unwrap_or(i64::MAX)
+}
+
+/// Journal entries for file rows that are about to be deleted
+fn deleted_entries(removed: Vec<files::Model>, source: JournalSource) -> Vec<JournalEntry> {
+    removed
+        .into_iter()
+        .map(|file| {
+            JournalEntry::new(JournalEventKind::Deleted, source)
+                .with_file(file.id)
+                .with_paths(Some(PathBuf::from(file.path)), None)
+                .with_hashes(Some(file.content_hash), None)
+        })
+        .collect()
+}
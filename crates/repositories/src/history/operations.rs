@@ -0,0 +1,681 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+};
+
+use entity::{command_history, file_has_tags, prelude::*, tag_aliases, tags};
+use model::commands::history::{HistoryEntry, TagCommand};
+use model::services::journal::JournalSource;
+use model::services::tag::{TagDetails, TagLink};
+
+use crate::manager::DatabaseManager;
+use crate::tags::operations::{
+    assign, details_of, link, links_of, store_details, unassign, unlink,
+};
+
+/// Number of commands kept in the history, older ones can no longer be undone
+pub const HISTORY_LIMIT: u64 = 200;
+
+/// A command that was undone or redone, as it was actually applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedCommand {
+    pub label: String,
+    pub command: TagCommand,
+}
+
+/// Repository for the persistent undo and redo history of tag changes
+///
+/// Every command is applied and recorded in one transaction. Commands are stored as they were
+/// actually applied, e.g. an assignment only lists the files that did not have the tag yet, so
+/// undoing it never removes a tag that was there before.
+#[derive(Debug)]
+pub struct HistoryOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl HistoryOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Apply a command and record it, discarding everything that could still be redone
+    ///
+    /// Returns the command as it was applied. Commands that change nothing are not recorded.
+    pub async fn execute(&self, label: &str, command: TagCommand) -> Result<TagCommand> {
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        let applied = apply(&transaction, command).await?;
+        if !applied.is_empty() {
+            CommandHistory::delete_many()
+                .filter(command_history::Column::Undone.eq(true))
+                .exec(&transaction)
+                .await
+                .context("Failed to discard the redo history")?;
+            command_history::ActiveModel {
+                id: NotSet,
+                created_at: Set(Utc::now().naive_utc()),
+                label: Set(label.to_string()),
+                command: Set(serde_json::to_string(&applied)?),
+                undone: Set(false),
+            }
+            .insert(&transaction)
+            .await
+            .context("Failed to record the command")?;
+            prune(&transaction).await?;
+        }
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(applied)
+    }
+
+    /// Revert the most recent command that has not been undone yet
+    pub async fn undo(&self) -> Result<Option<AppliedCommand>> {
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        let Some(model) = CommandHistory::find()
+            .filter(command_history::Column::Undone.eq(false))
+            .order_by_desc(command_history::Column::Id)
+            .one(&transaction)
+            .await
+            .context("Failed to query the command history")?
+        else {
+            return Ok(None);
+        };
+        let entry = HistoryEntry::try_from(model.clone())?;
+        let applied = apply(&transaction, entry.command.inverse()?).await?;
+        // Redoing reapplies what was actually reverted
+        let redo = applied.inverse()?;
+        let mut model = model.into_active_model();
+        model.command = Set(serde_json::to_string(&redo)?);
+        model.undone = Set(true);
+        model
+            .update(&transaction)
+            .await
+            .context("Failed to update the command history")?;
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(Some(AppliedCommand {
+            label: entry.label,
+            command: applied,
+        }))
+    }
+
+    /// Reapply the oldest undone command
+    pub async fn redo(&self) -> Result<Option<AppliedCommand>> {
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        let Some(model) = CommandHistory::find()
+            .filter(command_history::Column::Undone.eq(true))
+            .order_by_asc(command_history::Column::Id)
+            .one(&transaction)
+            .await
+            .context("Failed to query the command history")?
+        else {
+            return Ok(None);
+        };
+        let entry = HistoryEntry::try_from(model.clone())?;
+        let applied = apply(&transaction, entry.command).await?;
+        let mut model = model.into_active_model();
+        model.command = Set(serde_json::to_string(&applied)?);
+        model.undone = Set(false);
+        model
+            .update(&transaction)
+            .await
+            .context("Failed to update the command history")?;
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(Some(AppliedCommand {
+            label: entry.label,
+            command: applied,
+        }))
+    }
+
+    /// The most recent entries, newest first
+    pub async fn list(&self, limit: u64) -> Result<Vec<HistoryEntry>> {
+        let db = self.database_manager.get_connection();
+        CommandHistory::find()
+            .order_by_desc(command_history::Column::Id)
+            .limit(limit)
+            .all(db.as_ref())
+            .await
+            .context("Failed to query the command history")?
+            .into_iter()
+            .map(HistoryEntry::try_from)
+            .collect()
+    }
+}
+
+/// Drop the entries beyond [`HISTORY_LIMIT`]
+async fn prune<C>(connection: &C) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    let oldest_kept: Option<i32> = CommandHistory::find()
+        .select_only()
+        .column(command_history::Column::Id)
+        .order_by_desc(command_history::Column::Id)
+        .offset(HISTORY_LIMIT - 1)
+        .limit(1)
+        .into_tuple()
+        .one(connection)
+        .await
+        .context("Failed to query the command history")?;
+    if let Some(oldest_kept) = oldest_kept {
+        CommandHistory::delete_many()
+            .filter(command_history::Column::Id.lt(oldest_kept))
+            .exec(connection)
+            .await
+            .context("Failed to prune the command history")?;
+    }
+    Ok(())
+}
+
+/// Apply a command and return it as it was actually applied
+async fn apply<C>(connection: &C, command: TagCommand) -> Result<TagCommand>
+where
+    C: ConnectionTrait,
+{
+    let TagCommand::Batch { commands } = command else {
+        return apply_expanded(connection, command).await;
+    };
+    let mut applied = Vec::new();
+    for command in flatten(commands) {
+        applied.push(apply_expanded(connection, command).await?);
+    }
+    Ok(TagCommand::Batch { commands: applied })
+}
+
+/// Apply a command that is not a batch, a merge is applied as the commands it consists of
+async fn apply_expanded<C>(connection: &C, command: TagCommand) -> Result<TagCommand>
+where
+    C: ConnectionTrait,
+{
+    let TagCommand::MergeTag { tag_id, into } = command else {
+        return apply_single(connection, command).await;
+    };
+    let mut applied = Vec::new();
+    for command in expand_merge(connection, tag_id, into).await? {
+        applied.push(apply_single(connection, command).await?);
+    }
+    Ok(TagCommand::Batch { commands: applied })
+}
+
+/// The commands that merge a tag into another one, given the current state of both
+async fn expand_merge<C>(connection: &C, tag_id: i32, into: i32) -> Result<Vec<TagCommand>>
+where
+    C: ConnectionTrait,
+{
+    if tag_id == into {
+        bail!("tag {tag_id} cannot be merged into itself");
+    }
+    let tag = find_tag(connection, tag_id).await?;
+    let target = find_tag(connection, into).await?;
+    let file_ids: Vec<i32> = FileHasTags::find()
+        .select_only()
+        .column(file_has_tags::Column::FileId)
+        .filter(file_has_tags::Column::TagId.eq(tag_id))
+        .into_tuple()
+        .all(connection)
+        .await
+        .context("Failed to query tag assignments")?;
+    let links = links_of(connection, tag_id).await?;
+    let moved_links = links
+        .iter()
+        .map(|link| {
+            let repoint = |id| if id == tag_id { into } else { id };
+            TagLink {
+                super_tag_id: repoint(link.super_tag_id),
+                sub_tag_id: repoint(link.sub_tag_id),
+            }
+        })
+        .collect();
+    let mut details = details_of(connection, &[tag.clone(), target.clone()]).await?;
+    let target_details = details.pop().unwrap_or_default();
+    let tag_details = details.pop().unwrap_or_default();
+    let mut aliases = target_details.aliases.clone();
+    for alias in std::iter::once(tag.name).chain(tag_details.aliases) {
+        if alias != target.name && !aliases.contains(&alias) {
+            aliases.push(alias);
+        }
+    }
+    Ok(vec![
+        TagCommand::AssignTag {
+            tag_id: into,
+            file_ids,
+        },
+        TagCommand::LinkTags { links: moved_links },
+        TagCommand::UnlinkTags { links },
+        TagCommand::DeleteTag {
+            tag_id,
+            name: String::new(),
+            file_ids: Vec::new(),
+            details: TagDetails::default(),
+        },
+        TagCommand::EditTag {
+            tag_id: into,
+            from: TagDetails::default(),
+            to: TagDetails {
+                aliases,
+                ..target_details
+            },
+        },
+    ])
+}
+
+fn flatten(commands: Vec<TagCommand>) -> Vec<TagCommand> {
+    commands
+        .into_iter()
+        .flat_map(|command| match command {
+            TagCommand::Batch { commands } => flatten(commands),
+            command => vec![command],
+        })
+        .collect()
+}
+
+async fn apply_single<C>(connection: &C, command: TagCommand) -> Result<TagCommand>
+where
+    C: ConnectionTrait,
+{
+    match command {
+        TagCommand::CreateTag {
+            tag_id,
+            name,
+            file_ids,
+            details,
+        } => {
+            let now = Utc::now().naive_utc();
+            let tag = tags::ActiveModel {
+                id: tag_id.map_or(NotSet, Set),
+                name: Set(name),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(connection)
+            .await
+            .context("Failed to create tag")?;
+            let tag = store_details(connection, tag, &details).await?;
+            let file_ids = assign(connection, &tag, file_ids, JournalSource::User).await?;
+            Ok(TagCommand::CreateTag {
+                tag_id: Some(tag.id),
+                name: tag.name,
+                file_ids,
+                details,
+            })
+        }
+        TagCommand::RenameTag { tag_id, to, .. } => {
+            let tag = find_tag(connection, tag_id).await?;
+            let from = tag.name.clone();
+            let mut tag = tag.into_active_model();
+            tag.name = Set(to.clone());
+            tag.updated_at = Set(Utc::now().naive_utc());
+            tag.update(connection)
+                .await
+                .context("Failed to rename tag")?;
+            Ok(TagCommand::RenameTag { tag_id, from, to })
+        }
+        TagCommand::EditTag { tag_id, to, .. } => {
+            let tag = find_tag(connection, tag_id).await?;
+            let from = details_of(connection, std::slice::from_ref(&tag))
+                .await?
+                .pop()
+                .unwrap_or_default();
+            store_details(connection, tag, &to).await?;
+            Ok(TagCommand::EditTag { tag_id, from, to })
+        }
+        TagCommand::DeleteTag { tag_id, .. } => delete_tag(connection, tag_id).await,
+        TagCommand::AssignTag { tag_id, file_ids } => {
+            let tag = find_tag(connection, tag_id).await?;
+            let file_ids = assign(connection, &tag, file_ids, JournalSource::User).await?;
+            Ok(TagCommand::AssignTag { tag_id, file_ids })
+        }
+        TagCommand::RemoveTag { tag_id, file_ids } => {
+            let tag = find_tag(connection, tag_id).await?;
+            let file_ids = unassign(connection, &tag, file_ids, JournalSource::User).await?;
+            Ok(TagCommand::RemoveTag { tag_id, file_ids })
+        }
+        TagCommand::LinkTags { links } => Ok(TagCommand::LinkTags {
+            links: link(connection, links).await?,
+        }),
+        TagCommand::UnlinkTags { links } => Ok(TagCommand::UnlinkTags {
+            links: unlink(connection, links).await?,
+        }),
+        TagCommand::MergeTag { tag_id, into } => {
+            bail!("merge of tag {tag_id} into tag {into} was not expanded")
+        }
+        TagCommand::Batch { commands } => {
+            bail!(
+                "nested batch of {} commands was not flattened",
+                commands.len()
+            )
+        }
+    }
+}
+
+/// Delete a tag with its details and assignments, capturing them so it can be recreated
+async fn delete_tag<C>(connection: &C, tag_id: i32) -> Result<TagCommand>
+where
+    C: ConnectionTrait,
+{
+    let tag = find_tag(connection, tag_id).await?;
+    let file_ids: Vec<i32> = FileHasTags::find()
+        .select_only()
+        .column(file_has_tags::Column::FileId)
+        .filter(file_has_tags::Column::TagId.eq(tag_id))
+        .into_tuple()
+        .all(connection)
+        .await
+        .context("Failed to query tag assignments")?;
+    let file_ids = unassign(connection, &tag, file_ids, JournalSource::User).await?;
+    let details = details_of(connection, std::slice::from_ref(&tag))
+        .await?
+        .pop()
+        .unwrap_or_default();
+    TagAliases::delete_many()
+        .filter(tag_aliases::Column::TagId.eq(tag_id))
+        .exec(connection)
+        .await
+        .context("Failed to remove tag aliases")?;
+    Tags::delete_by_id(tag_id)
+        .exec(connection)
+        .await
+        .context("Failed to delete tag")?;
+    Ok(TagCommand::DeleteTag {
+        tag_id,
+        name: tag.name,
+        file_ids,
+        details,
+    })
+}
+
+async fn find_tag<C>(connection: &C, tag_id: i32) -> Result<tags::Model>
+where
+    C: ConnectionTrait,
+{
+    match Tags::find_by_id(tag_id)
+        .one(connection)
+        .await
+        .context("Failed to query tag")?
+    {
+        Some(tag) => Ok(tag),
+        None => bail!("tag {tag_id} does not exist"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_files, migrated_database};
+    use model::services::decorations::Color;
+    use tempfile::TempDir;
+
+    async fn assigned(database_manager: &DatabaseManager, tag_id: i32) -> Result<Vec<i32>> {
+        Ok(FileHasTags::find()
+            .select_only()
+            .column(file_has_tags::Column::FileId)
+            .filter(file_has_tags::Column::TagId.eq(tag_id))
+            .order_by_asc(file_has_tags::Column::FileId)
+            .into_tuple()
+            .all(database_manager.get_connection().as_ref())
+            .await?)
+    }
+
+    #[tokio::test]
+    async fn deleted_tag_is_restored_with_its_assignments() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 3).await?;
+        let history = HistoryOperations::new(Arc::clone(&database_manager));
+        let details = TagDetails {
+            color: Some(Color::new("Red", "#e5484d")?),
+            description: Some("Trips away from home".to_string()),
+            aliases: vec!["Vacation".to_string()],
+            ..TagDetails::default()
+        };
+
+        let created = history
+            .execute(
+                "Create tag Holiday",
+                TagCommand::CreateTag {
+                    tag_id: None,
+                    name: "Holiday".to_string(),
+                    file_ids: Vec::new(),
+                    details: details.clone(),
+                },
+            )
+            .await?;
+        let TagCommand::CreateTag {
+            tag_id: Some(tag_id),
+            ..
+        } = created
+        else {
+            panic!("the tag was not created: {created:?}");
+        };
+        history
+            .execute(
+                "Assign tag Holiday",
+                TagCommand::AssignTag {
+                    tag_id,
+                    file_ids: file_ids.clone(),
+                },
+            )
+            .await?;
+        let deleted = history
+            .execute(
+                "Delete tag Holiday",
+                TagCommand::DeleteTag {
+                    tag_id,
+                    name: String::new(),
+                    file_ids: Vec::new(),
+                    details: TagDetails::default(),
+                },
+            )
+            .await?;
+        assert_eq!(
+            deleted,
+            TagCommand::DeleteTag {
+                tag_id,
+                name: "Holiday".to_string(),
+                file_ids: file_ids.clone(),
+                details: details.clone(),
+            }
+        );
+        assert!(
+            Tags::find_by_id(tag_id)
+                .one(database_manager.get_connection().as_ref())
+                .await?
+                .is_none()
+        );
+
+        let undone = history.undo().await?.expect("the deletion can be undone");
+        assert_eq!(undone.label, "Delete tag Holiday");
+        assert_eq!(assigned(&database_manager, tag_id).await?, file_ids);
+        let db = database_manager.get_connection();
+        let restored = Tags::find_by_id(tag_id).all(db.as_ref()).await?;
+        assert_eq!(details_of(db.as_ref(), &restored).await?, vec![details]);
+
+        history.redo().await?.expect("the deletion can be redone");
+        assert!(assigned(&database_manager, tag_id).await?.is_empty());
+        assert!(history.redo().await?.is_none());
+
+        let labels: Vec<(String, bool)> = history
+            .list(10)
+            .await?
+            .into_iter()
+            .map(|entry| (entry.label, entry.undone))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("Delete tag Holiday".to_string(), false),
+                ("Assign tag Holiday".to_string(), false),
+                ("Create tag Holiday".to_string(), false),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn undoing_an_assignment_keeps_tags_that_were_already_there() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 2).await?;
+        let history = HistoryOperations::new(Arc::clone(&database_manager));
+        let TagCommand::CreateTag {
+            tag_id: Some(tag_id),
+            ..
+        } = history
+            .execute(
+                "Create tag Work",
+                TagCommand::CreateTag {
+                    tag_id: None,
+                    name: "Work".to_string(),
+                    file_ids: vec![file_ids[0]],
+                    details: TagDetails::default(),
+                },
+            )
+            .await?
+        else {
+            panic!("the tag was not created");
+        };
+
+        let applied = history
+            .execute(
+                "Assign tag Work",
+                TagCommand::AssignTag {
+                    tag_id,
+                    file_ids: file_ids.clone(),
+                },
+            )
+            .await?;
+        assert_eq!(
+            applied,
+            TagCommand::AssignTag {
+                tag_id,
+                file_ids: vec![file_ids[1]],
+            }
+        );
+        history.undo().await?;
+        assert_eq!(
+            assigned(&database_manager, tag_id).await?,
+            vec![file_ids[0]]
+        );
+
+        // A new command discards what could still be redone
+        history
+            .execute(
+                "Rename tag Work",
+                TagCommand::RenameTag {
+                    tag_id,
+                    from: "Work".to_string(),
+                    to: "Office".to_string(),
+                },
+            )
+            .await?;
+        assert!(history.redo().await?.is_none());
+        assert_eq!(history.list(10).await?.len(), 2);
+        Ok(())
+    }
+
+    async fn create_tag(
+        history: &HistoryOperations,
+        name: &str,
+        file_ids: Vec<i32>,
+    ) -> Result<i32> {
+        match history
+            .execute(
+                &format!("Create tag {name}"),
+                TagCommand::CreateTag {
+                    tag_id: None,
+                    name: name.to_string(),
+                    file_ids,
+                    details: TagDetails::default(),
+                },
+            )
+            .await?
+        {
+            TagCommand::CreateTag {
+                tag_id: Some(tag_id),
+                ..
+            } => Ok(tag_id),
+            command => bail!("the tag was not created: {command:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn merged_tag_moves_files_and_links_and_can_be_undone() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 3).await?;
+        let history = HistoryOperations::new(Arc::clone(&database_manager));
+        let invoices = create_tag(&history, "invoices", vec![file_ids[0], file_ids[1]]).await?;
+        let invoice = create_tag(&history, "invoice", vec![file_ids[1], file_ids[2]]).await?;
+        let finance = create_tag(&history, "finance", Vec::new()).await?;
+        let db = database_manager.get_connection();
+        link(
+            db.as_ref(),
+            vec![TagLink {
+                super_tag_id: finance,
+                sub_tag_id: invoice,
+            }],
+        )
+        .await?;
+
+        history
+            .execute(
+                "Merge invoice into invoices",
+                TagCommand::MergeTag {
+                    tag_id: invoice,
+                    into: invoices,
+                },
+            )
+            .await?;
+        assert_eq!(assigned(&database_manager, invoices).await?, file_ids);
+        assert!(Tags::find_by_id(invoice).one(db.as_ref()).await?.is_none());
+        assert_eq!(
+            links_of(db.as_ref(), invoices).await?,
+            vec![TagLink {
+                super_tag_id: finance,
+                sub_tag_id: invoices,
+            }]
+        );
+        let target = Tags::find_by_id(invoices).all(db.as_ref()).await?;
+        assert_eq!(
+            details_of(db.as_ref(), &target).await?[0].aliases,
+            vec!["invoice".to_string()]
+        );
+
+        history.undo().await?.expect("the merge can be undone");
+        assert_eq!(
+            assigned(&database_manager, invoices).await?,
+            vec![file_ids[0], file_ids[1]]
+        );
+        assert_eq!(
+            assigned(&database_manager, invoice).await?,
+            vec![file_ids[1], file_ids[2]]
+        );
+        assert!(links_of(db.as_ref(), invoices).await?.is_empty());
+        assert_eq!(links_of(db.as_ref(), invoice).await?.len(), 1);
+        assert!(
+            details_of(db.as_ref(), &target).await?[0]
+                .aliases
+                .is_empty()
+        );
+
+        history.redo().await?.expect("the merge can be redone");
+        assert_eq!(assigned(&database_manager, invoices).await?, file_ids);
+        assert!(Tags::find_by_id(invoice).one(db.as_ref()).await?.is_none());
+        Ok(())
+    }
+}
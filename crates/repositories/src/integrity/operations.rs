@@ -0,0 +1,603 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseBackend, EntityTrait, QueryFilter,
+    QuerySelect, QueryTrait, Statement, TransactionTrait,
+};
+
+use entity::{file_has_tags, file_system_identifier, files, folders, prelude::*, tags, thumbnails};
+use model::services::integrity::{IntegrityProblem, IntegrityReport};
+use model::services::journal::{JournalEntry, JournalEventKind, JournalSource};
+use model::services::thumbnail::{ThumbnailSize, ThumbnailStorageKind};
+
+use crate::journal::operations::JournalOperations;
+use crate::manager::DatabaseManager;
+use crate::tags::operations::ID_CHUNK_SIZE;
+use crate::thumbnail::cache::ThumbnailCache;
+
+/// What repairing the library changes, collected from the problems of a report
+#[derive(Debug, Default)]
+struct Repairs {
+    files: Vec<i32>,
+    folders: Vec<i32>,
+    parents: Vec<(i32, Option<i32>)>,
+    thumbnails: Vec<i32>,
+    tag_links: Vec<i32>,
+}
+
+/// Repository that checks the library database against the disk and against itself
+#[derive(Debug)]
+pub struct IntegrityOperations {
+    database_manager: Arc<DatabaseManager>,
+    cache: Option<ThumbnailCache>,
+}
+
+impl IntegrityOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self {
+            database_manager,
+            cache: None,
+        }
+    }
+
+    /// Check the thumbnails stored in a cache directory too
+    pub fn with_cache(mut self, cache: ThumbnailCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Check the database file, every file and folder below the library folders `roots` and
+    /// the rows referring to them
+    pub async fn verify(&self, roots: &[PathBuf]) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+        report.problems.extend(self.check_database().await?);
+        let missing_roots: Vec<PathBuf> = roots
+            .iter()
+            .zip(exists_on_disk(roots.to_vec()).await?)
+            .filter(|(_, exists)| !exists)
+            .map(|(root, _)| root.clone())
+            .collect();
+        self.check_folders(roots, &missing_roots, &mut report)
+            .await?;
+        self.check_files(&missing_roots, &mut report).await?;
+        report.problems.extend(self.check_references().await?);
+        report.problems.extend(self.check_thumbnail_data().await?);
+        Ok(report)
+    }
+
+    /// Fix the repairable problems of a report, missing files and folders are removed
+    ///
+    /// File system identifiers nothing refers to anymore are removed as well, including those of
+    /// the files removed here.
+    pub async fn repair(&self, report: &IntegrityReport) -> Result<()> {
+        let mut repairs = Repairs::default();
+        for problem in &report.problems {
+            match problem {
+                IntegrityProblem::MissingFile { file_id, .. } => repairs.files.push(*file_id),
+                IntegrityProblem::MissingFolder { folder_id, .. }
+                | IntegrityProblem::DetachedFolder { folder_id, .. } => {
+                    repairs.folders.push(*folder_id);
+                }
+                IntegrityProblem::WrongParent {
+                    folder_id,
+                    expected,
+                    ..
+                } => repairs.parents.push((*folder_id, *expected)),
+                IntegrityProblem::OrphanedThumbnail { thumbnail_id, .. }
+                | IntegrityProblem::MissingThumbnailData { thumbnail_id, .. } => {
+                    repairs.thumbnails.push(*thumbnail_id);
+                }
+                IntegrityProblem::OrphanedTagLink { link_id, .. } => {
+                    repairs.tag_links.push(*link_id);
+                }
+                IntegrityProblem::Corruption { .. }
+                | IntegrityProblem::MissingRoot { .. }
+                | IntegrityProblem::OrphanedIdentifier { .. } => {}
+            }
+        }
+
+        let db = self.database_manager.get_connection();
+        let txn = db
+            .begin()
+            .await
+            .context("Failed to start transaction for repairing the library")?;
+        Self::remove_files(&txn, &repairs.files).await?;
+        for chunk in repairs.folders.chunks(ID_CHUNK_SIZE) {
+            Folders::delete_many()
+                .filter(folders::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await
+                .context("Failed to remove missing folders")?;
+        }
+        for (folder_id, parent_folder_id) in repairs.parents {
+            Folders::update_many()
+                .col_expr(
+                    folders::Column::ParentFolderId,
+                    Expr::value(parent_folder_id),
+                )
+                .filter(folders::Column::Id.eq(folder_id))
+                .exec(&txn)
+                .await
+                .context("Failed to correct the parent of a folder")?;
+        }
+        for chunk in repairs.thumbnails.chunks(ID_CHUNK_SIZE) {
+            Thumbnails::delete_many()
+                .filter(thumbnails::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await
+                .context("Failed to remove broken thumbnails")?;
+        }
+        for chunk in repairs.tag_links.chunks(ID_CHUNK_SIZE) {
+            FileHasTags::delete_many()
+                .filter(file_has_tags::Column::Id.is_in(chunk.to_vec()))
+                .exec(&txn)
+                .await
+                .context("Failed to remove orphaned tag assignments")?;
+        }
+        FileSystemIdentifier::delete_many()
+            .filter(unreferenced_identifiers())
+            .exec(&txn)
+            .await
+            .context("Failed to remove unused file system identifiers")?;
+        txn.commit()
+            .await
+            .context("Failed to commit the repair of the library")?;
+        Ok(())
+    }
+
+    async fn check_database(&self) -> Result<Vec<IntegrityProblem>> {
+        let db = self.database_manager.get_connection();
+        let rows = db
+            .query_all(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                "PRAGMA integrity_check".to_string(),
+            ))
+            .await
+            .context("Failed to run the integrity check of the database")?;
+        let mut problems = Vec::new();
+        for row in rows {
+            let message: String = row
+                .try_get_by_index(0)
+                .context("Failed to read the integrity check of the database")?;
+            if message != "ok" {
+                problems.push(IntegrityProblem::Corruption { message });
+            }
+        }
+        Ok(problems)
+    }
+
+    async fn check_folders(
+        &self,
+        roots: &[PathBuf],
+        missing_roots: &[PathBuf],
+        report: &mut IntegrityReport,
+    ) -> Result<()> {
+        let db = self.database_manager.get_connection();
+        let rows: Vec<(i32, Option<i32>, String)> = Folders::find()
+            .select_only()
+            .column(folders::Column::Id)
+            .column(folders::Column::ParentFolderId)
+            .column(folders::Column::Path)
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query folders for verification")?;
+        let folder_ids: HashMap<PathBuf, i32> = rows
+            .iter()
+            .map(|(folder_id, _, path)| (PathBuf::from(path), *folder_id))
+            .collect();
+
+        let mut folders = Vec::new();
+        for (folder_id, parent_folder_id, path) in rows {
+            let path = PathBuf::from(path);
+            if missing_roots.contains(&path) {
+                report
+                    .problems
+                    .push(IntegrityProblem::MissingRoot { folder_id, path });
+            } else if !is_below_any(&path, missing_roots) {
+                folders.push((folder_id, parent_folder_id, path));
+            }
+        }
+        report.folders_checked = folders.len();
+        let paths = folders.iter().map(|(_, _, path)| path.clone()).collect();
+        for ((folder_id, parent_folder_id, path), exists) in
+            folders.into_iter().zip(exists_on_disk(paths).await?)
+        {
+            if !exists {
+                report
+                    .problems
+                    .push(IntegrityProblem::MissingFolder { folder_id, path });
+                continue;
+            }
+            let expected = if roots.contains(&path) {
+                None
+            } else if let Some(parent) = path.parent().and_then(|parent| folder_ids.get(parent)) {
+                Some(*parent)
+            } else {
+                report
+                    .problems
+                    .push(IntegrityProblem::DetachedFolder { folder_id, path });
+                continue;
+            };
+            if parent_folder_id != expected {
+                report.problems.push(IntegrityProblem::WrongParent {
+                    folder_id,
+                    path,
+                    parent_folder_id,
+                    expected,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    async fn check_files(
+        &self,
+        missing_roots: &[PathBuf],
+        report: &mut IntegrityReport,
+    ) -> Result<()> {
+        let db = self.database_manager.get_connection();
+        let rows: Vec<(i32, String)> = Files::find()
+            .select_only()
+            .column(files::Column::Id)
+            .column(files::Column::Path)
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query files for verification")?;
+        let files: Vec<(i32, PathBuf)> = rows
+            .into_iter()
+            .map(|(file_id, path)| (file_id, PathBuf::from(path)))
+            .filter(|(_, path)| !is_below_any(path, missing_roots))
+            .collect();
+        report.files_checked = files.len();
+        let paths = files.iter().map(|(_, path)| path.clone()).collect();
+        for ((file_id, path), exists) in files.into_iter().zip(exists_on_disk(paths).await?) {
+            if !exists {
+                report
+                    .problems
+                    .push(IntegrityProblem::MissingFile { file_id, path });
+            }
+        }
+        Ok(())
+    }
+
+    /// Identifiers, thumbnails and tag assignments whose rows they belong to are gone
+    async fn check_references(&self) -> Result<Vec<IntegrityProblem>> {
+        let db = self.database_manager.get_connection();
+        let identifiers: Vec<i32> = FileSystemIdentifier::find()
+            .select_only()
+            .column(file_system_identifier::Column::Id)
+            .filter(unreferenced_identifiers())
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query unused file system identifiers")?;
+        let thumbnails: Vec<(i32, i32)> = Thumbnails::find()
+            .select_only()
+            .column(thumbnails::Column::Id)
+            .column(thumbnails::Column::FileId)
+            .filter(thumbnails::Column::FileId.not_in_subquery(file_ids()))
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query orphaned thumbnails")?;
+        let tag_links: Vec<(i32, i32, i32)> = FileHasTags::find()
+            .select_only()
+            .column(file_has_tags::Column::Id)
+            .column(file_has_tags::Column::FileId)
+            .column(file_has_tags::Column::TagId)
+            .filter(
+                Condition::any()
+                    .add(file_has_tags::Column::FileId.not_in_subquery(file_ids()))
+                    .add(
+                        file_has_tags::Column::TagId.not_in_subquery(
+                            Tags::find()
+                                .select_only()
+                                .column(tags::Column::Id)
+                                .into_query(),
+                        ),
+                    ),
+            )
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query orphaned tag assignments")?;
+
+        let identifiers = identifiers
+            .into_iter()
+            .map(|identifier_id| IntegrityProblem::OrphanedIdentifier { identifier_id });
+        let thumbnails = thumbnails.into_iter().map(|(thumbnail_id, file_id)| {
+            IntegrityProblem::OrphanedThumbnail {
+                thumbnail_id,
+                file_id,
+            }
+        });
+        let tag_links = tag_links.into_iter().map(|(link_id, file_id, tag_id)| {
+            IntegrityProblem::OrphanedTagLink {
+                link_id,
+                file_id,
+                tag_id,
+            }
+        });
+        Ok(identifiers.chain(thumbnails).chain(tag_links).collect())
+    }
+
+    /// Thumbnails stored in the cache directory whose image is no longer there
+    async fn check_thumbnail_data(&self) -> Result<Vec<IntegrityProblem>> {
+        let Some(cache) = self.cache.as_ref() else {
+            return Ok(Vec::new());
+        };
+        let db = self.database_manager.get_connection();
+        let rows: Vec<(i32, i32, Option<String>, String, String)> = Thumbnails::find()
+            .select_only()
+            .column(thumbnails::Column::Id)
+            .column(thumbnails::Column::FileId)
+            .column(thumbnails::Column::ContentHash)
+            .column(thumbnails::Column::Size)
+            .column(thumbnails::Column::MimeType)
+            .filter(thumbnails::Column::Storage.eq(ThumbnailStorageKind::Directory.to_string()))
+            .filter(thumbnails::Column::FileId.in_subquery(file_ids()))
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query cached thumbnails for verification")?;
+
+        // A thumbnail without a usable cache path has no image either
+        let mut thumbnails = Vec::new();
+        let mut paths = Vec::new();
+        for (thumbnail_id, file_id, content_hash, size, mime_type) in rows {
+            let path = content_hash.and_then(|content_hash| {
+                let size = ThumbnailSize::try_from(size).ok()?;
+                cache.path_for(&content_hash, size, &mime_type).ok()
+            });
+            thumbnails.push((thumbnail_id, file_id));
+            paths.push(path.unwrap_or_default());
+        }
+        Ok(thumbnails
+            .into_iter()
+            .zip(exists_on_disk(paths).await?)
+            .filter(|(_, exists)| !exists)
+            .map(
+                |((thumbnail_id, file_id), _)| IntegrityProblem::MissingThumbnailData {
+                    thumbnail_id,
+                    file_id,
+                },
+            )
+            .collect())
+    }
+
+    /// Remove the rows of files missing on disk, recording their removal in the journal
+    async fn remove_files<C>(connection: &C, file_ids: &[i32]) -> Result<()>
+    where
+        C: ConnectionTrait,
+    {
+        for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+            let removed = Files::find()
+                .filter(files::Column::Id.is_in(chunk.to_vec()))
+                .all(connection)
+                .await
+                .context("Failed to query missing files")?;
+            Files::delete_many()
+                .filter(files::Column::Id.is_in(chunk.to_vec()))
+                .exec(connection)
+                .await
+                .context("Failed to remove missing files")?;
+            let entries = removed
+                .into_iter()
+                .map(|file| {
+                    JournalEntry::new(JournalEventKind::Deleted, JournalSource::Scanner)
+                        .with_file(file.id)
+                        .with_paths(Some(PathBuf::from(file.path)), None)
+                        .with_hashes(Some(file.content_hash), None)
+                        .with_detail("missing on disk")
+                })
+                .collect();
+            JournalOperations::record(connection, entries).await?;
+        }
+        Ok(())
+    }
+}
+
+fn file_ids() -> sea_orm::sea_query::SelectStatement {
+    Files::find()
+        .select_only()
+        .column(files::Column::Id)
+        .into_query()
+}
+
+fn unreferenced_identifiers() -> Condition {
+    Condition::all()
+        .add(
+            file_system_identifier::Column::Id.not_in_subquery(
+                Files::find()
+                    .select_only()
+                    .column(files::Column::FileSystemId)
+                    .into_query(),
+            ),
+        )
+        .add(
+            file_system_identifier::Column::Id.not_in_subquery(
+                Folders::find()
+                    .select_only()
+                    .column(folders::Column::FileSystemId)
+                    .into_query(),
+            ),
+        )
+}
+
+fn is_below_any(path: &Path, roots: &[PathBuf]) -> bool {
+    roots.iter().any(|root| path.starts_with(root))
+}
+
+/// Whether each path exists, a path that cannot be looked at counts as existing
+async fn exists_on_disk(paths: Vec<PathBuf>) -> Result<Vec<bool>> {
+    tokio::task::spawn_blocking(move || {
+        paths
+            .iter()
+            .map(|path| !matches!(path.try_exists(), Ok(false)))
+            .collect()
+    })
+    .await
+    .context("Failed to look for the library on disk")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_database;
+    use chrono::Utc;
+    use sea_orm::ActiveModelTrait;
+    use sea_orm::ActiveValue::Set;
+    use tempfile::TempDir;
+
+    async fn identifier<C: ConnectionTrait>(db: &C) -> Result<i32> {
+        let identifier = file_system_identifier::ActiveModel {
+            inode: Set(Some(1)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(identifier.id)
+    }
+
+    async fn insert_folder<C: ConnectionTrait>(
+        db: &C,
+        path: &Path,
+        parent_folder_id: Option<i32>,
+    ) -> Result<i32> {
+        let now = Utc::now().naive_utc();
+        let folder = folders::ActiveModel {
+            name: Set("folder".to_string()),
+            path: Set(path.to_string_lossy().to_string()),
+            parent_folder_id: Set(parent_folder_id),
+            content_hash: Set(String::new()),
+            identity_hash: Set(String::new()),
+            structure_hash: Set(String::new()),
+            file_system_id: Set(identifier(db).await?),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(folder.id)
+    }
+
+    async fn insert_file<C: ConnectionTrait>(
+        db: &C,
+        path: &Path,
+        file_type_id: i32,
+    ) -> Result<i32> {
+        let now = Utc::now().naive_utc();
+        let file = files::ActiveModel {
+            name: Set("file.txt".to_string()),
+            path: Set(path.to_string_lossy().to_string()),
+            content_hash: Set("ab".repeat(16)),
+            identity_hash: Set(String::new()),
+            file_system_id: Set(identifier(db).await?),
+            file_type_id: Set(file_type_id),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+        Ok(file.id)
+    }
+
+    #[tokio::test]
+    async fn problems_are_found_and_repaired() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let db = database_manager.get_connection();
+        let root = directory.path().join("library");
+        let unmounted = directory.path().join("unmounted");
+        std::fs::create_dir_all(root.join("kept"))?;
+        std::fs::create_dir_all(directory.path().join("outside"))?;
+        std::fs::write(root.join("kept.txt"), "kept")?;
+        let file_type = entity::file_types::ActiveModel {
+            name: Set("text".to_string()),
+            ..Default::default()
+        }
+        .insert(db.as_ref())
+        .await?;
+
+        let root_id = insert_folder(db.as_ref(), &root, None).await?;
+        let kept = insert_folder(db.as_ref(), &root.join("kept"), None).await?;
+        let gone = insert_folder(db.as_ref(), &root.join("gone"), Some(root_id)).await?;
+        let outside = insert_folder(db.as_ref(), &directory.path().join("outside"), None).await?;
+        let unmounted_id = insert_folder(db.as_ref(), &unmounted, None).await?;
+        insert_file(db.as_ref(), &root.join("kept.txt"), file_type.id).await?;
+        let missing = insert_file(db.as_ref(), &root.join("missing.txt"), file_type.id).await?;
+        insert_file(db.as_ref(), &unmounted.join("away.txt"), file_type.id).await?;
+        let orphan = identifier(db.as_ref()).await?;
+
+        let cache = ThumbnailCache::new(directory.path().join("thumbnails"));
+        let thumbnail = thumbnails::ActiveModel {
+            file_id: Set(missing),
+            size: Set(ThumbnailSize::Small.to_string()),
+            data: Set(Vec::new()),
+            mime_type: Set("image/png".to_string()),
+            file_size: Set(0),
+            created_at: Set(Utc::now().naive_utc()),
+            updated_at: Set(Utc::now().naive_utc()),
+            content_hash: Set(Some("ab".repeat(16))),
+            storage: Set(ThumbnailStorageKind::Directory.to_string()),
+            ..Default::default()
+        }
+        .insert(db.as_ref())
+        .await?;
+
+        let integrity = IntegrityOperations::new(Arc::clone(&database_manager)).with_cache(cache);
+        let roots = [root.clone(), unmounted.clone()];
+        let report = integrity.verify(&roots).await?;
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.folders_checked, 4);
+        let expected = [
+            IntegrityProblem::MissingRoot {
+                folder_id: unmounted_id,
+                path: unmounted.clone(),
+            },
+            IntegrityProblem::WrongParent {
+                folder_id: kept,
+                path: root.join("kept"),
+                parent_folder_id: None,
+                expected: Some(root_id),
+            },
+            IntegrityProblem::MissingFolder {
+                folder_id: gone,
+                path: root.join("gone"),
+            },
+            IntegrityProblem::DetachedFolder {
+                folder_id: outside,
+                path: directory.path().join("outside"),
+            },
+            IntegrityProblem::MissingFile {
+                file_id: missing,
+                path: root.join("missing.txt"),
+            },
+            IntegrityProblem::OrphanedIdentifier {
+                identifier_id: orphan,
+            },
+            IntegrityProblem::MissingThumbnailData {
+                thumbnail_id: thumbnail.id,
+                file_id: missing,
+            },
+        ];
+        assert_eq!(report.problems, expected);
+
+        integrity.repair(&report).await?;
+        let report = integrity.verify(&roots).await?;
+        assert_eq!(report.problems, expected[..1]);
+        assert!(Files::find_by_id(missing).one(db.as_ref()).await?.is_none());
+        let journal = FileEvents::find().all(db.as_ref()).await?;
+        assert_eq!(journal.len(), 1);
+        assert_eq!(journal[0].file_id, Some(missing));
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::path::{MAIN_SEPARATOR, Path};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+
+use entity::{file_events, prelude::*};
+use model::services::journal::JournalEntry;
+
+use crate::manager::DatabaseManager;
+
+/// Entries inserted per statement
+const RECORD_CHUNK_SIZE: usize = 1_000;
+
+/// Repository for the append-only journal of what happened to files
+///
+/// Entries are written in the same transaction as the change they describe, so the journal
+/// never records a change that was rolled back.
+#[derive(Debug)]
+pub struct JournalOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl JournalOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Append entries using the given connection or transaction
+    pub async fn record<C>(connection: &C, entries: Vec<JournalEntry>) -> Result<()>
+    where
+        C: ConnectionTrait,
+    {
+        let mut entries = entries.into_iter().peekable();
+        while entries.peek().is_some() {
+            // Every entry binds one parameter per column, stay below SQLite's limit
+            FileEvents::insert_many(
+                entries
+                    .by_ref()
+                    .take(RECORD_CHUNK_SIZE)
+                    .map(JournalEntry::to_active_model),
+            )
+            .exec(connection)
+            .await
+            .context("Failed to append to the file journal")?;
+        }
+        Ok(())
+    }
+
+    /// Append entries outside of any other change
+    pub async fn append(&self, entries: Vec<JournalEntry>) -> Result<()> {
+        let db = self.database_manager.get_connection();
+        Self::record(db.as_ref(), entries).await
+    }
+
+    /// The most recent entries of the whole library
+    pub async fn list_recent(&self, limit: u64) -> Result<Vec<JournalEntry>> {
+        self.list(Condition::all(), limit).await
+    }
+
+    /// The most recent entries of a file, including the ones from before it was moved
+    pub async fn list_for_file(&self, file_id: i32, limit: u64) -> Result<Vec<JournalEntry>> {
+        self.list(
+            Condition::all().add(file_events::Column::FileId.eq(file_id)),
+            limit,
+        )
+        .await
+    }
+
+    /// The most recent entries of files that are or were below a folder
+    pub async fn list_below(&self, folder_path: &Path, limit: u64) -> Result<Vec<JournalEntry>> {
+        let prefix = format!(
+            "{}{MAIN_SEPARATOR}",
+            folder_path
+                .to_string_lossy()
+                .trim_end_matches(MAIN_SEPARATOR)
+        );
+        self.list(
+            Condition::any()
+                .add(file_events::Column::OldPath.starts_with(&prefix))
+                .add(file_events::Column::NewPath.starts_with(&prefix)),
+            limit,
+        )
+        .await
+    }
+
+    async fn list(&self, condition: Condition, limit: u64) -> Result<Vec<JournalEntry>> {
+        let db = self.database_manager.get_connection();
+        FileEvents::find()
+            .filter(condition)
+            .order_by_desc(file_events::Column::OccurredAt)
+            .order_by_desc(file_events::Column::Id)
+            .limit(limit)
+            .all(db.as_ref())
+            .await
+            .context("Failed to query the file journal")?
+            .into_iter()
+            .map(JournalEntry::try_from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_database;
+    use model::services::journal::{JournalEventKind, JournalSource};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn journal_is_listed_per_file_and_folder_newest_first() -> Result<()> {
+        let directory = TempDir::new()?;
+        let journal = JournalOperations::new(migrated_database(&directory).await?);
+        let contract = PathBuf::from("/library/contracts/lease.pdf");
+        let moved = PathBuf::from("/library/archive/lease.pdf");
+        journal
+            .append(vec![
+                JournalEntry::new(JournalEventKind::Created, JournalSource::Scanner)
+                    .with_file(1)
+                    .with_paths(None, Some(contract.clone())),
+                JournalEntry::new(JournalEventKind::Created, JournalSource::Scanner)
+                    .with_file(2)
+                    .with_paths(None, Some(PathBuf::from("/library/contracts-old/a.pdf"))),
+                JournalEntry::new(JournalEventKind::Moved, JournalSource::Watcher)
+                    .with_file(1)
+                    .with_paths(Some(contract), Some(moved.clone())),
+                JournalEntry::new(JournalEventKind::Deleted, JournalSource::Watcher)
+                    .with_file(1)
+                    .with_paths(Some(moved), None),
+            ])
+            .await?;
+
+        let kinds = |entries: Vec<JournalEntry>| -> Vec<JournalEventKind> {
+            entries.into_iter().map(|entry| entry.kind).collect()
+        };
+        assert_eq!(
+            kinds(journal.list_for_file(1, 10).await?),
+            vec![
+                JournalEventKind::Deleted,
+                JournalEventKind::Moved,
+                JournalEventKind::Created
+            ]
+        );
+        // The sibling folder sharing the name prefix is not included
+        assert_eq!(
+            kinds(
+                journal
+                    .list_below(Path::new("/library/contracts/"), 10)
+                    .await?
+            ),
+            vec![JournalEventKind::Moved, JournalEventKind::Created]
+        );
+        assert_eq!(journal.list_recent(2).await?.len(), 2);
+        Ok(())
+    }
+}
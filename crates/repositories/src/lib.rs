@@ -1,4 +1,17 @@
+pub mod backup;
 pub mod config;
+pub mod curation;
+mod decorations;
+pub mod fields;
+pub mod folders;
 pub mod fs;
+pub mod history;
+pub mod integrity;
+pub mod journal;
 pub mod manager;
+pub mod media;
+pub mod searches;
+pub mod tags;
+#[cfg(test)]
+pub(crate) mod test_support;
 pub mod thumbnail;
@@ -43,6 +43,14 @@ impl DatabaseManager {
         &self.settings
     }
 
+    /// Close every connection, so that the database file can be replaced
+    pub async fn close(&self) -> Result<()> {
+        self.connection
+            .close_by_ref()
+            .await
+            .context("failed to close the database")
+    }
+
     pub async fn test_connection(&self) -> Result<()> {
         let statement = sea_orm::query::Statement::from_string(
             sea_orm::DatabaseBackend::Sqlite,
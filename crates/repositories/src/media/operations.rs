@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QuerySelect, QueryTrait, Set,
+    TryIntoModel,
+};
+
+use entity::{files, media_metadata, prelude::*};
+use model::services::media::MediaMetadata as Metadata;
+
+use crate::manager::DatabaseManager;
+
+/// Repository for photo metadata extracted from image files
+#[derive(Debug)]
+pub struct MediaMetadataOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl MediaMetadataOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Update or insert (upsert) the metadata of a file
+    pub async fn upsert_metadata(
+        &self,
+        file_id: i32,
+        metadata: Metadata,
+    ) -> Result<media_metadata::Model> {
+        let db = self.database_manager.get_connection();
+
+        let existing = MediaMetadata::find()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .one(db.as_ref())
+            .await
+            .context("Failed to check for existing media metadata")?;
+
+        let mut active_model = metadata.to_active_model(file_id);
+        let result = match existing {
+            Some(existing_model) => {
+                active_model.id = Set(existing_model.id);
+                active_model.created_at = Set(existing_model.created_at);
+                active_model
+                    .update(db.as_ref())
+                    .await
+                    .context("Failed to update media metadata")?
+            }
+            None => active_model
+                .save(db.as_ref())
+                .await
+                .context("Failed to save media metadata")?
+                .try_into_model()
+                .map_err(|_| anyhow::anyhow!("Failed to convert saved media metadata to model"))?,
+        };
+
+        Ok(result)
+    }
+
+    /// Get the metadata of a file, if it has been extracted
+    pub async fn get_for_file(&self, file_id: i32) -> Result<Option<Metadata>> {
+        let db = self.database_manager.get_connection();
+
+        let model = MediaMetadata::find()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .one(db.as_ref())
+            .await
+            .context("Failed to query media metadata for file")?;
+
+        Ok(model.map(Metadata::from))
+    }
+
+    /// Get the files whose metadata has never been extracted
+    pub async fn get_files_without_metadata(&self) -> Result<Vec<files::Model>> {
+        let db = self.database_manager.get_connection();
+
+        Files::find()
+            .filter(
+                files::Column::Id.not_in_subquery(
+                    MediaMetadata::find()
+                        .select_only()
+                        .column(media_metadata::Column::FileId)
+                        .into_query(),
+                ),
+            )
+            .all(db.as_ref())
+            .await
+            .context("Failed to query files without media metadata")
+    }
+
+    /// Delete the metadata of a file
+    pub async fn delete_for_file(&self, file_id: i32) -> Result<u64> {
+        let db = self.database_manager.get_connection();
+
+        let delete_result = MediaMetadata::delete_many()
+            .filter(media_metadata::Column::FileId.eq(file_id))
+            .exec(db.as_ref())
+            .await
+            .context("Failed to delete media metadata for file")?;
+
+        Ok(delete_result.rows_affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_files, migrated_database};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn files_without_metadata_are_listed_for_backfill() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_ids = insert_files(&database_manager, 2).await?;
+        let media = MediaMetadataOperations::new(Arc::clone(&database_manager));
+
+        media
+            .upsert_metadata(
+                file_ids[0],
+                Metadata {
+                    camera_model: Some("Pixel 8".to_string()),
+                    ..Metadata::default()
+                },
+            )
+            .await?;
+
+        let missing: Vec<i32> = media
+            .get_files_without_metadata()
+            .await?
+            .into_iter()
+            .map(|file| file.id)
+            .collect();
+        assert_eq!(missing, file_ids[1..]);
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder,
+};
+
+use entity::{prelude::*, saved_searches};
+use model::commands::filter::{Filter, SavedSearch};
+
+use crate::manager::DatabaseManager;
+
+/// Repository for the named searches of a library
+#[derive(Debug)]
+pub struct SavedSearchOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl SavedSearchOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Every saved search, ordered by name
+    pub async fn list(&self) -> Result<Vec<SavedSearch>> {
+        let db = self.database_manager.get_connection();
+        SavedSearches::find()
+            .order_by_asc(saved_searches::Column::Name)
+            .all(db.as_ref())
+            .await
+            .context("Failed to query saved searches")?
+            .into_iter()
+            .map(SavedSearch::try_from)
+            .collect()
+    }
+
+    pub async fn get(&self, search_id: i32) -> Result<Option<SavedSearch>> {
+        let db = self.database_manager.get_connection();
+        SavedSearches::find_by_id(search_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query saved search")?
+            .map(SavedSearch::try_from)
+            .transpose()
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<SavedSearch>> {
+        let db = self.database_manager.get_connection();
+        SavedSearches::find()
+            .filter(saved_searches::Column::Name.eq(name))
+            .one(db.as_ref())
+            .await
+            .context("Failed to query saved search")?
+            .map(SavedSearch::try_from)
+            .transpose()
+    }
+
+    pub async fn create(&self, name: &str, filter: &Filter) -> Result<SavedSearch> {
+        let db = self.database_manager.get_connection();
+        let now = Utc::now().naive_utc();
+        saved_searches::ActiveModel {
+            id: NotSet,
+            name: Set(name.to_string()),
+            filter: Set(serde_json::to_string(filter)?),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(db.as_ref())
+        .await
+        .context("Failed to save search")?
+        .try_into()
+    }
+
+    /// Replace the name and filter of a saved search
+    pub async fn update(&self, search_id: i32, name: &str, filter: &Filter) -> Result<SavedSearch> {
+        let db = self.database_manager.get_connection();
+        let Some(search) = SavedSearches::find_by_id(search_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query saved search")?
+        else {
+            bail!("saved search {search_id} does not exist");
+        };
+        let mut search = search.into_active_model();
+        search.name = Set(name.to_string());
+        search.filter = Set(serde_json::to_string(filter)?);
+        search.updated_at = Set(Utc::now().naive_utc());
+        search
+            .update(db.as_ref())
+            .await
+            .context("Failed to update saved search")?
+            .try_into()
+    }
+
+    /// Returns whether the search existed
+    pub async fn delete(&self, search_id: i32) -> Result<bool> {
+        let db = self.database_manager.get_connection();
+        let result = SavedSearches::delete_by_id(search_id)
+            .exec(db.as_ref())
+            .await
+            .context("Failed to delete saved search")?;
+        Ok(result.rows_affected > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::migrated_database;
+    use model::commands::filter::{DateFilter, TagFilter};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn saved_searches_keep_their_filter() -> Result<()> {
+        let directory = TempDir::new()?;
+        let searches = SavedSearchOperations::new(migrated_database(&directory).await?);
+        let filter = Filter {
+            tag_filter: Some(TagFilter {
+                untagged: true,
+                ..TagFilter::default()
+            }),
+            extensions: vec!["pdf".to_string()],
+            added: Some(DateFilter::LastDays { days: 7 }),
+            ..Filter::default()
+        };
+
+        let created = searches.create("Untagged PDFs", &filter).await?;
+        assert_eq!(created.filter, filter);
+        assert!(searches.create("Untagged PDFs", &filter).await.is_err());
+        searches.create("All", &Filter::default()).await?;
+        let names: Vec<String> = searches
+            .list()
+            .await?
+            .into_iter()
+            .map(|search| search.name)
+            .collect();
+        assert_eq!(names, vec!["All", "Untagged PDFs"]);
+
+        let updated = searches
+            .update(created.id, "New PDFs", &Filter::default())
+            .await?;
+        assert_eq!(searches.get(created.id).await?, Some(updated));
+        assert!(searches.find_by_name("Untagged PDFs").await?.is_none());
+        assert!(searches.delete(created.id).await?);
+        assert!(!searches.delete(created.id).await?);
+        Ok(())
+    }
+}
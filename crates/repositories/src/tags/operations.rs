@@ -0,0 +1,640 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+};
+
+use entity::{file_has_tags, files, prelude::*, tag_aliases, tag_has_tags, tags};
+use model::services::journal::{JournalEntry, JournalEventKind, JournalSource};
+use model::services::tag::{TagDetails, TagLink};
+use model::services::tag_suggestion::{SuggestableTag, SuggestionContext};
+
+use crate::decorations;
+use crate::journal::operations::JournalOperations;
+use crate::manager::DatabaseManager;
+
+/// Ids handled per query, keeping the bound parameters well below the limit of the database
+pub(crate) const ID_CHUNK_SIZE: usize = 2_000;
+
+/// Tags that were assigned without going through the undo history
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagAssignments {
+    /// Tags that did not exist yet
+    pub created_tags: Vec<i32>,
+    /// `(file_id, tag_id)` of every new assignment
+    pub assigned: Vec<(i32, i32)>,
+}
+
+/// Repository for reading tags with their details and for tag assignments made by the
+/// application itself, such as auto-tag rules
+#[derive(Debug)]
+pub struct TagOperations {
+    database_manager: Arc<DatabaseManager>,
+}
+
+impl TagOperations {
+    pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
+        Self { database_manager }
+    }
+
+    /// Every tag with its details, ordered by name
+    pub async fn list(&self) -> Result<Vec<(tags::Model, TagDetails)>> {
+        let db = self.database_manager.get_connection();
+        let tags = Tags::find()
+            .order_by_asc(tags::Column::Name)
+            .all(db.as_ref())
+            .await
+            .context("Failed to query tags")?;
+        let details = details_of(db.as_ref(), &tags).await?;
+        Ok(tags.into_iter().zip(details).collect())
+    }
+
+    /// What is needed to suggest tags for a file, `None` if the file does not exist
+    pub async fn suggestion_context(&self, file_id: i32) -> Result<Option<SuggestionContext>> {
+        let db = self.database_manager.get_connection();
+        let Some(file) = Files::find_by_id(file_id)
+            .one(db.as_ref())
+            .await
+            .context("Failed to query file")?
+        else {
+            return Ok(None);
+        };
+        let mut context = SuggestionContext {
+            file_name: file.name,
+            ..SuggestionContext::default()
+        };
+        context.file_tags = tags_of_files(db.as_ref(), &[file_id])
+            .await?
+            .into_iter()
+            .map(|(_, tag_id)| tag_id)
+            .collect();
+
+        let path = PathBuf::from(&file.path);
+        if let Some(parent) = path.parent() {
+            let siblings: Vec<i32> = Files::find()
+                .select_only()
+                .column(files::Column::Id)
+                .column(files::Column::Path)
+                .filter(files::Column::Path.starts_with(parent.to_string_lossy()))
+                .filter(files::Column::Id.ne(file_id))
+                .into_tuple::<(i32, String)>()
+                .all(db.as_ref())
+                .await
+                .context("Failed to query files")?
+                .into_iter()
+                .filter(|(_, path)| Path::new(path).parent() == Some(parent))
+                .map(|(id, _)| id)
+                .collect();
+            context.siblings = siblings.len();
+            for (_, tag_id) in tags_of_files(db.as_ref(), &siblings).await? {
+                *context.sibling_tags.entry(tag_id).or_default() += 1;
+            }
+        }
+
+        let mut files_by_tag: HashMap<i32, Vec<i32>> = HashMap::new();
+        for assignment in FileHasTags::find()
+            .filter(file_has_tags::Column::TagId.is_in(context.file_tags.clone()))
+            .filter(file_has_tags::Column::FileId.ne(file_id))
+            .all(db.as_ref())
+            .await
+            .context("Failed to query tag assignments")?
+        {
+            files_by_tag
+                .entry(assignment.tag_id)
+                .or_default()
+                .push(assignment.file_id);
+        }
+        let related: Vec<i32> = files_by_tag
+            .values()
+            .flatten()
+            .copied()
+            .collect::<HashSet<i32>>()
+            .into_iter()
+            .collect();
+        let mut tags_by_file: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (related_file, tag_id) in tags_of_files(db.as_ref(), &related).await? {
+            tags_by_file.entry(related_file).or_default().push(tag_id);
+        }
+        for (tag_id, file_ids) in files_by_tag {
+            let mut together: HashMap<i32, usize> = HashMap::new();
+            for other in file_ids
+                .iter()
+                .filter_map(|id| tags_by_file.get(id))
+                .flatten()
+            {
+                if *other != tag_id {
+                    *together.entry(*other).or_default() += 1;
+                }
+            }
+            context
+                .co_occurrences
+                .insert(tag_id, (file_ids.len(), together));
+        }
+
+        context.tags = self
+            .list()
+            .await?
+            .into_iter()
+            .map(|(tag, details)| SuggestableTag {
+                tag_id: tag.id,
+                name: tag.name,
+                aliases: details.aliases,
+            })
+            .collect();
+        Ok(Some(context))
+    }
+
+    /// The files among the given ones that have the tag, ordered by id
+    pub async fn files_with_tag(&self, tag_id: i32, file_ids: &[i32]) -> Result<Vec<i32>> {
+        let db = self.database_manager.get_connection();
+        let mut tagged = Vec::new();
+        for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+            tagged.extend(
+                FileHasTags::find()
+                    .select_only()
+                    .column(file_has_tags::Column::FileId)
+                    .filter(file_has_tags::Column::TagId.eq(tag_id))
+                    .filter(file_has_tags::Column::FileId.is_in(chunk.to_vec()))
+                    .into_tuple::<i32>()
+                    .all(db.as_ref())
+                    .await
+                    .context("Failed to query tag assignments")?,
+            );
+        }
+        tagged.sort_unstable();
+        tagged.dedup();
+        Ok(tagged)
+    }
+
+    /// Assign tags by name in one transaction, creating the tags that do not exist yet
+    ///
+    /// Files that already have a tag are left alone, so applying the same tags twice is a no-op.
+    pub async fn assign_by_name(
+        &self,
+        assignments: Vec<(i32, Vec<String>)>,
+        source: JournalSource,
+    ) -> Result<TagAssignments> {
+        let mut files_by_tag: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        for (file_id, names) in assignments {
+            for name in names {
+                files_by_tag.entry(name).or_default().push(file_id);
+            }
+        }
+        let mut result = TagAssignments::default();
+        if files_by_tag.is_empty() {
+            return Ok(result);
+        }
+
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        for (name, file_ids) in files_by_tag {
+            let existing = Tags::find()
+                .filter(tags::Column::Name.eq(&name))
+                .one(&transaction)
+                .await
+                .context("Failed to query tag")?;
+            let tag = if let Some(tag) = existing {
+                tag
+            } else {
+                let now = Utc::now().naive_utc();
+                let tag = tags::ActiveModel {
+                    id: NotSet,
+                    name: Set(name),
+                    created_at: Set(now),
+                    updated_at: Set(now),
+                    ..Default::default()
+                }
+                .insert(&transaction)
+                .await
+                .context("Failed to create tag")?;
+                result.created_tags.push(tag.id);
+                tag
+            };
+            let assigned = assign(&transaction, &tag, file_ids, source).await?;
+            result
+                .assigned
+                .extend(assigned.into_iter().map(|file_id| (file_id, tag.id)));
+        }
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(result)
+    }
+
+    /// Remove tags by name in one transaction, returns `(file_id, tag_id)` of every removal
+    pub async fn unassign_by_name(
+        &self,
+        removals: Vec<(i32, Vec<String>)>,
+        source: JournalSource,
+    ) -> Result<Vec<(i32, i32)>> {
+        let mut files_by_tag: BTreeMap<String, Vec<i32>> = BTreeMap::new();
+        for (file_id, names) in removals {
+            for name in names {
+                files_by_tag.entry(name).or_default().push(file_id);
+            }
+        }
+        let mut removed = Vec::new();
+        if files_by_tag.is_empty() {
+            return Ok(removed);
+        }
+
+        let db = self.database_manager.get_connection();
+        let transaction = db.begin().await.context("Failed to begin transaction")?;
+        for (name, file_ids) in files_by_tag {
+            let Some(tag) = Tags::find()
+                .filter(tags::Column::Name.eq(&name))
+                .one(&transaction)
+                .await
+                .context("Failed to query tag")?
+            else {
+                continue;
+            };
+            let unassigned = unassign(&transaction, &tag, file_ids, source).await?;
+            removed.extend(unassigned.into_iter().map(|file_id| (file_id, tag.id)));
+        }
+        transaction
+            .commit()
+            .await
+            .context("Failed to commit transaction")?;
+        Ok(removed)
+    }
+
+    /// The names of the tags of each file, ordered by name
+    pub async fn names_of_files(&self, file_ids: &[i32]) -> Result<HashMap<i32, Vec<String>>> {
+        let db = self.database_manager.get_connection();
+        let mut names: HashMap<i32, Vec<String>> = HashMap::new();
+        for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+            let rows: Vec<(i32, String)> = FileHasTags::find()
+                .select_only()
+                .column(file_has_tags::Column::FileId)
+                .column(tags::Column::Name)
+                .inner_join(Tags)
+                .filter(file_has_tags::Column::FileId.is_in(chunk.to_vec()))
+                .order_by_asc(tags::Column::Name)
+                .into_tuple()
+                .all(db.as_ref())
+                .await
+                .context("Failed to query tag assignments")?;
+            for (file_id, name) in rows {
+                names.entry(file_id).or_default().push(name);
+            }
+        }
+        Ok(names)
+    }
+}
+
+/// `(file_id, tag_id)` of every assignment of the files
+async fn tags_of_files<C>(connection: &C, file_ids: &[i32]) -> Result<Vec<(i32, i32)>>
+where
+    C: ConnectionTrait,
+{
+    let mut assignments = Vec::new();
+    for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+        assignments.extend(
+            FileHasTags::find()
+                .select_only()
+                .column(file_has_tags::Column::FileId)
+                .column(file_has_tags::Column::TagId)
+                .filter(file_has_tags::Column::FileId.is_in(chunk.to_vec()))
+                .into_tuple::<(i32, i32)>()
+                .all(connection)
+                .await
+                .context("Failed to query tag assignments")?,
+        );
+    }
+    Ok(assignments)
+}
+
+/// Read the details of the tags, in the same order
+pub(crate) async fn details_of<C>(connection: &C, tags: &[tags::Model]) -> Result<Vec<TagDetails>>
+where
+    C: ConnectionTrait,
+{
+    let tag_ids: Vec<i32> = tags.iter().map(|tag| tag.id).collect();
+    let color_ids: HashSet<i32> = tags.iter().filter_map(|tag| tag.color_id).collect();
+    let icon_ids: HashSet<i32> = tags.iter().filter_map(|tag| tag.icon_id).collect();
+    let colors = decorations::colors(connection, color_ids).await?;
+    let icons = decorations::icons(connection, icon_ids).await?;
+    let mut aliases: HashMap<i32, Vec<String>> = HashMap::new();
+    for chunk in tag_ids.chunks(ID_CHUNK_SIZE) {
+        for alias in TagAliases::find()
+            .filter(tag_aliases::Column::TagId.is_in(chunk.to_vec()))
+            .order_by_asc(tag_aliases::Column::Id)
+            .all(connection)
+            .await
+            .context("Failed to query tag aliases")?
+        {
+            aliases.entry(alias.tag_id).or_default().push(alias.alias);
+        }
+    }
+
+    Ok(tags
+        .iter()
+        .map(|tag| TagDetails {
+            color: tag
+                .color_id
+                .and_then(|color_id| colors.get(&color_id))
+                .cloned(),
+            icon: tag.icon_id.and_then(|icon_id| icons.get(&icon_id)).cloned(),
+            description: tag.description.clone(),
+            aliases: aliases.remove(&tag.id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Store the details of a tag, reusing colors and icons that are already in the database
+pub(crate) async fn store_details<C>(
+    connection: &C,
+    tag: tags::Model,
+    details: &TagDetails,
+) -> Result<tags::Model>
+where
+    C: ConnectionTrait,
+{
+    let color_id = match &details.color {
+        Some(color) => Some(decorations::color_id(connection, color).await?),
+        None => None,
+    };
+    let icon_id = match &details.icon {
+        Some(icon) => Some(decorations::icon_id(connection, icon).await?),
+        None => None,
+    };
+    let tag_id = tag.id;
+    let mut tag = tag.into_active_model();
+    tag.color_id = Set(color_id);
+    tag.icon_id = Set(icon_id);
+    tag.description = Set(details.description.clone());
+    tag.updated_at = Set(Utc::now().naive_utc());
+    let tag = tag
+        .update(connection)
+        .await
+        .context("Failed to update tag")?;
+
+    TagAliases::delete_many()
+        .filter(tag_aliases::Column::TagId.eq(tag_id))
+        .exec(connection)
+        .await
+        .context("Failed to remove tag aliases")?;
+    if !details.aliases.is_empty() {
+        TagAliases::insert_many(
+            details
+                .aliases
+                .iter()
+                .map(|alias| tag_aliases::ActiveModel {
+                    id: NotSet,
+                    tag_id: Set(tag_id),
+                    alias: Set(alias.clone()),
+                }),
+        )
+        .exec(connection)
+        .await
+        .context("Failed to store tag aliases")?;
+    }
+    Ok(tag)
+}
+
+/// Every edge of the tag hierarchy that starts or ends at the tag
+pub(crate) async fn links_of<C>(connection: &C, tag_id: i32) -> Result<Vec<TagLink>>
+where
+    C: ConnectionTrait,
+{
+    Ok(TagHasTags::find()
+        .filter(
+            tag_has_tags::Column::SuperTagId
+                .eq(tag_id)
+                .or(tag_has_tags::Column::SubTagId.eq(tag_id)),
+        )
+        .order_by_asc(tag_has_tags::Column::Id)
+        .all(connection)
+        .await
+        .context("Failed to query the tag hierarchy")?
+        .into_iter()
+        .map(|link| TagLink {
+            super_tag_id: link.super_tag_id,
+            sub_tag_id: link.sub_tag_id,
+        })
+        .collect())
+}
+
+/// Add the edges between existing tags that are not in the hierarchy yet, returning those edges
+///
+/// Edges from a tag to itself are skipped.
+pub(crate) async fn link<C>(connection: &C, links: Vec<TagLink>) -> Result<Vec<TagLink>>
+where
+    C: ConnectionTrait,
+{
+    let tag_ids: HashSet<i32> = links
+        .iter()
+        .flat_map(|link| [link.super_tag_id, link.sub_tag_id])
+        .collect();
+    // Tags may have been deleted since the command was recorded
+    let existing_tags: HashSet<i32> = Tags::find()
+        .select_only()
+        .column(tags::Column::Id)
+        .filter(tags::Column::Id.is_in(tag_ids.clone()))
+        .into_tuple::<i32>()
+        .all(connection)
+        .await
+        .context("Failed to query tags")?
+        .into_iter()
+        .collect();
+    let mut seen: HashSet<TagLink> = TagHasTags::find()
+        .filter(tag_has_tags::Column::SuperTagId.is_in(tag_ids.clone()))
+        .filter(tag_has_tags::Column::SubTagId.is_in(tag_ids))
+        .all(connection)
+        .await
+        .context("Failed to query the tag hierarchy")?
+        .into_iter()
+        .map(|link| TagLink {
+            super_tag_id: link.super_tag_id,
+            sub_tag_id: link.sub_tag_id,
+        })
+        .collect();
+    let added: Vec<TagLink> = links
+        .into_iter()
+        .filter(|link| {
+            link.super_tag_id != link.sub_tag_id
+                && existing_tags.contains(&link.super_tag_id)
+                && existing_tags.contains(&link.sub_tag_id)
+                && seen.insert(*link)
+        })
+        .collect();
+    if !added.is_empty() {
+        TagHasTags::insert_many(added.iter().map(|link| tag_has_tags::ActiveModel {
+            id: NotSet,
+            super_tag_id: Set(link.super_tag_id),
+            sub_tag_id: Set(link.sub_tag_id),
+        }))
+        .exec(connection)
+        .await
+        .context("Failed to link tags")?;
+    }
+    Ok(added)
+}
+
+/// Remove the edges that are in the hierarchy, returning those edges
+pub(crate) async fn unlink<C>(connection: &C, links: Vec<TagLink>) -> Result<Vec<TagLink>>
+where
+    C: ConnectionTrait,
+{
+    let mut removed = Vec::new();
+    for link in links {
+        let result = TagHasTags::delete_many()
+            .filter(tag_has_tags::Column::SuperTagId.eq(link.super_tag_id))
+            .filter(tag_has_tags::Column::SubTagId.eq(link.sub_tag_id))
+            .exec(connection)
+            .await
+            .context("Failed to unlink tags")?;
+        if result.rows_affected > 0 {
+            removed.push(link);
+        }
+    }
+    Ok(removed)
+}
+
+/// Assign the tag to the files that exist and do not have it yet, returning those files
+pub(crate) async fn assign<C>(
+    connection: &C,
+    tag: &tags::Model,
+    file_ids: Vec<i32>,
+    source: JournalSource,
+) -> Result<Vec<i32>>
+where
+    C: ConnectionTrait,
+{
+    let mut assigned = Vec::new();
+    for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+        assigned.extend(assign_chunk(connection, tag, chunk.to_vec(), source).await?);
+    }
+    Ok(assigned)
+}
+
+async fn assign_chunk<C>(
+    connection: &C,
+    tag: &tags::Model,
+    file_ids: Vec<i32>,
+    source: JournalSource,
+) -> Result<Vec<i32>>
+where
+    C: ConnectionTrait,
+{
+    if file_ids.is_empty() {
+        return Ok(file_ids);
+    }
+    let assigned: HashSet<i32> = FileHasTags::find()
+        .select_only()
+        .column(file_has_tags::Column::FileId)
+        .filter(file_has_tags::Column::TagId.eq(tag.id))
+        .filter(file_has_tags::Column::FileId.is_in(file_ids.clone()))
+        .into_tuple::<i32>()
+        .all(connection)
+        .await
+        .context("Failed to query tag assignments")?
+        .into_iter()
+        .collect();
+    // Files may have been deleted since the command was recorded
+    let files = Files::find()
+        .filter(files::Column::Id.is_in(file_ids))
+        .filter(files::Column::Id.is_not_in(assigned))
+        .order_by_asc(files::Column::Id)
+        .all(connection)
+        .await
+        .context("Failed to query files")?;
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    FileHasTags::insert_many(files.iter().map(|file| file_has_tags::ActiveModel {
+        id: NotSet,
+        file_id: Set(file.id),
+        tag_id: Set(tag.id),
+    }))
+    .exec(connection)
+    .await
+    .context("Failed to assign tag")?;
+    JournalOperations::record(
+        connection,
+        journal_entries(JournalEventKind::TagAssigned, source, tag, &files),
+    )
+    .await?;
+    Ok(files.into_iter().map(|file| file.id).collect())
+}
+
+/// Remove the tag from the files that have it, returning those files
+pub(crate) async fn unassign<C>(
+    connection: &C,
+    tag: &tags::Model,
+    file_ids: Vec<i32>,
+    source: JournalSource,
+) -> Result<Vec<i32>>
+where
+    C: ConnectionTrait,
+{
+    let mut removed = Vec::new();
+    for chunk in file_ids.chunks(ID_CHUNK_SIZE) {
+        removed.extend(unassign_chunk(connection, tag, chunk.to_vec(), source).await?);
+    }
+    Ok(removed)
+}
+
+async fn unassign_chunk<C>(
+    connection: &C,
+    tag: &tags::Model,
+    file_ids: Vec<i32>,
+    source: JournalSource,
+) -> Result<Vec<i32>>
+where
+    C: ConnectionTrait,
+{
+    if file_ids.is_empty() {
+        return Ok(file_ids);
+    }
+    let files = Files::find()
+        .inner_join(FileHasTags)
+        .filter(file_has_tags::Column::TagId.eq(tag.id))
+        .filter(files::Column::Id.is_in(file_ids))
+        .order_by_asc(files::Column::Id)
+        .all(connection)
+        .await
+        .context("Failed to query tag assignments")?;
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let file_ids: Vec<i32> = files.iter().map(|file| file.id).collect();
+    FileHasTags::delete_many()
+        .filter(file_has_tags::Column::TagId.eq(tag.id))
+        .filter(file_has_tags::Column::FileId.is_in(file_ids.clone()))
+        .exec(connection)
+        .await
+        .context("Failed to remove tag")?;
+    JournalOperations::record(
+        connection,
+        journal_entries(JournalEventKind::TagRemoved, source, tag, &files),
+    )
+    .await?;
+    Ok(file_ids)
+}
+
+fn journal_entries(
+    kind: JournalEventKind,
+    source: JournalSource,
+    tag: &tags::Model,
+    files: &[files::Model],
+) -> Vec<JournalEntry> {
+    files
+        .iter()
+        .map(|file| {
+            let path = PathBuf::from(&file.path);
+            JournalEntry::new(kind, source)
+                .with_file(file.id)
+                .with_paths(Some(path.clone()), Some(path))
+                .with_detail(tag.name.clone())
+        })
+        .collect()
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use entity::{file_system_identifier, file_types, files};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+use sea_orm::{ActiveModelTrait, Set};
+use tempfile::TempDir;
+
+use crate::config::DatabaseSettings;
+use crate::manager::DatabaseManager;
+
+/// Open a fully migrated library database inside `directory`
+pub(crate) async fn migrated_database(directory: &TempDir) -> Result<Arc<DatabaseManager>> {
+    let settings = DatabaseSettings::new(
+        format!(
+            "sqlite://{}?mode=rwc",
+            directory.path().join("db.sqlite").display()
+        ),
+        1_000,
+        SqliteJournalMode::Wal,
+        SqliteSynchronous::Normal,
+    );
+    let database_manager = Arc::new(DatabaseManager::new(settings).await?);
+    Migrator::up(database_manager.get_connection().as_ref(), None).await?;
+    Ok(database_manager)
+}
+
+/// Insert `count` files named `<index>.txt` below `/library`
+pub(crate) async fn insert_files(
+    database_manager: &DatabaseManager,
+    count: usize,
+) -> Result<Vec<i32>> {
+    let content_hashes: Vec<String> = (0..count).map(|index| format!("content-{index}")).collect();
+    insert_files_with_hashes(database_manager, &content_hashes).await
+}
+
+/// Insert a single file with the given content hash
+pub(crate) async fn insert_file(
+    database_manager: &DatabaseManager,
+    content_hash: &str,
+) -> Result<i32> {
+    let ids = insert_files_with_hashes(database_manager, &[content_hash.to_string()]).await?;
+    ids.first().copied().context("no file was inserted")
+}
+
+async fn insert_files_with_hashes(
+    database_manager: &DatabaseManager,
+    content_hashes: &[String],
+) -> Result<Vec<i32>> {
+    let db = database_manager.get_connection();
+    let file_system = file_system_identifier::ActiveModel {
+        inode: Set(Some(1)),
+        ..Default::default()
+    }
+    .insert(db.as_ref())
+    .await?;
+    let file_type = file_types::ActiveModel {
+        name: Set("text".to_string()),
+        ..Default::default()
+    }
+    .insert(db.as_ref())
+    .await?;
+    let now = Utc::now().naive_utc();
+    let mut ids = Vec::new();
+    for (index, content_hash) in content_hashes.iter().enumerate() {
+        let file = files::ActiveModel {
+            name: Set(format!("{index}.txt")),
+            path: Set(format!("/library/{index}.txt")),
+            content_hash: Set(content_hash.clone()),
+            identity_hash: Set(format!("identity-{index}")),
+            file_system_id: Set(file_system.id),
+            file_type_id: Set(file_type.id),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(db.as_ref())
+        .await?;
+        ids.push(file.id);
+    }
+    Ok(ids)
+}
@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, ensure};
+use tokio::fs;
+
+use model::services::thumbnail::ThumbnailSize;
+
+/// Result of a garbage collection run over the thumbnail cache directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThumbnailGcReport {
+    pub files_removed: u64,
+    pub bytes_freed: u64,
+}
+
+/// How long a cached file is protected from garbage collection after it was last written
+///
+/// Writers put the image on disk before they insert its `thumbnails` row, so a file that looks
+/// unreferenced may simply belong to a row that is about to be committed.
+pub const DEFAULT_GC_GRACE_PERIOD: Duration = Duration::from_secs(5 * 60);
+
+/// Content-addressed thumbnail cache on disk
+///
+/// Thumbnails are written to `<root>/<hash-prefix>/<content_hash>-<size>.<ext>`, so files with
+/// identical content share a single cached image.
+#[derive(Debug, Clone)]
+pub struct ThumbnailCache {
+    root: PathBuf,
+    gc_grace_period: Duration,
+}
+
+impl ThumbnailCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            gc_grace_period: DEFAULT_GC_GRACE_PERIOD,
+        }
+    }
+
+    /// Keep unreferenced files that were written within `grace_period` during garbage collection
+    pub fn with_gc_grace_period(mut self, grace_period: Duration) -> Self {
+        self.gc_grace_period = grace_period;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the cached thumbnail for the given content hash, size and MIME type
+    pub fn path_for(
+        &self,
+        content_hash: &str,
+        size: ThumbnailSize,
+        mime_type: &str,
+    ) -> Result<PathBuf> {
+        ensure!(
+            content_hash.len() >= 2 && content_hash.chars().all(|c| c.is_ascii_hexdigit()),
+            "content hash {content_hash:?} cannot be used as a thumbnail cache key"
+        );
+        let prefix = content_hash.get(..2).unwrap_or(content_hash);
+
+        Ok(self.root.join(prefix).join(format!(
+            "{content_hash}-{size}.{}",
+            Self::extension_for(mime_type)
+        )))
+    }
+
+    /// Write thumbnail data unless an identical thumbnail is already cached
+    pub async fn write(
+        &self,
+        content_hash: &str,
+        size: ThumbnailSize,
+        mime_type: &str,
+        data: &[u8],
+    ) -> Result<PathBuf> {
+        let path = self.path_for(content_hash, size, mime_type)?;
+        if fs::try_exists(&path).await.unwrap_or(false) {
+            // Refresh the modification time so garbage collection treats the reused file as new
+            if let Err(e) = Self::touch(&path).await {
+                tracing::warn!("Failed to refresh cached thumbnail {}: {e}", path.display());
+            } else {
+                return Ok(path);
+            }
+        }
+
+        let parent = path
+            .parent()
+            .context("thumbnail cache path has no parent directory")?;
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("failed to create cache directory {}", parent.display()))?;
+
+        // Write to a temporary file first so readers never observe a partial thumbnail
+        let temporary_path = path.with_extension("partial");
+        fs::write(&temporary_path, data)
+            .await
+            .with_context(|| format!("failed to write {}", temporary_path.display()))?;
+        fs::rename(&temporary_path, &path)
+            .await
+            .with_context(|| format!("failed to move thumbnail into {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    pub async fn read(
+        &self,
+        content_hash: &str,
+        size: ThumbnailSize,
+        mime_type: &str,
+    ) -> Result<Vec<u8>> {
+        let path = self.path_for(content_hash, size, mime_type)?;
+        fs::read(&path)
+            .await
+            .with_context(|| format!("failed to read cached thumbnail {}", path.display()))
+    }
+
+    /// Delete every cached file that is not part of `referenced`
+    ///
+    /// Files that are still being written and files modified within the grace period are kept,
+    /// because their `thumbnails` rows may not be committed yet.
+    pub async fn collect_garbage(
+        &self,
+        referenced: &HashSet<PathBuf>,
+    ) -> Result<ThumbnailGcReport> {
+        let mut report = ThumbnailGcReport::default();
+        if !fs::try_exists(&self.root).await.unwrap_or(false) {
+            return Ok(report);
+        }
+
+        let mut prefixes = fs::read_dir(&self.root)
+            .await
+            .with_context(|| format!("failed to list {}", self.root.display()))?;
+        while let Some(prefix) = prefixes.next_entry().await? {
+            if !prefix.file_type().await?.is_dir() {
+                continue;
+            }
+
+            let mut remaining = 0usize;
+            let mut entries = fs::read_dir(prefix.path()).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if referenced.contains(&path) || Self::is_partial(&path) {
+                    remaining += 1;
+                    continue;
+                }
+                let Ok(metadata) = entry.metadata().await else {
+                    remaining += 1;
+                    continue;
+                };
+                if self.is_recent(&metadata) {
+                    remaining += 1;
+                    continue;
+                }
+                let length = metadata.len();
+                match fs::remove_file(&path).await {
+                    Ok(()) => {
+                        report.files_removed += 1;
+                        report.bytes_freed += length;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to remove cached thumbnail {}: {e}", path.display());
+                        remaining += 1;
+                    }
+                }
+            }
+
+            if remaining == 0 {
+                // Another writer may have added a file in the meantime, so a failure is fine
+                let _removed = fs::remove_dir(prefix.path()).await;
+            }
+        }
+
+        Ok(report)
+    }
+
+    fn is_partial(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension == "partial")
+    }
+
+    fn is_recent(&self, metadata: &std::fs::Metadata) -> bool {
+        metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_none_or(|age| age < self.gc_grace_period)
+    }
+
+    async fn touch(path: &Path) -> Result<()> {
+        let file = fs::OpenOptions::new().write(true).open(path).await?;
+        file.into_std().await.set_modified(SystemTime::now())?;
+        Ok(())
+    }
+
+    fn extension_for(mime_type: &str) -> &'static str {
+        match mime_type {
+            "image/webp" => "webp",
+            "image/png" => "png",
+            "image/jpeg" => "jpg",
+            _ => "bin",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn cache_deduplicates_and_collects_unreferenced_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().join("thumbs");
+        let cache = ThumbnailCache::new(&root).with_gc_grace_period(Duration::ZERO);
+
+        let first = cache
+            .write("abcdef", ThumbnailSize::Small, "image/webp", &[1, 2, 3])
+            .await?;
+        let second = cache
+            .write("abcdef", ThumbnailSize::Small, "image/webp", &[4, 5, 6])
+            .await?;
+        assert_eq!(first, second);
+        assert_eq!(first, root.join("ab").join("abcdef-small.webp"));
+        assert_eq!(
+            cache
+                .read("abcdef", ThumbnailSize::Small, "image/webp")
+                .await?,
+            vec![1, 2, 3]
+        );
+
+        let orphan = cache
+            .write("123456", ThumbnailSize::Large, "image/png", &[7])
+            .await?;
+        let report = cache
+            .collect_garbage(&HashSet::from([first.clone()]))
+            .await?;
+
+        assert_eq!(report.files_removed, 1);
+        assert!(first.exists());
+        assert!(!orphan.exists());
+        assert!(!root.join("12").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn garbage_collection_keeps_files_that_are_still_being_stored() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = ThumbnailCache::new(temp_dir.path());
+
+        let fresh = cache
+            .write("abcdef", ThumbnailSize::Small, "image/webp", &[1])
+            .await?;
+        let partial = temp_dir.path().join("ab").join("abcdef-large.partial");
+        fs::write(&partial, [2]).await?;
+
+        let report = cache.collect_garbage(&HashSet::new()).await?;
+
+        assert_eq!(report, ThumbnailGcReport::default());
+        assert!(fresh.exists());
+        assert!(partial.exists());
+
+        let report = cache
+            .with_gc_grace_period(Duration::ZERO)
+            .collect_garbage(&HashSet::new())
+            .await?;
+        assert_eq!(report.files_removed, 1);
+        assert!(!fresh.exists());
+        assert!(partial.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn cache_rejects_keys_that_are_not_hex_digests() {
+        let cache = ThumbnailCache::new("/tmp");
+        assert!(
+            cache
+                .path_for("../etc", ThumbnailSize::Small, "image/webp")
+                .is_err()
+        );
+    }
+}
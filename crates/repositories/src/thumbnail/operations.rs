@@ -1,18 +1,21 @@
 use entity::files;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{Context, Result};
+use sea_orm::sea_query::Expr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QuerySelect,
-    QueryTrait, Set, TransactionTrait, TryIntoModel,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, EntityTrait, PaginatorTrait,
+    QueryFilter, QuerySelect, QueryTrait, Set, TransactionTrait, TryIntoModel,
 };
 
 use entity::{prelude::*, thumbnails};
 
-use model::services::thumbnail::{Thumbnail, ThumbnailSize};
+use model::services::thumbnail::{Thumbnail, ThumbnailSize, ThumbnailStorageKind};
 
 use crate::manager::DatabaseManager;
+use crate::thumbnail::cache::{ThumbnailCache, ThumbnailGcReport};
 
 /// Statistics about thumbnails in the database
 #[derive(Debug, Clone)]
@@ -23,15 +26,48 @@ pub struct ThumbnailStats {
     pub total_storage_bytes: u64,
 }
 
+/// Image data of a thumbnail prepared for the configured storage backend
+struct StoredThumbnail {
+    data: Vec<u8>,
+    content_hash: Option<String>,
+    storage: ThumbnailStorageKind,
+}
+
 /// Repository for thumbnail database operations
 #[derive(Debug)]
 pub struct ThumbnailOperations {
     database_manager: Arc<DatabaseManager>,
+    cache: Option<ThumbnailCache>,
+    storage: ThumbnailStorageKind,
 }
 
 impl ThumbnailOperations {
     pub fn new(database_manager: Arc<DatabaseManager>) -> Self {
-        Self { database_manager }
+        Self {
+            database_manager,
+            cache: None,
+            storage: ThumbnailStorageKind::Database,
+        }
+    }
+
+    /// Use a cache directory for reading thumbnails stored outside the database
+    pub fn with_cache(mut self, cache: ThumbnailCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Choose where new thumbnails are stored
+    pub fn with_storage(mut self, storage: ThumbnailStorageKind) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn storage(&self) -> ThumbnailStorageKind {
+        self.storage
+    }
+
+    pub fn cache(&self) -> Option<&ThumbnailCache> {
+        self.cache.as_ref()
     }
 
     /// Create a new thumbnail entry in the database
@@ -42,7 +78,9 @@ impl ThumbnailOperations {
     ) -> Result<thumbnails::Model> {
         let db = self.database_manager.get_connection();
 
-        let active_model = thumbnail.to_active_model(file_id);
+        let active_model = self
+            .to_stored_active_model(db.as_ref(), file_id, thumbnail)
+            .await?;
         let result = active_model
             .save(db.as_ref())
             .await
@@ -71,7 +109,7 @@ impl ThumbnailOperations {
             .context("Failed to query thumbnail by file ID and size")?;
 
         match model {
-            Some(m) => Ok(Some(Thumbnail::from_model(m)?)),
+            Some(m) => Ok(Some(self.load(m).await?)),
             None => Ok(None),
         }
     }
@@ -86,10 +124,9 @@ impl ThumbnailOperations {
             .await
             .context("Failed to query thumbnails for file")?;
 
-        let thumbnails: Result<Vec<Thumbnail>, _> =
-            models.into_iter().map(Thumbnail::from_model).collect();
-
-        thumbnails.context("Failed to convert thumbnail models")
+        self.load_all(models)
+            .await
+            .context("Failed to convert thumbnail models")
     }
 
     /// Get thumbnail for a specific file and size
@@ -110,7 +147,9 @@ impl ThumbnailOperations {
         let model = model
             .context("thumbnail is not in the database; it may not have been generated yet")?;
 
-        Thumbnail::from_model(model).context("Failed to convert thumbnail model")
+        self.load(model)
+            .await
+            .context("Failed to convert thumbnail model")
     }
 
     /// Get all thumbnail for a specific range of files and sizes
@@ -128,10 +167,9 @@ impl ThumbnailOperations {
             .await
             .context("Failed to query thumbnails for file")?;
 
-        let thumbnails: Result<Vec<Thumbnail>, _> =
-            models.into_iter().map(Thumbnail::from_model).collect();
-
-        thumbnails.context("Failed to convert thumbnail models")
+        self.load_all(models)
+            .await
+            .context("Failed to convert thumbnail models")
     }
 
     /// Delete all thumbnails for a specific file
@@ -157,7 +195,7 @@ impl ThumbnailOperations {
             .context("Failed to query thumbnail by ID")?;
 
         match model {
-            Some(m) => Ok(Some(Thumbnail::from_model(m)?)),
+            Some(m) => Ok(Some(self.load(m).await?)),
             None => Ok(None),
         }
     }
@@ -181,7 +219,10 @@ impl ThumbnailOperations {
         if let Some(existing_model) = existing {
             // Update existing thumbnail
             let mut active_model: thumbnails::ActiveModel = existing_model.into();
-            active_model.data = Set(thumbnail.data().to_vec());
+            let stored = self.store(db.as_ref(), file_id, &thumbnail).await?;
+            active_model.data = Set(stored.data);
+            active_model.content_hash = Set(stored.content_hash);
+            active_model.storage = Set(stored.storage.to_string());
             active_model.mime_type = Set(thumbnail.mime_type().to_string());
             active_model.file_size = Set(thumbnail.file_size() as i32);
             active_model.updated_at = Set(chrono::Local::now().naive_local());
@@ -235,6 +276,25 @@ impl ThumbnailOperations {
         Ok(file_ids)
     }
 
+    /// Count the files that don't have a thumbnail of the specified size
+    pub async fn count_files_without_thumbnail_size(&self, size: ThumbnailSize) -> Result<u64> {
+        let db = self.database_manager.get_connection();
+
+        files::Entity::find()
+            .filter(
+                files::Column::Id.not_in_subquery(
+                    Thumbnails::find()
+                        .select_only()
+                        .column(thumbnails::Column::FileId)
+                        .filter(thumbnails::Column::Size.eq(size.to_string()))
+                        .into_query(),
+                ),
+            )
+            .count(db.as_ref())
+            .await
+            .context("Failed to count files without thumbnails")
+    }
+
     pub async fn get_files_without_thumbnails_sizes(
         &self,
         sizes: Vec<ThumbnailSize>,
@@ -278,7 +338,9 @@ impl ThumbnailOperations {
         let mut created_count = 0u64;
 
         for (file_id, thumbnail) in thumbnails_data {
-            let active_model = thumbnail.to_active_model(file_id);
+            let active_model = self
+                .to_stored_active_model(&txn, file_id, thumbnail)
+                .await?;
 
             active_model
                 .save(&txn)
@@ -299,30 +361,31 @@ impl ThumbnailOperations {
     pub async fn get_thumbnail_stats(&self) -> Result<ThumbnailStats> {
         let db = self.database_manager.get_connection();
 
-        // Get all thumbnails for processing statistics
-        let all_thumbnails = Thumbnails::find()
+        // Only the columns needed for the statistics, the image data can be large
+        let rows: Vec<(String, String, i32)> = Thumbnails::find()
+            .select_only()
+            .column(thumbnails::Column::Size)
+            .column(thumbnails::Column::MimeType)
+            .column(thumbnails::Column::FileSize)
+            .into_tuple()
             .all(db.as_ref())
             .await
             .context("Failed to fetch thumbnails for statistics")?;
 
-        let total_thumbnails = all_thumbnails.len() as u64;
+        let total_thumbnails = rows.len() as u64;
         let mut thumbnails_by_size = HashMap::new();
         let mut thumbnails_by_type = HashMap::new();
         let mut total_storage_bytes = 0u64;
 
-        for thumbnail_model in all_thumbnails {
+        for (size, mime_type, file_size) in rows {
             // Count by size
-            *thumbnails_by_size
-                .entry(thumbnail_model.size.clone())
-                .or_insert(0) += 1;
+            *thumbnails_by_size.entry(size).or_insert(0) += 1;
 
             // Count by MIME type
-            *thumbnails_by_type
-                .entry(thumbnail_model.mime_type.clone())
-                .or_insert(0) += 1;
+            *thumbnails_by_type.entry(mime_type).or_insert(0) += 1;
 
             // Sum storage bytes
-            total_storage_bytes += thumbnail_model.file_size as u64;
+            total_storage_bytes += u64::try_from(file_size).unwrap_or(0);
         }
 
         Ok(ThumbnailStats {
@@ -360,7 +423,10 @@ impl ThumbnailOperations {
             if let Some(existing_model) = existing {
                 // Update existing
                 let mut active_model: thumbnails::ActiveModel = existing_model.into();
-                active_model.data = Set(thumbnail.data().to_vec());
+                let stored = self.store(&txn, file_id, &thumbnail).await?;
+                active_model.data = Set(stored.data);
+                active_model.content_hash = Set(stored.content_hash);
+                active_model.storage = Set(stored.storage.to_string());
                 active_model.mime_type = Set(thumbnail.mime_type().to_string());
                 active_model.file_size = Set(thumbnail.file_size() as i32);
                 active_model.updated_at = Set(chrono::Local::now().naive_local());
@@ -373,7 +439,9 @@ impl ThumbnailOperations {
                 updated_count += 1;
             } else {
                 // Create new
-                let active_model = thumbnail.to_active_model(file_id);
+                let active_model = self
+                    .to_stored_active_model(&txn, file_id, thumbnail)
+                    .await?;
                 active_model
                     .save(&txn)
                     .await
@@ -410,6 +478,57 @@ impl ThumbnailOperations {
         Ok(delete_result.rows_affected)
     }
 
+    /// Delete thumbnails that were generated from an older version of their file
+    ///
+    /// Thumbnails created before content hashes were recorded are kept, since it is unknown
+    /// which version of the file they show.
+    pub async fn delete_stale_thumbnails(&self) -> Result<u64> {
+        let db = self.database_manager.get_connection();
+
+        let delete_result = Thumbnails::delete_many()
+            .filter(thumbnails::Column::ContentHash.is_not_null())
+            .filter(
+                Expr::tuple([
+                    Expr::col((Thumbnails, thumbnails::Column::FileId)).into(),
+                    Expr::col((Thumbnails, thumbnails::Column::ContentHash)).into(),
+                ])
+                .not_in_subquery(
+                    Files::find()
+                        .select_only()
+                        .column(files::Column::Id)
+                        .column(files::Column::ContentHash)
+                        .into_query(),
+                ),
+            )
+            .exec(db.as_ref())
+            .await
+            .context("Failed to delete stale thumbnails")?;
+
+        Ok(delete_result.rows_affected)
+    }
+
+    /// Delete thumbnails of a file that were not generated from its current content
+    pub async fn delete_stale_thumbnails_for_file(
+        &self,
+        file_id: i32,
+        content_hash: &str,
+    ) -> Result<u64> {
+        let db = self.database_manager.get_connection();
+
+        let delete_result = Thumbnails::delete_many()
+            .filter(thumbnails::Column::FileId.eq(file_id))
+            .filter(
+                Condition::any()
+                    .add(thumbnails::Column::ContentHash.is_null())
+                    .add(thumbnails::Column::ContentHash.ne(content_hash)),
+            )
+            .exec(db.as_ref())
+            .await
+            .context("Failed to delete stale thumbnails for file")?;
+
+        Ok(delete_result.rows_affected)
+    }
+
     pub async fn get_thumbnails_for_filter(
         &self,
         file_ids: Vec<i32>,
@@ -422,11 +541,215 @@ impl ThumbnailOperations {
             .all(&*connection)
             .await?;
 
-        Ok(thumbnails
-            .into_iter()
-            .map(TryInto::try_into)
-            .map(|x| x.unwrap())
-            .collect())
+        self.load_all(thumbnails).await
+    }
+
+    // ===== CACHE DIRECTORY METHODS =====
+
+    /// Paths of cached thumbnails for the given files, for thumbnails stored in the cache directory
+    pub async fn get_cached_thumbnail_paths(
+        &self,
+        file_ids: Vec<i32>,
+        size: ThumbnailSize,
+    ) -> Result<HashMap<i32, PathBuf>> {
+        let Some(cache) = self.cache.as_ref() else {
+            return Ok(HashMap::new());
+        };
+        let connection = self.database_manager.get_connection();
+
+        let models = Thumbnails::find()
+            .filter(thumbnails::Column::FileId.is_in(file_ids))
+            .filter(thumbnails::Column::Size.eq(size.to_string()))
+            .filter(thumbnails::Column::Storage.eq(ThumbnailStorageKind::Directory.to_string()))
+            .all(&*connection)
+            .await
+            .context("Failed to query cached thumbnails")?;
+
+        let mut paths = HashMap::new();
+        for model in models {
+            if let Some(content_hash) = model.content_hash.as_deref() {
+                paths.insert(
+                    model.file_id,
+                    cache.path_for(content_hash, size, &model.mime_type)?,
+                );
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Move every thumbnail into the configured storage backend
+    ///
+    /// Returns the number of thumbnails that were moved.
+    pub async fn migrate_storage(&self) -> Result<u64> {
+        let db = self.database_manager.get_connection();
+        let source = match self.storage {
+            ThumbnailStorageKind::Database => ThumbnailStorageKind::Directory,
+            ThumbnailStorageKind::Directory => ThumbnailStorageKind::Database,
+        };
+
+        let mut migrated = 0u64;
+        loop {
+            // Migrated rows no longer match the filter, so always read the first batch
+            let batch = Thumbnails::find()
+                .filter(thumbnails::Column::Storage.eq(source.to_string()))
+                .limit(100)
+                .all(db.as_ref())
+                .await
+                .context("Failed to query thumbnails for storage migration")?;
+            if batch.is_empty() {
+                break;
+            }
+
+            let txn = db
+                .begin()
+                .await
+                .context("Failed to start transaction for thumbnail storage migration")?;
+            for model in batch {
+                let file_id = model.file_id;
+                let thumbnail = self.load(model.clone()).await?;
+                let stored = self.store(&txn, file_id, &thumbnail).await?;
+
+                let mut active_model: thumbnails::ActiveModel = model.into();
+                active_model.data = Set(stored.data);
+                active_model.content_hash = Set(stored.content_hash);
+                active_model.storage = Set(stored.storage.to_string());
+                active_model
+                    .update(&txn)
+                    .await
+                    .context("Failed to update migrated thumbnail")?;
+                migrated += 1;
+            }
+            txn.commit()
+                .await
+                .context("Failed to commit thumbnail storage migration")?;
+        }
+
+        Ok(migrated)
+    }
+
+    /// Delete cached thumbnail files that are no longer referenced by the database
+    pub async fn collect_garbage(&self) -> Result<ThumbnailGcReport> {
+        let Some(cache) = self.cache.as_ref() else {
+            return Ok(ThumbnailGcReport::default());
+        };
+        let db = self.database_manager.get_connection();
+
+        let rows: Vec<(Option<String>, String, String)> = Thumbnails::find()
+            .select_only()
+            .column(thumbnails::Column::ContentHash)
+            .column(thumbnails::Column::Size)
+            .column(thumbnails::Column::MimeType)
+            .filter(thumbnails::Column::Storage.eq(ThumbnailStorageKind::Directory.to_string()))
+            .into_tuple()
+            .all(db.as_ref())
+            .await
+            .context("Failed to query referenced thumbnails")?;
+
+        let mut referenced = HashSet::new();
+        for (content_hash, size, mime_type) in rows {
+            if let Some(content_hash) = content_hash {
+                let size = ThumbnailSize::try_from(size)?;
+                referenced.insert(cache.path_for(&content_hash, size, &mime_type)?);
+            }
+        }
+
+        cache.collect_garbage(&referenced).await
+    }
+
+    /// Convert a thumbnail into an active model, writing its data to the configured storage
+    async fn to_stored_active_model<C>(
+        &self,
+        connection: &C,
+        file_id: i32,
+        thumbnail: Thumbnail,
+    ) -> Result<thumbnails::ActiveModel>
+    where
+        C: ConnectionTrait,
+    {
+        let stored = self.store(connection, file_id, &thumbnail).await?;
+        let mut active_model = thumbnail.to_active_model(file_id);
+        active_model.data = Set(stored.data);
+        active_model.content_hash = Set(stored.content_hash);
+        active_model.storage = Set(stored.storage.to_string());
+        Ok(active_model)
+    }
+
+    /// Write thumbnail data to the configured storage and return the column values to persist
+    async fn store<C>(
+        &self,
+        connection: &C,
+        file_id: i32,
+        thumbnail: &Thumbnail,
+    ) -> Result<StoredThumbnail>
+    where
+        C: ConnectionTrait,
+    {
+        let content_hash: Option<String> = Files::find_by_id(file_id)
+            .select_only()
+            .column(files::Column::ContentHash)
+            .into_tuple()
+            .one(connection)
+            .await
+            .context("Failed to query content hash for thumbnail")?;
+
+        match self.storage {
+            ThumbnailStorageKind::Database => Ok(StoredThumbnail {
+                data: thumbnail.data().to_vec(),
+                content_hash,
+                storage: ThumbnailStorageKind::Database,
+            }),
+            ThumbnailStorageKind::Directory => {
+                let cache = self
+                    .cache
+                    .as_ref()
+                    .context("no thumbnail cache directory is configured")?;
+                let content_hash =
+                    content_hash.with_context(|| format!("file {file_id} does not exist"))?;
+                cache
+                    .write(
+                        &content_hash,
+                        thumbnail.size(),
+                        thumbnail.mime_type(),
+                        thumbnail.data(),
+                    )
+                    .await?;
+
+                Ok(StoredThumbnail {
+                    data: Vec::new(),
+                    content_hash: Some(content_hash),
+                    storage: ThumbnailStorageKind::Directory,
+                })
+            }
+        }
+    }
+
+    /// Convert a model into a thumbnail, reading its data from the cache directory if needed
+    async fn load(&self, model: thumbnails::Model) -> Result<Thumbnail> {
+        let storage = ThumbnailStorageKind::try_from(model.storage.as_str())?;
+        let content_hash = model.content_hash.clone();
+        let mut thumbnail = Thumbnail::from_model(model)?;
+
+        if storage == ThumbnailStorageKind::Directory {
+            let cache = self
+                .cache
+                .as_ref()
+                .context("thumbnail is stored in a cache directory that is not configured")?;
+            let content_hash =
+                content_hash.context("cached thumbnail does not record its content hash")?;
+            thumbnail.data = cache
+                .read(&content_hash, thumbnail.size(), thumbnail.mime_type())
+                .await?;
+        }
+
+        Ok(thumbnail)
+    }
+
+    async fn load_all(&self, models: Vec<thumbnails::Model>) -> Result<Vec<Thumbnail>> {
+        let mut thumbnails = Vec::with_capacity(models.len());
+        for model in models {
+            thumbnails.push(self.load(model).await?);
+        }
+        Ok(thumbnails)
     }
 }
 
@@ -435,7 +758,9 @@ mod tests {
     use super::*;
     use crate::config::DatabaseSettings;
     use crate::manager::DatabaseManager;
+    use crate::test_support::{insert_file, migrated_database};
     use sea_orm::sqlx::sqlite::{SqliteJournalMode, SqliteSynchronous};
+    use tempfile::TempDir;
 
     #[tokio::test]
     async fn thumbnail_repository_uses_configured_database() -> Result<()> {
@@ -453,4 +778,226 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn stale_thumbnails_are_deleted_after_content_changes() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_id = insert_file(&database_manager, "aaaa").await?;
+        let repository = ThumbnailOperations::new(Arc::clone(&database_manager));
+
+        for size in ThumbnailSize::all() {
+            repository
+                .create_thumbnail(file_id, Thumbnail::with_image_data(size, vec![1, 2, 3]))
+                .await?;
+        }
+        assert_eq!(repository.delete_stale_thumbnails().await?, 0);
+        assert_eq!(
+            repository
+                .delete_stale_thumbnails_for_file(file_id, "aaaa")
+                .await?,
+            0
+        );
+
+        // Simulate the watcher storing a new version of the file
+        let db = database_manager.get_connection();
+        let mut file: files::ActiveModel = Files::find_by_id(file_id)
+            .one(db.as_ref())
+            .await?
+            .context("file was inserted")?
+            .into();
+        file.content_hash = Set("bbbb".to_string());
+        file.update(db.as_ref()).await?;
+
+        assert_eq!(repository.delete_stale_thumbnails().await?, 3);
+        assert!(
+            repository
+                .get_thumbnails_for_file(file_id)
+                .await?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn directory_storage_round_trips_through_the_cache() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_id = insert_file(&database_manager, "abcdef").await?;
+        let repository = ThumbnailOperations::new(Arc::clone(&database_manager))
+            .with_cache(ThumbnailCache::new(directory.path().join("thumbs")))
+            .with_storage(ThumbnailStorageKind::Directory);
+
+        let model = repository
+            .create_thumbnail(
+                file_id,
+                Thumbnail::new(
+                    ThumbnailSize::Small,
+                    vec![7, 8, 9],
+                    "image/webp".to_string(),
+                ),
+            )
+            .await?;
+        assert!(model.data.is_empty());
+        assert_eq!(model.content_hash.as_deref(), Some("abcdef"));
+
+        let thumbnail = repository
+            .get_thumbnail_for_file_and_size(file_id, ThumbnailSize::Small)
+            .await?;
+        assert_eq!(thumbnail.data(), &[7, 8, 9]);
+
+        let paths = repository
+            .get_cached_thumbnail_paths(vec![file_id], ThumbnailSize::Small)
+            .await?;
+        assert!(paths[&file_id].is_file());
+        assert_eq!(repository.collect_garbage().await?.files_removed, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn storage_migrates_into_the_cache_and_back() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_id = insert_file(&database_manager, "abcdef").await?;
+        let cache = ThumbnailCache::new(directory.path().join("thumbs"))
+            .with_gc_grace_period(std::time::Duration::ZERO);
+        let in_database = ThumbnailOperations::new(Arc::clone(&database_manager))
+            .with_cache(cache.clone())
+            .with_storage(ThumbnailStorageKind::Database);
+        let in_directory = ThumbnailOperations::new(Arc::clone(&database_manager))
+            .with_cache(cache)
+            .with_storage(ThumbnailStorageKind::Directory);
+        let images = [
+            (ThumbnailSize::Small, vec![1, 2, 3]),
+            (ThumbnailSize::Large, vec![4, 5, 6, 7]),
+        ];
+        for (size, data) in &images {
+            in_database
+                .create_thumbnail(
+                    file_id,
+                    Thumbnail::new(*size, data.clone(), "image/webp".to_string()),
+                )
+                .await?;
+        }
+        let db = database_manager.get_connection();
+        let rows = |storage: ThumbnailStorageKind| {
+            Thumbnails::find()
+                .filter(thumbnails::Column::Storage.eq(storage.to_string()))
+                .all(db.as_ref())
+        };
+
+        assert_eq!(in_directory.migrate_storage().await?, 2);
+        assert_eq!(in_directory.migrate_storage().await?, 0);
+        let moved = rows(ThumbnailStorageKind::Directory).await?;
+        assert_eq!(moved.len(), 2);
+        assert!(moved.iter().all(|row| row.data.is_empty()));
+        for (size, data) in &images {
+            let thumbnail = in_directory
+                .get_thumbnail_for_file_and_size(file_id, *size)
+                .await?;
+            assert_eq!(thumbnail.data(), data.as_slice());
+        }
+        let cached = in_directory
+            .get_cached_thumbnail_paths(vec![file_id], ThumbnailSize::Small)
+            .await?;
+        assert!(cached[&file_id].is_file());
+        assert_eq!(in_directory.collect_garbage().await?.files_removed, 0);
+
+        assert_eq!(in_database.migrate_storage().await?, 2);
+        assert!(rows(ThumbnailStorageKind::Directory).await?.is_empty());
+        for (size, data) in &images {
+            let thumbnail = in_database
+                .get_thumbnail_for_file_and_size(file_id, *size)
+                .await?;
+            assert_eq!(thumbnail.data(), data.as_slice());
+        }
+
+        // The images left in the cache belong to no row any more
+        assert_eq!(in_database.collect_garbage().await?.files_removed, 2);
+        assert!(!cached[&file_id].exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn garbage_collection_during_a_store_keeps_the_thumbnail() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_id = insert_file(&database_manager, "abcdef").await?;
+        let cache = ThumbnailCache::new(directory.path().join("thumbs"));
+        let repository = ThumbnailOperations::new(Arc::clone(&database_manager))
+            .with_cache(cache.clone())
+            .with_storage(ThumbnailStorageKind::Directory);
+
+        // An unreferenced image of identical content left behind long ago by a deleted file, which
+        // a store reuses instead of writing it again
+        let reused = cache
+            .write("abcdef", ThumbnailSize::Large, "image/webp", &[4, 5, 6])
+            .await?;
+        std::fs::File::options()
+            .write(true)
+            .open(&reused)?
+            .set_modified(std::time::SystemTime::UNIX_EPOCH)?;
+
+        // Workers have written their images but not yet inserted the rows when the GC runs
+        cache
+            .write("abcdef", ThumbnailSize::Small, "image/webp", &[1, 2, 3])
+            .await?;
+        cache
+            .write("abcdef", ThumbnailSize::Large, "image/webp", &[9])
+            .await?;
+        assert_eq!(repository.collect_garbage().await?.files_removed, 0);
+
+        for (size, data) in [
+            (ThumbnailSize::Small, vec![1, 2, 3]),
+            (ThumbnailSize::Large, vec![4, 5, 6]),
+        ] {
+            repository
+                .create_thumbnail(
+                    file_id,
+                    Thumbnail::new(size, data.clone(), "image/webp".to_string()),
+                )
+                .await?;
+            let thumbnail = repository
+                .get_thumbnail_for_file_and_size(file_id, size)
+                .await?;
+            assert_eq!(thumbnail.data(), data.as_slice());
+        }
+
+        assert!(reused.is_file());
+        assert_eq!(repository.collect_garbage().await?.files_removed, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn thumbnail_stats_sum_sizes_without_loading_images() -> Result<()> {
+        let directory = TempDir::new()?;
+        let database_manager = migrated_database(&directory).await?;
+        let file_id = insert_file(&database_manager, "aaaa").await?;
+        let repository = ThumbnailOperations::new(Arc::clone(&database_manager));
+
+        for (size, length) in [(ThumbnailSize::Small, 3), (ThumbnailSize::Large, 5)] {
+            repository
+                .create_thumbnail(file_id, Thumbnail::with_image_data(size, vec![0; length]))
+                .await?;
+        }
+
+        let stats = repository.get_thumbnail_stats().await?;
+        assert_eq!(stats.total_thumbnails, 2);
+        assert_eq!(stats.total_storage_bytes, 8);
+        assert_eq!(stats.thumbnails_by_size.len(), 2);
+        insert_file(&database_manager, "bbbb").await?;
+        assert_eq!(
+            repository
+                .count_files_without_thumbnail_size(ThumbnailSize::Small)
+                .await?,
+            1
+        );
+        assert_eq!(
+            repository
+                .count_files_without_thumbnail_size(ThumbnailSize::Medium)
+                .await?,
+            2
+        );
+        Ok(())
+    }
 }
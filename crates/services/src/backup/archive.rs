@@ -0,0 +1,237 @@
+use anyhow::{Context, Result, ensure};
+use chrono::{NaiveDateTime, Utc};
+use model::services::backup::{
+    self, BackupConfig, BackupManifest, CONFIG_FILE, DATABASE_FILE, MANIFEST_FILE,
+};
+use repositories::backup::operations::BackupOperations;
+use repositories::manager::DatabaseManager;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A backup folder holding a copy of the database and the configuration of a library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub manifest: BackupManifest,
+}
+
+impl Backup {
+    #[must_use]
+    pub fn database(&self) -> PathBuf {
+        self.path.join(DATABASE_FILE)
+    }
+
+    #[must_use]
+    pub fn config(&self) -> PathBuf {
+        self.path.join(CONFIG_FILE)
+    }
+
+    /// Read a backup folder and check that its database is intact and matches the manifest
+    pub async fn inspect(path: &Path) -> Result<Self> {
+        let manifest = tokio::fs::read_to_string(path.join(MANIFEST_FILE))
+            .await
+            .with_context(|| format!("{} is not a backup", path.display()))?;
+        let backup = Self {
+            path: path.to_path_buf(),
+            manifest: toml::from_str(&manifest)
+                .with_context(|| format!("Failed to read the manifest of {}", path.display()))?,
+        };
+        ensure!(
+            backup.config().is_file(),
+            "the backup has no library configuration"
+        );
+        let schema_version = BackupOperations::inspect(&backup.database()).await?;
+        ensure!(
+            schema_version == backup.manifest.schema_version,
+            "the backup database has schema version {schema_version}, its manifest {}",
+            backup.manifest.schema_version
+        );
+        Ok(backup)
+    }
+}
+
+/// Writes and prunes the backups of one library
+#[derive(Debug)]
+pub struct LibraryBackups {
+    library: String,
+    share_path: PathBuf,
+    config: BackupConfig,
+    backup_operations: BackupOperations,
+    /// Held while a backup is being written
+    writing: Mutex<()>,
+}
+
+impl LibraryBackups {
+    /// Backups of the library stored in `share_path`, its folder name names the backups
+    pub fn new(
+        database_manager: Arc<DatabaseManager>,
+        share_path: impl Into<PathBuf>,
+        config: BackupConfig,
+    ) -> Self {
+        let share_path = share_path.into();
+        Self {
+            library: share_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            share_path,
+            config,
+            backup_operations: BackupOperations::new(database_manager),
+            writing: Mutex::new(()),
+        }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &BackupConfig {
+        &self.config
+    }
+
+    /// Where the backups are written
+    #[must_use]
+    pub fn folder(&self) -> PathBuf {
+        self.config
+            .folder
+            .clone()
+            .unwrap_or_else(|| self.share_path.join("backups"))
+    }
+
+    /// Back up the database and configuration, then remove the backups beyond the ones to keep
+    ///
+    /// The backup is written to a hidden folder first and only shows up once it is complete.
+    pub async fn create(&self) -> Result<Backup> {
+        let _writing = self.writing.lock().await;
+        let manifest = BackupManifest {
+            library: self.library.clone(),
+            created_at: Utc::now().naive_utc(),
+            schema_version: self.backup_operations.schema_version().await?,
+        };
+        let path = self.folder().join(manifest.folder_name());
+        let partial = self.folder().join(format!(".{}", manifest.folder_name()));
+        tokio::fs::create_dir_all(&partial)
+            .await
+            .with_context(|| format!("Failed to create {}", partial.display()))?;
+        let written = self.write(&partial, &manifest).await;
+        if let Err(error) = written {
+            drop(tokio::fs::remove_dir_all(&partial).await);
+            return Err(error);
+        }
+        tokio::fs::rename(&partial, &path)
+            .await
+            .with_context(|| format!("Failed to move the backup to {}", path.display()))?;
+
+        for expired in backup::expired(self.dated().await?, self.config.keep) {
+            tokio::fs::remove_dir_all(&expired)
+                .await
+                .with_context(|| format!("Failed to remove old backup {}", expired.display()))?;
+        }
+        Ok(Backup { path, manifest })
+    }
+
+    /// Wait until a backup that is being written is complete
+    pub async fn wait_until_idle(&self) {
+        drop(self.writing.lock().await);
+    }
+
+    /// The backups of the library, newest first
+    pub async fn list(&self) -> Result<Vec<Backup>> {
+        let mut backups = Vec::new();
+        let folder = self.folder();
+        let mut entries = match tokio::fs::read_dir(&folder).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(backups),
+            Err(error) => {
+                return Err(error).with_context(|| format!("Failed to read {}", folder.display()));
+            }
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            // Backups still being written are hidden
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            // Other libraries may share the folder
+            let Ok(manifest) = tokio::fs::read_to_string(path.join(MANIFEST_FILE)).await else {
+                continue;
+            };
+            if let Ok(manifest) = toml::from_str::<BackupManifest>(&manifest)
+                && manifest.library == self.library
+            {
+                backups.push(Backup { path, manifest });
+            }
+        }
+        backups.sort_by_key(|backup| Reverse(backup.manifest.created_at));
+        Ok(backups)
+    }
+
+    async fn write(&self, folder: &Path, manifest: &BackupManifest) -> Result<()> {
+        self.backup_operations
+            .copy_to(&folder.join(DATABASE_FILE))
+            .await?;
+        tokio::fs::copy(self.share_path.join(CONFIG_FILE), folder.join(CONFIG_FILE))
+            .await
+            .context("Failed to copy the library configuration")?;
+        // Written last, a folder without a manifest is no backup
+        tokio::fs::write(folder.join(MANIFEST_FILE), toml::to_string(manifest)?)
+            .await
+            .context("Failed to write the backup manifest")?;
+        Ok(())
+    }
+
+    async fn dated(&self) -> Result<Vec<(NaiveDateTime, PathBuf)>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .map(|backup| (backup.manifest.created_at, backup.path))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use migration::{Migrator, MigratorTrait};
+    use repositories::config::DatabaseSettings;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn backups_are_complete_and_pruned() -> Result<()> {
+        let directory = TempDir::new()?;
+        let share_path = directory.path().join("Photos");
+        std::fs::create_dir(&share_path)?;
+        std::fs::write(share_path.join(CONFIG_FILE), "name = \"Photos\"")?;
+        let settings = DatabaseSettings {
+            con_string: format!(
+                "sqlite://{}?mode=rwc",
+                share_path.join(DATABASE_FILE).display()
+            ),
+            timeout: 1_000,
+            ..DatabaseSettings::default()
+        };
+        let database_manager = Arc::new(DatabaseManager::new(settings).await?);
+        Migrator::up(database_manager.get_connection().as_ref(), None).await?;
+        let config = BackupConfig {
+            keep: 2,
+            ..BackupConfig::default()
+        };
+        let backups = LibraryBackups::new(database_manager, &share_path, config);
+
+        let first = backups.create().await?;
+        assert!(first.path.starts_with(share_path.join("backups")));
+        assert_eq!(Backup::inspect(&first.path).await?, first);
+        let second = backups.create().await?;
+        let third = backups.create().await?;
+        assert_eq!(backups.list().await?, vec![third, second]);
+        assert!(!first.path.exists());
+
+        std::fs::create_dir(directory.path().join("empty"))?;
+        assert!(
+            Backup::inspect(&directory.path().join("empty"))
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+}
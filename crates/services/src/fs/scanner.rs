@@ -9,9 +9,12 @@ use tokio::fs;
 
 use model::services::file::FileSystemFile as File;
 use model::services::folder::FileSystemFolder as Folder;
+use model::services::journal::JournalSource;
 use repositories::fs::operations::{FileMetadata, FileRepository as FileOperations};
 use tracing::info;
 
+use crate::tagging::auto_tag::AutoTagger;
+
 /// Types of synchronization operations
 #[derive(Debug, Clone)]
 pub enum SyncOperation {
@@ -39,6 +42,10 @@ pub struct SyncReport {
     pub folders_updated: usize,
     pub folders_deleted: usize,
     pub folders_skipped: usize,
+    /// Tags assigned by the auto-tag rules
+    pub tags_assigned: usize,
+    /// Tags the auto-tag rules created
+    pub created_tags: Vec<i32>,
     pub errors: Vec<String>,
     pub duration: std::time::Duration,
 }
@@ -56,6 +63,8 @@ impl SyncReport {
             folders_updated: 0,
             folders_deleted: 0,
             folders_skipped: 0,
+            tags_assigned: 0,
+            created_tags: Vec::new(),
             errors: Vec::new(),
             duration: std::time::Duration::from_secs(0),
         }
@@ -71,6 +80,7 @@ impl SyncReport {
 pub struct DirectoryScanner {
     file_operations: Arc<FileOperations>,
     config: ScanConfig,
+    auto_tagger: Option<Arc<AutoTagger>>,
 }
 
 impl DirectoryScanner {
@@ -79,6 +89,7 @@ impl DirectoryScanner {
         Self {
             file_operations,
             config: ScanConfig::default(),
+            auto_tagger: None,
         }
     }
 
@@ -87,9 +98,16 @@ impl DirectoryScanner {
         Self {
             file_operations,
             config,
+            auto_tagger: None,
         }
     }
 
+    /// Apply the auto-tag rules to every file that is added or changed
+    pub fn with_auto_tagger(mut self, auto_tagger: Arc<AutoTagger>) -> Self {
+        self.auto_tagger = Some(auto_tagger);
+        self
+    }
+
     /// Synchronize a directory with the database
     pub async fn sync_directory(&self, dir_path: &Path) -> Result<SyncReport> {
         let start_time = Instant::now();
@@ -415,6 +433,7 @@ impl DirectoryScanner {
                     "Successfully processed batch of {} files",
                     file_report.file_inserted + file_report.file_updated
                 );
+                self.apply_auto_tags(batch, report).await;
             }
             Err(e) => {
                 let error_msg = format!("Failed to execute insert batch: {:?}", e);
@@ -425,6 +444,34 @@ impl DirectoryScanner {
         batch.clear();
     }
 
+    /// Tag the files of a stored batch according to the auto-tag rules
+    async fn apply_auto_tags(&self, batch: &[File], report: &mut SyncReport) {
+        let Some(auto_tagger) = self
+            .auto_tagger
+            .as_ref()
+            .filter(|tagger| !tagger.is_empty())
+        else {
+            return;
+        };
+        let paths: Vec<PathBuf> = batch.iter().map(|file| file.path.clone()).collect();
+        let result = match self.file_operations.get_files_by_paths(&paths).await {
+            Ok(files) => auto_tagger.apply(&files, JournalSource::Scanner).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(assignments) => {
+                report.tags_assigned += assignments.assigned.len();
+                report.created_tags.extend(assignments.created_tags);
+            }
+            Err(e) => {
+                report
+                    .errors
+                    .push(format!("Failed to apply auto-tag rules: {e:?}"));
+                tracing::error!("Auto-tagging failed: {e:?}");
+            }
+        }
+    }
+
     async fn execute_upsert_folder_batch(&self, batch: &mut Vec<Folder>, report: &mut SyncReport) {
         if batch.is_empty() {
             return;
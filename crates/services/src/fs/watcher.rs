@@ -1,19 +1,32 @@
+use crate::fs::scanner::DirectoryScanner;
+use crate::tagging::auto_tag::AutoTagger;
+use crate::thumbnails::thumbnails::ThumbnailProcessorHandler;
 use anyhow::{Context, Result, bail, ensure};
+use entity::files;
+use events::changes::{ChangeBus, LibraryChange};
 use events::{FileEvent, FolderEvent};
 use hash::hash::{FileHash, FolderHash};
 use model::services::CanonPath;
+use model::services::journal::JournalSource;
+use model::services::watch::WatchMode;
 use notify::event::{CreateKind, EventKind, RemoveKind};
-use notify::{Error, RecommendedWatcher, RecursiveMode};
+use notify::{ErrorKind, PollWatcher, RecommendedWatcher, RecursiveMode};
 use notify_debouncer_full::{
     DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache, new_debouncer,
+    new_debouncer_opt,
 };
 use repositories::fs::operations::FileRepository as FileOperations;
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tokio::sync::mpsc::{self, Receiver, Sender, UnboundedSender};
-use tokio::sync::{Mutex, oneshot};
+#[cfg(test)]
+use tokio::sync::Mutex;
+use tokio::sync::mpsc::{self, Sender, UnboundedSender};
+use tokio::sync::{RwLock, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 use tracing::{error, info, warn};
 
 #[derive(Debug)]
@@ -43,20 +56,105 @@ impl From<FolderEvent> for FSEvent {
 #[async_trait::async_trait]
 pub trait FileWatcherEventHandler: Send + Sync {
     async fn handle_event(&self, event: FSEvent) -> Result<()>;
+
+    /// Bring everything below a watched root up to date after its events were lost
+    async fn resync(&self, root: &Path) -> Result<()> {
+        warn!(
+            "Events below {} were lost and this handler cannot recover them",
+            root.display()
+        );
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 pub struct DatabaseFileWatcherEventHandler {
-    pub db_operations: FileOperations,
-    pub changes: Option<UnboundedSender<()>>,
+    pub db_operations: Arc<FileOperations>,
+    pub changes: Option<ChangeBus>,
+    /// Regenerates thumbnails of files whose content changed
+    pub thumbnails: Option<ThumbnailProcessorHandler>,
+    /// Tags files that were added or changed according to the rules of the library
+    pub auto_tagger: Option<Arc<AutoTagger>>,
+}
+
+impl DatabaseFileWatcherEventHandler {
+    /// Apply the auto-tag rules to a stored file, returning the resulting library changes
+    async fn auto_tag(&self, file: &files::Model) -> Vec<LibraryChange> {
+        let Some(auto_tagger) = &self.auto_tagger else {
+            return Vec::new();
+        };
+        match auto_tagger
+            .apply(std::slice::from_ref(file), JournalSource::Watcher)
+            .await
+        {
+            Ok(assignments) => {
+                let mut changes: Vec<LibraryChange> = assignments
+                    .created_tags
+                    .into_iter()
+                    .map(|tag_id| LibraryChange::TagsChanged { tag_id })
+                    .collect();
+                changes.extend(
+                    assignments
+                        .assigned
+                        .into_iter()
+                        .map(|(file_id, tag_id)| LibraryChange::TagAssigned { file_id, tag_id }),
+                );
+                changes
+            }
+            Err(e) => {
+                warn!("Failed to apply auto-tag rules to {}: {e:?}", file.path);
+                Vec::new()
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl FileWatcherEventHandler for DatabaseFileWatcherEventHandler {
     async fn handle_event(&self, event: FSEvent) -> Result<()> {
-        FileWatcher::to_database(event, &self.db_operations).await?;
+        let (stored_file, mut library_changes) =
+            FileWatcher::to_database(event, &self.db_operations).await?;
+        if let Some(file) = &stored_file {
+            library_changes.extend(self.auto_tag(file).await);
+        }
+        if let (Some(file), Some(thumbnails)) = (stored_file, &self.thumbnails) {
+            thumbnails.invalidate_files(vec![file.into()]).await?;
+        }
+        if let Some(changes) = &self.changes {
+            for change in library_changes {
+                changes.publish(change);
+            }
+        }
+        Ok(())
+    }
+
+    async fn resync(&self, root: &Path) -> Result<()> {
+        info!(
+            "Resynchronizing {} after lost watcher events",
+            root.display()
+        );
+        let mut scanner = DirectoryScanner::new(Arc::clone(&self.db_operations));
+        if let Some(auto_tagger) = &self.auto_tagger {
+            scanner = scanner.with_auto_tagger(Arc::clone(auto_tagger));
+        }
+        let report = scanner.sync_directory(root).await?;
+        info!(
+            "Resync of {} finished: {} files inserted, {} updated, {} deleted",
+            root.display(),
+            report.files_inserted,
+            report.files_updated,
+            report.files_deleted
+        );
+        if let Some(thumbnails) = &self.thumbnails {
+            thumbnails.queue_missing_files().await?;
+        }
         if let Some(changes) = &self.changes {
-            let _send_result = changes.send(());
+            for tag_id in report.created_tags {
+                changes.publish(LibraryChange::TagsChanged { tag_id });
+            }
+            changes.publish(LibraryChange::FolderResynced {
+                root: root.to_path_buf(),
+            });
         }
         Ok(())
     }
@@ -78,25 +176,87 @@ impl FileWatcherEventHandler for TestFileWatcherEventHandler {
     }
 }
 
-type RawEventReceiver = Option<
-    Arc<Mutex<tokio::sync::mpsc::Receiver<std::result::Result<Vec<DebouncedEvent>, Vec<Error>>>>>,
->;
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FileWatcherHandler {
     pub sender: mpsc::UnboundedSender<FileWatcherMessage>,
 }
 
+impl FileWatcherHandler {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<FileWatcherMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (Self { sender }, receiver)
+    }
+
+    pub fn watch_path(&self, path: CanonPath, mode: WatchMode) -> Result<()> {
+        self.sender
+            .send(FileWatcherMessage::WatchPath(path, mode))?;
+        Ok(())
+    }
+
+    pub fn unwatch_path(&self, path: CanonPath) -> Result<()> {
+        self.sender.send(FileWatcherMessage::UnwatchPath(path))?;
+        Ok(())
+    }
+
+    pub async fn get_watch_paths(&self) -> Result<HashMap<CanonPath, WatchMode>> {
+        let (respond_to, response) = oneshot::channel();
+        self.sender
+            .send(FileWatcherMessage::GetWatchPaths(respond_to))?;
+        Ok(response.await?)
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        self.sender.send(FileWatcherMessage::Pause)?;
+        Ok(())
+    }
+
+    pub fn resume(&self) -> Result<()> {
+        self.sender.send(FileWatcherMessage::Resume)?;
+        Ok(())
+    }
+
+    /// Stop the watcher and wait until it no longer touches the database
+    pub async fn stop(&self) -> Result<()> {
+        let (respond_to, response) = oneshot::channel();
+        self.sender.send(FileWatcherMessage::Stop(respond_to))?;
+        Ok(response.await?)
+    }
+}
+
 pub enum FileWatcherMessage {
-    WatchPath(CanonPath),
+    /// Start watching a folder, replacing the mode it was watched with before
+    WatchPath(CanonPath, WatchMode),
     UnwatchPath(CanonPath),
-    GetWatchPaths(oneshot::Sender<HashSet<CanonPath>>),
+    GetWatchPaths(oneshot::Sender<HashMap<CanonPath, WatchMode>>),
+    /// Drop changes until resumed; the watched folders are kept
+    Pause,
+    /// Handle changes again and resync every folder to pick up what was missed while paused
+    Resume,
+    /// Tear down all watchers and reply once the event handler has finished its last work
+    Stop(oneshot::Sender<()>),
+}
+
+/// Work applied to the event handler in the order the watcher observed it
+#[derive(Debug)]
+enum WatcherWork {
+    Event(FSEvent),
+    /// Events below the root were lost, compare the directory tree with the database instead
+    Resync(PathBuf),
 }
 
 pub struct FileWatcher {
     watcher: Option<Debouncer<RecommendedWatcher, RecommendedCache>>,
+    /// One polling watcher per folder, as the poll interval is set per watcher
+    poll_watchers: HashMap<CanonPath, Debouncer<PollWatcher, RecommendedCache>>,
+    /// Scheduled resyncs of folders that are not watched at all
+    rescans: HashMap<CanonPath, JoinHandle<()>>,
+    raw_sender: Option<Sender<DebounceEventResult>>,
+    work_sender: Option<Sender<WatcherWork>>,
     pub message_receiver: mpsc::UnboundedReceiver<FileWatcherMessage>,
-    watched_paths: Option<HashSet<CanonPath>>,
+    watched_paths: Arc<RwLock<HashMap<CanonPath, WatchMode>>>,
+    paused: Arc<AtomicBool>,
+    event_task: Option<JoinHandle<()>>,
+    handler_task: Option<JoinHandle<()>>,
 }
 
 impl FileWatcher {
@@ -104,47 +264,84 @@ impl FileWatcher {
         &mut self,
         event_handler: Box<dyn FileWatcherEventHandler>,
     ) -> Result<()> {
-        let (r_tx, mut r_rx) = tokio::sync::mpsc::channel(100);
-        let rt = tokio::runtime::Handle::current();
-        let (p_tx, mut p_rx) = tokio::sync::mpsc::channel::<FSEvent>(100);
-
-        let debouncer = new_debouncer(
-            Duration::from_secs(2),
-            None,
-            move |result: DebounceEventResult| {
-                let r_tx_clone = r_tx.clone();
-                rt.spawn(async move {
-                    if let Err(e) = r_tx_clone.send(result).await {
-                        info!("Error sending event result: {:?}", e);
-                    };
-                });
-            },
-        );
+        let (r_tx, mut r_rx) = tokio::sync::mpsc::channel::<DebounceEventResult>(100);
+        let (p_tx, mut p_rx) = tokio::sync::mpsc::channel::<WatcherWork>(100);
+
+        let debouncer = new_debouncer(Duration::from_secs(2), None, forward_events(r_tx.clone()));
+        self.raw_sender = Some(r_tx);
+        self.work_sender = Some(p_tx.clone());
 
-        tokio::spawn(async move {
+        let watched_paths = Arc::clone(&self.watched_paths);
+        self.event_task = Some(tokio::spawn(async move {
             while let Some(res) = r_rx.recv().await {
+                let roots: HashSet<CanonPath> =
+                    watched_paths.read().await.keys().cloned().collect();
+                let mut resync_roots = BTreeSet::new();
+                let mut events = Vec::new();
                 match res {
-                    Ok(events) => {
-                        for event in events {
-                            if let Err(e) = to_file_or_folder_event_and_send(event, &p_tx).await {
-                                error!("Failed to process event: {:?}", e);
+                    Ok(batch) => {
+                        for event in batch {
+                            if event.need_rescan() {
+                                warn!("Watcher reported lost events: {:?}", event.event);
+                                resync_roots.extend(affected_roots(&roots, &event.paths));
+                            } else {
+                                events.push(event);
+                            }
+                        }
+                    }
+                    Err(errors) => {
+                        for e in errors {
+                            error!("Watcher error: {:?}", e);
+                            if !matches!(e.kind, ErrorKind::PathNotFound | ErrorKind::WatchNotFound)
+                            {
+                                resync_roots.extend(affected_roots(&roots, &e.paths));
                             }
                         }
                     }
-                    Err(e) => {
-                        error!("errors: {:?}", e);
+                }
+
+                for event in coalesce_events(events) {
+                    // The resync below picks up these changes as well
+                    let covered = event
+                        .paths
+                        .iter()
+                        .any(|path| resync_roots.iter().any(|root| path.starts_with(root)));
+                    if covered {
+                        continue;
+                    }
+                    if let Err(e) = to_file_or_folder_event_and_send(event, &p_tx).await {
+                        error!("Failed to process event: {:?}", e);
+                    }
+                }
+                for root in resync_roots {
+                    if p_tx.send(WatcherWork::Resync(root)).await.is_err() {
+                        info!("Event handler stopped before the resync could be scheduled");
                     }
                 }
             }
-        });
+        }));
 
-        tokio::spawn(async move {
-            while let Some(event) = p_rx.recv().await {
-                if let Err(e) = event_handler.handle_event(event).await {
-                    error!("Failed to store event to database: {:?}", e);
+        let paused = Arc::clone(&self.paused);
+        self.handler_task = Some(tokio::spawn(async move {
+            while let Some(work) = p_rx.recv().await {
+                // Resuming resyncs every folder, which covers whatever is dropped here
+                if paused.load(Ordering::Acquire) {
+                    continue;
+                }
+                match work {
+                    WatcherWork::Event(event) => {
+                        if let Err(e) = event_handler.handle_event(event).await {
+                            error!("Failed to store event to database: {:?}", e);
+                        }
+                    }
+                    WatcherWork::Resync(root) => {
+                        if let Err(e) = event_handler.resync(&root).await {
+                            error!("Failed to resync {}: {:?}", root.display(), e);
+                        }
+                    }
                 }
             }
-        });
+        }));
 
         match debouncer {
             Ok(watcher) => {
@@ -159,8 +356,15 @@ impl FileWatcher {
     pub fn new(message_receiver: mpsc::UnboundedReceiver<FileWatcherMessage>) -> FileWatcher {
         Self {
             watcher: None,
+            poll_watchers: HashMap::new(),
+            rescans: HashMap::new(),
+            raw_sender: None,
+            work_sender: None,
             message_receiver,
-            watched_paths: None,
+            watched_paths: Arc::new(RwLock::new(HashMap::new())),
+            paused: Arc::new(AtomicBool::new(false)),
+            event_task: None,
+            handler_task: None,
         }
     }
 
@@ -168,8 +372,8 @@ impl FileWatcher {
         self.init_watcher(event_handler).await?;
         while let Some(res) = self.message_receiver.recv().await {
             match res {
-                FileWatcherMessage::WatchPath(path) => {
-                    match self.watch(path).await {
+                FileWatcherMessage::WatchPath(path, mode) => {
+                    match self.watch(path, mode).await {
                         Ok(()) => info!("Path is being watched successfully"),
                         Err(e) => error!("The path could not be watched due to: {e:#?}"),
                     };
@@ -177,28 +381,96 @@ impl FileWatcher {
                 FileWatcherMessage::UnwatchPath(path) => {
                     self.unwatch(path).await?;
                 }
-                FileWatcherMessage::GetWatchPaths(sender) => match self.watched_paths.as_ref() {
-                    Some(paths) => {
-                        let _ = sender.send(paths.to_owned());
+                FileWatcherMessage::GetWatchPaths(sender) => {
+                    let _ = sender.send(self.watched_paths.read().await.clone());
+                }
+                FileWatcherMessage::Pause => {
+                    info!("Pausing the file watcher");
+                    self.paused.store(true, Ordering::Release);
+                }
+                FileWatcherMessage::Resume => {
+                    if self.paused.swap(false, Ordering::AcqRel) {
+                        info!("Resuming the file watcher");
+                        self.resync_all().await;
                     }
-                    None => (),
-                },
+                }
+                FileWatcherMessage::Stop(respond_to) => {
+                    self.shutdown().await;
+                    let _ = respond_to.send(());
+                    return Ok(());
+                }
             }
         }
+        // Every handler was dropped without stopping the watcher first
+        self.shutdown().await;
         Ok(())
     }
 
+    async fn resync_all(&self) {
+        let Some(work_sender) = self.work_sender.as_ref() else {
+            return;
+        };
+        let roots: Vec<PathBuf> = self
+            .watched_paths
+            .read()
+            .await
+            .keys()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+        for root in roots {
+            if work_sender.send(WatcherWork::Resync(root)).await.is_err() {
+                warn!("Event handler stopped before the resync could be scheduled");
+                return;
+            }
+        }
+    }
+
+    /// Stop every watcher and wait for the event handler to drain the work already queued
+    ///
+    /// Nothing reaches the event handler once this returns, so its database can be closed.
+    async fn shutdown(&mut self) {
+        info!("Stopping the file watcher");
+        self.watcher = None;
+        self.poll_watchers.clear();
+        for (_, rescan) in self.rescans.drain() {
+            rescan.abort();
+        }
+        // Events still being debounced are lost; a restarted watcher begins with a full sync
+        if let Some(event_task) = self.event_task.take() {
+            event_task.abort();
+        }
+        self.raw_sender = None;
+        self.work_sender = None;
+        if let Some(handler_task) = self.handler_task.take()
+            && let Err(e) = handler_task.await
+        {
+            error!("Event handler task failed: {:?}", e);
+        }
+        self.watched_paths.write().await.clear();
+    }
+
     pub async fn unwatch(&mut self, path: CanonPath) -> Result<()> {
         let path_display = path.as_str()?.to_string();
-        let watched_paths = self
+        let mode = self
             .watched_paths
-            .as_mut()
-            .context("cannot unwatch a path before any paths have been watched")?;
-
-        ensure!(
-            watched_paths.remove(&path),
-            "path {path_display} is not being watched"
-        );
+            .write()
+            .await
+            .remove(&path)
+            .with_context(|| format!("path {path_display} is not being watched"))?;
+        match mode {
+            WatchMode::Native => {
+                if let Some(watcher) = self.watcher.as_mut() {
+                    watcher.unwatch(path.as_ref())?;
+                }
+            }
+            // Dropping the debouncer stops its polling thread
+            WatchMode::Poll { .. } => drop(self.poll_watchers.remove(&path)),
+            WatchMode::Rescan { .. } => {
+                if let Some(rescan) = self.rescans.remove(&path) {
+                    rescan.abort();
+                }
+            }
+        }
         Ok(())
     }
 
@@ -208,45 +480,109 @@ impl FileWatcher {
     //separately. This is true for each folder added to watcher, but also changes based on the
     //library that is currently being looked at. I assume we want to use different db files for
     //different vault configs.
-    pub async fn watch(&mut self, path: CanonPath) -> Result<()> {
+    pub async fn watch(&mut self, path: CanonPath, mode: WatchMode) -> Result<()> {
         ensure!(
             path.try_exists()
                 .with_context(|| format!("failed to inspect watch path {path:?}"))?,
             "watch path {path:?} does not exist"
         );
-        if let Some(watcher) = self.watcher.as_mut() {
-            watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
+        let previous = self.watched_paths.read().await.get(&path).copied();
+        match previous {
+            Some(previous) if previous == mode => {
+                warn!(
+                    "Trying to add the same path twice to the watch list. No change to the watch list committed"
+                );
+                return Ok(());
+            }
+            Some(_) => self.unwatch(path.clone()).await?,
+            None => {}
         }
-        match self.watched_paths.as_mut() {
-            Some(paths) => {
-                if !paths.insert(path.to_owned()) {
-                    warn!(
-                        "Trying to add the same path twice to the watch list. No change to the watch list committed"
-                    );
+
+        match mode {
+            WatchMode::Native => {
+                if let Some(watcher) = self.watcher.as_mut() {
+                    watcher.watch(path.as_ref(), RecursiveMode::Recursive)?;
                 }
             }
-            None => {
-                let mut hs = HashSet::new();
-                hs.insert(path.to_owned());
-                self.watched_paths = Some(hs);
+            WatchMode::Poll { .. } => {
+                let raw_sender = self
+                    .raw_sender
+                    .clone()
+                    .context("cannot poll a path before the watcher is initialized")?;
+                let config = notify::Config::default()
+                    .with_poll_interval(mode.interval().unwrap_or(Duration::from_secs(30)));
+                let mut debouncer = new_debouncer_opt::<_, PollWatcher, _>(
+                    Duration::from_secs(2),
+                    None,
+                    forward_events(raw_sender),
+                    RecommendedCache::new(),
+                    config,
+                )?;
+                debouncer.watch(path.as_ref(), RecursiveMode::Recursive)?;
+                self.poll_watchers.insert(path.clone(), debouncer);
+            }
+            WatchMode::Rescan { .. } => {
+                let work_sender = self
+                    .work_sender
+                    .clone()
+                    .context("cannot schedule rescans before the watcher is initialized")?;
+                let interval = mode.interval().unwrap_or(Duration::from_secs(15 * 60));
+                let root = path.as_ref().to_path_buf();
+                let rescan = tokio::spawn(async move {
+                    let mut ticker = tokio::time::interval(interval);
+                    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                    // The first tick fires immediately, but the folder was just scanned
+                    ticker.tick().await;
+                    loop {
+                        ticker.tick().await;
+                        if work_sender
+                            .send(WatcherWork::Resync(root.clone()))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+                self.rescans.insert(path.clone(), rescan);
             }
         }
-        info!("Watching path: {path:#?}");
+        self.watched_paths.write().await.insert(path.clone(), mode);
+        info!("Watching path ({mode}): {path:#?}");
 
         Ok(())
     }
 
-    async fn to_database(event: FSEvent, db_operations: &FileOperations) -> Result<()> {
+    /// Store the event in the database
+    ///
+    /// Returns the file that was created or modified, if any, and the resulting library changes.
+    async fn to_database(
+        event: FSEvent,
+        db_operations: &FileOperations,
+    ) -> Result<(Option<files::Model>, Vec<LibraryChange>)> {
+        let mut changes = Vec::new();
         if let Some(file_event) = event.file_event {
             match file_event.kind {
                 EventKind::Create(_) | EventKind::Modify(_) => {
                     // File was created or modified, insert/update in database
                     match db_operations.upsert_file_from_event(&file_event).await {
-                        Ok(file_model) => {
+                        Ok(stored) => {
+                            let file_model = stored.model;
                             info!(
                                 "Successfully stored file: {} (ID: {})",
                                 file_model.path, file_model.id
                             );
+                            let file_id = file_model.id;
+                            changes.push(match stored.previous_path {
+                                None => LibraryChange::FileAdded { file_id },
+                                Some(from) if from != file_model.path => LibraryChange::FileMoved {
+                                    file_id,
+                                    from: PathBuf::from(from),
+                                    to: PathBuf::from(&file_model.path),
+                                },
+                                Some(_) => LibraryChange::FileModified { file_id },
+                            });
+                            return Ok((Some(file_model), changes));
                         }
                         Err(e) => {
                             error!("Failed to upsert file: {:?}", e);
@@ -259,18 +595,23 @@ impl FileWatcher {
                     for path in &file_event.paths {
                         info!("File with path {path:#?} is getting removed from db");
                         match db_operations.delete_file_by_path(path).await {
-                            Ok(deleted) => {
-                                if deleted {
+                            Ok(file_ids) => {
+                                if file_ids.is_empty() {
                                     info!(
-                                        "Successfully removed file from database: {}",
+                                        "File not found in database (already removed?): {}",
                                         path.display()
                                     );
                                 } else {
                                     info!(
-                                        "File not found in database (already removed?): {}",
+                                        "Successfully removed file from database: {}",
                                         path.display()
                                     );
                                 }
+                                changes.extend(
+                                    file_ids
+                                        .into_iter()
+                                        .map(|file_id| LibraryChange::FileRemoved { file_id }),
+                                );
                             }
                             Err(e) => {
                                 error!("Failed to delete file from database: {:?}", e);
@@ -289,11 +630,25 @@ impl FileWatcher {
                 EventKind::Create(_) | EventKind::Modify(_) => {
                     // File was created or modified, insert/update in database
                     match db_operations.upsert_folder_from_event(&folder_event).await {
-                        Ok(folder_model) => {
+                        Ok(stored) => {
+                            let folder_model = stored.model;
                             info!(
                                 "Successfully stored file: {} (ID: {})",
                                 folder_model.path, folder_model.id
                             );
+                            let folder_id = folder_model.id;
+                            match stored.previous_path {
+                                None => changes.push(LibraryChange::FolderAdded { folder_id }),
+                                Some(from) if from != folder_model.path => {
+                                    changes.push(LibraryChange::FolderMoved {
+                                        folder_id,
+                                        from: PathBuf::from(from),
+                                        to: PathBuf::from(&folder_model.path),
+                                    });
+                                }
+                                // Hash updates of an unchanged folder are not shown anywhere
+                                Some(_) => {}
+                            }
                         }
                         Err(e) => {
                             error!("Failed to upsert file: {:?}", e);
@@ -305,18 +660,23 @@ impl FileWatcher {
                     // File was deleted, remove from database
                     for path in &folder_event.paths {
                         match db_operations.delete_folder_by_path(path).await {
-                            Ok(deleted) => {
-                                if deleted {
+                            Ok(folder_ids) => {
+                                if folder_ids.is_empty() {
                                     info!(
-                                        "Successfully removed file from database: {}",
+                                        "File not found in database (already removed?): {}",
                                         path.display()
                                     );
                                 } else {
                                     info!(
-                                        "File not found in database (already removed?): {}",
+                                        "Successfully removed file from database: {}",
                                         path.display()
                                     );
                                 }
+                                changes.extend(
+                                    folder_ids.into_iter().map(|folder_id| {
+                                        LibraryChange::FolderRemoved { folder_id }
+                                    }),
+                                );
                             }
                             Err(e) => {
                                 error!("Failed to delete file from database: {:?}", e);
@@ -333,13 +693,13 @@ impl FileWatcher {
         } else {
             bail!("watcher event contains neither a file event nor a folder event");
         }
-        Ok(())
+        Ok((None, changes))
     }
 }
 
 async fn to_file_or_folder_event_and_send(
     event: DebouncedEvent,
-    processed_event_tx: &Sender<FSEvent>,
+    processed_event_tx: &Sender<WatcherWork>,
 ) -> Result<()> {
     let path = event
         .paths
@@ -365,7 +725,7 @@ async fn to_file_or_folder_event_and_send(
 
 async fn to_file_event_and_send(
     event: DebouncedEvent,
-    processed_event_tx: &Sender<FSEvent>,
+    processed_event_tx: &Sender<WatcherWork>,
 ) -> Result<()> {
     let kind = event.kind;
     let paths = event.paths.to_owned();
@@ -391,7 +751,10 @@ async fn to_file_event_and_send(
     };
     info!("Constructed FileEvent from Raw Stream");
 
-    if let Err(e) = processed_event_tx.send(file_event.into()).await {
+    if let Err(e) = processed_event_tx
+        .send(WatcherWork::Event(file_event.into()))
+        .await
+    {
         info!("Error sending processed event into channel: {e:#?}");
     } else {
         info!("Sending processed event successful")
@@ -401,7 +764,7 @@ async fn to_file_event_and_send(
 
 async fn to_folder_event_and_send(
     event: DebouncedEvent,
-    processed_event_tx: &Sender<FSEvent>,
+    processed_event_tx: &Sender<WatcherWork>,
 ) -> Result<()> {
     let kind = event.kind;
     let paths = event.paths.to_owned();
@@ -427,10 +790,286 @@ async fn to_folder_event_and_send(
     };
     info!("Constructed FileEvent from Raw Stream");
 
-    if let Err(e) = processed_event_tx.send(folder_event.into()).await {
+    if let Err(e) = processed_event_tx
+        .send(WatcherWork::Event(folder_event.into()))
+        .await
+    {
         info!("Error sending processed event into channel: {e:#?}");
     } else {
         info!("Sending processed event successful")
     }
     Ok(())
 }
+
+/// Debouncer callback passing batches into the event pipeline
+///
+/// The debouncer calls it from its own thread, so blocking here holds back further batches until
+/// the pipeline has caught up instead of piling up tasks.
+fn forward_events(sender: Sender<DebounceEventResult>) -> impl FnMut(DebounceEventResult) + Send {
+    move |result: DebounceEventResult| {
+        if let Err(e) = sender.blocking_send(result) {
+            info!("Error sending event result: {:?}", e);
+        }
+    }
+}
+
+/// Watched roots that contain one of the paths, or all of them when no path is known
+fn affected_roots(roots: &HashSet<CanonPath>, paths: &[PathBuf]) -> Vec<PathBuf> {
+    roots
+        .iter()
+        .map(|root| root.as_ref().to_path_buf())
+        .filter(|root| {
+            paths.is_empty()
+                || paths
+                    .iter()
+                    .any(|path| path.starts_with(root) || root.starts_with(path))
+        })
+        .collect()
+}
+
+/// Merge the events of one debounced batch so that every path is applied once
+///
+/// The latest event of a path wins, except that a path created and removed again within the
+/// batch is dropped entirely. Renames involve two paths and are passed on unchanged.
+fn coalesce_events(events: Vec<DebouncedEvent>) -> Vec<DebouncedEvent> {
+    // Coalesced events in order, with whether the path was created within this batch
+    let mut coalesced: Vec<Option<(DebouncedEvent, bool)>> = Vec::new();
+    let mut positions: HashMap<PathBuf, usize> = HashMap::new();
+
+    for event in events {
+        if matches!(event.kind, EventKind::Access(_)) {
+            continue;
+        }
+        let [path] = event.paths.as_slice() else {
+            for path in &event.paths {
+                positions.remove(path);
+            }
+            coalesced.push(Some((event, false)));
+            continue;
+        };
+        let path = path.clone();
+
+        let previous = positions
+            .remove(&path)
+            .and_then(|position| coalesced.get_mut(position)?.take());
+        let created = match previous {
+            Some((_, true)) if matches!(event.kind, EventKind::Remove(_)) => continue,
+            Some((_, created)) => created,
+            None => matches!(event.kind, EventKind::Create(_)),
+        };
+        positions.insert(path, coalesced.len());
+        coalesced.push(Some((event, created)));
+    }
+
+    coalesced
+        .into_iter()
+        .flatten()
+        .map(|(event, _)| event)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::Event;
+    use notify::event::{DataChange, Flag, ModifyKind, RenameMode};
+    use std::time::Instant;
+
+    fn event(kind: EventKind, paths: &[&str]) -> DebouncedEvent {
+        let event = paths.iter().fold(Event::new(kind), |event, path| {
+            event.add_path(PathBuf::from(path))
+        });
+        DebouncedEvent::new(event, Instant::now())
+    }
+
+    fn summary(events: &[DebouncedEvent]) -> Vec<(EventKind, Vec<PathBuf>)> {
+        events
+            .iter()
+            .map(|event| (event.kind, event.paths.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_coalesce_keeps_one_event_per_path() {
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        let events = coalesce_events(vec![
+            event(EventKind::Create(CreateKind::File), &["/a"]),
+            event(modify, &["/a"]),
+            event(modify, &["/b"]),
+            event(modify, &["/a"]),
+            event(modify, &["/b"]),
+        ]);
+
+        assert_eq!(
+            summary(&events),
+            vec![
+                (modify, vec![PathBuf::from("/a")]),
+                (modify, vec![PathBuf::from("/b")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_drops_files_created_and_removed_in_one_batch() {
+        let events = coalesce_events(vec![
+            event(EventKind::Create(CreateKind::File), &["/tmp.part"]),
+            event(EventKind::Remove(RemoveKind::File), &["/tmp.part"]),
+            // The file existed before, so its removal must still reach the database
+            event(EventKind::Remove(RemoveKind::File), &["/old"]),
+            event(EventKind::Create(CreateKind::File), &["/old"]),
+            event(EventKind::Remove(RemoveKind::File), &["/old"]),
+        ]);
+
+        assert_eq!(
+            summary(&events),
+            vec![(
+                EventKind::Remove(RemoveKind::File),
+                vec![PathBuf::from("/old")]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_coalesce_passes_renames_through() {
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+        let events = coalesce_events(vec![
+            event(EventKind::Create(CreateKind::File), &["/a"]),
+            event(rename, &["/a", "/b"]),
+            event(EventKind::Access(notify::event::AccessKind::Any), &["/b"]),
+        ]);
+
+        assert_eq!(summary(&events).len(), 2);
+        assert_eq!(
+            events[1].paths,
+            vec![PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+    }
+
+    #[test]
+    fn test_affected_roots_of_rescan_events() -> Result<()> {
+        let first = tempfile::tempdir()?;
+        let second = tempfile::tempdir()?;
+        let roots: HashSet<CanonPath> = [first.path(), second.path()]
+            .into_iter()
+            .map(|path| CanonPath::from(path.to_path_buf()))
+            .collect();
+        let first_root = CanonPath::from(first.path().to_path_buf());
+
+        let rescan = DebouncedEvent::new(
+            Event::new(EventKind::Other).set_flag(Flag::Rescan),
+            Instant::now(),
+        );
+        assert!(rescan.need_rescan());
+        assert_eq!(affected_roots(&roots, &rescan.paths).len(), 2);
+
+        let nested = first_root.as_ref().join("archive/photo.jpg");
+        assert_eq!(
+            affected_roots(&roots, &[nested]),
+            vec![first_root.as_ref().to_path_buf()]
+        );
+        Ok(())
+    }
+
+    struct ResyncRecorder {
+        resyncs: UnboundedSender<PathBuf>,
+    }
+
+    #[async_trait::async_trait]
+    impl FileWatcherEventHandler for ResyncRecorder {
+        async fn handle_event(&self, _event: FSEvent) -> Result<()> {
+            Ok(())
+        }
+
+        async fn resync(&self, root: &Path) -> Result<()> {
+            self.resyncs.send(root.to_path_buf())?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rescan_mode_resyncs_on_schedule() -> Result<()> {
+        let folder = tempfile::tempdir()?;
+        let root = CanonPath::from(folder.path().to_path_buf());
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (resyncs, mut resynced) = mpsc::unbounded_channel();
+        tokio::spawn(FileWatcher::new(receiver).run(Box::new(ResyncRecorder { resyncs })));
+
+        let mode = WatchMode::Rescan { interval_secs: 1 };
+        sender.send(FileWatcherMessage::WatchPath(root.clone(), mode))?;
+        let (respond_to, watched) = oneshot::channel();
+        sender.send(FileWatcherMessage::GetWatchPaths(respond_to))?;
+        assert_eq!(watched.await?.get(&root), Some(&mode));
+
+        let resynced_root = tokio::time::timeout(Duration::from_secs(5), resynced.recv())
+            .await?
+            .context("watcher stopped")?;
+        assert_eq!(resynced_root, root.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lost_events_resync_the_watched_root() -> Result<()> {
+        let folder = tempfile::tempdir()?;
+        let root = CanonPath::from(folder.path().to_path_buf());
+        let (_handler, receiver) = FileWatcherHandler::new();
+        let (resyncs, mut resynced) = mpsc::unbounded_channel();
+        let mut watcher = FileWatcher::new(receiver);
+        watcher
+            .init_watcher(Box::new(ResyncRecorder { resyncs }))
+            .await?;
+        watcher.watch(root.clone(), WatchMode::Native).await?;
+        let raw_sender = watcher
+            .raw_sender
+            .clone()
+            .context("watcher not initialized")?;
+
+        let photo = root.as_ref().join("photo.jpg");
+        let rescan = DebouncedEvent::new(
+            Event::new(EventKind::Other)
+                .set_flag(Flag::Rescan)
+                .add_path(photo.clone()),
+            Instant::now(),
+        );
+        let modify = DebouncedEvent::new(
+            Event::new(EventKind::Modify(ModifyKind::Any)).add_path(photo.clone()),
+            Instant::now(),
+        );
+        let overflow = notify::Error::generic("event queue overflow").add_path(photo);
+        for batch in [Ok(vec![rescan, modify]), Err(vec![overflow])] {
+            raw_sender.send(batch).await?;
+            let resynced_root = tokio::time::timeout(Duration::from_secs(5), resynced.recv())
+                .await?
+                .context("watcher stopped")?;
+            assert_eq!(resynced_root, root.as_ref().to_path_buf());
+        }
+
+        watcher.shutdown().await;
+        assert!(resynced.recv().await.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume_resyncs_and_stop_releases_the_handler() -> Result<()> {
+        let folder = tempfile::tempdir()?;
+        let root = CanonPath::from(folder.path().to_path_buf());
+        let (watcher, receiver) = FileWatcherHandler::new();
+        let (resyncs, mut resynced) = mpsc::unbounded_channel();
+        let running =
+            tokio::spawn(FileWatcher::new(receiver).run(Box::new(ResyncRecorder { resyncs })));
+
+        watcher.watch_path(root.clone(), WatchMode::Native)?;
+        watcher.pause()?;
+        watcher.resume()?;
+        let resynced_root = tokio::time::timeout(Duration::from_secs(5), resynced.recv())
+            .await?
+            .context("watcher stopped")?;
+        assert_eq!(resynced_root, root.as_ref().to_path_buf());
+
+        watcher.stop().await?;
+        // The event handler is dropped once the watcher has stopped
+        assert!(resynced.recv().await.is_none());
+        running.await??;
+        assert!(watcher.get_watch_paths().await.is_err());
+        Ok(())
+    }
+}
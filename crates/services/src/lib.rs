@@ -1,2 +1,4 @@
+pub mod backup;
 pub mod fs;
+pub mod tagging;
 pub mod thumbnails;
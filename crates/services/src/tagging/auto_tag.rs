@@ -0,0 +1,118 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use entity::files;
+use model::services::auto_tag::{AutoTagCandidate, AutoTagRule, AutoTagRules};
+use model::services::journal::JournalSource;
+use repositories::manager::DatabaseManager;
+use repositories::tags::operations::{TagAssignments, TagOperations};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::thumbnails::generator::ThumbnailGenerator;
+
+/// Tags a file would receive from the auto-tag rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutoTagMatch {
+    pub file_id: i32,
+    pub path: PathBuf,
+    pub tags: Vec<String>,
+}
+
+/// Applies the auto-tag rules of a library to files that were added or changed
+#[derive(Debug)]
+pub struct AutoTagger {
+    rules: AutoTagRules,
+    tag_operations: TagOperations,
+}
+
+impl AutoTagger {
+    /// Compile the rules, failing if one of them has an invalid pattern
+    pub fn new(database_manager: Arc<DatabaseManager>, rules: &[AutoTagRule]) -> Result<Self> {
+        Ok(Self {
+            rules: AutoTagRules::compile(rules)?,
+            tag_operations: TagOperations::new(database_manager),
+        })
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Match the files against the rules without changing anything
+    pub async fn preview(&self, files: &[files::Model]) -> Vec<AutoTagMatch> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        let mut matches = Vec::new();
+        for file in files {
+            let path = PathBuf::from(&file.path);
+            let Some(candidate) = self.candidate(&path).await else {
+                continue;
+            };
+            let tags = self.rules.tags_for(&candidate);
+            if !tags.is_empty() {
+                matches.push(AutoTagMatch {
+                    file_id: file.id,
+                    path,
+                    tags,
+                });
+            }
+        }
+        matches
+    }
+
+    /// Assign the tags of the matching rules, creating tags that do not exist yet
+    pub async fn apply(
+        &self,
+        files: &[files::Model],
+        source: JournalSource,
+    ) -> Result<TagAssignments> {
+        let assignments = self
+            .preview(files)
+            .await
+            .into_iter()
+            .map(|found| (found.file_id, found.tags))
+            .collect();
+        self.tag_operations
+            .assign_by_name(assignments, source)
+            .await
+    }
+
+    /// Read what the rules match on, or `None` if the file is gone
+    async fn candidate(&self, path: &Path) -> Option<AutoTagCandidate> {
+        let metadata = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                warn!("Skipping auto-tag rules for {}: {e}", path.display());
+                return None;
+            }
+        };
+        let modified = metadata
+            .modified()
+            .ok()
+            .map(|modified| DateTime::<Local>::from(modified).naive_local());
+        let mime_type = infer::get_from_path(path)
+            .ok()
+            .flatten()
+            .map_or("unknown", |kind| kind.mime_type())
+            .to_string();
+        let taken_at = if self.rules.needs_taken_date() && mime_type.starts_with("image/") {
+            ThumbnailGenerator::new()
+                .extract_metadata_from_file_path(path)
+                .ok()
+                .flatten()
+                .and_then(|metadata| metadata.taken_at)
+        } else {
+            None
+        };
+        Some(AutoTagCandidate {
+            path: path.to_path_buf(),
+            mime_type,
+            size: metadata.len(),
+            modified,
+            taken_at,
+        })
+    }
+}
@@ -0,0 +1,2 @@
+pub mod auto_tag;
+pub mod tag_sync;
@@ -0,0 +1,440 @@
+use anyhow::{Context, Result};
+use entity::files;
+use model::services::journal::JournalSource;
+use model::services::tag_sync::{
+    self, SIDECAR_EXTENSION, TagSyncConfig, TagSyncConflict, xmp_keywords, xmp_with_keywords,
+};
+use repositories::manager::DatabaseManager;
+use repositories::tags::operations::TagOperations;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What syncing tags with the files changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagSyncReport {
+    pub files_checked: usize,
+    /// Files whose attribute or sidecar was written
+    pub files_written: usize,
+    /// Tags that were created for keywords found in files
+    pub created_tags: Vec<i32>,
+    /// `(file_id, tag_id)` of every tag assigned in the library
+    pub assigned: Vec<(i32, i32)>,
+    /// `(file_id, tag_id)` of every tag removed in the library
+    pub removed: Vec<(i32, i32)>,
+    /// Files whose tags could not be read or written, with the reason
+    pub problems: Vec<String>,
+}
+
+/// Keeps the tags of the library in the extended attributes and XMP sidecars of the files
+#[derive(Debug)]
+pub struct TagSync {
+    config: TagSyncConfig,
+    tag_operations: TagOperations,
+}
+
+impl TagSync {
+    pub fn new(database_manager: Arc<DatabaseManager>, config: TagSyncConfig) -> Self {
+        Self {
+            config,
+            tag_operations: TagOperations::new(database_manager),
+        }
+    }
+
+    /// Bring the tags found in the files into the library following the conflict policy, then
+    /// write the result back to the files
+    pub async fn import(
+        &self,
+        files: &[files::Model],
+        source: JournalSource,
+    ) -> Result<TagSyncReport> {
+        self.sync(files, self.config.conflict, source).await
+    }
+
+    /// Write the tags of the library to the files, replacing what the files had
+    pub async fn export(&self, files: &[files::Model]) -> Result<TagSyncReport> {
+        self.sync(files, TagSyncConflict::Library, JournalSource::User)
+            .await
+    }
+
+    async fn sync(
+        &self,
+        files: &[files::Model],
+        conflict: TagSyncConflict,
+        source: JournalSource,
+    ) -> Result<TagSyncReport> {
+        let mut report = TagSyncReport::default();
+        // Sidecars are files of the library too, but have no sidecars of their own
+        let mut files: Vec<(i32, PathBuf)> = files
+            .iter()
+            .map(|file| (file.id, PathBuf::from(&file.path)))
+            .filter(|(_, path)| !is_sidecar(path))
+            .collect();
+        let file_ids: Vec<i32> = files.iter().map(|(file_id, _)| *file_id).collect();
+        let mut library_tags = self.tag_operations.names_of_files(&file_ids).await?;
+        // Sidecar names depend on the other files of a folder, so each folder is read once
+        files.sort_by(|(_, left), (_, right)| left.parent().cmp(&right.parent()));
+        let mut outcomes = Vec::with_capacity(files.len());
+        for folder in files.chunk_by(|(_, left), (_, right)| left.parent() == right.parent()) {
+            let folder: Vec<(i32, PathBuf, Vec<String>)> = folder
+                .iter()
+                .map(|(file_id, path)| {
+                    let library = library_tags.remove(file_id).unwrap_or_default();
+                    (*file_id, path.clone(), library)
+                })
+                .collect();
+            let config = self.config;
+            let synced = tokio::task::spawn_blocking(move || {
+                let stems = match folder.first().and_then(|(_, path, _)| path.parent()) {
+                    Some(parent) if config.xmp => stem_counts(parent),
+                    _ => StemCounts::new(),
+                };
+                folder
+                    .into_iter()
+                    .map(|(file_id, path, library)| {
+                        let outcome = sync_file(config, conflict, &path, &library, &stems);
+                        (file_id, library, outcome)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .context("Failed to sync the tags of a folder")?;
+            outcomes.extend(synced);
+        }
+        let mut assignments = Vec::new();
+        let mut removals = Vec::new();
+        for (file_id, library, outcome) in outcomes {
+            report.files_checked += 1;
+            let (tags, written) = match outcome {
+                Ok(outcome) => outcome,
+                Err(error) => {
+                    report.problems.push(format!("{error:#}"));
+                    continue;
+                }
+            };
+            if written {
+                report.files_written += 1;
+            }
+            let added: Vec<String> = tags
+                .iter()
+                .filter(|tag| !library.contains(tag))
+                .cloned()
+                .collect();
+            let removed: Vec<String> = library
+                .into_iter()
+                .filter(|tag| !tags.contains(tag))
+                .collect();
+            if !added.is_empty() {
+                assignments.push((file_id, added));
+            }
+            if !removed.is_empty() {
+                removals.push((file_id, removed));
+            }
+        }
+        let assigned = self
+            .tag_operations
+            .assign_by_name(assignments, source)
+            .await?;
+        report.created_tags = assigned.created_tags;
+        report.assigned = assigned.assigned;
+        report.removed = self
+            .tag_operations
+            .unassign_by_name(removals, source)
+            .await?;
+        Ok(report)
+    }
+}
+
+/// Resolve the tags of one file and write them where they differ
+///
+/// Returns the tags the file should have in the library and whether the file was written.
+fn sync_file(
+    config: TagSyncConfig,
+    conflict: TagSyncConflict,
+    path: &Path,
+    library: &[String],
+    stems: &StemCounts,
+) -> Result<(Vec<String>, bool)> {
+    let found = FileTags::read(config, path, stems)?;
+    let tags = tag_sync::resolve(conflict, library, found.combined().as_deref());
+    let written = found.write(config, path, &tags, stems)?;
+    Ok((tags, written))
+}
+
+/// The tags a file has in each place they are kept, `None` where there is nothing
+#[derive(Debug, Default)]
+struct FileTags {
+    xattr: Option<Vec<String>>,
+    xmp: Option<Sidecar>,
+}
+
+#[derive(Debug)]
+struct Sidecar {
+    path: PathBuf,
+    text: String,
+    keywords: Vec<String>,
+}
+
+impl FileTags {
+    fn read(config: TagSyncConfig, path: &Path, stems: &StemCounts) -> Result<Self> {
+        let mut found = Self::default();
+        if config.xattr {
+            found.xattr = xattr::read(path)
+                .with_context(|| format!("Failed to read the tags of {}", path.display()))?;
+        }
+        if config.xmp
+            && let Some(sidecar) = existing_sidecar(path, stems)
+        {
+            let text = std::fs::read_to_string(&sidecar)
+                .with_context(|| format!("Failed to read {}", sidecar.display()))?;
+            found.xmp = Some(Sidecar {
+                keywords: xmp_keywords(&text),
+                path: sidecar,
+                text,
+            });
+        }
+        Ok(found)
+    }
+
+    /// The tags of both places together, `None` if the file has neither
+    fn combined(&self) -> Option<Vec<String>> {
+        let sidecar = self.xmp.as_ref().map(|sidecar| &sidecar.keywords);
+        if self.xattr.is_none() && sidecar.is_none() {
+            return None;
+        }
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.xattr.iter().chain(sidecar).flatten() {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        Some(tags)
+    }
+
+    /// Write the tags where they differ, returns whether anything was written
+    ///
+    /// A sidecar is only created for a file that has tags.
+    fn write(
+        self,
+        config: TagSyncConfig,
+        path: &Path,
+        tags: &[String],
+        stems: &StemCounts,
+    ) -> Result<bool> {
+        let mut written = false;
+        if config.xattr && !same_tags(self.xattr.as_deref().unwrap_or_default(), tags) {
+            xattr::write(path, tags)
+                .with_context(|| format!("Failed to write the tags of {}", path.display()))?;
+            written = true;
+        }
+        if !config.xmp {
+            return Ok(written);
+        }
+        let (sidecar, text) = match self.xmp {
+            Some(sidecar) if same_tags(&sidecar.keywords, tags) => return Ok(written),
+            Some(sidecar) => (sidecar.path, Some(sidecar.text)),
+            None if tags.is_empty() => return Ok(written),
+            None => (new_sidecar(path, stems), None),
+        };
+        let text = xmp_with_keywords(text.as_deref(), tags)
+            .with_context(|| format!("Failed to update {}", sidecar.display()))?;
+        std::fs::write(&sidecar, text)
+            .with_context(|| format!("Failed to write {}", sidecar.display()))?;
+        Ok(true)
+    }
+}
+
+fn same_tags(left: &[String], right: &[String]) -> bool {
+    left.len() == right.len() && left.iter().all(|tag| right.contains(tag))
+}
+
+fn is_sidecar(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case(SIDECAR_EXTENSION))
+}
+
+/// `photo.jpg.xmp` as some photo managers write it, or `photo.xmp` as most others do
+///
+/// `photo.xmp` is left alone when it could belong to another file, like the `photo.cr2` of a
+/// raw and JPEG pair.
+fn existing_sidecar(path: &Path, stems: &StemCounts) -> Option<PathBuf> {
+    let appended = appended_sidecar(path);
+    if appended.is_file() {
+        return Some(appended);
+    }
+    let sidecar = path.with_extension(SIDECAR_EXTENSION);
+    (sidecar.is_file() && !shares_stem(path, stems)).then_some(sidecar)
+}
+
+fn new_sidecar(path: &Path, stems: &StemCounts) -> PathBuf {
+    if shares_stem(path, stems) {
+        appended_sidecar(path)
+    } else {
+        path.with_extension(SIDECAR_EXTENSION)
+    }
+}
+
+fn appended_sidecar(path: &Path) -> PathBuf {
+    let mut appended = path.as_os_str().to_owned();
+    appended.push(format!(".{SIDECAR_EXTENSION}"));
+    PathBuf::from(appended)
+}
+
+/// Number of files in a folder for each name without extension, sidecars left out
+type StemCounts = HashMap<OsString, usize>;
+
+fn stem_counts(folder: &Path) -> StemCounts {
+    let mut counts = StemCounts::new();
+    let Ok(entries) = std::fs::read_dir(folder) else {
+        return counts;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if let Some(stem) = path.file_stem().filter(|_| !is_sidecar(&path)) {
+            *counts.entry(stem.to_owned()).or_default() += 1;
+        }
+    }
+    counts
+}
+
+/// Whether another file next to this one has the same name without its extension
+fn shares_stem(path: &Path, stems: &StemCounts) -> bool {
+    path.file_stem()
+        .and_then(|stem| stems.get(stem))
+        .is_some_and(|count| *count > 1)
+}
+
+#[cfg(target_os = "linux")]
+mod xattr {
+    use model::services::tag_sync::{XATTR_NAME, xattr_tags, xattr_value};
+    use rustix::fs::{XattrFlags, getxattr, removexattr, setxattr};
+    use rustix::io::Errno;
+    use std::io;
+    use std::path::Path;
+
+    /// The tags of the attribute, `None` if the file does not have it
+    pub(super) fn read(path: &Path) -> io::Result<Option<Vec<String>>> {
+        let size = match getxattr(path, XATTR_NAME, &mut [0_u8; 0][..]) {
+            Ok(size) => size,
+            Err(Errno::NODATA | Errno::NOTSUP) => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        let mut value = vec![0; size];
+        let size = getxattr(path, XATTR_NAME, &mut value[..])?;
+        value.truncate(size);
+        Ok(Some(xattr_tags(&String::from_utf8_lossy(&value))))
+    }
+
+    /// Set the attribute, or remove it when there are no tags
+    pub(super) fn write(path: &Path, tags: &[String]) -> io::Result<()> {
+        if tags.is_empty() {
+            return match removexattr(path, XATTR_NAME) {
+                Ok(()) | Err(Errno::NODATA) => Ok(()),
+                Err(error) => Err(error.into()),
+            };
+        }
+        setxattr(
+            path,
+            XATTR_NAME,
+            xattr_value(tags).as_bytes(),
+            XattrFlags::empty(),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod xattr {
+    use std::io;
+    use std::path::Path;
+
+    pub(super) fn read(_path: &Path) -> io::Result<Option<Vec<String>>> {
+        Ok(None)
+    }
+
+    pub(super) fn write(_path: &Path, _tags: &[String]) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "tags in extended attributes are only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_sidecars_are_named_after_files_that_share_a_stem() -> Result<()> {
+        let folder = tempfile::tempdir()?;
+        for name in [
+            "photo.jpg",
+            "photo.cr2",
+            "photo.xmp",
+            "solo.png",
+            "solo.xmp",
+        ] {
+            std::fs::write(folder.path().join(name), name)?;
+        }
+        let stems = stem_counts(folder.path());
+        let photo = folder.path().join("photo.jpg");
+        let solo = folder.path().join("solo.png");
+        let new = folder.path().join("new.gif");
+
+        // Sidecars themselves do not make a stem shared
+        assert!(shares_stem(&photo, &stems));
+        assert!(!shares_stem(&solo, &stems));
+        assert_eq!(
+            existing_sidecar(&solo, &stems),
+            Some(folder.path().join("solo.xmp"))
+        );
+        assert_eq!(new_sidecar(&new, &stems), folder.path().join("new.xmp"));
+
+        // `photo.xmp` may belong to the raw file, so the JPEG gets a sidecar of its own
+        assert_eq!(existing_sidecar(&photo, &stems), None);
+        let appended = folder.path().join("photo.jpg.xmp");
+        assert_eq!(new_sidecar(&photo, &stems), appended);
+        std::fs::write(&appended, xmp_with_keywords(None, &tags(&["Raw"]))?)?;
+        assert_eq!(existing_sidecar(&photo, &stems), Some(appended));
+        Ok(())
+    }
+
+    #[test]
+    fn test_conflicts_between_library_and_sidecar_follow_the_policy() -> Result<()> {
+        let folder = tempfile::tempdir()?;
+        let config = TagSyncConfig {
+            xmp: true,
+            ..TagSyncConfig::default()
+        };
+        let library = tags(&["Family"]);
+        for (conflict, expected, written) in [
+            (TagSyncConflict::Merge, tags(&["Family", "Beach"]), true),
+            (TagSyncConflict::Library, tags(&["Family"]), true),
+            (TagSyncConflict::Files, tags(&["Beach"]), false),
+        ] {
+            let path = folder.path().join(format!("{conflict:?}.jpg"));
+            let sidecar = folder.path().join(format!("{conflict:?}.xmp"));
+            std::fs::write(&path, "")?;
+            std::fs::write(&sidecar, xmp_with_keywords(None, &tags(&["Beach"]))?)?;
+            let stems = stem_counts(folder.path());
+
+            let synced = sync_file(config, conflict, &path, &library, &stems)?;
+            assert_eq!(synced, (expected.clone(), written), "{conflict:?}");
+            assert_eq!(xmp_keywords(&std::fs::read_to_string(&sidecar)?), expected);
+        }
+
+        // Without a sidecar the library tags are kept and written to a new one
+        let path = folder.path().join("untagged.jpg");
+        std::fs::write(&path, "")?;
+        let stems = stem_counts(folder.path());
+        let synced = sync_file(config, TagSyncConflict::Files, &path, &library, &stems)?;
+        assert_eq!(synced, (library.clone(), true));
+        let sidecar = std::fs::read_to_string(folder.path().join("untagged.xmp"))?;
+        assert_eq!(xmp_keywords(&sidecar), library);
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+//! Metadata fields read from raw EXIF chunks
+//!
+//! The `image` decoders hand out the EXIF chunk of JPEG, PNG, WebP and TIFF files but only
+//! interpret the orientation tag, so the fields shown in the library are read here.
+
+use ::exif::{Exif, Field, In, Reader, Tag, Value};
+use chrono::NaiveDateTime;
+use model::services::media::MediaMetadata;
+
+/// Read capture date, camera, GPS position and orientation from a raw EXIF chunk
+///
+/// Unknown or malformed fields are left empty instead of failing the whole chunk.
+pub(crate) fn read_metadata(chunk: Vec<u8>) -> MediaMetadata {
+    let mut metadata = MediaMetadata::default();
+    let exif = Reader::new()
+        .continue_on_error(true)
+        .read_raw(chunk)
+        .or_else(|error| error.distill_partial_result(|_| {}));
+    let Ok(exif) = exif else {
+        return metadata;
+    };
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+
+    metadata.camera_make = field(Tag::Make).and_then(ascii);
+    metadata.camera_model = field(Tag::Model).and_then(ascii);
+    metadata.orientation = field(Tag::Orientation)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| u16::try_from(value).ok())
+        .filter(|value| (1..=8).contains(value));
+
+    // Fall back to the modification date of the image when the capture date is missing
+    metadata.taken_at = [Tag::DateTimeOriginal, Tag::DateTime]
+        .into_iter()
+        .find_map(|tag| field(tag).and_then(ascii).and_then(|v| parse_date_time(&v)));
+
+    metadata.latitude = coordinate(&exif, Tag::GPSLatitudeRef, Tag::GPSLatitude, "S");
+    metadata.longitude = coordinate(&exif, Tag::GPSLongitudeRef, Tag::GPSLongitude, "W");
+
+    metadata
+}
+
+fn ascii(field: &Field) -> Option<String> {
+    let Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let value = String::from_utf8_lossy(values.first()?);
+    let value = value.trim_end_matches('\0').trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn coordinate(exif: &Exif, reference: Tag, value: Tag, negative: &str) -> Option<f64> {
+    let Value::Rational(parts) = &exif.get_field(value, In::PRIMARY)?.value else {
+        return None;
+    };
+    if parts.iter().any(|part| part.denom == 0) {
+        return None;
+    }
+    let degrees = match parts.as_slice() {
+        [degrees, minutes, seconds] => {
+            degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+    let reference = exif.get_field(reference, In::PRIMARY).and_then(ascii);
+    Some(if reference.as_deref() == Some(negative) {
+        -degrees
+    } else {
+        degrees
+    })
+}
+
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y:%m:%d %H:%M:%S").ok()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    const TAG_MAKE: u16 = 0x010F;
+    const TAG_MODEL: u16 = 0x0110;
+    const TAG_ORIENTATION: u16 = 0x0112;
+    const TAG_EXIF_IFD: u16 = 0x8769;
+    const TAG_GPS_IFD: u16 = 0x8825;
+    const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+    const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+    const TAG_GPS_LATITUDE: u16 = 0x0002;
+    const TAG_GPS_LONGITUDE_REF: u16 = 0x0003;
+    const TAG_GPS_LONGITUDE: u16 = 0x0004;
+
+    const TYPE_ASCII: u16 = 2;
+    const TYPE_SHORT: u16 = 3;
+    const TYPE_LONG: u16 = 4;
+    const TYPE_RATIONAL: u16 = 5;
+
+    /// Build a little-endian EXIF chunk with camera, orientation, capture date and GPS tags
+    pub(crate) fn sample_chunk(orientation: u16) -> Vec<u8> {
+        fn entry(out: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32) {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // Layout: header (8) | IFD0 (2 + 5 * 12 + 4 = 66) | Exif IFD (2 + 12 + 4 = 18)
+        // | GPS IFD (2 + 4 * 12 + 4 = 54) | data
+        let ifd0 = 8u32;
+        let exif_ifd = ifd0 + 66;
+        let gps_ifd = exif_ifd + 18;
+        let data = gps_ifd + 54;
+        let make = b"Google\0";
+        let model = b"Pixel 8\0";
+        let date = b"2024:05:03 14:30:00\0";
+        let make_offset = data;
+        let model_offset = make_offset + make.len() as u32;
+        let date_offset = model_offset + model.len() as u32;
+        let latitude_offset = date_offset + date.len() as u32;
+        let longitude_offset = latitude_offset + 24;
+
+        let mut out = vec![0x49, 0x49, 42, 0];
+        out.extend_from_slice(&ifd0.to_le_bytes());
+
+        out.extend_from_slice(&5u16.to_le_bytes());
+        entry(
+            &mut out,
+            TAG_MAKE,
+            TYPE_ASCII,
+            make.len() as u32,
+            make_offset,
+        );
+        entry(
+            &mut out,
+            TAG_MODEL,
+            TYPE_ASCII,
+            model.len() as u32,
+            model_offset,
+        );
+        entry(
+            &mut out,
+            TAG_ORIENTATION,
+            TYPE_SHORT,
+            1,
+            u32::from(orientation),
+        );
+        entry(&mut out, TAG_EXIF_IFD, TYPE_LONG, 1, exif_ifd);
+        entry(&mut out, TAG_GPS_IFD, TYPE_LONG, 1, gps_ifd);
+        out.extend_from_slice(&0u32.to_le_bytes());
+
+        out.extend_from_slice(&1u16.to_le_bytes());
+        entry(
+            &mut out,
+            TAG_DATE_TIME_ORIGINAL,
+            TYPE_ASCII,
+            date.len() as u32,
+            date_offset,
+        );
+        out.extend_from_slice(&0u32.to_le_bytes());
+
+        out.extend_from_slice(&4u16.to_le_bytes());
+        entry(
+            &mut out,
+            TAG_GPS_LATITUDE_REF,
+            TYPE_ASCII,
+            2,
+            u32::from(b'N'),
+        );
+        entry(
+            &mut out,
+            TAG_GPS_LATITUDE,
+            TYPE_RATIONAL,
+            3,
+            latitude_offset,
+        );
+        entry(
+            &mut out,
+            TAG_GPS_LONGITUDE_REF,
+            TYPE_ASCII,
+            2,
+            u32::from(b'W'),
+        );
+        entry(
+            &mut out,
+            TAG_GPS_LONGITUDE,
+            TYPE_RATIONAL,
+            3,
+            longitude_offset,
+        );
+        out.extend_from_slice(&0u32.to_le_bytes());
+
+        out.extend_from_slice(make);
+        out.extend_from_slice(model);
+        out.extend_from_slice(date);
+        for (numerator, denominator) in [(52u32, 1u32), (30, 1), (0, 1), (13, 1), (15, 1), (36, 1)]
+        {
+            out.extend_from_slice(&numerator.to_le_bytes());
+            out.extend_from_slice(&denominator.to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_read_metadata_from_exif_chunk() {
+        let metadata = read_metadata(sample_chunk(6));
+
+        assert_eq!(metadata.camera_make.as_deref(), Some("Google"));
+        assert_eq!(metadata.camera_model.as_deref(), Some("Pixel 8"));
+        assert_eq!(metadata.orientation, Some(6));
+        assert_eq!(
+            metadata.taken_at,
+            NaiveDate::from_ymd_opt(2024, 5, 3).and_then(|date| date.and_hms_opt(14, 30, 0))
+        );
+        assert!((metadata.latitude.unwrap() - 52.5).abs() < 1e-9);
+        assert!((metadata.longitude.unwrap() + 13.26).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_read_metadata_ignores_malformed_chunks() {
+        assert!(read_metadata(b"not exif".to_vec()).is_empty());
+        assert!(
+            read_metadata(sample_chunk(6)[..40].to_vec())
+                .camera_model
+                .is_none()
+        );
+    }
+}
@@ -1,23 +1,38 @@
+use crate::thumbnails::exif;
 use anyhow::{Context, Result, ensure};
 use image::imageops::FilterType;
-use image::{DynamicImage, ImageBuffer, ImageFormat, Rgba};
+use image::{
+    DynamicImage, ImageBuffer, ImageDecoder, ImageFormat, ImageReader, Rgba, metadata::Orientation,
+};
+use model::services::media::MediaMetadata;
 use model::services::thumbnail::{Thumbnail, ThumbnailSize};
-use std::io::Cursor;
+use std::io::{BufRead, Cursor, Seek};
 use std::path::Path;
 
 pub struct ThumbnailGenerator {
     filter_type: FilterType,
+    format: ImageFormat,
 }
 
 impl ThumbnailGenerator {
     pub fn new() -> Self {
         Self {
             filter_type: FilterType::Lanczos3, // High quality resizing
+            format: ImageFormat::Png,
         }
     }
 
     pub fn with_filter(filter_type: FilterType) -> Self {
-        Self { filter_type }
+        Self {
+            filter_type,
+            format: ImageFormat::Png,
+        }
+    }
+
+    /// Encode thumbnails in the given format instead of PNG
+    pub fn with_format(mut self, format: ImageFormat) -> Self {
+        self.format = format;
+        self
     }
 
     pub async fn generate_image_thumbnail(
@@ -25,21 +40,30 @@ impl ThumbnailGenerator {
         image_data: &[u8],
         size: ThumbnailSize,
     ) -> Result<Thumbnail> {
-        let img =
-            image::load_from_memory(image_data).context("Failed to load image from memory")?;
+        let mut decoder = ImageReader::new(Cursor::new(image_data))
+            .with_guessed_format()?
+            .into_decoder()
+            .context("Failed to load image from memory")?;
+        // Photos from phones are often stored sideways with an EXIF orientation tag
+        let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+        let mut img =
+            DynamicImage::from_decoder(decoder).context("Failed to load image from memory")?;
+        img.apply_orientation(orientation);
 
         let (target_width, target_height) = size.dimensions();
 
         // Preserve aspect ratio by using thumbnail() instead of resize()
         let thumbnail = img.thumbnail(target_width, target_height);
 
-        // Encode as PNG for consistent output
-        let mut output = Vec::new();
-        thumbnail
-            .write_to(&mut Cursor::new(&mut output), ImageFormat::Png)
+        let output = self
+            .encode(thumbnail)
             .context("Failed to encode thumbnail")?;
 
-        Ok(Thumbnail::with_image_data(size, output))
+        Ok(Thumbnail::new(
+            size,
+            output,
+            self.format.to_mime_type().to_string(),
+        ))
     }
 
     pub async fn generate_from_file_path(
@@ -69,6 +93,49 @@ impl ThumbnailGenerator {
         }
     }
 
+    /// Read the EXIF metadata and dimensions of an image without decoding its pixels
+    pub fn extract_metadata(&self, image_data: &[u8]) -> Result<MediaMetadata> {
+        Self::read_metadata(ImageReader::new(Cursor::new(image_data)))
+    }
+
+    /// Read the metadata of an image file, or `None` if the file is not a supported image
+    pub fn extract_metadata_from_file_path(
+        &self,
+        file_path: &Path,
+    ) -> Result<Option<MediaMetadata>> {
+        let reader = ImageReader::open(file_path)
+            .with_context(|| format!("Failed to read file: {}", file_path.display()))?;
+        if reader.format().is_none() {
+            return Ok(None);
+        }
+        Self::read_metadata(reader).map(Some)
+    }
+
+    fn read_metadata<R: BufRead + Seek>(reader: ImageReader<R>) -> Result<MediaMetadata> {
+        let mut decoder = reader
+            .with_guessed_format()?
+            .into_decoder()
+            .context("Failed to read image header")?;
+
+        let mut metadata = decoder
+            .exif_metadata()
+            .ok()
+            .flatten()
+            .map(exif::read_metadata)
+            .unwrap_or_default();
+
+        // Report the dimensions the image is displayed with
+        let (width, height) = decoder.dimensions();
+        let rotated = matches!(metadata.orientation, Some(5..=8));
+        (metadata.width, metadata.height) = if rotated {
+            (Some(height), Some(width))
+        } else {
+            (Some(width), Some(height))
+        };
+
+        Ok(metadata)
+    }
+
     async fn generate_file_icon(&self, mime_type: &str, size: ThumbnailSize) -> Result<Thumbnail> {
         let (width, height) = size.dimensions();
         let mut img = ImageBuffer::new(width, height);
@@ -82,13 +149,27 @@ impl ThumbnailGenerator {
         }
 
         // TODO: Add file type icon/text overlay in future iteration
-        let mut output = Vec::new();
-        let dynamic_img = DynamicImage::ImageRgba8(img);
-        dynamic_img
-            .write_to(&mut Cursor::new(&mut output), ImageFormat::Png)
+        let output = self
+            .encode(DynamicImage::ImageRgba8(img))
             .context("Failed to encode file icon")?;
 
-        Ok(Thumbnail::new(size, output, "image/png".to_string()))
+        Ok(Thumbnail::new(
+            size,
+            output,
+            self.format.to_mime_type().to_string(),
+        ))
+    }
+
+    fn encode(&self, image: DynamicImage) -> Result<Vec<u8>> {
+        // The WebP encoder only supports 8-bit RGB(A) images
+        let image = match self.format {
+            ImageFormat::WebP => DynamicImage::ImageRgba8(image.into_rgba8()),
+            _ => image,
+        };
+
+        let mut output = Vec::new();
+        image.write_to(&mut Cursor::new(&mut output), self.format)?;
+        Ok(output)
     }
 
     fn get_file_type_color(&self, mime_type: &str) -> Rgba<u8> {
@@ -122,5 +203,59 @@ mod tests {
 
         let generator_custom = ThumbnailGenerator::with_filter(FilterType::Nearest);
         assert_eq!(generator_custom.filter_type, FilterType::Nearest);
+        assert_eq!(generator_custom.format, ImageFormat::Png);
+    }
+
+    /// Wrap an EXIF chunk into the APP1 segment of an encoded JPEG
+    fn jpeg_with_exif(image: &DynamicImage, chunk: &[u8]) -> Result<Vec<u8>> {
+        let mut jpeg = Vec::new();
+        image.write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)?;
+
+        let length = u16::try_from(chunk.len() + 8)?;
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&length.to_be_bytes());
+        segment.extend_from_slice(b"Exif\0\0");
+        segment.extend_from_slice(chunk);
+
+        // Insert right after the SOI marker
+        jpeg.splice(2..2, segment);
+        Ok(jpeg)
+    }
+
+    #[tokio::test]
+    async fn test_thumbnail_applies_exif_orientation() -> Result<()> {
+        // A landscape sensor image stored with "rotate 90° clockwise" orientation
+        let source = jpeg_with_exif(
+            &DynamicImage::new_rgb8(64, 32),
+            &exif::tests::sample_chunk(6),
+        )?;
+
+        let generator = ThumbnailGenerator::new();
+        let thumbnail = generator
+            .generate_image_thumbnail(&source, ThumbnailSize::Large)
+            .await?;
+        let decoded = image::load_from_memory(thumbnail.data())?;
+        assert!(decoded.height() > decoded.width());
+
+        let metadata = generator.extract_metadata(&source)?;
+        assert_eq!(metadata.orientation, Some(6));
+        assert_eq!(metadata.camera_model.as_deref(), Some("Pixel 8"));
+        assert_eq!((metadata.width, metadata.height), (Some(32), Some(64)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_generate_webp_thumbnail() -> Result<()> {
+        let mut source = Vec::new();
+        DynamicImage::new_rgb8(64, 32).write_to(&mut Cursor::new(&mut source), ImageFormat::Png)?;
+
+        let generator = ThumbnailGenerator::new().with_format(ImageFormat::WebP);
+        let thumbnail = generator
+            .generate_image_thumbnail(&source, ThumbnailSize::Small)
+            .await?;
+
+        assert_eq!(thumbnail.mime_type(), "image/webp");
+        assert_eq!(image::guess_format(thumbnail.data())?, ImageFormat::WebP);
+        Ok(())
     }
 }
@@ -1,2 +1,4 @@
+mod exif;
 pub mod generator;
+pub mod queue;
 pub mod thumbnails;
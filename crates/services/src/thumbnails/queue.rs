@@ -0,0 +1,258 @@
+//! Priority queue feeding the thumbnail workers
+//!
+//! Jobs are ordered by priority first and by arrival within a priority. A file and size is
+//! only ever pending once: queueing it again keeps the original place in line unless the new
+//! request has a higher priority, in which case the job moves up.
+
+use crate::thumbnails::thumbnails::ThumbnailJob;
+use model::services::thumbnail::ThumbnailSize;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use tokio::sync::{Mutex, Notify};
+
+/// How urgently a thumbnail is needed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ThumbnailPriority {
+    /// Backfill of the whole library
+    #[default]
+    Background,
+    /// Explicitly requested or invalidated by a file change
+    Requested,
+    /// The file is currently shown on screen
+    Visible,
+}
+
+type JobKey = (i32, ThumbnailSize);
+
+#[derive(Debug, PartialEq, Eq)]
+struct Entry {
+    priority: ThumbnailPriority,
+    sequence: u64,
+    key: JobKey,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Higher priority first, then older entries first
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueState {
+    heap: BinaryHeap<Entry>,
+    /// Pending jobs with their arrival sequence; a reprioritized job gets a second heap entry
+    /// and the one whose priority no longer matches is skipped when popped
+    pending: HashMap<JobKey, (ThumbnailJob, u64)>,
+    next_sequence: u64,
+    processing: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct JobQueue {
+    state: Mutex<QueueState>,
+    notify: Notify,
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a job, coalescing it with a pending job for the same file and size
+    ///
+    /// Returns `false` when the job was merged into one that was already pending.
+    pub async fn push(&self, job: ThumbnailJob) -> bool {
+        let key = (job.file_id, job.size);
+        let mut guard = self.state.lock().await;
+        let state = &mut *guard;
+
+        if let Some((pending, sequence)) = state.pending.get_mut(&key) {
+            // The path may have changed since the job was queued
+            pending.file_path = job.file_path;
+            if job.priority > pending.priority {
+                pending.priority = job.priority;
+                let entry = Entry {
+                    priority: job.priority,
+                    sequence: *sequence,
+                    key,
+                };
+                state.heap.push(entry);
+            }
+            return false;
+        }
+
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.heap.push(Entry {
+            priority: job.priority,
+            sequence,
+            key,
+        });
+        state.pending.insert(key, (job, sequence));
+        drop(guard);
+        self.notify.notify_one();
+        true
+    }
+
+    /// Take the most urgent job, if any
+    pub async fn pop(&self) -> Option<ThumbnailJob> {
+        let mut state = self.state.lock().await;
+        while let Some(entry) = state.heap.pop() {
+            let is_current = state
+                .pending
+                .get(&entry.key)
+                .is_some_and(|(job, sequence)| {
+                    *sequence == entry.sequence && job.priority == entry.priority
+                });
+            if is_current {
+                let (job, _) = state.pending.remove(&entry.key)?;
+                state.processing += 1;
+                return Some(job);
+            }
+        }
+        None
+    }
+
+    /// Wait until a job is available and take it
+    pub async fn next(&self) -> ThumbnailJob {
+        loop {
+            // Register interest before checking so a push in between is not missed
+            let notified = self.notify.notified();
+            if let Some(job) = self.pop().await {
+                return job;
+            }
+            notified.await;
+        }
+    }
+
+    /// Mark a job taken with [`Self::pop`] or [`Self::next`] as done
+    pub async fn finish(&self) {
+        let mut state = self.state.lock().await;
+        state.processing = state.processing.saturating_sub(1);
+    }
+
+    /// Lower visible jobs of files that scrolled out of view to [`ThumbnailPriority::Requested`]
+    pub async fn demote_visible(&self, visible_file_ids: &HashSet<i32>) {
+        let mut guard = self.state.lock().await;
+        let state = &mut *guard;
+        let demoted: Vec<JobKey> = state
+            .pending
+            .iter()
+            .filter(|((file_id, _), (job, _))| {
+                job.priority == ThumbnailPriority::Visible && !visible_file_ids.contains(file_id)
+            })
+            .map(|(key, _)| *key)
+            .collect();
+
+        for key in demoted {
+            if let Some((job, sequence)) = state.pending.get_mut(&key) {
+                job.priority = ThumbnailPriority::Requested;
+                let entry = Entry {
+                    priority: job.priority,
+                    sequence: *sequence,
+                    key,
+                };
+                state.heap.push(entry);
+            }
+        }
+    }
+
+    pub async fn pending_count(&self) -> usize {
+        self.state.lock().await.pending.len()
+    }
+
+    pub async fn processing_count(&self) -> usize {
+        self.state.lock().await.processing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    fn job(file_id: i32, priority: ThumbnailPriority) -> ThumbnailJob {
+        ThumbnailJob::new(
+            file_id,
+            PathBuf::from(format!("/library/{file_id}.jpg")),
+            ThumbnailSize::Medium,
+            priority,
+        )
+    }
+
+    async fn drain(queue: &JobQueue) -> Vec<i32> {
+        let mut file_ids = Vec::new();
+        while let Some(job) = queue.pop().await {
+            file_ids.push(job.file_id);
+        }
+        file_ids
+    }
+
+    #[tokio::test]
+    async fn test_higher_priority_jobs_go_first() {
+        let queue = JobQueue::new();
+        queue.push(job(1, ThumbnailPriority::Background)).await;
+        queue.push(job(2, ThumbnailPriority::Background)).await;
+        queue.push(job(3, ThumbnailPriority::Requested)).await;
+        queue.push(job(4, ThumbnailPriority::Visible)).await;
+        queue.push(job(5, ThumbnailPriority::Visible)).await;
+
+        assert_eq!(drain(&queue).await, vec![4, 5, 3, 1, 2]);
+        assert_eq!(queue.processing_count().await, 5);
+    }
+
+    #[tokio::test]
+    async fn test_duplicates_are_coalesced_and_upgraded() {
+        let queue = JobQueue::new();
+        assert!(queue.push(job(1, ThumbnailPriority::Background)).await);
+        assert!(queue.push(job(2, ThumbnailPriority::Background)).await);
+        assert!(!queue.push(job(1, ThumbnailPriority::Background)).await);
+        assert!(!queue.push(job(2, ThumbnailPriority::Visible)).await);
+        // A lower priority request does not move a job down
+        assert!(!queue.push(job(2, ThumbnailPriority::Requested)).await);
+        assert_eq!(queue.pending_count().await, 2);
+
+        let first = queue.pop().await.expect("pending job");
+        assert_eq!(first.file_id, 2);
+        assert_eq!(first.priority, ThumbnailPriority::Visible);
+        assert_eq!(drain(&queue).await, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_demote_visible_keeps_files_on_screen() {
+        let queue = JobQueue::new();
+        queue.push(job(1, ThumbnailPriority::Visible)).await;
+        queue.push(job(2, ThumbnailPriority::Visible)).await;
+        queue.push(job(3, ThumbnailPriority::Requested)).await;
+
+        queue.demote_visible(&HashSet::from([2])).await;
+
+        // Demoted jobs keep their place in line among the requested ones
+        assert_eq!(drain(&queue).await, vec![2, 1, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_next_wakes_up_on_push() {
+        let queue = std::sync::Arc::new(JobQueue::new());
+        let waiter = tokio::spawn({
+            let queue = std::sync::Arc::clone(&queue);
+            async move { queue.next().await.file_id }
+        });
+        tokio::task::yield_now().await;
+        queue.push(job(7, ThumbnailPriority::Background)).await;
+
+        let started = Instant::now();
+        assert_eq!(waiter.await.expect("waiter finished"), 7);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
@@ -1,35 +1,50 @@
 use crate::thumbnails::generator::ThumbnailGenerator;
+use crate::thumbnails::queue::{JobQueue, ThumbnailPriority};
 use anyhow::{Context, Result};
+use events::changes::{ChangeBus, LibraryChange};
 use model::services::file::FileSystemFile as File;
 use model::services::thumbnail::ThumbnailSize;
+use repositories::media::operations::MediaMetadataOperations;
 use repositories::thumbnail::operations::ThumbnailOperations;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::{Mutex, mpsc, oneshot};
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 
-#[derive(Debug, Clone, PartialEq)]
-pub(crate) enum ThumbnailJobStatus {
-    Pending,
-    Processing,
-    Completed,
-    Failed,
-}
-
 #[derive(Debug, Clone)]
 pub(crate) struct ThumbnailJob {
     pub file_id: i32,
     pub file_path: PathBuf,
     pub size: ThumbnailSize,
-    pub status: ThumbnailJobStatus,
+    pub priority: ThumbnailPriority,
     pub created_at: Instant,
     pub retry_count: u32,
 }
 
+impl ThumbnailJob {
+    pub fn new(
+        file_id: i32,
+        file_path: PathBuf,
+        size: ThumbnailSize,
+        priority: ThumbnailPriority,
+    ) -> Self {
+        Self {
+            file_id,
+            file_path,
+            size,
+            priority,
+            created_at: Instant::now(),
+            retry_count: 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub(crate) struct ProcessingStats {
+pub struct ProcessingStats {
     pub pending_jobs: usize,
     pub processing_jobs: usize,
     pub completed_jobs: u64,
@@ -39,6 +54,17 @@ pub(crate) struct ProcessingStats {
     pub avg_processing_time: Duration,
 }
 
+/// A job that failed on every attempt and is no longer retried automatically
+#[derive(Debug, Clone)]
+pub struct FailedJob {
+    pub file_id: i32,
+    pub file_path: PathBuf,
+    pub size: ThumbnailSize,
+    pub reason: String,
+    pub attempts: u32,
+    pub failed_at: SystemTime,
+}
+
 #[derive(Debug)]
 pub enum ThumbnailMessage {
     QueueFiles {
@@ -51,16 +77,39 @@ pub enum ThumbnailMessage {
         size: ThumbnailSize,
     },
     QueueMissingFiles,
+    /// Generate thumbnails of the files on screen ahead of everything else
+    ///
+    /// Files that were visible before but are not part of this set fall back to
+    /// [`ThumbnailPriority::Requested`].
+    QueueVisibleFiles {
+        file_infos: Vec<File>,
+        size: ThumbnailSize,
+    },
+    /// Drop thumbnails generated from older content of the files and queue new ones
+    InvalidateFiles {
+        file_infos: Vec<File>,
+    },
     GetStats {
         respond_to: oneshot::Sender<ProcessingStats>,
     },
     GetPendingCount {
         respond_to: oneshot::Sender<usize>,
     },
-    Shutdown,
+    ListFailedJobs {
+        respond_to: oneshot::Sender<Vec<FailedJob>>,
+    },
+    /// Queue permanently failed jobs again; `None` retries all of them
+    RetryFailedJobs {
+        file_ids: Option<Vec<i32>>,
+        respond_to: oneshot::Sender<usize>,
+    },
+    /// Stop the workers and reply once every task of the processor has finished
+    Shutdown {
+        respond_to: oneshot::Sender<()>,
+    },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ThumbnailProcessorHandler {
     pub sender: mpsc::UnboundedSender<ThumbnailMessage>,
 }
@@ -73,6 +122,7 @@ pub(crate) struct ProcessorConfig {
     pub retry_delay: Duration,
     pub processing_timeout: Duration,
     pub memory_limit_mb: usize,
+    pub maintenance_interval: Duration,
 }
 
 impl Default for ProcessorConfig {
@@ -84,6 +134,7 @@ impl Default for ProcessorConfig {
             retry_delay: Duration::from_secs(5),
             processing_timeout: Duration::from_secs(30),
             memory_limit_mb: 100,
+            maintenance_interval: Duration::from_secs(15 * 60),
         }
     }
 }
@@ -105,47 +156,66 @@ impl Default for ProcessingStats {
 // Worker struct responsible for actual thumbnail generation
 pub struct ThumbnailWorker {
     worker_id: usize,
-    job_queue: Arc<Mutex<Vec<ThumbnailJob>>>,
+    job_queue: Arc<JobQueue>,
     repository: Arc<ThumbnailOperations>,
+    metadata_repository: Option<Arc<MediaMetadataOperations>>,
     generator: Arc<ThumbnailGenerator>,
     stats: Arc<Mutex<ProcessingStats>>,
+    failures: Arc<Mutex<Vec<FailedJob>>>,
+    changes: Option<ChangeBus>,
     config: ProcessorConfig,
 }
 
 impl ThumbnailWorker {
     pub fn new(
         worker_id: usize,
-        job_queue: Arc<Mutex<Vec<ThumbnailJob>>>,
+        job_queue: Arc<JobQueue>,
         repository: Arc<ThumbnailOperations>,
         generator: Arc<ThumbnailGenerator>,
         stats: Arc<Mutex<ProcessingStats>>,
+        failures: Arc<Mutex<Vec<FailedJob>>>,
         config: ProcessorConfig,
     ) -> Self {
         Self {
             worker_id,
             job_queue,
             repository,
+            metadata_repository: None,
             generator,
             stats,
+            failures,
+            changes: None,
             config,
         }
     }
 
-    pub async fn run(self, shutdown_signal: Arc<tokio::sync::Notify>) {
+    pub fn with_metadata_repository(
+        mut self,
+        metadata_repository: Option<Arc<MediaMetadataOperations>>,
+    ) -> Self {
+        self.metadata_repository = metadata_repository;
+        self
+    }
+
+    pub fn with_changes(mut self, changes: Option<ChangeBus>) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    pub async fn run(self, shutdown: CancellationToken) {
         info!("Worker {} started", self.worker_id);
 
         loop {
             tokio::select! {
-                _ = shutdown_signal.notified() => {
+                () = shutdown.cancelled() => {
                     info!("Worker {} received shutdown signal", self.worker_id);
                     break;
                 }
-                _ = tokio::time::sleep(Duration::from_millis(100)) => {
-                    if let Some(job) = self.get_next_job().await {
-                        if let Err(e) = self.process_job(job).await {
-                            error!("Worker {} failed to process job: {}", self.worker_id, e);
-                        }
+                job = self.job_queue.next() => {
+                    if let Err(e) = self.process_job(job).await {
+                        error!("Worker {} failed to process job: {}", self.worker_id, e);
                     }
+                    self.job_queue.finish().await;
                 }
             }
         }
@@ -153,21 +223,6 @@ impl ThumbnailWorker {
         info!("Worker {} stopped", self.worker_id);
     }
 
-    async fn get_next_job(&self) -> Option<ThumbnailJob> {
-        let mut queue = self.job_queue.lock().await;
-
-        // Find first pending job
-        for (index, job) in queue.iter().enumerate() {
-            if job.status == ThumbnailJobStatus::Pending {
-                let mut job = queue.remove(index);
-                job.status = ThumbnailJobStatus::Processing;
-                return Some(job);
-            }
-        }
-
-        None
-    }
-
     async fn process_job(&self, job: ThumbnailJob) -> anyhow::Result<()> {
         let start_time = Instant::now();
 
@@ -186,10 +241,10 @@ impl ThumbnailWorker {
 
         match generation_result {
             Ok(Ok(thumbnail)) => {
-                // Save thumbnail to database
+                // Save thumbnail to database, replacing one that was invalidated meanwhile
                 match self
                     .repository
-                    .create_thumbnail(job.file_id, thumbnail)
+                    .upsert_thumbnail(job.file_id, thumbnail)
                     .await
                 {
                     Ok(_) => {
@@ -206,13 +261,28 @@ impl ThumbnailWorker {
                         let mut stats = self.stats.lock().await;
                         stats.completed_jobs += 1;
                         self.update_avg_processing_time(&mut stats, processing_time);
+                        drop(stats);
+                        self.failures.lock().await.retain(|failed| {
+                            (failed.file_id, failed.size) != (job.file_id, job.size)
+                        });
+                        if let Some(changes) = &self.changes {
+                            changes.publish(LibraryChange::ThumbnailReady {
+                                file_id: job.file_id,
+                            });
+                        }
+
+                        // Metadata belongs to the file, so extract it along with one size only
+                        if job.size == ThumbnailSize::fallback() {
+                            self.store_metadata(&job).await;
+                        }
                     }
                     Err(e) => {
                         error!(
                             "Worker {} failed to save thumbnail to database: {}",
                             self.worker_id, e
                         );
-                        self.handle_failed_job(job).await;
+                        self.handle_failed_job(job, format!("could not save thumbnail: {e}"))
+                            .await;
                         return Err(e);
                     }
                 }
@@ -222,7 +292,7 @@ impl ThumbnailWorker {
                     "Worker {} failed to generate thumbnail for file {}: {}",
                     self.worker_id, job.file_id, e
                 );
-                self.handle_failed_job(job).await;
+                self.handle_failed_job(job, format!("{e:#}")).await;
                 return Err(e);
             }
             Err(_) => {
@@ -230,7 +300,14 @@ impl ThumbnailWorker {
                     "Worker {} thumbnail generation timed out for file {}",
                     self.worker_id, job.file_id
                 );
-                self.handle_failed_job(job).await;
+                self.handle_failed_job(
+                    job,
+                    format!(
+                        "generation timed out after {:?}",
+                        self.config.processing_timeout
+                    ),
+                )
+                .await;
                 anyhow::bail!("worker {} thumbnail generation timed out", self.worker_id);
             }
         }
@@ -238,13 +315,41 @@ impl ThumbnailWorker {
         Ok(())
     }
 
-    async fn handle_failed_job(&self, mut job: ThumbnailJob) {
+    async fn store_metadata(&self, job: &ThumbnailJob) {
+        let Some(metadata_repository) = self.metadata_repository.as_ref() else {
+            return;
+        };
+
+        match self
+            .generator
+            .extract_metadata_from_file_path(&job.file_path)
+        {
+            Ok(Some(metadata)) => {
+                if let Err(e) = metadata_repository
+                    .upsert_metadata(job.file_id, metadata)
+                    .await
+                {
+                    warn!(
+                        "Worker {} failed to save metadata for file {}: {}",
+                        self.worker_id, job.file_id, e
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!(
+                    "Worker {} could not read metadata for file {}: {}",
+                    self.worker_id, job.file_id, e
+                );
+            }
+        }
+    }
+
+    async fn handle_failed_job(&self, mut job: ThumbnailJob, reason: String) {
         job.retry_count += 1;
 
         if job.retry_count < self.config.max_retries {
-            // Retry the job after delay
-            job.status = ThumbnailJobStatus::Pending;
-
+            // Retry the job after delay, keeping its priority
             let delay = self.config.retry_delay * job.retry_count; // Exponential backoff
             info!(
                 "Worker {} retrying failed job (attempt {}/{})",
@@ -255,24 +360,33 @@ impl ThumbnailWorker {
                 let job_queue = Arc::clone(&self.job_queue);
                 async move {
                     tokio::time::sleep(delay).await;
-                    let mut queue = job_queue.lock().await;
-                    queue.push(job);
+                    job_queue.push(job).await;
                 }
             });
         } else {
-            // Move to failed jobs
-            job.status = ThumbnailJobStatus::Failed;
-
             let mut stats = self.stats.lock().await;
             stats.failed_jobs += 1;
+            drop(stats);
 
             tracing::warn!(
-                "Worker {} job permanently failed after {} retries: file {} size {:?}",
+                "Worker {} job permanently failed after {} retries: file {} size {:?}: {}",
                 self.worker_id,
                 self.config.max_retries,
                 job.file_id,
-                job.size
+                job.size,
+                reason
             );
+
+            let mut failures = self.failures.lock().await;
+            failures.retain(|failed| (failed.file_id, failed.size) != (job.file_id, job.size));
+            failures.push(FailedJob {
+                file_id: job.file_id,
+                file_path: job.file_path,
+                size: job.size,
+                reason,
+                attempts: job.retry_count,
+                failed_at: SystemTime::now(),
+            });
         }
     }
 
@@ -295,12 +409,15 @@ impl ThumbnailWorker {
 // Processor struct responsible for message handling and coordination
 pub struct ThumbnailProcessor {
     message_receiver: mpsc::UnboundedReceiver<ThumbnailMessage>,
-    job_queue: Arc<Mutex<Vec<ThumbnailJob>>>,
+    job_queue: Arc<JobQueue>,
     repository: Arc<ThumbnailOperations>,
+    metadata_repository: Option<Arc<MediaMetadataOperations>>,
     generator: Arc<ThumbnailGenerator>,
     stats: Arc<Mutex<ProcessingStats>>,
+    failures: Arc<Mutex<Vec<FailedJob>>>,
+    changes: Option<ChangeBus>,
     config: ProcessorConfig,
-    shutdown_signal: Arc<tokio::sync::Notify>,
+    shutdown: CancellationToken,
 }
 
 impl ThumbnailProcessor {
@@ -311,12 +428,15 @@ impl ThumbnailProcessor {
     ) -> Self {
         Self {
             message_receiver,
-            job_queue: Arc::new(Mutex::new(Vec::new())),
+            job_queue: Arc::new(JobQueue::new()),
             repository,
+            metadata_repository: None,
             generator,
             stats: Arc::new(Mutex::new(ProcessingStats::default())),
+            failures: Arc::new(Mutex::new(Vec::new())),
+            changes: None,
             config: ProcessorConfig::default(),
-            shutdown_signal: Arc::new(tokio::sync::Notify::new()),
+            shutdown: CancellationToken::new(),
         }
     }
 
@@ -325,6 +445,21 @@ impl ThumbnailProcessor {
         self
     }
 
+    /// Store the EXIF metadata of images while generating their thumbnails
+    pub fn with_metadata_repository(
+        mut self,
+        metadata_repository: Arc<MediaMetadataOperations>,
+    ) -> Self {
+        self.metadata_repository = Some(metadata_repository);
+        self
+    }
+
+    /// Announce every stored thumbnail on the bus
+    pub fn with_changes(mut self, changes: ChangeBus) -> Self {
+        self.changes = Some(changes);
+        self
+    }
+
     pub async fn run(mut self) -> Result<()> {
         info!(
             "Starting ThumbnailProcessor with {} workers",
@@ -340,24 +475,61 @@ impl ThumbnailProcessor {
                 Arc::clone(&self.repository),
                 Arc::clone(&self.generator),
                 Arc::clone(&self.stats),
+                Arc::clone(&self.failures),
                 self.config.clone(),
-            );
+            )
+            .with_metadata_repository(self.metadata_repository.clone())
+            .with_changes(self.changes.clone());
 
-            let shutdown_signal = Arc::clone(&self.shutdown_signal);
-            let handle = tokio::spawn(async move {
-                worker.run(shutdown_signal).await;
-            });
+            let handle = tokio::spawn(worker.run(self.shutdown.clone()));
             worker_handles.push(handle);
         }
 
         // Start stats updater task
         let stats_handle = self.spawn_stats_updater().await;
+        let maintenance_handle = self.spawn_maintenance_task();
+
+        let stopped = self.handle_messages().await;
+
+        // Signal shutdown to all workers, including those busy with a job right now
+        self.shutdown.cancel();
+
+        // Wait for all workers to complete
+        for handle in worker_handles {
+            if let Err(e) = handle.await {
+                error!("Worker task failed: {}", e);
+            }
+        }
+
+        // Stop stats updater
+        if let Err(e) = stats_handle.await {
+            error!("Stats updater task failed: {}", e);
+        }
+        if let Err(e) = maintenance_handle.await {
+            error!("Thumbnail maintenance task failed: {}", e);
+        }
+
+        info!("ThumbnailProcessor stopped successfully");
+        // Only reply now, so the caller may close the database once the reply arrives
+        if let Some(respond_to) = stopped? {
+            let _ = respond_to.send(());
+        }
+        Ok(())
+    }
 
-        // Main message processing loop
+    /// Main message processing loop
+    ///
+    /// Returns the reply to a shutdown request, or `None` when every handler was dropped.
+    async fn handle_messages(&mut self) -> Result<Option<oneshot::Sender<()>>> {
         while let Some(message) = self.message_receiver.recv().await {
             match message {
                 ThumbnailMessage::QueueFiles { file_infos, sizes } => {
-                    self.queue_files_for_processing(file_infos, sizes).await?;
+                    self.queue_files_for_processing(
+                        file_infos,
+                        sizes,
+                        ThumbnailPriority::Requested,
+                    )
+                    .await?;
                 }
                 ThumbnailMessage::QueueSingleFile {
                     file_id,
@@ -369,6 +541,12 @@ impl ThumbnailProcessor {
                 ThumbnailMessage::QueueMissingFiles => {
                     self.queue_missing_files().await?;
                 }
+                ThumbnailMessage::QueueVisibleFiles { file_infos, size } => {
+                    self.queue_visible_files(file_infos, size).await?;
+                }
+                ThumbnailMessage::InvalidateFiles { file_infos } => {
+                    self.invalidate_files(file_infos).await?;
+                }
                 ThumbnailMessage::GetStats { respond_to } => {
                     let stats = self.get_current_stats().await;
                     let _ = respond_to.send(stats);
@@ -377,38 +555,32 @@ impl ThumbnailProcessor {
                     let count = self.get_pending_job_count().await;
                     let _ = respond_to.send(count);
                 }
-                ThumbnailMessage::Shutdown => {
+                ThumbnailMessage::ListFailedJobs { respond_to } => {
+                    let failures = self.failures.lock().await.clone();
+                    let _ = respond_to.send(failures);
+                }
+                ThumbnailMessage::RetryFailedJobs {
+                    file_ids,
+                    respond_to,
+                } => {
+                    let count = self.retry_failed_jobs(file_ids).await;
+                    let _ = respond_to.send(count);
+                }
+                ThumbnailMessage::Shutdown { respond_to } => {
                     info!("Shutdown signal received, stopping processor");
-                    break;
+                    return Ok(Some(respond_to));
                 }
             }
         }
-
-        // Signal shutdown to all workers
-        self.shutdown_signal.notify_waiters();
-
-        // Wait for all workers to complete
-        for handle in worker_handles {
-            if let Err(e) = handle.await {
-                error!("Worker task failed: {}", e);
-            }
-        }
-
-        // Stop stats updater
-        if let Err(e) = stats_handle.await {
-            error!("Stats updater task failed: {}", e);
-        }
-
-        info!("ThumbnailProcessor stopped successfully");
-        Ok(())
+        Ok(None)
     }
 
     async fn queue_files_for_processing(
         &mut self,
         file_infos: Vec<File>,
         sizes: Vec<ThumbnailSize>,
+        priority: ThumbnailPriority,
     ) -> Result<usize> {
-        let mut queue = self.job_queue.lock().await;
         let mut queued_count = 0;
 
         for file_info in file_infos {
@@ -416,45 +588,9 @@ impl ThumbnailProcessor {
                 let file_id = file_info
                     .id
                     .context("file ID for thumbnail generation was not provided")?;
-                // Check if thumbnail already exists
-                match self.repository.get_by_file_and_size(file_id, size).await {
-                    Ok(Some(_)) => {
-                        debug!(
-                            "Thumbnail already exists for file {} size {:?}",
-                            file_id, size
-                        );
-                        continue;
-                    }
-                    Ok(None) => {
-                        // Need to generate thumbnail
-                        let job = ThumbnailJob {
-                            file_id: file_id,
-                            file_path: file_info.path.clone(),
-                            size,
-                            status: ThumbnailJobStatus::Pending,
-                            created_at: Instant::now(),
-                            retry_count: 0,
-                        };
-                        queue.push(job);
-                        queued_count += 1;
-                    }
-                    Err(e) => {
-                        warn!(
-                            "Failed to check existing thumbnail for file {}: {}",
-                            file_id, e
-                        );
-                        // Queue anyway to be safe
-                        let job = ThumbnailJob {
-                            file_id: file_id,
-                            file_path: file_info.path.clone(),
-                            size,
-                            status: ThumbnailJobStatus::Pending,
-                            created_at: Instant::now(),
-                            retry_count: 0,
-                        };
-                        queue.push(job);
-                        queued_count += 1;
-                    }
+                let job = ThumbnailJob::new(file_id, file_info.path.clone(), size, priority);
+                if self.queue_job(job).await {
+                    queued_count += 1;
                 }
             }
         }
@@ -463,8 +599,42 @@ impl ThumbnailProcessor {
         Ok(queued_count)
     }
 
+    /// Queue a job unless its thumbnail already exists; returns whether a new job was added
+    async fn queue_job(&self, job: ThumbnailJob) -> bool {
+        match self
+            .repository
+            .get_by_file_and_size(job.file_id, job.size)
+            .await
+        {
+            Ok(Some(_)) => {
+                debug!(
+                    "Thumbnail already exists for file {} size {:?}",
+                    job.file_id, job.size
+                );
+                false
+            }
+            Ok(None) => self.job_queue.push(job).await,
+            Err(e) => {
+                warn!(
+                    "Failed to check existing thumbnail for file {}: {}",
+                    job.file_id, e
+                );
+                // Queue anyway to be safe
+                self.job_queue.push(job).await
+            }
+        }
+    }
+
     async fn queue_missing_files(&mut self) -> Result<usize> {
-        let mut queued_jobs = 0;
+        // Files changed while the watcher was not running still have their old thumbnails
+        let stale = self.repository.delete_stale_thumbnails().await?;
+        if stale > 0 {
+            info!(
+                "Deleted {} stale thumbnails before queueing missing ones",
+                stale
+            );
+        }
+
         let all_thumbnail_sizes = ThumbnailSize::all().to_vec();
         let file_models = self
             .repository
@@ -472,61 +642,137 @@ impl ThumbnailProcessor {
             .await?;
         let files: Vec<File> = file_models.into_iter().map(|v| v.into()).collect();
 
-        self.queue_files_for_processing(files, ThumbnailSize::all().to_vec())
+        let queued = self
+            .queue_files_for_processing(
+                files,
+                ThumbnailSize::all().to_vec(),
+                ThumbnailPriority::Background,
+            )
+            .await?;
+        self.queue_metadata_backfill().await?;
+        Ok(queued)
+    }
+
+    /// Extract metadata of images whose thumbnails were generated before metadata was stored
+    async fn queue_metadata_backfill(&self) -> Result<()> {
+        let Some(metadata_repository) = self.metadata_repository.clone() else {
+            return Ok(());
+        };
+        let files = metadata_repository.get_files_without_metadata().await?;
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let generator = Arc::clone(&self.generator);
+        tokio::spawn(async move {
+            let mut stored = 0usize;
+            for file in files {
+                let generator = Arc::clone(&generator);
+                let path = PathBuf::from(&file.path);
+                let extracted = tokio::task::spawn_blocking(move || {
+                    generator.extract_metadata_from_file_path(&path)
+                })
+                .await;
+                match extracted {
+                    Ok(Ok(Some(metadata))) => {
+                        match metadata_repository.upsert_metadata(file.id, metadata).await {
+                            Ok(_) => stored += 1,
+                            Err(e) => warn!("Failed to save metadata for file {}: {}", file.id, e),
+                        }
+                    }
+                    Ok(Ok(None)) => {}
+                    Ok(Err(e)) => {
+                        debug!("Could not read metadata for file {}: {}", file.id, e);
+                    }
+                    Err(e) => warn!("Metadata extraction for file {} failed: {}", file.id, e),
+                }
+            }
+            if stored > 0 {
+                info!("Backfilled metadata of {} images", stored);
+            }
+        });
+        Ok(())
+    }
+
+    async fn queue_visible_files(
+        &mut self,
+        file_infos: Vec<File>,
+        size: ThumbnailSize,
+    ) -> Result<usize> {
+        let visible: HashSet<i32> = file_infos.iter().filter_map(|file| file.id).collect();
+        self.job_queue.demote_visible(&visible).await;
+
+        self.queue_files_for_processing(file_infos, vec![size], ThumbnailPriority::Visible)
             .await
     }
 
+    async fn invalidate_files(&mut self, file_infos: Vec<File>) -> Result<usize> {
+        for file_info in &file_infos {
+            let file_id = file_info
+                .id
+                .context("file ID for thumbnail invalidation was not provided")?;
+            let deleted = self
+                .repository
+                .delete_stale_thumbnails_for_file(file_id, &file_info.content_hash)
+                .await?;
+            if deleted > 0 {
+                debug!("Invalidated {} thumbnails of file {}", deleted, file_id);
+            }
+        }
+
+        // Jobs still queued for the old content generate from the file's current state anyway
+        self.queue_files_for_processing(
+            file_infos,
+            ThumbnailSize::all().to_vec(),
+            ThumbnailPriority::Requested,
+        )
+        .await
+    }
+
+    async fn retry_failed_jobs(&mut self, file_ids: Option<Vec<i32>>) -> usize {
+        let retried: Vec<FailedJob> = {
+            let mut failures = self.failures.lock().await;
+            let (retried, remaining) =
+                std::mem::take(&mut *failures)
+                    .into_iter()
+                    .partition(|failed| {
+                        file_ids
+                            .as_ref()
+                            .is_none_or(|file_ids| file_ids.contains(&failed.file_id))
+                    });
+            *failures = remaining;
+            retried
+        };
+
+        let mut queued_count = 0;
+        for failed in retried {
+            let job = ThumbnailJob::new(
+                failed.file_id,
+                failed.file_path,
+                failed.size,
+                ThumbnailPriority::Requested,
+            );
+            if self.queue_job(job).await {
+                queued_count += 1;
+            }
+        }
+
+        info!("Retrying {} failed thumbnail jobs", queued_count);
+        queued_count
+    }
+
     async fn queue_single_file(
         &mut self,
         file_id: i32,
         file_path: PathBuf,
         size: ThumbnailSize,
     ) -> Result<()> {
-        // Check if thumbnail already exists
-        match self.repository.get_by_file_and_size(file_id, size).await {
-            Ok(Some(_)) => {
-                debug!(
-                    "Thumbnail already exists for file {} size {:?}",
-                    file_id, size
-                );
-                return Ok(());
-            }
-            Ok(None) => {
-                // Need to generate thumbnail
-                let job = ThumbnailJob {
-                    file_id,
-                    file_path,
-                    size,
-                    status: ThumbnailJobStatus::Pending,
-                    created_at: Instant::now(),
-                    retry_count: 0,
-                };
-
-                let mut queue = self.job_queue.lock().await;
-                queue.push(job);
-                info!(
-                    "Queued single thumbnail job for file {} size {:?}",
-                    file_id, size
-                );
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to check existing thumbnail for file {}: {}",
-                    file_id, e
-                );
-                // Queue anyway to be safe
-                let job = ThumbnailJob {
-                    file_id,
-                    file_path,
-                    size,
-                    status: ThumbnailJobStatus::Pending,
-                    created_at: Instant::now(),
-                    retry_count: 0,
-                };
-
-                let mut queue = self.job_queue.lock().await;
-                queue.push(job);
-            }
+        let job = ThumbnailJob::new(file_id, file_path, size, ThumbnailPriority::Requested);
+        if self.queue_job(job).await {
+            info!(
+                "Queued single thumbnail job for file {} size {:?}",
+                file_id, size
+            );
         }
 
         Ok(())
@@ -534,7 +780,7 @@ impl ThumbnailProcessor {
 
     async fn spawn_stats_updater(&self) -> tokio::task::JoinHandle<()> {
         let stats = Arc::clone(&self.stats);
-        let shutdown_signal = Arc::clone(&self.shutdown_signal);
+        let shutdown = self.shutdown.clone();
 
         tokio::spawn(async move {
             let mut last_completed = 0u64;
@@ -542,7 +788,7 @@ impl ThumbnailProcessor {
 
             loop {
                 tokio::select! {
-                    _ = shutdown_signal.notified() => break,
+                    () = shutdown.cancelled() => break,
                     _ = tokio::time::sleep(Duration::from_secs(5)) => {
                         let mut stats_guard = stats.lock().await;
                         let now = Instant::now();
@@ -561,18 +807,39 @@ impl ThumbnailProcessor {
         })
     }
 
+    fn spawn_maintenance_task(&self) -> tokio::task::JoinHandle<()> {
+        let repository = Arc::clone(&self.repository);
+        let shutdown = self.shutdown.clone();
+        let interval = self.config.maintenance_interval;
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    () = shutdown.cancelled() => break,
+                    _ = tokio::time::sleep(interval) => {
+                        match repository.delete_orphaned_thumbnails().await {
+                            Ok(0) => {}
+                            Ok(deleted) => info!("Deleted {} orphaned thumbnails", deleted),
+                            Err(e) => warn!("Failed to delete orphaned thumbnails: {}", e),
+                        }
+                        match repository.delete_stale_thumbnails().await {
+                            Ok(0) => {}
+                            Ok(deleted) => info!("Deleted {} stale thumbnails", deleted),
+                            Err(e) => warn!("Failed to delete stale thumbnails: {}", e),
+                        }
+                        if let Err(e) = repository.collect_garbage().await {
+                            warn!("Failed to clean up the thumbnail cache: {}", e);
+                        }
+                    }
+                }
+            }
+        })
+    }
+
     async fn get_current_stats(&self) -> ProcessingStats {
+        let pending_jobs = self.job_queue.pending_count().await;
+        let processing_jobs = self.job_queue.processing_count().await;
         let stats = self.stats.lock().await;
-        let queue = self.job_queue.lock().await;
-
-        let pending_jobs = queue
-            .iter()
-            .filter(|job| job.status == ThumbnailJobStatus::Pending)
-            .count();
-        let processing_jobs = queue
-            .iter()
-            .filter(|job| job.status == ThumbnailJobStatus::Processing)
-            .count();
 
         ProcessingStats {
             pending_jobs,
@@ -586,11 +853,7 @@ impl ThumbnailProcessor {
     }
 
     async fn get_pending_job_count(&self) -> usize {
-        let queue = self.job_queue.lock().await;
-        queue
-            .iter()
-            .filter(|job| job.status == ThumbnailJobStatus::Pending)
-            .count()
+        self.job_queue.pending_count().await
     }
 }
 
@@ -623,6 +886,23 @@ impl ThumbnailProcessorHandler {
         Ok(())
     }
 
+    /// Move the thumbnails of the files on screen to the front of the queue
+    pub async fn queue_visible_files(
+        &self,
+        file_infos: Vec<File>,
+        size: ThumbnailSize,
+    ) -> Result<()> {
+        self.sender
+            .send(ThumbnailMessage::QueueVisibleFiles { file_infos, size })?;
+        Ok(())
+    }
+
+    pub async fn invalidate_files(&self, file_infos: Vec<File>) -> Result<()> {
+        self.sender
+            .send(ThumbnailMessage::InvalidateFiles { file_infos })?;
+        Ok(())
+    }
+
     pub async fn queue_single_file(
         &self,
         file_id: i32,
@@ -655,7 +935,72 @@ impl ThumbnailProcessorHandler {
         Ok(response.await?)
     }
 
+    pub async fn list_failed_jobs(&self) -> Result<Vec<FailedJob>> {
+        let (respond_to, response) = oneshot::channel();
+
+        self.sender
+            .send(ThumbnailMessage::ListFailedJobs { respond_to })?;
+
+        Ok(response.await?)
+    }
+
+    /// Queue permanently failed jobs again and return how many were queued
+    pub async fn retry_failed_jobs(&self, file_ids: Option<Vec<i32>>) -> Result<usize> {
+        let (respond_to, response) = oneshot::channel();
+
+        self.sender.send(ThumbnailMessage::RetryFailedJobs {
+            file_ids,
+            respond_to,
+        })?;
+
+        Ok(response.await?)
+    }
+
+    /// Stop the processor and wait until its workers no longer touch the database
     pub async fn shutdown(&self) -> Result<()> {
-        Ok(self.sender.send(ThumbnailMessage::Shutdown)?)
+        let (respond_to, response) = oneshot::channel();
+        self.sender
+            .send(ThumbnailMessage::Shutdown { respond_to })?;
+        Ok(response.await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use repositories::config::DatabaseSettings;
+    use repositories::manager::DatabaseManager;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn shutdown_reaches_workers_that_have_not_started_waiting() -> Result<()> {
+        let directory = TempDir::new()?;
+        let settings = DatabaseSettings {
+            con_string: format!(
+                "sqlite://{}?mode=rwc",
+                directory.path().join("library.db").display()
+            ),
+            timeout: 1_000,
+            ..DatabaseSettings::default()
+        };
+        let database_manager = Arc::new(DatabaseManager::new(settings).await?);
+        let repository = Arc::new(ThumbnailOperations::new(database_manager));
+        let (handler, receiver) = ThumbnailProcessorHandler::new();
+        let processor = ThumbnailProcessor::new(
+            receiver,
+            Arc::clone(&repository),
+            Arc::new(ThumbnailGenerator::new()),
+        );
+
+        // The shutdown is handled before the workers got to run on this thread
+        let running = tokio::spawn(processor.run());
+        timeout(Duration::from_secs(5), handler.shutdown())
+            .await
+            .context("Processor tasks missed the shutdown signal")??;
+        // Workers and the maintenance task have released the repository by the time of the reply
+        assert!(Arc::strong_count(&repository) <= 2);
+        running.await??;
+        assert_eq!(Arc::strong_count(&repository), 1);
+        Ok(())
     }
 }
@@ -74,6 +74,7 @@ fn check_library_write_save_and_retrieve() -> Result<()> {
         LibraryPathConfig {
             name: Some("Hello".to_string()),
             path: Some(PathBuf::new().join("home/emmi/Documents/")),
+            ..LibraryPathConfig::default()
         },
     ];
     if let Some(lib_config) = lib.library_config.as_mut() {